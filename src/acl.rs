@@ -0,0 +1,44 @@
+use std::sync::Arc;
+
+/// Name of the user every connection starts out as
+pub const DEFAULT_USER: &str = "default";
+
+/// Users that clients can authenticate as
+///
+/// There is only the default user so far. It has full permissions, and needs the
+/// configured password if there is one, accepting any password otherwise.
+#[derive(Clone, Default)]
+pub struct Acl {
+    password: Option<Arc<str>>,
+}
+
+impl Acl {
+    pub fn new(requirepass: Option<String>) -> Self {
+        Self {
+            password: requirepass.map(Into::into),
+        }
+    }
+
+    /// Whether connections have to authenticate before running commands
+    pub fn requires_auth(&self) -> bool {
+        self.password.is_some()
+    }
+
+    /// ACL flags of `user` as reported by `ACL GETUSER`, `None` if there is no
+    /// such user
+    pub fn flags(&self, user: &[u8]) -> Option<Vec<&'static str>> {
+        (user == DEFAULT_USER.as_bytes()).then(|| match self.password {
+            Some(_) => vec!["on"],
+            None => vec!["on", "nopass"],
+        })
+    }
+
+    /// Whether `password` is valid for `user`
+    pub fn authenticate(&self, user: &[u8], password: &[u8]) -> bool {
+        user == DEFAULT_USER.as_bytes()
+            && self
+                .password
+                .as_ref()
+                .is_none_or(|expected| expected.as_bytes() == password)
+    }
+}
@@ -0,0 +1,279 @@
+//! Append only file, logging every write command so the dataset survives a
+//! restart
+//!
+//! The file holds the stream of commands replicas are fed, SELECTs included,
+//! each encoded as a RESP array. It is replayed on startup, before any client is
+//! accepted. Commands setting a relative expiry, like EXPIRE, are fed as a
+//! PEXPIREAT at the time they set, so replaying them doesn't start the
+//! countdown over.
+//!
+//! Commands that fail to be written are kept and written again later, so a
+//! full disk loses nothing once space is freed. Until then write commands are
+//! refused.
+
+use crate::{
+    client::ClientList,
+    cmd::{Command, command_frame, pexpireat_frame},
+    config::AppendFsync,
+    db::{Db, Storage},
+    frame::{Frame, FrameValue},
+    log, rdb,
+};
+use bytes::BytesMut;
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, Write},
+    ops::Deref,
+    path::Path,
+    sync::Arc,
+};
+use tokio_util::codec::{Decoder, Encoder};
+
+/// An open append only file
+pub struct Aof {
+    /// Shared so it can be fsynced without holding up writes to it
+    file: Arc<File>,
+    fsync: AppendFsync,
+    /// Bytes of complete commands in the file
+    len: u64,
+    /// Encoded commands not written yet, because writing them failed
+    pending: BytesMut,
+    /// Whether a failed write may have left part of `pending` in the file
+    torn: bool,
+}
+
+impl Aof {
+    /// Replays the file at `path` into `db`, then opens it for appending
+    ///
+    /// A command cut off at the end of the file, as left behind by a crash in
+    /// the middle of a write, is dropped and the file truncated before it. Fails
+    /// if the file holds anything other than commands.
+    pub fn open(path: &Path, fsync: AppendFsync, db: &Db) -> io::Result<Self> {
+        let contents = match fs::read(path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Vec::new(),
+            Err(e) => return Err(e),
+        };
+        let replayed = replay(&contents, db)?;
+
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        if replayed < contents.len() {
+            log!(
+                Warning,
+                "Dropping the last {} bytes of the append only file, which end in the middle of a command",
+                contents.len() - replayed
+            );
+            file.set_len(replayed as u64)?;
+        }
+
+        Ok(Self {
+            file: Arc::new(file),
+            fsync,
+            len: replayed as u64,
+            pending: BytesMut::new(),
+            torn: false,
+        })
+    }
+
+    pub fn fsync_policy(&self) -> AppendFsync {
+        self.fsync
+    }
+
+    /// Handle to the file for fsyncing it elsewhere
+    pub fn file(&self) -> Arc<File> {
+        self.file.clone()
+    }
+
+    /// Appends `frames` to the file, after any commands an earlier failed write
+    /// left behind
+    ///
+    /// The file isn't fsynced, which is up to the caller. If writing fails, the
+    /// frames are kept for [`Aof::write_pending`] to try again.
+    pub fn append(&mut self, frames: &[FrameValue]) -> io::Result<()> {
+        for frame in frames {
+            Frame::new()
+                .encode(frame.clone(), &mut self.pending)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("{e:?}")))?;
+        }
+        self.write_pending()
+    }
+
+    /// Writes out the commands failed writes left behind, first cutting off
+    /// whatever part of them made it into the file
+    pub fn write_pending(&mut self) -> io::Result<()> {
+        if self.pending.is_empty() {
+            return Ok(());
+        }
+        if self.torn {
+            self.file.set_len(self.len)?;
+            self.torn = false;
+        }
+        if let Err(e) = (&*self.file).write_all(&self.pending) {
+            self.torn = true;
+            return Err(e);
+        }
+        self.len += self.pending.len() as u64;
+        self.pending.clear();
+        Ok(())
+    }
+
+    /// Replaces the contents of the file with commands recreating `databases`,
+    /// which are indexed by database number, and fsyncs it
+    ///
+    /// Each key is restored from its DUMP payload, followed by a PEXPIREAT if it
+    /// has an expiry.
+    pub fn rewrite<S: Storage>(&mut self, databases: &[impl Deref<Target = S>]) -> io::Result<()> {
+        let mut frames = Vec::new();
+        for (index, keyspace) in databases.iter().enumerate() {
+            let mut keys = keyspace.iter().peekable();
+            if keys.peek().is_none() {
+                continue;
+            }
+
+            frames.push(command_frame(&["SELECT", &index.to_string()]));
+            for (key, value, expires_at) in keys {
+                frames.push(FrameValue::Array(vec![
+                    FrameValue::BulkString("RESTORE".into()),
+                    FrameValue::BulkString(key.clone()),
+                    FrameValue::BulkString("0".into()),
+                    FrameValue::BulkString(rdb::encode(value)),
+                    FrameValue::BulkString("REPLACE".into()),
+                ]));
+                if let Some(at) = expires_at {
+                    frames.push(pexpireat_frame(key.clone(), at));
+                }
+            }
+        }
+
+        self.file.set_len(0)?;
+        self.len = 0;
+        self.pending.clear();
+        self.torn = false;
+        self.append(&frames)?;
+        self.file.sync_data()
+    }
+}
+
+/// Applies the commands in `contents` to `db`, returning how many bytes of it
+/// held complete commands
+fn replay(contents: &[u8], db: &Db) -> io::Result<usize> {
+    let bad_format = |what: String| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("bad file format reading the append only file: {what}"),
+        )
+    };
+
+    let mut db = db.clone();
+    let clients = ClientList::default();
+    let client = clients.register("aof".into(), Arc::default());
+    let mut buf = BytesMut::from(contents);
+    let mut codec = Frame::new();
+    while let Some(frame) = codec
+        .decode(&mut buf)
+        .map_err(|e| bad_format(format!("{e:?}")))?
+    {
+        match Command::from_frame(frame.clone()) {
+            Ok(Command::Select(cmd)) => {
+                cmd.apply(&mut db);
+            }
+            Ok(cmd) => {
+                cmd.apply_write(&db, &client, frame);
+            }
+            Err(e) => return Err(bad_format(format!("{:?}", e.into_frame()))),
+        }
+    }
+    Ok(contents.len() - buf.len())
+}
+
+#[cfg(test)]
+mod aof_tests {
+    use super::*;
+    use crate::cmd::run;
+    use std::path::PathBuf;
+
+    /// Path in the temporary directory unique to this process and `name`
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("mini-redis-{}-{name}.aof", std::process::id()))
+    }
+
+    #[test]
+    fn test_truncated_command_is_dropped() {
+        let path = temp_path("truncated");
+        let complete = "*3\r\n$3\r\nSET\r\n$3\r\nkey\r\n$5\r\nvalue\r\n";
+        fs::write(&path, format!("{complete}*3\r\n$3\r\nSET\r\n$3\r\nkey")).unwrap();
+
+        let db = Db::new();
+        Aof::open(&path, AppendFsync::No, &db).unwrap();
+        assert_eq!(
+            run(&db, &["GET", "key"]),
+            FrameValue::BulkString("value".into())
+        );
+        assert_eq!(fs::read(&path).unwrap(), complete.as_bytes());
+
+        fs::write(&path, "+OK\r\n").unwrap();
+        assert!(Aof::open(&path, AppendFsync::No, &Db::new()).is_err());
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_failed_writes_are_kept_and_refuse_writes() {
+        let full = || OpenOptions::new().append(true).open("/dev/full").unwrap();
+        let frame = command_frame(&["SET", "key", "value"]);
+        let mut aof = Aof {
+            file: Arc::new(full()),
+            fsync: AppendFsync::No,
+            len: 0,
+            pending: BytesMut::new(),
+            torn: false,
+        };
+        assert!(aof.append(std::slice::from_ref(&frame)).is_err());
+
+        // Space being freed, the command goes out on the next try
+        let path = temp_path("full");
+        aof.file = Arc::new(File::create(&path).unwrap());
+        aof.write_pending().unwrap();
+        let mut encoded = BytesMut::new();
+        Frame::new().encode(frame.clone(), &mut encoded).unwrap();
+        assert_eq!(fs::read(&path).unwrap(), encoded);
+        fs::remove_file(&path).unwrap();
+
+        aof.file = Arc::new(full());
+        let db = Db::new();
+        db.replication().enable_aof(aof);
+        let reply = db.replication().execute(0, || {
+            (FrameValue::SimpleString("OK".into()), Some(frame.clone()))
+        });
+        assert!(matches!(&reply, FrameValue::Error(e) if e.starts_with(b"MISCONF")));
+        assert_eq!(db.replication().aof_error(), Some(reply));
+    }
+
+    #[test]
+    fn test_rewrite_recreates_databases() {
+        let path = temp_path("rewrite");
+        let _ = fs::remove_file(&path);
+        let db = Db::new();
+        run(&db, &["SET", "key", "value"]);
+        run(&db, &["EXPIRE", "key", "100"]);
+        run(&db.select(2).unwrap(), &["HSET", "hash", "field", "value"]);
+
+        let mut aof = Aof::open(&path, AppendFsync::No, &Db::new()).unwrap();
+        aof.rewrite(&db.read_all()).unwrap();
+
+        let restored = Db::new();
+        Aof::open(&path, AppendFsync::No, &restored).unwrap();
+        assert_eq!(
+            run(&restored, &["GET", "key"]),
+            FrameValue::BulkString("value".into())
+        );
+        assert!(matches!(
+            run(&restored, &["TTL", "key"]),
+            FrameValue::Integer(99 | 100)
+        ));
+        assert_eq!(
+            run(&restored.select(2).unwrap(), &["HLEN", "hash"]),
+            FrameValue::Integer(1)
+        );
+        fs::remove_file(&path).unwrap();
+    }
+}
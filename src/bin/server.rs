@@ -1,9 +1,20 @@
-use mini_redis::{DEFAULT_PORT, server};
-use tokio::net::TcpListener;
+use mini_redis::{
+    DEFAULT_PORT,
+    config::Config,
+    log::{self, Logger},
+    server,
+};
+use std::net::{Ipv4Addr, SocketAddr};
+use tokio::signal;
 
 #[tokio::main]
 async fn main() -> std::io::Result<()> {
-    let listener = TcpListener::bind(format!("127.0.0.1:{}", DEFAULT_PORT)).await?;
-    server::run(listener).await;
-    Ok(())
+    let config = Config::default();
+    config.validate()?;
+    let _ = log::init(Logger::open(&config)?);
+    let listener = server::bind(
+        SocketAddr::from((Ipv4Addr::LOCALHOST, DEFAULT_PORT)),
+        &config,
+    )?;
+    server::run(listener, config, signal::ctrl_c()).await
 }
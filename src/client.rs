@@ -0,0 +1,222 @@
+use crate::connection::{ConnectionStats, StatsSnapshot};
+use std::{
+    collections::BTreeMap,
+    fmt::Write,
+    sync::{Arc, Mutex, PoisonError},
+    time::Instant,
+};
+use tokio::sync::Notify;
+
+/// Registry of connected clients, backing `CLIENT LIST`
+#[derive(Clone, Default)]
+pub struct ClientList {
+    shared: Arc<Mutex<Registry>>,
+    pause: Arc<Pause>,
+}
+
+/// Commands held back by `CLIENT PAUSE`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PauseMode {
+    /// Only write commands wait
+    Write,
+    All,
+}
+
+/// Pause in effect across every connection, if any
+#[derive(Default)]
+struct Pause {
+    until: Mutex<Option<(Instant, PauseMode)>>,
+    /// Woken when the pause is lifted or changed
+    changed: Notify,
+}
+
+#[derive(Default)]
+struct Registry {
+    next_id: u64,
+    clients: BTreeMap<u64, ClientEntry>,
+    /// Traffic of the connections that have closed
+    retired: StatsSnapshot,
+}
+
+struct ClientEntry {
+    addr: String,
+    created: Instant,
+    stats: Arc<ConnectionStats>,
+    /// User the connection authenticated as, `None` until it has
+    user: Option<String>,
+    /// RESP version negotiated with HELLO
+    protocol: u8,
+}
+
+/// Context of the connection a command is running on
+pub struct Client {
+    id: u64,
+    clients: ClientList,
+}
+
+impl ClientList {
+    /// Registers a new connection, handing back its context
+    pub fn register(&self, addr: String, stats: Arc<ConnectionStats>) -> Client {
+        let mut registry = self.shared.lock().unwrap();
+        registry.next_id += 1;
+        let id = registry.next_id;
+        registry.clients.insert(
+            id,
+            ClientEntry {
+                addr,
+                created: Instant::now(),
+                stats,
+                user: None,
+                protocol: 2,
+            },
+        );
+
+        Client {
+            id,
+            clients: self.clone(),
+        }
+    }
+
+    /// Whether client `id` is connected
+    pub fn contains(&self, id: u64) -> bool {
+        self.shared.lock().unwrap().clients.contains_key(&id)
+    }
+
+    /// Forgets client `id`, folding its traffic into the totals
+    ///
+    /// Connections call this while unwinding from a panic too, so a poisoned
+    /// registry is used as it is rather than panicking a second time.
+    pub fn remove(&self, id: u64) {
+        let mut registry = self.shared.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Some(entry) = registry.clients.remove(&id) {
+            registry.retired = registry.retired + entry.stats.snapshot();
+        }
+    }
+
+    /// Commands and raw bytes of every connection so far, closed ones included,
+    /// as `INFO stats` reports them
+    pub fn totals(&self) -> StatsSnapshot {
+        let registry = self.shared.lock().unwrap();
+        registry
+            .clients
+            .values()
+            .map(|entry| entry.stats.snapshot())
+            .fold(registry.retired, |total, stats| total + stats)
+    }
+
+    /// Holds back commands of every connection until `until`
+    ///
+    /// Like in Redis, a pause already in effect is only ever extended, and a pause
+    /// of all commands isn't narrowed down to writes.
+    pub fn pause(&self, until: Instant, mode: PauseMode) {
+        let mut pause = self.pause.until.lock().unwrap();
+        *pause = match *pause {
+            Some((current, current_mode)) if current > Instant::now() => Some((
+                current.max(until),
+                if current_mode == PauseMode::All {
+                    PauseMode::All
+                } else {
+                    mode
+                },
+            )),
+            _ => Some((until, mode)),
+        };
+        self.pause.changed.notify_waiters();
+    }
+
+    /// Lifts the pause, if there is one, letting waiting commands run
+    pub fn unpause(&self) {
+        self.pause.until.lock().unwrap().take();
+        self.pause.changed.notify_waiters();
+    }
+
+    /// Waits for as long as a pause holds back a command, `write` or not
+    pub async fn wait_unpaused(&self, write: bool) {
+        loop {
+            // Registered before checking so a change in between isn't missed
+            let changed = self.pause.changed.notified();
+            let until = match *self.pause.until.lock().unwrap() {
+                Some((until, mode)) if write || mode == PauseMode::All => until,
+                _ => return,
+            };
+            if until <= Instant::now() {
+                return;
+            }
+            tokio::select! {
+                _ = tokio::time::sleep_until(until.into()) => {}
+                _ = changed => {}
+            }
+        }
+    }
+}
+
+impl Client {
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// User the connection is authenticated as, if it is
+    pub fn user(&self) -> Option<String> {
+        self.with_entry(|entry| entry.user.clone()).flatten()
+    }
+
+    pub fn set_user(&self, user: &str) {
+        self.with_entry(|entry| entry.user = Some(user.to_string()));
+    }
+
+    pub fn protocol(&self) -> u8 {
+        self.with_entry(|entry| entry.protocol).unwrap_or(2)
+    }
+
+    pub fn set_protocol(&self, protocol: u8) {
+        self.with_entry(|entry| entry.protocol = protocol);
+    }
+
+    /// Every connected client, this one included
+    pub fn clients(&self) -> &ClientList {
+        &self.clients
+    }
+
+    fn with_entry<T>(&self, f: impl FnOnce(&mut ClientEntry) -> T) -> Option<T> {
+        let mut registry = self.clients.shared.lock().unwrap();
+        registry.clients.get_mut(&self.id).map(f)
+    }
+
+    /// `CLIENT INFO` line describing this connection
+    pub fn info(&self) -> String {
+        let registry = self.clients.shared.lock().unwrap();
+        let mut info = String::new();
+        if let Some(entry) = registry.clients.get(&self.id) {
+            entry.write_line(self.id, &mut info);
+        }
+        info
+    }
+
+    /// `CLIENT LIST` lines describing every connection
+    pub fn list(&self) -> String {
+        let registry = self.clients.shared.lock().unwrap();
+        let mut list = String::new();
+        for (id, entry) in registry.clients.iter() {
+            entry.write_line(*id, &mut list);
+        }
+        list
+    }
+}
+
+impl ClientEntry {
+    fn write_line(&self, id: u64, dst: &mut String) {
+        let stats = self.stats.snapshot();
+        let _ = writeln!(
+            dst,
+            "id={} addr={} age={} tot-net-in={} tot-net-out={} tot-cmds={} user={} resp={}",
+            id,
+            self.addr,
+            self.created.elapsed().as_secs(),
+            stats.net_input,
+            stats.net_output,
+            stats.commands,
+            self.user.as_deref().unwrap_or(""),
+            self.protocol,
+        );
+    }
+}
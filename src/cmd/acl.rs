@@ -0,0 +1,114 @@
+use super::{CommandError, Parse, are_equal, help};
+use crate::{
+    acl::DEFAULT_USER,
+    client::Client,
+    db::{Db, Storage},
+    frame::FrameValue,
+};
+use bytes::Bytes;
+
+/// `ACL` subcommands
+pub enum AclSubcommand {
+    /// Name of the user the connection is authenticated as
+    WhoAmI,
+    /// Description of a user
+    GetUser(Bytes),
+    Help,
+}
+
+/// Usage of each subcommand, for `ACL HELP`
+const HELP: &[&str] = &[
+    "GETUSER <username>",
+    "    Get the user's details.",
+    "WHOAMI",
+    "    Return the current connection username.",
+];
+
+impl AclSubcommand {
+    pub fn parse_frames(parse: &mut Parse) -> Result<Self, CommandError> {
+        let subcommand = parse.next_bytes()?;
+
+        let cmd = match subcommand.as_ref() {
+            sub if are_equal(sub, b"WHOAMI") => Self::WhoAmI,
+            sub if are_equal(sub, b"GETUSER") => Self::GetUser(parse.next_bytes()?),
+            sub if are_equal(sub, b"HELP") => Self::Help,
+            _ => return Err(CommandError::UnknownSubcommand("ACL", subcommand)),
+        };
+
+        parse.finish()?;
+        Ok(cmd)
+    }
+
+    pub fn apply<S: Storage>(self, db: &Db<S>, client: &Client) -> FrameValue {
+        match self {
+            Self::Help => help("ACL", HELP),
+            Self::WhoAmI => FrameValue::BulkString(
+                client
+                    .user()
+                    .unwrap_or_else(|| DEFAULT_USER.to_string())
+                    .into(),
+            ),
+            Self::GetUser(user) => {
+                let Some(flags) = db.acl().flags(&user) else {
+                    return FrameValue::NullBulkString;
+                };
+                let bulk = |s: &'static str| FrameValue::BulkString(s.into());
+
+                // Passwords aren't listed, Redis shows their SHA-256 digests
+                FrameValue::Array(vec![
+                    bulk("flags"),
+                    FrameValue::Array(flags.into_iter().map(bulk).collect()),
+                    bulk("commands"),
+                    bulk("+@all"),
+                    bulk("keys"),
+                    bulk("~*"),
+                    bulk("channels"),
+                    bulk("&*"),
+                ])
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod acl_tests {
+    use crate::{acl::Acl, cmd::run, db::Db, frame::FrameValue};
+
+    #[test]
+    fn test_whoami() {
+        assert_eq!(
+            run(&Db::new(), &["ACL", "WHOAMI"]),
+            FrameValue::BulkString("default".into())
+        );
+    }
+
+    #[test]
+    fn test_getuser_flags() {
+        let flags = |db: &Db| {
+            let FrameValue::Array(fields) = run(db, &["ACL", "GETUSER", "default"]) else {
+                panic!("expected an array");
+            };
+            fields
+                .chunks(2)
+                .find(|pair| pair[0] == FrameValue::BulkString("flags".into()))
+                .map(|pair| pair[1].clone())
+        };
+
+        assert_eq!(
+            flags(&Db::new()),
+            Some(FrameValue::Array(vec![
+                FrameValue::BulkString("on".into()),
+                FrameValue::BulkString("nopass".into()),
+            ]))
+        );
+        let db = Db::new().with_acl(Acl::new(Some("secret".into())));
+        assert_eq!(
+            flags(&db),
+            Some(FrameValue::Array(vec![FrameValue::BulkString("on".into())]))
+        );
+        assert_eq!(
+            run(&db, &["ACL", "GETUSER", "nobody"]),
+            FrameValue::NullBulkString
+        );
+    }
+}
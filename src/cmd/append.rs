@@ -0,0 +1,44 @@
+use super::{CommandError, Parse, string_too_long, wrong_type};
+use crate::{
+    db::{Db, DbValue, Storage},
+    frame::FrameValue,
+};
+use bytes::{Bytes, BytesMut};
+
+/// Appends to the string value of a key, creating it if missing
+pub struct Append {
+    key: Bytes,
+    value: Bytes,
+}
+
+impl Append {
+    pub fn parse_frames(parse: &mut Parse) -> Result<Self, CommandError> {
+        let key = parse.next_bytes()?;
+        let value = parse.next_bytes()?;
+        parse.finish()?;
+        Ok(Self { key, value })
+    }
+
+    pub fn apply<S: Storage>(self, db: &Db<S>) -> FrameValue {
+        let mut entries = db.lock();
+        let len = match entries.peek(&self.key) {
+            Some(DbValue::String(bytes)) => bytes.len(),
+            _ => 0,
+        };
+        if len + self.value.len() > db.max_value_len() {
+            return string_too_long();
+        }
+
+        let current = match entries.get_or_insert_with(self.key, || DbValue::String(Bytes::new())) {
+            DbValue::String(bytes) => bytes,
+            _ => return wrong_type(),
+        };
+
+        let mut appended = BytesMut::with_capacity(current.len() + self.value.len());
+        appended.extend_from_slice(current);
+        appended.extend_from_slice(&self.value);
+        *current = appended.freeze();
+
+        FrameValue::Integer(current.len() as i64)
+    }
+}
@@ -0,0 +1,109 @@
+use super::{CommandError, Parse};
+use crate::{
+    acl::DEFAULT_USER,
+    client::Client,
+    db::{Db, Storage},
+    frame::FrameValue,
+};
+use bytes::Bytes;
+
+/// Authenticates the connection as a user, the default one if none is named
+pub struct Auth {
+    user: Option<Bytes>,
+    password: Bytes,
+}
+
+impl Auth {
+    pub fn parse_frames(parse: &mut Parse) -> Result<Self, CommandError> {
+        let first = parse.next_bytes()?;
+        let cmd = match parse.next_optional_bytes()? {
+            Some(password) => Self {
+                user: Some(first),
+                password,
+            },
+            None => Self {
+                user: None,
+                password: first,
+            },
+        };
+        parse.finish()?;
+        Ok(cmd)
+    }
+
+    pub fn apply<S: Storage>(self, db: &Db<S>, client: &Client) -> FrameValue {
+        if self.user.is_none() && !db.acl().requires_auth() {
+            return FrameValue::Error(
+                "ERR AUTH <password> called without any password configured for the default user. Are you sure your configuration is correct?"
+                    .into(),
+            );
+        }
+
+        let user = self.user.unwrap_or_else(|| DEFAULT_USER.into());
+        match authenticate(db, client, &user, &self.password) {
+            Ok(()) => FrameValue::SimpleString("OK".into()),
+            Err(e) => e,
+        }
+    }
+}
+
+/// Checks the credentials and authenticates `client` as `user` if they are valid,
+/// otherwise leaves the connection as it was
+pub fn authenticate<S: Storage>(
+    db: &Db<S>,
+    client: &Client,
+    user: &[u8],
+    password: &[u8],
+) -> Result<(), FrameValue> {
+    if !db.acl().authenticate(user, password) {
+        return Err(FrameValue::Error(
+            "WRONGPASS invalid username-password pair or user is disabled.".into(),
+        ));
+    }
+    client.set_user(&String::from_utf8_lossy(user));
+    Ok(())
+}
+
+#[cfg(test)]
+mod auth_tests {
+    use crate::{acl::Acl, cmd::run, db::Db, frame::FrameValue};
+
+    #[test]
+    fn test_auth() {
+        let db = Db::new().with_acl(Acl::new(Some("secret".into())));
+
+        assert_eq!(
+            run(&db, &["AUTH", "secret"]),
+            FrameValue::SimpleString("OK".into())
+        );
+        assert_eq!(
+            run(&db, &["AUTH", "default", "secret"]),
+            FrameValue::SimpleString("OK".into())
+        );
+        assert_eq!(
+            run(&db, &["AUTH", "wrong"]),
+            FrameValue::Error(
+                "WRONGPASS invalid username-password pair or user is disabled.".into()
+            )
+        );
+        assert_eq!(
+            run(&db, &["AUTH", "someone", "secret"]),
+            FrameValue::Error(
+                "WRONGPASS invalid username-password pair or user is disabled.".into()
+            )
+        );
+    }
+
+    #[test]
+    fn test_auth_without_password_configured() {
+        let db = Db::new();
+
+        assert!(matches!(
+            run(&db, &["AUTH", "anything"]),
+            FrameValue::Error(msg) if msg.starts_with(b"ERR AUTH <password> called without")
+        ));
+        assert_eq!(
+            run(&db, &["AUTH", "default", "anything"]),
+            FrameValue::SimpleString("OK".into())
+        );
+    }
+}
@@ -0,0 +1,215 @@
+use super::{CommandError, Parse, are_equal, wrong_type};
+use crate::{
+    db::{Db, DbValue, Storage},
+    frame::FrameValue,
+};
+use bytes::Bytes;
+
+/// Counts the set bits of a string, optionally within a range
+pub struct BitCount {
+    key: Bytes,
+    range: Option<(i64, i64, ByteOrBit)>,
+}
+
+/// Unit the range of a bit command is expressed in
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ByteOrBit {
+    Byte,
+    Bit,
+}
+
+impl ByteOrBit {
+    pub fn parse(bytes: &[u8]) -> Result<Self, CommandError> {
+        if are_equal(bytes, b"BYTE") {
+            Ok(Self::Byte)
+        } else if are_equal(bytes, b"BIT") {
+            Ok(Self::Bit)
+        } else {
+            Err(CommandError::Syntax)
+        }
+    }
+}
+
+impl BitCount {
+    pub fn parse_frames(parse: &mut Parse) -> Result<Self, CommandError> {
+        let key = parse.next_bytes()?;
+
+        let range = match parse.remaining() {
+            0 => None,
+            // Redis 7 requires `end` whenever `start` is given
+            1 => return Err(CommandError::Syntax),
+            _ => {
+                let start = parse.next_int()?;
+                let end = parse.next_int()?;
+                let unit = match parse.next_optional_bytes()? {
+                    Some(unit) => ByteOrBit::parse(&unit)?,
+                    None => ByteOrBit::Byte,
+                };
+                Some((start, end, unit))
+            }
+        };
+
+        if parse.remaining() > 0 {
+            return Err(CommandError::Syntax);
+        }
+
+        Ok(Self { key, range })
+    }
+
+    pub fn apply<S: Storage>(self, db: &Db<S>) -> FrameValue {
+        let entries = db.read_key(&self.key);
+        let bytes = match entries.get_shared(&self.key) {
+            Some(DbValue::String(bytes)) => bytes,
+            Some(_) => return wrong_type(),
+            None => return FrameValue::Integer(0),
+        };
+
+        let count = match self.range {
+            Some((start, end, unit)) => count_range(bytes, start, end, unit),
+            None => popcount(bytes),
+        };
+
+        FrameValue::Integer(count as i64)
+    }
+}
+
+/// Counts set bits within `start..=end` where both ends are in `unit`s
+fn count_range(bytes: &[u8], start: i64, end: i64, unit: ByteOrBit) -> u64 {
+    let len = match unit {
+        ByteOrBit::Byte => bytes.len(),
+        ByteOrBit::Bit => bytes.len() * 8,
+    };
+
+    let Some((start, end)) = normalize_range(start, end, len) else {
+        return 0;
+    };
+
+    match unit {
+        ByteOrBit::Byte => popcount(&bytes[start..=end]),
+        ByteOrBit::Bit => {
+            let (first, last) = (start / 8, end / 8);
+            let count = popcount(&bytes[first..=last]);
+
+            // Bits are numbered from the most significant bit of each byte,
+            // so mask off whatever lies before `start` and after `end`
+            let head = bytes[first] & !(0xFF >> (start % 8));
+            let tail = bytes[last] & 0xFFu8.checked_shr((end % 8 + 1) as u32).unwrap_or(0);
+
+            count - head.count_ones() as u64 - tail.count_ones() as u64
+        }
+    }
+}
+
+/// Resolves Redis style inclusive indices, where negative ones count from the end,
+/// against a length
+///
+/// Returns `None` if the range is empty.
+pub fn normalize_range(start: i64, end: i64, len: usize) -> Option<(usize, usize)> {
+    let len = len as i64;
+    let start = if start < 0 { len + start } else { start }.max(0);
+    let end = if end < 0 { len + end } else { end }.max(0).min(len - 1);
+
+    if len == 0 || start > end {
+        None
+    } else {
+        Some((start as usize, end as usize))
+    }
+}
+
+/// Counts set bits a word at a time, finishing off the remainder bytewise
+fn popcount(bytes: &[u8]) -> u64 {
+    let chunks = bytes.chunks_exact(8);
+    let remainder = chunks.remainder();
+
+    let words: u64 = chunks
+        .map(|chunk| u64::from_ne_bytes(chunk.try_into().unwrap()).count_ones() as u64)
+        .sum();
+    let rest: u64 = remainder.iter().map(|byte| byte.count_ones() as u64).sum();
+
+    words + rest
+}
+
+#[cfg(test)]
+mod bitcount_tests {
+    use super::*;
+    use crate::cmd::run;
+
+    fn db_with(key: &str, value: &str) -> Db {
+        let db = Db::new();
+        run(&db, &["SET", key, value]);
+        db
+    }
+
+    #[test]
+    fn test_popcount() {
+        assert_eq!(popcount(b""), 0);
+        assert_eq!(popcount(&[0xFF; 19]), 19 * 8);
+        assert_eq!(popcount(b"foobar"), 26);
+    }
+
+    #[test]
+    fn test_whole_string() {
+        let db = db_with("key", "foobar");
+
+        assert_eq!(run(&db, &["BITCOUNT", "key"]), FrameValue::Integer(26));
+        assert_eq!(run(&db, &["BITCOUNT", "missing"]), FrameValue::Integer(0));
+    }
+
+    #[test]
+    fn test_byte_range() {
+        let db = db_with("key", "foobar");
+
+        assert_eq!(
+            run(&db, &["BITCOUNT", "key", "0", "0"]),
+            FrameValue::Integer(4)
+        );
+        assert_eq!(
+            run(&db, &["BITCOUNT", "key", "1", "1"]),
+            FrameValue::Integer(6)
+        );
+        assert_eq!(
+            run(&db, &["BITCOUNT", "key", "1", "1", "BYTE"]),
+            FrameValue::Integer(6)
+        );
+        assert_eq!(
+            run(&db, &["BITCOUNT", "key", "-2", "-1"]),
+            FrameValue::Integer(7)
+        );
+        assert_eq!(
+            run(&db, &["BITCOUNT", "key", "4", "2"]),
+            FrameValue::Integer(0)
+        );
+    }
+
+    #[test]
+    fn test_bit_range() {
+        let db = db_with("key", "foobar");
+
+        assert_eq!(
+            run(&db, &["BITCOUNT", "key", "5", "30", "BIT"]),
+            FrameValue::Integer(17)
+        );
+        assert_eq!(
+            run(&db, &["BITCOUNT", "key", "0", "-1", "bit"]),
+            FrameValue::Integer(26)
+        );
+    }
+
+    #[test]
+    fn test_syntax_errors() {
+        let db = db_with("key", "foobar");
+
+        assert_eq!(
+            run(&db, &["BITCOUNT", "key", "0"]),
+            FrameValue::Error("ERR syntax error".into())
+        );
+        assert_eq!(
+            run(&db, &["BITCOUNT", "key", "0", "1", "WORD"]),
+            FrameValue::Error("ERR syntax error".into())
+        );
+        assert_eq!(
+            run(&db, &["BITCOUNT"]),
+            FrameValue::Error("ERR wrong number of arguments for 'bitcount' command".into())
+        );
+    }
+}
@@ -0,0 +1,197 @@
+use super::{
+    CommandError, Parse,
+    bitcount::{ByteOrBit, normalize_range},
+    wrong_type,
+};
+use crate::{
+    db::{Db, DbValue, Storage},
+    frame::FrameValue,
+};
+use bytes::Bytes;
+
+/// Finds the first set or clear bit of a string, optionally within a range
+pub struct BitPos {
+    key: Bytes,
+    bit: i64,
+    range: Option<(i64, Option<i64>, ByteOrBit)>,
+}
+
+impl BitPos {
+    pub fn parse_frames(parse: &mut Parse) -> Result<Self, CommandError> {
+        let key = parse.next_bytes()?;
+        let bit = parse.next_int()?;
+
+        let range = match parse.remaining() {
+            0 => None,
+            1 => Some((parse.next_int()?, None, ByteOrBit::Byte)),
+            _ => {
+                let start = parse.next_int()?;
+                let end = parse.next_int()?;
+                let unit = match parse.next_optional_bytes()? {
+                    Some(unit) => ByteOrBit::parse(&unit)?,
+                    None => ByteOrBit::Byte,
+                };
+                Some((start, Some(end), unit))
+            }
+        };
+
+        if parse.remaining() > 0 {
+            return Err(CommandError::Syntax);
+        }
+
+        Ok(Self { key, bit, range })
+    }
+
+    pub fn apply<S: Storage>(self, db: &Db<S>) -> FrameValue {
+        let bit = match self.bit {
+            0 => false,
+            1 => true,
+            _ => return FrameValue::Error("ERR The bit argument must be 1 or 0.".into()),
+        };
+
+        let entries = db.read_key(&self.key);
+        let bytes = match entries.get_shared(&self.key) {
+            Some(DbValue::String(bytes)) => bytes,
+            Some(_) => return wrong_type(),
+            // A missing key is an empty string, which is all clear bits
+            None => return FrameValue::Integer(if bit { -1 } else { 0 }),
+        };
+
+        let (start, end, unit) = self.range.unwrap_or((0, None, ByteOrBit::Byte));
+        let len = match unit {
+            ByteOrBit::Byte => bytes.len(),
+            ByteOrBit::Bit => bytes.len() * 8,
+        };
+        let Some((start, end_index)) = normalize_range(start, end.unwrap_or(-1), len) else {
+            return FrameValue::Integer(-1);
+        };
+        let (first, last) = match unit {
+            ByteOrBit::Byte => (start * 8, end_index * 8 + 7),
+            ByteOrBit::Bit => (start, end_index),
+        };
+
+        let pos = match find_bit(bytes, bit, first, last) {
+            Some(pos) => pos as i64,
+            // Without an explicit end the string counts as padded with clear bits,
+            // so the first one lies just past the range
+            None if !bit && end.is_none() => last as i64 + 1,
+            None => -1,
+        };
+        FrameValue::Integer(pos)
+    }
+}
+
+/// Position of the first bit equal to `bit` within bits `first..=last`
+///
+/// Bytes that can't hold it are skipped whole, bits are only checked one by one
+/// within the byte that does.
+fn find_bit(bytes: &[u8], bit: bool, first: usize, last: usize) -> Option<usize> {
+    let skip = if bit { 0x00 } else { 0xFF };
+
+    (first / 8..=last / 8)
+        .filter(|&index| bytes[index] != skip)
+        .find_map(|index| {
+            let from = first.max(index * 8);
+            let to = last.min(index * 8 + 7);
+            (from..=to).find(|pos| (bytes[index] >> (7 - pos % 8)) & 1 == bit as u8)
+        })
+}
+
+#[cfg(test)]
+mod bitpos_tests {
+    use super::*;
+    use crate::cmd::run;
+
+    fn db_with(key: &str, value: &[u8]) -> Db {
+        let db = Db::new();
+        db.lock().insert(
+            Bytes::copy_from_slice(key.as_bytes()),
+            DbValue::String(Bytes::copy_from_slice(value)),
+        );
+        db
+    }
+
+    #[test]
+    fn test_first_set_bit() {
+        let db = db_with("key", b"\x00\x0f\xff");
+
+        assert_eq!(run(&db, &["BITPOS", "key", "1"]), FrameValue::Integer(12));
+        assert_eq!(
+            run(&db, &["BITPOS", "key", "1", "2"]),
+            FrameValue::Integer(16)
+        );
+        assert_eq!(
+            run(&db, &["BITPOS", "key", "1", "-3", "0"]),
+            FrameValue::Integer(-1)
+        );
+        assert_eq!(
+            run(&db, &["BITPOS", "key", "1", "13", "-1", "BIT"]),
+            FrameValue::Integer(13)
+        );
+        assert_eq!(
+            run(&db, &["BITPOS", "key", "1", "0", "11", "bit"]),
+            FrameValue::Integer(-1)
+        );
+        assert_eq!(
+            run(&db, &["BITPOS", "missing", "1"]),
+            FrameValue::Integer(-1)
+        );
+    }
+
+    #[test]
+    fn test_first_clear_bit() {
+        let db = db_with("key", b"\xff\xf0\x00");
+
+        assert_eq!(run(&db, &["BITPOS", "key", "0"]), FrameValue::Integer(12));
+        assert_eq!(
+            run(&db, &["BITPOS", "key", "0", "2", "-1"]),
+            FrameValue::Integer(16)
+        );
+        assert_eq!(
+            run(&db, &["BITPOS", "key", "0", "3", "10", "BIT"]),
+            FrameValue::Integer(-1)
+        );
+        assert_eq!(
+            run(&db, &["BITPOS", "missing", "0"]),
+            FrameValue::Integer(0)
+        );
+    }
+
+    #[test]
+    fn test_clear_bit_past_end_of_string() {
+        let db = db_with("key", b"\xff\xff");
+
+        // The string counts as padded with clear bits unless an end is given
+        assert_eq!(run(&db, &["BITPOS", "key", "0"]), FrameValue::Integer(16));
+        assert_eq!(
+            run(&db, &["BITPOS", "key", "0", "1"]),
+            FrameValue::Integer(16)
+        );
+        assert_eq!(
+            run(&db, &["BITPOS", "key", "0", "0", "-1"]),
+            FrameValue::Integer(-1)
+        );
+        assert_eq!(
+            run(&db, &["BITPOS", "key", "0", "0", "-1", "BIT"]),
+            FrameValue::Integer(-1)
+        );
+    }
+
+    #[test]
+    fn test_errors() {
+        let db = db_with("key", b"\xff");
+
+        assert_eq!(
+            run(&db, &["BITPOS", "key", "2"]),
+            FrameValue::Error("ERR The bit argument must be 1 or 0.".into())
+        );
+        assert_eq!(
+            run(&db, &["BITPOS", "key", "1", "0", "1", "WORD"]),
+            FrameValue::Error("ERR syntax error".into())
+        );
+        assert_eq!(
+            run(&db, &["BITPOS", "key"]),
+            FrameValue::Error("ERR wrong number of arguments for 'bitpos' command".into())
+        );
+    }
+}
@@ -0,0 +1,199 @@
+use super::{CommandError, Parse, are_equal, help};
+use crate::{
+    client::{Client, PauseMode},
+    frame::FrameValue,
+    pubsub::Subscriber,
+};
+use std::time::{Duration, Instant};
+
+/// `CLIENT` subcommands
+pub enum ClientSubcommand {
+    Info,
+    List,
+    /// Holds back commands of every connection for a while, the connection
+    /// loop waiting it out before running each command
+    Pause(Duration, PauseMode),
+    Unpause,
+    /// Turns invalidation messages for the keys the connection reads on or off,
+    /// which the connection loop handles as it owns the subscription state
+    ///
+    /// With `REDIRECT`, they go to another client's subscription instead, which
+    /// is how RESP2 connections, that can't take pushes, get them.
+    Tracking {
+        on: bool,
+        redirect: Option<u64>,
+    },
+    /// Accepted for compatibility, eviction of clients is not implemented
+    NoEvict,
+    Help,
+}
+
+/// Usage of each subcommand, for `CLIENT HELP`
+const HELP: &[&str] = &[
+    "INFO",
+    "    Return information about the current client connection.",
+    "LIST",
+    "    Return information about client connections.",
+    "NO-EVICT (ON|OFF)",
+    "    Protect current client connection from eviction.",
+    "PAUSE <timeout> [WRITE|ALL]",
+    "    Suspend all, or just write, clients for <timeout> milliseconds.",
+    "TRACKING (ON|OFF) [REDIRECT <id>]",
+    "    Control server assisted client side caching.",
+    "UNPAUSE",
+    "    Stop the current client pause, resuming traffic.",
+];
+
+impl ClientSubcommand {
+    pub fn parse_frames(parse: &mut Parse) -> Result<Self, CommandError> {
+        let subcommand = parse.next_bytes()?;
+
+        let cmd = match subcommand.as_ref() {
+            sub if are_equal(sub, b"INFO") => Self::Info,
+            sub if are_equal(sub, b"LIST") => Self::List,
+            sub if are_equal(sub, b"PAUSE") => {
+                let timeout = parse.next_int()?;
+                let timeout = u64::try_from(timeout).map_err(|_| CommandError::NotInteger)?;
+                let mode = match parse.next_optional_bytes()? {
+                    None => PauseMode::All,
+                    Some(mode) if are_equal(&mode, b"ALL") => PauseMode::All,
+                    Some(mode) if are_equal(&mode, b"WRITE") => PauseMode::Write,
+                    Some(_) => return Err(CommandError::Syntax),
+                };
+                Self::Pause(Duration::from_millis(timeout), mode)
+            }
+            sub if are_equal(sub, b"UNPAUSE") => Self::Unpause,
+            sub if are_equal(sub, b"HELP") => Self::Help,
+            sub if are_equal(sub, b"TRACKING") => {
+                let toggle = parse.next_bytes()?;
+                let on = if are_equal(&toggle, b"ON") {
+                    true
+                } else if are_equal(&toggle, b"OFF") {
+                    false
+                } else {
+                    return Err(CommandError::Syntax);
+                };
+                let redirect = match parse.next_optional_bytes()? {
+                    Some(option) if are_equal(&option, b"REDIRECT") => {
+                        let id = parse.next_int()?;
+                        Some(u64::try_from(id).map_err(|_| CommandError::NotInteger)?)
+                    }
+                    Some(_) => return Err(CommandError::Syntax),
+                    None => None,
+                };
+                Self::Tracking { on, redirect }
+            }
+            sub if are_equal(sub, b"NO-EVICT") => {
+                let toggle = parse.next_bytes()?;
+                if !are_equal(&toggle, b"ON") && !are_equal(&toggle, b"OFF") {
+                    return Err(CommandError::Syntax);
+                }
+                Self::NoEvict
+            }
+            _ => return Err(CommandError::UnknownSubcommand("CLIENT", subcommand)),
+        };
+
+        parse.finish()?;
+        Ok(cmd)
+    }
+
+    pub fn apply(self, client: &Client) -> FrameValue {
+        match self {
+            Self::Info => FrameValue::BulkString(client.info().into()),
+            Self::List => FrameValue::BulkString(client.list().into()),
+            Self::Pause(timeout, mode) => {
+                client.clients().pause(Instant::now() + timeout, mode);
+                FrameValue::SimpleString("OK".into())
+            }
+            Self::Unpause => {
+                client.clients().unpause();
+                FrameValue::SimpleString("OK".into())
+            }
+            Self::Tracking { .. } => {
+                unreachable!("tracking is managed by the connection loop")
+            }
+            Self::NoEvict => FrameValue::SimpleString("OK".into()),
+            Self::Help => help("CLIENT", HELP),
+        }
+    }
+
+    pub fn is_tracking(&self) -> bool {
+        matches!(self, Self::Tracking { .. })
+    }
+
+    /// Turns tracking on or off for `client`
+    ///
+    /// RESP2 has no pushes to deliver invalidations in between replies, so a
+    /// RESP2 connection must redirect them, as in Redis.
+    pub fn apply_tracking(self, subscriber: &mut Subscriber, client: &Client) -> FrameValue {
+        let Self::Tracking { on, redirect } = self else {
+            unreachable!("only CLIENT TRACKING changes tracking");
+        };
+        if on {
+            match redirect {
+                None if client.protocol() < 3 => {
+                    return FrameValue::Error(
+                        "ERR CLIENT TRACKING ON needs REDIRECT with RESP2, or HELLO 3 first".into(),
+                    );
+                }
+                Some(id) if !client.clients().contains(id) => {
+                    return FrameValue::Error(
+                        "ERR The client ID you want redirect to does not exist".into(),
+                    );
+                }
+                _ => {}
+            }
+        }
+        subscriber.set_tracking(on, redirect);
+        FrameValue::SimpleString("OK".into())
+    }
+}
+
+#[cfg(test)]
+mod client_tests {
+    use crate::{cmd::run, db::Db, frame::FrameValue};
+
+    #[test]
+    fn test_no_evict() {
+        let db = Db::new();
+
+        assert_eq!(
+            run(&db, &["CLIENT", "NO-EVICT", "on"]),
+            FrameValue::SimpleString("OK".into())
+        );
+        assert_eq!(
+            run(&db, &["CLIENT", "no-evict", "OFF"]),
+            FrameValue::SimpleString("OK".into())
+        );
+        assert_eq!(
+            run(&db, &["CLIENT", "NO-EVICT", "maybe"]),
+            FrameValue::Error("ERR syntax error".into())
+        );
+    }
+
+    #[test]
+    fn test_pause_arguments() {
+        let db = Db::new();
+
+        assert_eq!(
+            run(&db, &["CLIENT", "PAUSE", "0", "write"]),
+            FrameValue::SimpleString("OK".into())
+        );
+        assert_eq!(
+            run(&db, &["CLIENT", "PAUSE", "-1"]),
+            FrameValue::Error("ERR value is not an integer or out of range".into())
+        );
+        assert_eq!(
+            run(&db, &["CLIENT", "PAUSE", "10", "READS"]),
+            FrameValue::Error("ERR syntax error".into())
+        );
+    }
+
+    #[test]
+    fn test_unknown_subcommand() {
+        assert_eq!(
+            run(&Db::new(), &["CLIENT", "FOO"]),
+            FrameValue::Error("ERR unknown subcommand 'FOO'. Try CLIENT HELP.".into())
+        );
+    }
+}
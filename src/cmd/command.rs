@@ -0,0 +1,213 @@
+use super::{
+    CommandError, Parse, are_equal, help,
+    table::{COMMAND_TABLE, CommandSpec, lookup},
+};
+use crate::frame::FrameValue;
+use bytes::Bytes;
+
+/// `COMMAND` introspection subcommands
+pub enum CommandSubcommand {
+    /// Describes the named commands, or every command if none are named
+    Info(Vec<Bytes>),
+    /// Extracts the keys from a full command line
+    GetKeys(Vec<Bytes>),
+    Help,
+}
+
+/// Usage of each subcommand, for `COMMAND HELP`
+const HELP: &[&str] = &[
+    "(no subcommand)",
+    "    Return details about all Redis commands.",
+    "GETKEYS <full-command>",
+    "    Return the keys from a full Redis command.",
+    "INFO [<command-name> ...]",
+    "    Return details about multiple Redis commands.",
+    "    If no command names are given, documentation details for all",
+    "    commands are returned.",
+];
+
+impl CommandSubcommand {
+    pub fn parse_frames(parse: &mut Parse) -> Result<Self, CommandError> {
+        let Some(subcommand) = parse.next_optional_bytes()? else {
+            return Ok(Self::Info(vec![]));
+        };
+
+        match subcommand.as_ref() {
+            sub if are_equal(sub, b"INFO") => Ok(Self::Info(parse.rest_bytes()?)),
+            sub if are_equal(sub, b"GETKEYS") => {
+                let args = parse.rest_bytes()?;
+                if args.is_empty() {
+                    return Err(CommandError::ArgumentCount);
+                }
+                Ok(Self::GetKeys(args))
+            }
+            sub if are_equal(sub, b"HELP") => Ok(Self::Help),
+            _ => Err(CommandError::UnknownSubcommand("COMMAND", subcommand)),
+        }
+    }
+
+    pub fn apply(self) -> FrameValue {
+        match self {
+            Self::Info(names) if names.is_empty() => {
+                FrameValue::Array(COMMAND_TABLE.iter().map(info).collect())
+            }
+            Self::Info(names) => FrameValue::Array(
+                names
+                    .iter()
+                    .map(|name| lookup(name).map_or(FrameValue::NullBulkArray, info))
+                    .collect(),
+            ),
+            Self::GetKeys(args) => get_keys(&args),
+            Self::Help => help("COMMAND", HELP),
+        }
+    }
+}
+
+/// Keys of the command line `args` according to its first key, last key and step
+fn get_keys(args: &[Bytes]) -> FrameValue {
+    let Some(spec) = lookup(&args[0]) else {
+        return FrameValue::Error("ERR Invalid command specified".into());
+    };
+    if !spec.accepts_argc(args.len()) {
+        return FrameValue::Error("ERR Invalid number of arguments specified for command".into());
+    }
+
+    let args: Vec<_> = args.iter().cloned().map(FrameValue::BulkString).collect();
+    let keys: Vec<_> = spec
+        .key_positions(&args)
+        .map(|position| args[position].clone())
+        .collect();
+
+    if keys.is_empty() {
+        FrameValue::Error("ERR The command has no key arguments".into())
+    } else {
+        FrameValue::Array(keys)
+    }
+}
+
+/// `[name, arity, flags, first_key, last_key, step]`
+fn info(spec: &CommandSpec) -> FrameValue {
+    FrameValue::Array(vec![
+        FrameValue::BulkString(spec.name.into()),
+        FrameValue::Integer(spec.arity),
+        FrameValue::Array(
+            spec.flags
+                .iter()
+                .map(|flag| FrameValue::SimpleString((*flag).into()))
+                .collect(),
+        ),
+        FrameValue::Integer(spec.first_key),
+        FrameValue::Integer(spec.last_key),
+        FrameValue::Integer(spec.step),
+    ])
+}
+
+#[cfg(test)]
+mod command_tests {
+    use crate::{cmd::run, db::Db, frame::FrameValue};
+
+    #[test]
+    fn test_info_reports_arity() {
+        let FrameValue::Array(infos) = run(&Db::new(), &["COMMAND", "INFO", "set", "nosuch"])
+        else {
+            panic!("expected an array");
+        };
+
+        assert_eq!(
+            infos[0],
+            FrameValue::Array(vec![
+                FrameValue::BulkString("set".into()),
+                FrameValue::Integer(-3),
+                FrameValue::Array(vec![FrameValue::SimpleString("write".into())]),
+                FrameValue::Integer(1),
+                FrameValue::Integer(1),
+                FrameValue::Integer(1),
+            ])
+        );
+        assert_eq!(infos[1], FrameValue::NullBulkArray);
+
+        // Keys counted by `numkeys` have no fixed range
+        let FrameValue::Array(infos) = run(&Db::new(), &["COMMAND", "INFO", "sintercard"]) else {
+            panic!("expected an array");
+        };
+        let FrameValue::Array(info) = &infos[0] else {
+            panic!("expected an array");
+        };
+        assert_eq!(
+            info[2..],
+            [
+                FrameValue::Array(vec![
+                    FrameValue::SimpleString("readonly".into()),
+                    FrameValue::SimpleString("movablekeys".into()),
+                ]),
+                FrameValue::Integer(0),
+                FrameValue::Integer(0),
+                FrameValue::Integer(0),
+            ]
+        );
+    }
+
+    fn keys(keys: &[&str]) -> FrameValue {
+        FrameValue::Array(
+            keys.iter()
+                .map(|key| FrameValue::BulkString(key.to_string().into()))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn test_getkeys() {
+        let db = Db::new();
+
+        assert_eq!(
+            run(&db, &["COMMAND", "GETKEYS", "SET", "key", "value"]),
+            keys(&["key"])
+        );
+        assert_eq!(
+            run(&db, &["COMMAND", "GETKEYS", "get", "key"]),
+            keys(&["key"])
+        );
+        assert_eq!(
+            run(&db, &["COMMAND", "GETKEYS", "MSET", "a", "1", "b", "2"]),
+            keys(&["a", "b"])
+        );
+        assert_eq!(
+            run(&db, &["COMMAND", "GETKEYS", "DEL", "a", "b", "c"]),
+            keys(&["a", "b", "c"])
+        );
+        assert_eq!(
+            run(
+                &db,
+                &[
+                    "COMMAND",
+                    "GETKEYS",
+                    "SINTERCARD",
+                    "2",
+                    "a",
+                    "b",
+                    "LIMIT",
+                    "1"
+                ]
+            ),
+            keys(&["a", "b"])
+        );
+    }
+
+    #[test]
+    fn test_getkeys_errors() {
+        let db = Db::new();
+
+        assert_eq!(
+            run(&db, &["COMMAND", "GETKEYS", "NOSUCH", "key"]),
+            FrameValue::Error("ERR Invalid command specified".into())
+        );
+        assert_eq!(
+            run(&db, &["COMMAND", "GETKEYS", "GET", "a", "b"]),
+            FrameValue::Error("ERR Invalid number of arguments specified for command".into())
+        );
+        assert_eq!(
+            run(&db, &["COMMAND", "GETKEYS", "PING"]),
+            FrameValue::Error("ERR The command has no key arguments".into())
+        );
+    }
+}
@@ -0,0 +1,22 @@
+use super::{CommandError, Parse};
+use crate::{
+    db::{Db, Storage},
+    frame::FrameValue,
+};
+
+/// Counts the keys of the selected database
+///
+/// Expired keys count until they are removed, either on access or by the
+/// background sweep.
+pub struct DbSize;
+
+impl DbSize {
+    pub fn parse_frames(parse: &mut Parse) -> Result<Self, CommandError> {
+        parse.finish()?;
+        Ok(Self)
+    }
+
+    pub fn apply<S: Storage>(self, db: &Db<S>) -> FrameValue {
+        FrameValue::Integer(db.read().len() as i64)
+    }
+}
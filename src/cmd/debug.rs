@@ -0,0 +1,260 @@
+use super::{
+    CommandError, Parse, are_equal, glob, help,
+    object::{encoding, refcount},
+};
+use crate::{
+    db::{Db, Storage},
+    frame::FrameValue,
+    rdb,
+};
+use bytes::Bytes;
+use std::{str::from_utf8, time::Duration};
+
+/// `DEBUG` subcommands, hooks for tests to observe and steer the server
+pub enum DebugSubcommand {
+    /// Turns the background sweep of expired keys on or off
+    SetActiveExpire(bool),
+    /// Internals of a key, including whether it expired without being removed
+    Object(Bytes),
+    /// Waits before replying, a stand-in for a slow command
+    Sleep(Duration),
+    /// Snapshots every database, empties them and loads the snapshot back
+    Reload,
+    /// Runs the glob matcher on a pattern and a string, to check it against
+    /// cases from the Redis matcher
+    StringMatchLen {
+        pattern: Bytes,
+        string: Bytes,
+    },
+    Help,
+}
+
+/// Usage of each subcommand, for `DEBUG HELP`
+const HELP: &[&str] = &[
+    "OBJECT <key>",
+    "    Show low level info about the <key> and associated value.",
+    "RELOAD",
+    "    Snapshot every database in memory, empty them and load the snapshot back.",
+    "SET-ACTIVE-EXPIRE <0|1>",
+    "    Setting it to 0 disables expiring keys in background when they are not",
+    "    accessed (otherwise the Redis behavior). Setting it to 1 reenables back the",
+    "    default.",
+    "SLEEP <seconds>",
+    "    Stop the server for <seconds>. Decimals allowed.",
+    "STRINGMATCH-LEN <pattern> <string>",
+    "    Match <string> against the glob <pattern>, replying 1 if it matches and 0",
+    "    if it does not.",
+];
+
+impl DebugSubcommand {
+    pub fn parse_frames(parse: &mut Parse) -> Result<Self, CommandError> {
+        let subcommand = parse.next_bytes()?;
+
+        let cmd = match subcommand.as_ref() {
+            sub if are_equal(sub, b"SET-ACTIVE-EXPIRE") => match parse.next_int()? {
+                0 => Self::SetActiveExpire(false),
+                1 => Self::SetActiveExpire(true),
+                _ => return Err(CommandError::Syntax),
+            },
+            sub if are_equal(sub, b"OBJECT") => Self::Object(parse.next_bytes()?),
+            sub if are_equal(sub, b"RELOAD") => Self::Reload,
+            sub if are_equal(sub, b"HELP") => Self::Help,
+            sub if are_equal(sub, b"STRINGMATCH-LEN") => Self::StringMatchLen {
+                pattern: parse.next_bytes()?,
+                string: parse.next_bytes()?,
+            },
+            sub if are_equal(sub, b"SLEEP") => {
+                let seconds = parse.next_bytes()?;
+                from_utf8(&seconds)
+                    .ok()
+                    .and_then(|seconds| seconds.parse::<f64>().ok())
+                    .and_then(|seconds| Duration::try_from_secs_f64(seconds).ok())
+                    .map(Self::Sleep)
+                    .ok_or(CommandError::Syntax)?
+            }
+            _ => return Err(CommandError::UnknownSubcommand("DEBUG", subcommand)),
+        };
+
+        parse.finish()?;
+        Ok(cmd)
+    }
+
+    pub fn apply<S: Storage>(self, db: &Db<S>) -> FrameValue {
+        match self {
+            Self::Help => help("DEBUG", HELP),
+            // Blocks like it does in Redis, the connection loop sleeps through
+            // `Command::apply_async` instead
+            Self::Sleep(duration) => {
+                std::thread::sleep(duration);
+                FrameValue::SimpleString("OK".into())
+            }
+            Self::SetActiveExpire(enabled) => {
+                db.set_active_expire(enabled);
+                FrameValue::SimpleString("OK".into())
+            }
+            // There is no dump file to save to, so the snapshot makes the round
+            // trip in memory, with every database locked so no write slips in
+            Self::Reload => {
+                let mut databases = db.lock_all();
+                let snapshot = rdb::snapshot(&databases);
+                if !rdb::load(&snapshot, &mut databases) {
+                    return FrameValue::Error("ERR Error trying to load the RDB dump".into());
+                }
+                FrameValue::SimpleString("OK".into())
+            }
+            Self::StringMatchLen { pattern, string } => {
+                FrameValue::Integer(glob::matches(&pattern, &string) as i64)
+            }
+            Self::Object(key) => {
+                let entries = db.lock();
+                let Some(info) = entries.inspect(&key) else {
+                    return FrameValue::Error("ERR no such key".into());
+                };
+                FrameValue::SimpleString(
+                    format!(
+                        "Value refcount:{} encoding:{} serializedlength:{} lru_seconds_idle:{} expired:{}",
+                        refcount(info.value),
+                        encoding(info.value, db.encoding_limits()),
+                        rdb::encode(info.value).len(),
+                        info.idle.as_secs(),
+                        info.expired as u8,
+                    )
+                    .into(),
+                )
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod debug_tests {
+    use crate::{cmd::run, db::Db, frame::FrameValue};
+    use std::{thread::sleep, time::Duration};
+
+    fn debug_object(db: &Db, key: &str) -> String {
+        match run(db, &["DEBUG", "OBJECT", key]) {
+            FrameValue::SimpleString(info) => String::from_utf8(info.to_vec()).unwrap(),
+            other => panic!("expected a simple string, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_object_shows_unpurged_expired_key() {
+        let db = Db::new();
+        run(&db, &["SET", "key", "value"]);
+        assert!(debug_object(&db, "key").ends_with("expired:0"));
+
+        run(&db, &["PEXPIRE", "key", "10"]);
+        sleep(Duration::from_millis(20));
+        assert!(debug_object(&db, "key").ends_with("expired:1"));
+
+        run(&db, &["GET", "key"]);
+        assert_eq!(
+            run(&db, &["DEBUG", "OBJECT", "key"]),
+            FrameValue::Error("ERR no such key".into())
+        );
+    }
+
+    #[test]
+    fn test_object_agrees_with_object_command() {
+        let db = Db::new();
+        run(&db, &["SET", "number", "42"]);
+        run(&db, &["SADD", "set", "1", "2"]);
+        run(&db, &["HSET", "hash", "field", "value"]);
+
+        assert!(debug_object(&db, "number").starts_with("Value refcount:2147483647 encoding:int "));
+        assert!(debug_object(&db, "set").starts_with("Value refcount:1 encoding:intset "));
+        assert!(debug_object(&db, "hash").starts_with("Value refcount:1 encoding:listpack "));
+    }
+
+    #[test]
+    fn test_set_active_expire_arguments() {
+        let db = Db::new();
+
+        assert_eq!(
+            run(&db, &["DEBUG", "SET-ACTIVE-EXPIRE", "0"]),
+            FrameValue::SimpleString("OK".into())
+        );
+        assert_eq!(
+            run(&db, &["DEBUG", "SET-ACTIVE-EXPIRE", "2"]),
+            FrameValue::Error("ERR syntax error".into())
+        );
+    }
+
+    #[test]
+    fn test_reload_keeps_values_and_ttls() {
+        let db = Db::new();
+        run(&db, &["SET", "string", "value"]);
+        run(&db, &["RPUSH", "list", "a", "b"]);
+        run(&db, &["SADD", "set", "member"]);
+        run(&db, &["HSET", "hash", "field", "value"]);
+        run(&db, &["EXPIRE", "string", "100"]);
+        let other = db.select(3).unwrap();
+        run(&other, &["SET", "elsewhere", "value"]);
+
+        assert_eq!(
+            run(&db, &["DEBUG", "RELOAD"]),
+            FrameValue::SimpleString("OK".into())
+        );
+
+        assert_eq!(run(&db, &["DBSIZE"]), FrameValue::Integer(4));
+        assert_eq!(
+            run(&db, &["GET", "string"]),
+            FrameValue::BulkString("value".into())
+        );
+        assert!(matches!(
+            run(&db, &["TTL", "string"]),
+            FrameValue::Integer(ttl) if (99..=100).contains(&ttl)
+        ));
+        assert_eq!(run(&db, &["TTL", "list"]), FrameValue::Integer(-1));
+        assert_eq!(
+            run(&db, &["LINDEX", "list", "1"]),
+            FrameValue::BulkString("b".into())
+        );
+        assert_eq!(
+            run(&db, &["SMEMBERS", "set"]),
+            FrameValue::Array(vec![FrameValue::BulkString("member".into())])
+        );
+        assert_eq!(
+            run(&db, &["HVALS", "hash"]),
+            FrameValue::Array(vec![FrameValue::BulkString("value".into())])
+        );
+        assert_eq!(
+            run(&other, &["GET", "elsewhere"]),
+            FrameValue::BulkString("value".into())
+        );
+    }
+
+    #[test]
+    fn test_stringmatch_len() {
+        let db = Db::new();
+        let matches =
+            |pattern: &str, string: &str| run(&db, &["DEBUG", "STRINGMATCH-LEN", pattern, string]);
+
+        for (pattern, string, expected) in [
+            (r"a\*b", "a*b", 1),
+            (r"a\*b", "axb", 0),
+            (r"a\*b", "a*bb", 0),
+            ("[^a]", "b", 1),
+            ("[^a]", "a", 0),
+            ("[^a]", "bc", 0),
+            ("[^a]", "", 0),
+            ("a*b*c", "abc", 1),
+            ("a*b*c", "aXbYc", 1),
+            ("a*b*c", "abcbc", 1),
+            ("a*b*c", "ab", 0),
+            ("a*b*c", "acb", 0),
+        ] {
+            assert_eq!(
+                matches(pattern, string),
+                FrameValue::Integer(expected),
+                "{pattern} against {string:?}"
+            );
+        }
+
+        assert_eq!(
+            run(&db, &["DEBUG", "STRINGMATCH-LEN", "a*"]),
+            FrameValue::Error("ERR wrong number of arguments for 'debug' command".into())
+        );
+    }
+}
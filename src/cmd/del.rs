@@ -0,0 +1,45 @@
+use super::{CommandError, Parse};
+use crate::{
+    db::{Db, Storage},
+    frame::FrameValue,
+};
+use bytes::Bytes;
+
+/// Removes keys of any type, replying with how many existed
+pub struct Del {
+    keys: Vec<Bytes>,
+}
+
+impl Del {
+    pub fn parse_frames(parse: &mut Parse) -> Result<Self, CommandError> {
+        let keys = parse.rest_bytes()?;
+        Ok(Self { keys })
+    }
+
+    pub fn apply<S: Storage>(self, db: &Db<S>) -> FrameValue {
+        let mut entries = db.lock();
+        let removed = self
+            .keys
+            .iter()
+            .filter(|key| entries.remove(key).is_some())
+            .count();
+
+        FrameValue::Integer(removed as i64)
+    }
+}
+
+#[cfg(test)]
+mod del_tests {
+    use crate::{cmd::run, db::Db, frame::FrameValue};
+
+    #[test]
+    fn test_del() {
+        let db = Db::new();
+        run(&db, &["SET", "a", "1"]);
+        run(&db, &["SADD", "b", "1"]);
+
+        assert_eq!(run(&db, &["DEL", "a", "b", "c"]), FrameValue::Integer(2));
+        assert_eq!(run(&db, &["GET", "a"]), FrameValue::NullBulkString);
+        assert_eq!(run(&db, &["DEL", "a"]), FrameValue::Integer(0));
+    }
+}
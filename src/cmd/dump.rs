@@ -0,0 +1,27 @@
+use super::{CommandError, Parse};
+use crate::{
+    db::{Db, Storage},
+    frame::FrameValue,
+    rdb,
+};
+use bytes::Bytes;
+
+/// Serializes the value of a key in the format read back by RESTORE
+pub struct Dump {
+    key: Bytes,
+}
+
+impl Dump {
+    pub fn parse_frames(parse: &mut Parse) -> Result<Self, CommandError> {
+        let key = parse.next_bytes()?;
+        parse.finish()?;
+        Ok(Self { key })
+    }
+
+    pub fn apply<S: Storage>(self, db: &Db<S>) -> FrameValue {
+        match db.read_key(&self.key).get_shared(&self.key) {
+            Some(value) => FrameValue::BulkString(rdb::encode(value)),
+            None => FrameValue::NullBulkString,
+        }
+    }
+}
@@ -0,0 +1,20 @@
+use super::{CommandError, Parse};
+use crate::frame::FrameValue;
+use bytes::Bytes;
+
+/// Replies with the given message
+pub struct Echo {
+    msg: Bytes,
+}
+
+impl Echo {
+    pub fn parse_frames(parse: &mut Parse) -> Result<Self, CommandError> {
+        let msg = parse.next_bytes()?;
+        parse.finish()?;
+        Ok(Self { msg })
+    }
+
+    pub fn apply(self) -> FrameValue {
+        FrameValue::BulkString(self.msg)
+    }
+}
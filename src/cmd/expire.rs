@@ -0,0 +1,332 @@
+use super::{CommandError, Parse, pexpireat_frame};
+use crate::{
+    db::{Db, Storage, instant_at_unix},
+    frame::FrameValue,
+};
+use bytes::Bytes;
+use std::time::{Duration, Instant};
+
+/// Unit a TTL is given or reported in
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TimeUnit {
+    Seconds,
+    Millis,
+}
+
+impl TimeUnit {
+    /// Duration of `amount` units, `None` if it is negative or too long
+    pub fn duration(self, amount: i64) -> Option<Duration> {
+        let amount = u64::try_from(amount).ok()?;
+        match self {
+            Self::Seconds => amount.checked_mul(1000).map(Duration::from_millis),
+            Self::Millis => Some(Duration::from_millis(amount)),
+        }
+    }
+
+    /// `duration` in whole units, rounded to the nearest one
+    pub fn amount(self, duration: Duration) -> i64 {
+        let millis = duration.as_millis() as i64;
+        match self {
+            Self::Seconds => (millis + 500) / 1000,
+            Self::Millis => millis,
+        }
+    }
+
+    /// Instant at which the Unix time `timestamp` in these units falls, `None`
+    /// if it is negative or too far off to represent
+    pub fn instant_at(self, timestamp: i64) -> Option<Instant> {
+        instant_at_unix(self.duration(timestamp)?)
+    }
+}
+
+/// Sets a key to expire after a number of seconds or milliseconds
+///
+/// A TTL that isn't positive deletes the key straight away.
+pub struct Expire {
+    key: Bytes,
+    ttl: i64,
+    unit: TimeUnit,
+}
+
+impl Expire {
+    pub fn parse_frames(parse: &mut Parse, unit: TimeUnit) -> Result<Self, CommandError> {
+        let key = parse.next_bytes()?;
+        let ttl = parse.next_int()?;
+        parse.finish()?;
+        Ok(Self { key, ttl, unit })
+    }
+
+    pub fn apply<S: Storage>(self, db: &Db<S>) -> FrameValue {
+        match self.expires_at() {
+            Ok(expires_at) => self.expire(db, expires_at),
+            Err(error) => error,
+        }
+    }
+
+    /// Like [`Expire::apply`], also returning what replicas and the append only
+    /// file get in its place: a PEXPIREAT at the time the key now expires, or
+    /// a DEL if it was deleted
+    pub fn apply_write<S: Storage>(self, db: &Db<S>) -> (FrameValue, Option<FrameValue>) {
+        let expires_at = match self.expires_at() {
+            Ok(expires_at) => expires_at,
+            Err(error) => return (error, None),
+        };
+        let propagated = match expires_at {
+            Some(at) => pexpireat_frame(self.key.clone(), at),
+            None => FrameValue::Array(vec![
+                FrameValue::BulkString("DEL".into()),
+                FrameValue::BulkString(self.key.clone()),
+            ]),
+        };
+        (self.expire(db, expires_at), Some(propagated))
+    }
+
+    /// When the key is to expire, `None` for straight away, or `Err` if the TTL
+    /// is too long
+    fn expires_at(&self) -> Result<Option<Instant>, FrameValue> {
+        if self.ttl <= 0 {
+            return Ok(None);
+        }
+
+        match self
+            .unit
+            .duration(self.ttl)
+            .and_then(|ttl| Instant::now().checked_add(ttl))
+        {
+            Some(at) => Ok(Some(at)),
+            None => {
+                let name = match self.unit {
+                    TimeUnit::Seconds => "expire",
+                    TimeUnit::Millis => "pexpire",
+                };
+                Err(FrameValue::Error(
+                    format!("ERR invalid expire time in '{name}' command").into(),
+                ))
+            }
+        }
+    }
+
+    fn expire<S: Storage>(&self, db: &Db<S>, expires_at: Option<Instant>) -> FrameValue {
+        let mut entries = db.lock();
+        let changed = match expires_at {
+            Some(at) => entries.set_expiry(&self.key, Some(at)),
+            None => entries.remove(&self.key).is_some(),
+        };
+        FrameValue::Integer(changed as i64)
+    }
+}
+
+/// Sets a key to expire at a Unix time in seconds or milliseconds
+///
+/// A time that has already passed deletes the key straight away.
+pub struct ExpireAt {
+    key: Bytes,
+    timestamp: i64,
+    unit: TimeUnit,
+}
+
+impl ExpireAt {
+    pub fn parse_frames(parse: &mut Parse, unit: TimeUnit) -> Result<Self, CommandError> {
+        let key = parse.next_bytes()?;
+        let timestamp = parse.next_int()?;
+        parse.finish()?;
+        Ok(Self {
+            key,
+            timestamp,
+            unit,
+        })
+    }
+
+    pub fn apply<S: Storage>(self, db: &Db<S>) -> FrameValue {
+        let mut entries = db.lock();
+
+        // Negative timestamps are simply in the past, not invalid
+        let expires_at = match self.timestamp {
+            ..=0 => Instant::now(),
+            timestamp => match self.unit.instant_at(timestamp) {
+                Some(at) => at,
+                None => {
+                    let name = match self.unit {
+                        TimeUnit::Seconds => "expireat",
+                        TimeUnit::Millis => "pexpireat",
+                    };
+                    return FrameValue::Error(
+                        format!("ERR invalid expire time in '{name}' command").into(),
+                    );
+                }
+            },
+        };
+
+        if expires_at <= Instant::now() {
+            return FrameValue::Integer(entries.remove(&self.key).is_some() as i64);
+        }
+
+        FrameValue::Integer(entries.set_expiry(&self.key, Some(expires_at)) as i64)
+    }
+}
+
+/// Reports the time left before a key expires
+///
+/// Replies -2 if the key is missing and -1 if it has no expiry.
+pub struct Ttl {
+    key: Bytes,
+    unit: TimeUnit,
+}
+
+impl Ttl {
+    pub fn parse_frames(parse: &mut Parse, unit: TimeUnit) -> Result<Self, CommandError> {
+        let key = parse.next_bytes()?;
+        parse.finish()?;
+        Ok(Self { key, unit })
+    }
+
+    pub fn apply<S: Storage>(self, db: &Db<S>) -> FrameValue {
+        let ttl = match db.read_key(&self.key).expiry(&self.key) {
+            None => -2,
+            Some(None) => -1,
+            Some(Some(at)) => self
+                .unit
+                .amount(at.saturating_duration_since(Instant::now())),
+        };
+        FrameValue::Integer(ttl)
+    }
+}
+
+#[cfg(test)]
+mod expire_tests {
+    use super::{Expire, TimeUnit};
+    use crate::{cmd::run, db::Db, frame::FrameValue};
+    use std::{
+        thread::sleep,
+        time::{Duration, SystemTime, UNIX_EPOCH},
+    };
+
+    #[test]
+    fn test_expire_and_ttl() {
+        let db = Db::new();
+        run(&db, &["SET", "key", "value"]);
+
+        assert_eq!(run(&db, &["TTL", "key"]), FrameValue::Integer(-1));
+        assert_eq!(run(&db, &["EXPIRE", "key", "100"]), FrameValue::Integer(1));
+        assert_eq!(run(&db, &["TTL", "key"]), FrameValue::Integer(100));
+        assert!(matches!(
+            run(&db, &["PTTL", "key"]),
+            FrameValue::Integer(ms) if ms > 99_000 && ms <= 100_000
+        ));
+
+        // Overwriting the value drops the expiry
+        run(&db, &["SET", "key", "other"]);
+        assert_eq!(run(&db, &["TTL", "key"]), FrameValue::Integer(-1));
+
+        assert_eq!(
+            run(&db, &["EXPIRE", "missing", "10"]),
+            FrameValue::Integer(0)
+        );
+        assert_eq!(run(&db, &["TTL", "missing"]), FrameValue::Integer(-2));
+    }
+
+    #[test]
+    fn test_relative_expiry_propagates_as_absolute() {
+        let db = Db::new();
+        run(&db, &["SET", "key", "value"]);
+        let expire = |ttl| Expire {
+            key: "key".into(),
+            ttl,
+            unit: TimeUnit::Seconds,
+        };
+
+        let (reply, propagated) = expire(100).apply_write(&db);
+        assert_eq!(reply, FrameValue::Integer(1));
+        let Some(FrameValue::Array(args)) = propagated else {
+            panic!("expected a command");
+        };
+        assert_eq!(args[0], FrameValue::BulkString("PEXPIREAT".into()));
+        let FrameValue::BulkString(millis) = &args[2] else {
+            panic!("expected a timestamp");
+        };
+        let at: u128 = std::str::from_utf8(millis).unwrap().parse().unwrap();
+        let expected = (SystemTime::now() + Duration::from_secs(100))
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_millis();
+        assert!(expected - at < 1000, "{at} is not 100s from now");
+
+        assert_eq!(
+            expire(0).apply_write(&db).1,
+            Some(FrameValue::Array(vec![
+                FrameValue::BulkString("DEL".into()),
+                FrameValue::BulkString("key".into()),
+            ]))
+        );
+    }
+
+    #[test]
+    fn test_keys_expire() {
+        let db = Db::new();
+        run(&db, &["SET", "key", "value"]);
+
+        assert_eq!(run(&db, &["PEXPIRE", "key", "10"]), FrameValue::Integer(1));
+        sleep(Duration::from_millis(20));
+        assert_eq!(run(&db, &["GET", "key"]), FrameValue::NullBulkString);
+        assert_eq!(run(&db, &["TTL", "key"]), FrameValue::Integer(-2));
+    }
+
+    #[test]
+    fn test_expireat_future() {
+        let db = Db::new();
+        run(&db, &["SET", "key", "value"]);
+
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+        let at = (now.as_secs() + 100).to_string();
+        assert_eq!(run(&db, &["EXPIREAT", "key", &at]), FrameValue::Integer(1));
+        assert!(matches!(
+            run(&db, &["TTL", "key"]),
+            FrameValue::Integer(ttl) if (99..=100).contains(&ttl)
+        ));
+
+        let at = (now.as_millis() + 50_000).to_string();
+        assert_eq!(run(&db, &["PEXPIREAT", "key", &at]), FrameValue::Integer(1));
+        assert!(matches!(
+            run(&db, &["PTTL", "key"]),
+            FrameValue::Integer(ms) if ms > 49_000 && ms <= 50_000
+        ));
+
+        assert_eq!(
+            run(&db, &["EXPIREAT", "missing", &at]),
+            FrameValue::Integer(0)
+        );
+    }
+
+    #[test]
+    fn test_expireat_past_deletes() {
+        let db = Db::new();
+        run(&db, &["SET", "key", "value"]);
+
+        assert_eq!(run(&db, &["EXPIREAT", "key", "1"]), FrameValue::Integer(1));
+        assert_eq!(run(&db, &["DBSIZE"]), FrameValue::Integer(0));
+        assert_eq!(run(&db, &["EXPIREAT", "key", "1"]), FrameValue::Integer(0));
+
+        run(&db, &["SET", "key", "value"]);
+        assert_eq!(
+            run(&db, &["PEXPIREAT", "key", "-5"]),
+            FrameValue::Integer(1)
+        );
+        assert_eq!(run(&db, &["GET", "key"]), FrameValue::NullBulkString);
+    }
+
+    #[test]
+    fn test_non_positive_ttl_deletes() {
+        let db = Db::new();
+        run(&db, &["SET", "key", "value"]);
+
+        assert_eq!(run(&db, &["EXPIRE", "key", "0"]), FrameValue::Integer(1));
+        assert_eq!(run(&db, &["GET", "key"]), FrameValue::NullBulkString);
+        assert_eq!(run(&db, &["EXPIRE", "key", "-1"]), FrameValue::Integer(0));
+
+        run(&db, &["SET", "key", "value"]);
+        assert_eq!(
+            run(&db, &["EXPIRE", "key", &i64::MAX.to_string()]),
+            FrameValue::Error("ERR invalid expire time in 'expire' command".into())
+        );
+    }
+}
@@ -0,0 +1,27 @@
+use super::{CommandError, Parse, wrong_type};
+use crate::{
+    db::{Db, DbValue, Storage},
+    frame::FrameValue,
+};
+use bytes::Bytes;
+
+/// Gets the string value of a key
+pub struct Get {
+    key: Bytes,
+}
+
+impl Get {
+    pub fn parse_frames(parse: &mut Parse) -> Result<Self, CommandError> {
+        let key = parse.next_bytes()?;
+        parse.finish()?;
+        Ok(Self { key })
+    }
+
+    pub fn apply<S: Storage>(self, db: &Db<S>) -> FrameValue {
+        match db.read_key(&self.key).get_shared(&self.key) {
+            Some(DbValue::String(value)) => FrameValue::BulkString(value.clone()),
+            Some(_) => wrong_type(),
+            None => FrameValue::NullBulkString,
+        }
+    }
+}
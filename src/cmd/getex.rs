@@ -0,0 +1,214 @@
+use super::{CommandError, Parse, are_equal, expire::TimeUnit, pexpireat_frame, wrong_type};
+use crate::{
+    db::{Db, DbValue, Storage},
+    frame::FrameValue,
+};
+use bytes::Bytes;
+use std::time::Instant;
+
+/// Gets the string value of a key, optionally changing its expiry
+///
+/// Without options it behaves exactly like GET.
+pub struct GetEx {
+    key: Bytes,
+    opt: Option<GetExOption>,
+}
+
+/// How GETEX changes the expiry of the key it reads
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum GetExOption {
+    /// `EX`/`PX`: expire after a number of units
+    After(i64, TimeUnit),
+    /// `EXAT`/`PXAT`: expire at a Unix time in units
+    At(i64, TimeUnit),
+    /// `PERSIST`: drop any expiry
+    Persist,
+}
+
+impl GetExOption {
+    fn parse(parse: &mut Parse, name: &[u8]) -> Result<Self, CommandError> {
+        let opt = if are_equal(name, b"EX") {
+            Self::After(parse.next_int()?, TimeUnit::Seconds)
+        } else if are_equal(name, b"PX") {
+            Self::After(parse.next_int()?, TimeUnit::Millis)
+        } else if are_equal(name, b"EXAT") {
+            Self::At(parse.next_int()?, TimeUnit::Seconds)
+        } else if are_equal(name, b"PXAT") {
+            Self::At(parse.next_int()?, TimeUnit::Millis)
+        } else if are_equal(name, b"PERSIST") {
+            Self::Persist
+        } else {
+            return Err(CommandError::Syntax);
+        };
+        Ok(opt)
+    }
+
+    /// New expiry of the key, or `Err` if the given time is invalid
+    fn expires_at(self) -> Result<Option<Instant>, FrameValue> {
+        let expires_at = match self {
+            Self::Persist => return Ok(None),
+            Self::After(ttl, unit) if ttl > 0 => unit
+                .duration(ttl)
+                .and_then(|ttl| Instant::now().checked_add(ttl)),
+            Self::At(timestamp, unit) if timestamp > 0 => unit.instant_at(timestamp),
+            _ => None,
+        };
+
+        expires_at
+            .map(Some)
+            .ok_or_else(|| FrameValue::Error("ERR invalid expire time in 'getex' command".into()))
+    }
+}
+
+impl GetEx {
+    pub fn parse_frames(parse: &mut Parse) -> Result<Self, CommandError> {
+        let key = parse.next_bytes()?;
+        let opt = match parse.next_optional_bytes()? {
+            Some(name) => Some(GetExOption::parse(parse, &name)?),
+            None => None,
+        };
+
+        // The options are mutually exclusive, so anything left over is an error
+        if parse.remaining() > 0 {
+            return Err(CommandError::Syntax);
+        }
+
+        Ok(Self { key, opt })
+    }
+
+    pub fn apply<S: Storage>(self, db: &Db<S>) -> FrameValue {
+        match self.opt.map(GetExOption::expires_at).transpose() {
+            Ok(expires_at) => self.get(db, expires_at),
+            Err(error) => error,
+        }
+    }
+
+    /// Like [`GetEx::apply`], also returning what replicas and the append only
+    /// file get in its place: `frame` as it was sent, unless it sets an expiry
+    /// relative to now, which is sent as a PEXPIREAT at the time it comes to
+    pub fn apply_write<S: Storage>(
+        self,
+        db: &Db<S>,
+        frame: FrameValue,
+    ) -> (FrameValue, Option<FrameValue>) {
+        let expires_at = match self.opt.map(GetExOption::expires_at).transpose() {
+            Ok(expires_at) => expires_at,
+            Err(error) => return (error, None),
+        };
+        let propagated = match (self.opt, expires_at) {
+            (Some(GetExOption::After(..)), Some(Some(at))) => pexpireat_frame(self.key.clone(), at),
+            _ => frame,
+        };
+
+        let reply = self.get(db, expires_at);
+        let changed = matches!(reply, FrameValue::BulkString(_));
+        (reply, changed.then_some(propagated))
+    }
+
+    fn get<S: Storage>(&self, db: &Db<S>, expires_at: Option<Option<Instant>>) -> FrameValue {
+        let mut entries = db.lock();
+        let value = match entries.get(&self.key) {
+            Some(DbValue::String(value)) => value.clone(),
+            Some(_) => return wrong_type(),
+            None => return FrameValue::NullBulkString,
+        };
+
+        match expires_at {
+            // A time that has already passed deletes the key, as Redis does
+            Some(Some(at)) if at <= Instant::now() => {
+                entries.remove(&self.key);
+            }
+            Some(at) => {
+                entries.set_expiry(&self.key, at);
+            }
+            None => {}
+        }
+
+        FrameValue::BulkString(value)
+    }
+}
+
+#[cfg(test)]
+mod getex_tests {
+    use crate::{cmd::run, db::Db, frame::FrameValue};
+
+    fn db_with(key: &str, value: &str) -> Db {
+        let db = Db::new();
+        run(&db, &["SET", key, value]);
+        db
+    }
+
+    fn value(value: &str) -> FrameValue {
+        FrameValue::BulkString(value.to_owned().into())
+    }
+
+    #[test]
+    fn test_ex_sets_ttl() {
+        let db = db_with("key", "value");
+
+        assert_eq!(run(&db, &["GETEX", "key", "EX", "100"]), value("value"));
+        assert_eq!(run(&db, &["TTL", "key"]), FrameValue::Integer(100));
+
+        assert_eq!(run(&db, &["GETEX", "key", "px", "5000"]), value("value"));
+        assert_eq!(run(&db, &["TTL", "key"]), FrameValue::Integer(5));
+    }
+
+    #[test]
+    fn test_persist_clears_ttl() {
+        let db = db_with("key", "value");
+        run(&db, &["EXPIRE", "key", "100"]);
+
+        assert_eq!(run(&db, &["GETEX", "key", "PERSIST"]), value("value"));
+        assert_eq!(run(&db, &["TTL", "key"]), FrameValue::Integer(-1));
+    }
+
+    #[test]
+    fn test_plain_getex_keeps_ttl() {
+        let db = db_with("key", "value");
+        run(&db, &["EXPIRE", "key", "100"]);
+
+        assert_eq!(run(&db, &["GETEX", "key"]), value("value"));
+        assert_eq!(run(&db, &["TTL", "key"]), FrameValue::Integer(100));
+        assert_eq!(run(&db, &["GETEX", "missing"]), FrameValue::NullBulkString);
+    }
+
+    #[test]
+    fn test_exat() {
+        let db = db_with("key", "value");
+
+        assert_eq!(
+            run(&db, &["GETEX", "key", "EXAT", "99999999999"]),
+            value("value")
+        );
+        assert!(matches!(run(&db, &["TTL", "key"]), FrameValue::Integer(ttl) if ttl > 0));
+
+        // A timestamp in the past deletes the key after replying with it
+        assert_eq!(run(&db, &["GETEX", "key", "PXAT", "1"]), value("value"));
+        assert_eq!(run(&db, &["GET", "key"]), FrameValue::NullBulkString);
+    }
+
+    #[test]
+    fn test_invalid_options() {
+        let db = db_with("key", "value");
+
+        assert_eq!(
+            run(&db, &["GETEX", "key", "EX", "10", "PERSIST"]),
+            FrameValue::Error("ERR syntax error".into())
+        );
+        assert_eq!(
+            run(&db, &["GETEX", "key", "KEEPTTL"]),
+            FrameValue::Error("ERR syntax error".into())
+        );
+        assert_eq!(
+            run(&db, &["GETEX", "key", "EX", "0"]),
+            FrameValue::Error("ERR invalid expire time in 'getex' command".into())
+        );
+        run(&db, &["LPUSH", "list", "a"]);
+        assert_eq!(
+            run(&db, &["GETEX", "list"]),
+            FrameValue::Error(
+                "WRONGTYPE Operation against a key holding the wrong kind of value".into()
+            )
+        );
+    }
+}
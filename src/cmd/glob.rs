@@ -0,0 +1,145 @@
+//! Glob-style patterns, as used by `MATCH` options
+//!
+//! `*` matches any run of bytes, `?` any single byte and `[...]` any byte in the
+//! set, which may hold ranges like `a-z` and be negated with a leading `^`. A
+//! `\` makes the next byte match literally.
+
+/// Whether `string` matches `pattern` in full
+pub fn matches(pattern: &[u8], string: &[u8]) -> bool {
+    let tokens = tokenize(pattern);
+
+    // Classic wildcard matching: on a mismatch, retry from the last `*` with it
+    // swallowing one more byte. Every other token matches exactly one byte, so
+    // this never needs to revisit earlier stars.
+    let (mut t, mut s) = (0, 0);
+    let mut star: Option<(usize, usize)> = None;
+    while s < string.len() {
+        match tokens.get(t) {
+            Some(Token::Star) => {
+                star = Some((t, s));
+                t += 1;
+            }
+            Some(token) if token.matches(string[s]) => {
+                t += 1;
+                s += 1;
+            }
+            _ => match star {
+                Some((star_t, star_s)) => {
+                    star = Some((star_t, star_s + 1));
+                    t = star_t + 1;
+                    s = star_s + 1;
+                }
+                None => return false,
+            },
+        }
+    }
+
+    tokens[t..].iter().all(|token| *token == Token::Star)
+}
+
+#[derive(Debug, PartialEq)]
+enum Token {
+    Star,
+    Any,
+    Byte(u8),
+    Class {
+        negated: bool,
+        ranges: Vec<(u8, u8)>,
+    },
+}
+
+impl Token {
+    fn matches(&self, byte: u8) -> bool {
+        match self {
+            Self::Star | Self::Any => true,
+            Self::Byte(expected) => byte == *expected,
+            Self::Class { negated, ranges } => {
+                ranges
+                    .iter()
+                    .any(|&(low, high)| (low..=high).contains(&byte))
+                    != *negated
+            }
+        }
+    }
+}
+
+fn tokenize(pattern: &[u8]) -> Vec<Token> {
+    let mut tokens = Vec::with_capacity(pattern.len());
+    let mut bytes = pattern.iter().copied().peekable();
+
+    while let Some(byte) = bytes.next() {
+        let token = match byte {
+            // Consecutive stars match the same as one
+            b'*' if tokens.last() == Some(&Token::Star) => continue,
+            b'*' => Token::Star,
+            b'?' => Token::Any,
+            b'\\' => Token::Byte(bytes.next().unwrap_or(b'\\')),
+            b'[' => {
+                let negated = bytes.next_if_eq(&b'^').is_some();
+                let mut ranges = Vec::new();
+                // An unterminated set runs to the end of the pattern
+                while let Some(byte) = bytes.next() {
+                    let low = match byte {
+                        b']' => break,
+                        b'\\' => bytes.next().unwrap_or(b'\\'),
+                        byte => byte,
+                    };
+                    let range = match bytes.next_if_eq(&b'-') {
+                        Some(_) => match bytes.next_if(|&byte| byte != b']') {
+                            Some(high) => (low.min(high), low.max(high)),
+                            // A trailing `-` is literal
+                            None => {
+                                ranges.push((b'-', b'-'));
+                                (low, low)
+                            }
+                        },
+                        None => (low, low),
+                    };
+                    ranges.push(range);
+                }
+                Token::Class { negated, ranges }
+            }
+            byte => Token::Byte(byte),
+        };
+        tokens.push(token);
+    }
+
+    tokens
+}
+
+#[cfg(test)]
+mod glob_tests {
+    use super::matches;
+
+    #[test]
+    fn test_wildcards() {
+        assert!(matches(b"*", b""));
+        assert!(matches(b"*", b"anything"));
+        assert!(matches(b"user:*", b"user:1000"));
+        assert!(!matches(b"user:*", b"session:1"));
+        assert!(matches(b"h?llo", b"hello"));
+        assert!(!matches(b"h?llo", b"hllo"));
+        assert!(matches(b"*a*b*c", b"xxaxxbxxbc"));
+        assert!(!matches(b"*a*b*c", b"xxaxxbxxb"));
+        assert!(matches(b"a**b", b"ab"));
+    }
+
+    #[test]
+    fn test_sets() {
+        assert!(matches(b"h[ae]llo", b"hallo"));
+        assert!(!matches(b"h[ae]llo", b"hillo"));
+        assert!(matches(b"h[^e]llo", b"hallo"));
+        assert!(!matches(b"h[^e]llo", b"hello"));
+        assert!(matches(b"h[a-b]llo", b"hbllo"));
+        assert!(matches(b"h[b-a]llo", b"hallo"));
+        assert!(matches(b"[a-]", b"-"));
+        assert!(matches(b"[\\]]", b"]"));
+    }
+
+    #[test]
+    fn test_escapes() {
+        assert!(matches(b"\\*", b"*"));
+        assert!(!matches(b"\\*", b"x"));
+        assert!(matches(b"a\\?", b"a?"));
+    }
+}
@@ -0,0 +1,78 @@
+use super::{CommandError, Parse, wrong_type};
+use crate::{
+    db::{Db, DbValue, Storage},
+    frame::FrameValue,
+};
+use bytes::Bytes;
+
+/// Removes fields from a hash, deleting the key once it is empty
+pub struct HDel {
+    key: Bytes,
+    fields: Vec<Bytes>,
+}
+
+impl HDel {
+    pub fn parse_frames(parse: &mut Parse) -> Result<Self, CommandError> {
+        let key = parse.next_bytes()?;
+        let fields = parse.rest_bytes()?;
+        Ok(Self { key, fields })
+    }
+
+    pub fn apply<S: Storage>(self, db: &Db<S>) -> FrameValue {
+        let mut entries = db.lock();
+        let hash = match entries.get_mut(&self.key) {
+            Some(DbValue::Hash(hash)) => hash,
+            Some(_) => return wrong_type(),
+            None => return FrameValue::Integer(0),
+        };
+
+        let removed = self
+            .fields
+            .iter()
+            .filter(|field| hash.remove(field).is_some())
+            .count();
+
+        if hash.is_empty() {
+            entries.remove(&self.key);
+        }
+
+        FrameValue::Integer(removed as i64)
+    }
+}
+
+#[cfg(test)]
+mod hdel_tests {
+    use super::*;
+    use crate::cmd::run;
+
+    #[test]
+    fn test_deleting_every_field_removes_key() {
+        let db = Db::new();
+        run(&db, &["HSET", "hash", "a", "1", "b", "2"]);
+
+        assert_eq!(
+            run(&db, &["HDEL", "hash", "a", "missing"]),
+            FrameValue::Integer(1)
+        );
+        assert_eq!(run(&db, &["HLEN", "hash"]), FrameValue::Integer(1));
+
+        assert_eq!(run(&db, &["HDEL", "hash", "b"]), FrameValue::Integer(1));
+        assert!(db.lock().get(&Bytes::from("hash")).is_none());
+        assert_eq!(run(&db, &["HDEL", "hash", "b"]), FrameValue::Integer(0));
+    }
+
+    #[test]
+    fn test_wrong_type() {
+        let db = Db::new();
+        run(&db, &["SET", "string", "value"]);
+
+        assert_eq!(run(&db, &["HDEL", "string", "a"]), crate::cmd::wrong_type());
+        assert_eq!(
+            run(&db, &["HEXISTS", "string", "a"]),
+            crate::cmd::wrong_type()
+        );
+        assert_eq!(run(&db, &["HLEN", "string"]), crate::cmd::wrong_type());
+        assert_eq!(run(&db, &["HKEYS", "string"]), crate::cmd::wrong_type());
+        assert_eq!(run(&db, &["HVALS", "string"]), crate::cmd::wrong_type());
+    }
+}
@@ -0,0 +1,89 @@
+use super::{CommandError, Parse, are_equal, auth::authenticate};
+use crate::{
+    client::Client,
+    db::{Db, Storage},
+    frame::FrameValue,
+};
+use bytes::Bytes;
+
+/// Negotiates the protocol version, optionally authenticating at the same time,
+/// and describes the server
+///
+/// Replies are still encoded as RESP2 whichever version is negotiated.
+pub struct Hello {
+    protocol: Option<i64>,
+    auth: Option<(Bytes, Bytes)>,
+}
+
+impl Hello {
+    pub fn parse_frames(parse: &mut Parse) -> Result<Self, CommandError> {
+        let mut hello = Self {
+            protocol: None,
+            auth: None,
+        };
+        if parse.remaining() == 0 {
+            return Ok(hello);
+        }
+
+        hello.protocol = Some(parse.next_int()?);
+        while let Some(option) = parse.next_optional_bytes()? {
+            if are_equal(&option, b"AUTH") {
+                hello.auth = Some((parse.next_bytes()?, parse.next_bytes()?));
+            } else {
+                return Err(CommandError::Syntax);
+            }
+        }
+        Ok(hello)
+    }
+
+    pub fn apply<S: Storage>(self, db: &Db<S>, client: &Client) -> FrameValue {
+        if let Some(protocol) = self.protocol
+            && protocol != 2
+            && protocol != 3
+        {
+            return FrameValue::Error("NOPROTO unsupported protocol version".into());
+        }
+
+        match self.auth {
+            Some((user, password)) => {
+                if let Err(e) = authenticate(db, client, &user, &password) {
+                    return e;
+                }
+            }
+            None if client.user().is_none() => {
+                return FrameValue::Error(
+                    "NOAUTH HELLO must be called with the client already authenticated, otherwise the HELLO <proto> AUTH <user> <pass> option can be used to authenticate the client and select the RESP protocol version at the same time"
+                        .into(),
+                );
+            }
+            None => {}
+        }
+
+        if let Some(protocol) = self.protocol {
+            client.set_protocol(protocol as u8);
+        }
+
+        let role = match db.replication().master() {
+            Some(_) => "replica",
+            None => "master",
+        };
+        let fields = [
+            ("server", FrameValue::BulkString("redis".into())),
+            (
+                "version",
+                FrameValue::BulkString(env!("CARGO_PKG_VERSION").into()),
+            ),
+            ("proto", FrameValue::Integer(client.protocol().into())),
+            ("id", FrameValue::Integer(client.id() as i64)),
+            ("mode", FrameValue::BulkString("standalone".into())),
+            ("role", FrameValue::BulkString(role.into())),
+            ("modules", FrameValue::Array(vec![])),
+        ];
+        FrameValue::Array(
+            fields
+                .into_iter()
+                .flat_map(|(name, value)| [FrameValue::BulkString(name.into()), value])
+                .collect(),
+        )
+    }
+}
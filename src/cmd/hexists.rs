@@ -0,0 +1,29 @@
+use super::{CommandError, Parse, wrong_type};
+use crate::{
+    db::{Db, DbValue, Storage},
+    frame::FrameValue,
+};
+use bytes::Bytes;
+
+/// Checks whether a field is present in a hash
+pub struct HExists {
+    key: Bytes,
+    field: Bytes,
+}
+
+impl HExists {
+    pub fn parse_frames(parse: &mut Parse) -> Result<Self, CommandError> {
+        let key = parse.next_bytes()?;
+        let field = parse.next_bytes()?;
+        parse.finish()?;
+        Ok(Self { key, field })
+    }
+
+    pub fn apply<S: Storage>(self, db: &Db<S>) -> FrameValue {
+        match db.read_key(&self.key).get_shared(&self.key) {
+            Some(DbValue::Hash(hash)) => FrameValue::Integer(hash.contains_key(&self.field) as i64),
+            Some(_) => wrong_type(),
+            None => FrameValue::Integer(0),
+        }
+    }
+}
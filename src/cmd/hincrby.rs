@@ -0,0 +1,100 @@
+use super::{CommandError, Parse, wrong_type};
+use crate::{
+    db::{Db, DbValue, Storage},
+    frame::FrameValue,
+};
+use bytes::Bytes;
+use std::str::from_utf8;
+
+/// Increments the integer stored in a hash field
+///
+/// Missing keys and fields count as 0.
+pub struct HIncrBy {
+    key: Bytes,
+    field: Bytes,
+    delta: i64,
+}
+
+impl HIncrBy {
+    pub fn parse_frames(parse: &mut Parse) -> Result<Self, CommandError> {
+        let key = parse.next_bytes()?;
+        let field = parse.next_bytes()?;
+        let delta = parse.next_int()?;
+        parse.finish()?;
+        Ok(Self { key, field, delta })
+    }
+
+    pub fn apply<S: Storage>(self, db: &Db<S>) -> FrameValue {
+        let mut entries = db.lock();
+        let hash = match entries.get_or_insert_with(self.key, || DbValue::Hash(Default::default()))
+        {
+            DbValue::Hash(hash) => hash,
+            _ => return wrong_type(),
+        };
+
+        let current = match hash.get(&self.field) {
+            Some(value) => match from_utf8(value).ok().and_then(|s| s.parse::<i64>().ok()) {
+                Some(current) => current,
+                None => return FrameValue::Error("ERR hash value is not an integer".into()),
+            },
+            None => 0,
+        };
+
+        let Some(new) = current.checked_add(self.delta) else {
+            return FrameValue::Error("ERR increment or decrement would overflow".into());
+        };
+
+        hash.insert(self.field, new.to_string().into());
+        FrameValue::Integer(new)
+    }
+}
+
+#[cfg(test)]
+mod hincrby_tests {
+    use super::*;
+    use crate::cmd::run;
+
+    #[test]
+    fn test_increment_from_missing() {
+        let db = Db::new();
+
+        assert_eq!(
+            run(&db, &["HINCRBY", "hash", "field", "5"]),
+            FrameValue::Integer(5)
+        );
+        assert_eq!(
+            run(&db, &["HINCRBY", "hash", "field", "-7"]),
+            FrameValue::Integer(-2)
+        );
+        assert_eq!(
+            run(&db, &["HINCRBY", "hash", "other", "1"]),
+            FrameValue::Integer(1)
+        );
+    }
+
+    #[test]
+    fn test_non_integer_value() {
+        let db = Db::new();
+        run(&db, &["HSET", "hash", "field", "abc"]);
+
+        assert_eq!(
+            run(&db, &["HINCRBY", "hash", "field", "1"]),
+            FrameValue::Error("ERR hash value is not an integer".into())
+        );
+        assert_eq!(
+            run(&db, &["HINCRBY", "hash", "field", "x"]),
+            FrameValue::Error("ERR value is not an integer or out of range".into())
+        );
+    }
+
+    #[test]
+    fn test_overflow() {
+        let db = Db::new();
+        run(&db, &["HSET", "hash", "field", &i64::MAX.to_string()]);
+
+        assert_eq!(
+            run(&db, &["HINCRBY", "hash", "field", "1"]),
+            FrameValue::Error("ERR increment or decrement would overflow".into())
+        );
+    }
+}
@@ -0,0 +1,60 @@
+use super::{BulkArray, CommandError, Parse, wrong_type};
+use crate::{
+    db::{Db, DbValue, Storage},
+    frame::FrameValue,
+};
+use bytes::Bytes;
+
+/// Lists every field of a hash
+pub struct HKeys {
+    key: Bytes,
+}
+
+impl HKeys {
+    pub fn parse_frames(parse: &mut Parse) -> Result<Self, CommandError> {
+        let key = parse.next_bytes()?;
+        parse.finish()?;
+        Ok(Self { key })
+    }
+
+    pub fn stream<S: Storage>(self, db: &Db<S>) -> Result<BulkArray, FrameValue> {
+        match db.read_key(&self.key).get_shared(&self.key) {
+            Some(DbValue::Hash(hash)) => Ok(BulkArray::new(hash.keys().cloned().collect())),
+            Some(_) => Err(wrong_type()),
+            None => Ok(BulkArray::new(Vec::new())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod hkeys_tests {
+    use super::*;
+    use crate::cmd::{run, sorted_bulk_strings};
+
+    #[test]
+    fn test_field_and_value_listings() {
+        let db = Db::new();
+        run(&db, &["HSET", "hash", "b", "2", "a", "1"]);
+
+        assert_eq!(
+            sorted_bulk_strings(run(&db, &["HKEYS", "hash"])),
+            ["a", "b"]
+        );
+        assert_eq!(
+            sorted_bulk_strings(run(&db, &["HVALS", "hash"])),
+            ["1", "2"]
+        );
+        assert_eq!(run(&db, &["HEXISTS", "hash", "a"]), FrameValue::Integer(1));
+        assert_eq!(run(&db, &["HEXISTS", "hash", "c"]), FrameValue::Integer(0));
+        assert_eq!(run(&db, &["HLEN", "hash"]), FrameValue::Integer(2));
+    }
+
+    #[test]
+    fn test_missing_key() {
+        let db = Db::new();
+
+        assert_eq!(run(&db, &["HKEYS", "hash"]), FrameValue::Array(vec![]));
+        assert_eq!(run(&db, &["HVALS", "hash"]), FrameValue::Array(vec![]));
+        assert_eq!(run(&db, &["HLEN", "hash"]), FrameValue::Integer(0));
+    }
+}
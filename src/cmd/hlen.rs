@@ -0,0 +1,27 @@
+use super::{CommandError, Parse, wrong_type};
+use crate::{
+    db::{Db, DbValue, Storage},
+    frame::FrameValue,
+};
+use bytes::Bytes;
+
+/// Number of fields in a hash
+pub struct HLen {
+    key: Bytes,
+}
+
+impl HLen {
+    pub fn parse_frames(parse: &mut Parse) -> Result<Self, CommandError> {
+        let key = parse.next_bytes()?;
+        parse.finish()?;
+        Ok(Self { key })
+    }
+
+    pub fn apply<S: Storage>(self, db: &Db<S>) -> FrameValue {
+        match db.read_key(&self.key).get_shared(&self.key) {
+            Some(DbValue::Hash(hash)) => FrameValue::Integer(hash.len() as i64),
+            Some(_) => wrong_type(),
+            None => FrameValue::Integer(0),
+        }
+    }
+}
@@ -0,0 +1,148 @@
+use super::{CommandError, Parse, are_equal, wrong_type};
+use crate::{
+    db::{Db, DbValue, Storage},
+    dict::Dict,
+    frame::FrameValue,
+    random::{out_of_range, sample},
+};
+use bytes::Bytes;
+
+/// Returns random fields of a hash, optionally along with their values
+///
+/// Without a count a single field is returned, or null if the hash is missing.
+pub struct HRandField {
+    key: Bytes,
+    /// Requested count and whether values are interleaved with the fields
+    count: Option<(i64, bool)>,
+}
+
+impl HRandField {
+    pub fn parse_frames(parse: &mut Parse) -> Result<Self, CommandError> {
+        let key = parse.next_bytes()?;
+        let count = match parse.remaining() {
+            0 => None,
+            _ => {
+                let count = parse.next_int()?;
+                let with_values = match parse.next_optional_bytes()? {
+                    Some(option) if are_equal(&option, b"WITHVALUES") => true,
+                    Some(_) => return Err(CommandError::Syntax),
+                    None => false,
+                };
+                Some((count, with_values))
+            }
+        };
+        parse.finish()?;
+        Ok(Self { key, count })
+    }
+
+    pub fn apply<S: Storage>(self, db: &Db<S>) -> FrameValue {
+        let entries = db.read_key(&self.key);
+        let empty = Dict::default();
+        let hash = match entries.get_shared(&self.key) {
+            Some(DbValue::Hash(hash)) => hash,
+            Some(_) => return wrong_type(),
+            None => &empty,
+        };
+        let mut rng = db.rng();
+
+        match self.count {
+            Some((count, with_values)) => match sample(hash.iter(), hash.len(), count, &mut rng) {
+                Some(picked) => FrameValue::Array(
+                    picked
+                        .into_iter()
+                        .flat_map(|(field, value)| {
+                            let field = FrameValue::BulkString(field.clone());
+                            let value = with_values.then(|| FrameValue::BulkString(value.clone()));
+                            std::iter::once(field).chain(value)
+                        })
+                        .collect(),
+                ),
+                None => out_of_range(),
+            },
+            None => hash
+                .random(&mut rng)
+                .map_or(FrameValue::NullBulkString, |(field, _)| {
+                    FrameValue::BulkString(field.clone())
+                }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod hrandfield_tests {
+    use super::*;
+    use crate::cmd::{run, sorted_bulk_strings};
+
+    fn db_with_hash() -> Db {
+        let db = Db::new();
+        run(&db, &["HSET", "hash", "a", "1", "b", "2", "c", "3"]);
+        db
+    }
+
+    #[test]
+    fn test_distinct_fields() {
+        let db = db_with_hash();
+
+        let picked = sorted_bulk_strings(run(&db, &["HRANDFIELD", "hash", "2"]));
+        assert_eq!(picked.len(), 2);
+        assert_ne!(picked[0], picked[1]);
+        assert!(
+            picked
+                .iter()
+                .all(|f| [&b"a"[..], b"b", b"c"].contains(&&f[..]))
+        );
+
+        let all = sorted_bulk_strings(run(&db, &["HRANDFIELD", "hash", "5"]));
+        assert_eq!(all, ["a", "b", "c"]);
+    }
+
+    #[test]
+    fn test_with_values() {
+        let db = db_with_hash();
+
+        let FrameValue::Array(reply) = run(&db, &["HRANDFIELD", "hash", "-6", "WITHVALUES"]) else {
+            panic!("expected an array");
+        };
+        assert_eq!(reply.len(), 12);
+        for pair in reply.chunks(2) {
+            let expected = match &pair[0] {
+                FrameValue::BulkString(field) if field == "a" => "1",
+                FrameValue::BulkString(field) if field == "b" => "2",
+                FrameValue::BulkString(field) if field == "c" => "3",
+                other => panic!("unexpected field {other:?}"),
+            };
+            assert_eq!(pair[1], FrameValue::BulkString(expected.into()));
+        }
+    }
+
+    #[test]
+    fn test_single_field_and_errors() {
+        let db = db_with_hash();
+
+        assert!(matches!(
+            run(&db, &["HRANDFIELD", "hash"]),
+            FrameValue::BulkString(_)
+        ));
+        assert_eq!(
+            run(&db, &["HRANDFIELD", "missing"]),
+            FrameValue::NullBulkString
+        );
+        assert_eq!(
+            run(&db, &["HRANDFIELD", "hash", "1", "WITHSCORES"]),
+            FrameValue::Error("ERR syntax error".into())
+        );
+    }
+
+    #[test]
+    fn test_huge_negative_count() {
+        let db = db_with_hash();
+
+        for count in [i64::MIN.to_string(), "-10000000000".to_string()] {
+            assert_eq!(run(&db, &["HRANDFIELD", "hash", &count]), out_of_range());
+            assert_eq!(
+                run(&db, &["HRANDFIELD", "hash", &count, "WITHVALUES"]),
+                out_of_range()
+            );
+        }
+    }
+}
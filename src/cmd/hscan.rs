@@ -0,0 +1,143 @@
+use super::{
+    CommandError, Parse,
+    scan::{ScanArgs, empty_page},
+    wrong_type,
+};
+use crate::{
+    db::{Db, DbValue, Storage},
+    frame::FrameValue,
+};
+use bytes::Bytes;
+
+/// Iterates over the fields of a hash a page at a time, replying with each
+/// field followed by its value
+pub struct HScan {
+    key: Bytes,
+    args: ScanArgs,
+}
+
+impl HScan {
+    pub fn parse_frames(parse: &mut Parse) -> Result<Self, CommandError> {
+        let key = parse.next_bytes()?;
+        let args = ScanArgs::parse(parse)?;
+        Ok(Self { key, args })
+    }
+
+    pub fn apply<S: Storage>(self, db: &Db<S>) -> FrameValue {
+        match db.read_key(&self.key).get_shared(&self.key) {
+            Some(DbValue::Hash(hash)) => self.args.page(
+                |cursor| {
+                    hash.scan_from(cursor)
+                        .map(|(position, field, value)| (position, (field, value)))
+                },
+                |(field, _)| field,
+                |(field, value), frames| {
+                    frames.push(FrameValue::BulkString(field.clone()));
+                    frames.push(FrameValue::BulkString(value.clone()));
+                },
+            ),
+            Some(_) => wrong_type(),
+            None => empty_page(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod hscan_tests {
+    use crate::{cmd::run, db::Db, frame::FrameValue};
+    use bytes::Bytes;
+    use std::collections::HashMap;
+
+    /// Runs HSCAN with `options` from cursor 0 until it comes back to 0
+    fn scan_all(db: &Db, key: &str, options: &[&str]) -> (HashMap<Bytes, Bytes>, usize) {
+        let mut found = HashMap::new();
+        let mut cursor = "0".to_string();
+        let mut calls = 0;
+        loop {
+            let mut args = vec!["HSCAN", key, &cursor];
+            args.extend_from_slice(options);
+            let FrameValue::Array(reply) = run(db, &args) else {
+                panic!("expected an array");
+            };
+            let [FrameValue::BulkString(next), FrameValue::Array(elements)] = &reply[..] else {
+                panic!("expected a cursor and elements");
+            };
+            for pair in elements.chunks(2) {
+                let [FrameValue::BulkString(field), FrameValue::BulkString(value)] = pair else {
+                    panic!("expected field and value");
+                };
+                found.insert(field.clone(), value.clone());
+            }
+
+            calls += 1;
+            cursor = String::from_utf8(next.to_vec()).unwrap();
+            if cursor == "0" {
+                return (found, calls);
+            }
+        }
+    }
+
+    fn db_with_hash(fields: usize) -> Db {
+        let db = Db::new();
+        for i in 0..fields {
+            run(
+                &db,
+                &["HSET", "hash", &format!("field:{i}"), &i.to_string()],
+            );
+        }
+        db
+    }
+
+    #[test]
+    fn test_scan_to_completion() {
+        let db = db_with_hash(100);
+
+        let (found, calls) = scan_all(&db, "hash", &[]);
+        assert_eq!(found.len(), 100);
+        assert_eq!(found[&Bytes::from("field:42")], Bytes::from("42"));
+        assert_eq!(calls, 10);
+
+        let (found, calls) = scan_all(&db, "hash", &["COUNT", "1000"]);
+        assert_eq!((found.len(), calls), (100, 1));
+    }
+
+    #[test]
+    fn test_match() {
+        let db = db_with_hash(100);
+
+        let (found, _) = scan_all(&db, "hash", &["MATCH", "field:1?", "COUNT", "7"]);
+        let mut fields: Vec<_> = found.into_keys().collect();
+        fields.sort();
+        assert_eq!(
+            fields,
+            (10..20)
+                .map(|i| Bytes::from(format!("field:{i}")))
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_missing_key_and_errors() {
+        let db = db_with_hash(1);
+
+        assert_eq!(
+            run(&db, &["HSCAN", "missing", "0"]),
+            FrameValue::Array(vec![
+                FrameValue::BulkString("0".into()),
+                FrameValue::Array(vec![]),
+            ])
+        );
+        assert_eq!(
+            run(&db, &["HSCAN", "hash", "abc"]),
+            FrameValue::Error("ERR invalid cursor".into())
+        );
+        assert_eq!(
+            run(&db, &["HSCAN", "hash", "0", "COUNT", "0"]),
+            FrameValue::Error("ERR syntax error".into())
+        );
+        assert_eq!(
+            run(&db, &["HSCAN", "hash", "0", "TYPE", "string"]),
+            FrameValue::Error("ERR syntax error".into())
+        );
+    }
+}
@@ -0,0 +1,45 @@
+use super::{CommandError, Parse, wrong_type};
+use crate::{
+    db::{Db, DbValue, Storage},
+    frame::FrameValue,
+};
+use bytes::Bytes;
+
+/// Sets fields of a hash, creating it if needed
+pub struct HSet {
+    key: Bytes,
+    pairs: Vec<(Bytes, Bytes)>,
+}
+
+impl HSet {
+    pub fn parse_frames(parse: &mut Parse) -> Result<Self, CommandError> {
+        let key = parse.next_bytes()?;
+        if !parse.remaining().is_multiple_of(2) {
+            return Err(CommandError::ArgumentCount);
+        }
+
+        let mut pairs = Vec::with_capacity(parse.remaining() / 2);
+        while parse.remaining() > 0 {
+            pairs.push((parse.next_bytes()?, parse.next_bytes()?));
+        }
+
+        Ok(Self { key, pairs })
+    }
+
+    pub fn apply<S: Storage>(self, db: &Db<S>) -> FrameValue {
+        let mut entries = db.lock();
+        let hash = match entries.get_or_insert_with(self.key, || DbValue::Hash(Default::default()))
+        {
+            DbValue::Hash(hash) => hash,
+            _ => return wrong_type(),
+        };
+
+        let added = self
+            .pairs
+            .into_iter()
+            .filter(|(field, value)| hash.insert(field.clone(), value.clone()).is_none())
+            .count();
+
+        FrameValue::Integer(added as i64)
+    }
+}
@@ -0,0 +1,73 @@
+use super::{CommandError, Parse, wrong_type};
+use crate::{
+    db::{Db, DbValue, Storage},
+    frame::FrameValue,
+};
+use bytes::Bytes;
+use std::str::from_utf8;
+
+/// Increments the integer held in the string value of a key
+///
+/// Strings are stored as plain bytes whichever command wrote them, so whether
+/// the value is a number is only decided here. A missing key counts as 0.
+pub struct Incr {
+    key: Bytes,
+}
+
+impl Incr {
+    pub fn parse_frames(parse: &mut Parse) -> Result<Self, CommandError> {
+        let key = parse.next_bytes()?;
+        parse.finish()?;
+        Ok(Self { key })
+    }
+
+    pub fn apply<S: Storage>(self, db: &Db<S>) -> FrameValue {
+        let mut entries = db.lock();
+        let value = match entries.get_or_insert_with(self.key, || DbValue::String("0".into())) {
+            DbValue::String(bytes) => bytes,
+            _ => return wrong_type(),
+        };
+
+        let Some(current) = from_utf8(value).ok().and_then(|s| s.parse::<i64>().ok()) else {
+            return FrameValue::Error("ERR value is not an integer or out of range".into());
+        };
+        let Some(new) = current.checked_add(1) else {
+            return FrameValue::Error("ERR increment or decrement would overflow".into());
+        };
+
+        *value = new.to_string().into();
+        FrameValue::Integer(new)
+    }
+}
+
+#[cfg(test)]
+mod incr_tests {
+    use super::*;
+    use crate::cmd::run;
+
+    #[test]
+    fn test_append_and_incr_share_string_values() {
+        let db = Db::new();
+
+        assert_eq!(run(&db, &["APPEND", "key", "10"]), FrameValue::Integer(2));
+        assert_eq!(run(&db, &["INCR", "key"]), FrameValue::Integer(11));
+        assert_eq!(run(&db, &["APPEND", "key", "x"]), FrameValue::Integer(3));
+        assert_eq!(
+            run(&db, &["GET", "key"]),
+            FrameValue::BulkString("11x".into())
+        );
+        assert_eq!(
+            run(&db, &["INCR", "key"]),
+            FrameValue::Error("ERR value is not an integer or out of range".into())
+        );
+    }
+
+    #[test]
+    fn test_incr_missing_and_wrong_type() {
+        let db = Db::new();
+        run(&db, &["SADD", "set", "a"]);
+
+        assert_eq!(run(&db, &["INCR", "counter"]), FrameValue::Integer(1));
+        assert_eq!(run(&db, &["INCR", "set"]), wrong_type());
+    }
+}
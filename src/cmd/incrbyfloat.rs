@@ -0,0 +1,135 @@
+use super::{CommandError, Parse, wrong_type};
+use crate::{
+    db::{Db, DbValue, Storage},
+    frame::FrameValue,
+};
+use bytes::Bytes;
+use std::str::from_utf8;
+
+/// Increments the floating point number held in the string value of a key
+///
+/// A missing key counts as 0. The result is stored and returned in the shortest
+/// decimal form that reads back as the same number, without an exponent or
+/// trailing zeros.
+pub struct IncrByFloat {
+    key: Bytes,
+    delta: Bytes,
+}
+
+impl IncrByFloat {
+    pub fn parse_frames(parse: &mut Parse) -> Result<Self, CommandError> {
+        let key = parse.next_bytes()?;
+        let delta = parse.next_bytes()?;
+        parse.finish()?;
+        Ok(Self { key, delta })
+    }
+
+    pub fn apply<S: Storage>(self, db: &Db<S>) -> FrameValue {
+        let Some(delta) = parse_float(&self.delta) else {
+            return not_a_float();
+        };
+
+        let mut entries = db.lock();
+        let current = match entries.get(&self.key) {
+            Some(DbValue::String(bytes)) => match parse_float(bytes) {
+                Some(current) => current,
+                None => return not_a_float(),
+            },
+            Some(_) => return wrong_type(),
+            None => 0.0,
+        };
+
+        let new = current + delta;
+        if !new.is_finite() {
+            return FrameValue::Error("ERR increment would produce NaN or Infinity".into());
+        }
+
+        // Updated in place so the key keeps its expiry
+        let new = Bytes::from(new.to_string());
+        match entries.get_mut(&self.key) {
+            Some(DbValue::String(bytes)) => *bytes = new.clone(),
+            _ => entries.insert(self.key, DbValue::String(new.clone())),
+        }
+        FrameValue::BulkString(new)
+    }
+}
+
+/// Parses a float the way Redis accepts one, without surrounding whitespace
+/// and never as NaN
+pub fn parse_float(bytes: &[u8]) -> Option<f64> {
+    from_utf8(bytes)
+        .ok()
+        .and_then(|s| s.parse::<f64>().ok())
+        .filter(|value| !value.is_nan())
+}
+
+pub fn not_a_float() -> FrameValue {
+    FrameValue::Error("ERR value is not a valid float".into())
+}
+
+#[cfg(test)]
+mod incrbyfloat_tests {
+    use super::*;
+    use crate::cmd::run;
+
+    #[test]
+    fn test_missing_key_counts_as_zero() {
+        let db = Db::new();
+
+        assert_eq!(
+            run(&db, &["INCRBYFLOAT", "key", "2.5"]),
+            FrameValue::BulkString("2.5".into())
+        );
+        assert_eq!(
+            run(&db, &["GET", "key"]),
+            FrameValue::BulkString("2.5".into())
+        );
+    }
+
+    #[test]
+    fn test_fractional_increments() {
+        let db = Db::new();
+        run(&db, &["SET", "key", "10.50"]);
+
+        assert_eq!(
+            run(&db, &["INCRBYFLOAT", "key", "0.1"]),
+            FrameValue::BulkString("10.6".into())
+        );
+        assert_eq!(
+            run(&db, &["INCRBYFLOAT", "key", "-5.6"]),
+            FrameValue::BulkString("5".into())
+        );
+        assert_eq!(
+            run(&db, &["INCRBYFLOAT", "key", "5.0e3"]),
+            FrameValue::BulkString("5005".into())
+        );
+    }
+
+    #[test]
+    fn test_rejects_non_numeric_and_non_finite() {
+        let db = Db::new();
+        run(&db, &["SET", "text", "abc"]);
+        run(&db, &["SET", "big", "1e308"]);
+        run(&db, &["SADD", "set", "a"]);
+
+        assert_eq!(
+            run(&db, &["INCRBYFLOAT", "text", "1"]),
+            FrameValue::Error("ERR value is not a valid float".into())
+        );
+        assert_eq!(
+            run(&db, &["INCRBYFLOAT", "key", "nan"]),
+            FrameValue::Error("ERR value is not a valid float".into())
+        );
+        assert_eq!(
+            run(&db, &["INCRBYFLOAT", "big", "1e308"]),
+            FrameValue::Error("ERR increment would produce NaN or Infinity".into())
+        );
+        assert_eq!(
+            run(&db, &["INCRBYFLOAT", "key", "inf"]),
+            FrameValue::Error("ERR increment would produce NaN or Infinity".into())
+        );
+        assert_eq!(run(&db, &["INCRBYFLOAT", "set", "1"]), wrong_type());
+        // Failed increments leave no key behind
+        assert_eq!(run(&db, &["GET", "key"]), FrameValue::NullBulkString);
+    }
+}
@@ -0,0 +1,147 @@
+use super::{CommandError, Parse, are_equal};
+use crate::{
+    client::Client,
+    db::{Db, Storage},
+    frame::FrameValue,
+    replication::LinkState,
+};
+use bytes::Bytes;
+use std::fmt::Write;
+
+/// Sections INFO knows how to render, in the order they are reported
+const SECTIONS: &[&str] = &["stats", "replication"];
+
+/// Reports server state as `field:value` lines grouped into sections
+///
+/// With no arguments, or `default`, `all` or `everything`, every section is
+/// reported. Otherwise only the named ones are, and unknown names are ignored.
+pub struct Info {
+    sections: Vec<Bytes>,
+}
+
+impl Info {
+    pub fn parse_frames(parse: &mut Parse) -> Result<Self, CommandError> {
+        Ok(Self {
+            sections: parse.rest_bytes()?,
+        })
+    }
+
+    pub fn apply<S: Storage>(self, db: &Db<S>, client: &Client) -> FrameValue {
+        let everything = self.sections.is_empty()
+            || self.sections.iter().any(|section| {
+                [&b"default"[..], b"all", b"everything"]
+                    .iter()
+                    .any(|name| are_equal(section, name))
+            });
+
+        let reports: Vec<String> = SECTIONS
+            .iter()
+            .filter(|name| {
+                everything
+                    || self
+                        .sections
+                        .iter()
+                        .any(|section| are_equal(section, name.as_bytes()))
+            })
+            .map(|&name| match name {
+                "stats" => stats(db, client),
+                "replication" => replication(db),
+                _ => unreachable!("every section in SECTIONS is rendered"),
+            })
+            .collect();
+
+        FrameValue::BulkString(reports.join("\r\n").into())
+    }
+}
+
+fn stats<S: Storage>(db: &Db<S>, client: &Client) -> String {
+    let lookups = db.lookup_stats();
+    let totals = client.clients().totals();
+    let mut report = String::from("# Stats\r\n");
+
+    let _ = write!(report, "total_commands_processed:{}\r\n", totals.commands);
+    let _ = write!(report, "total_net_input_bytes:{}\r\n", totals.net_input);
+    let _ = write!(report, "total_net_output_bytes:{}\r\n", totals.net_output);
+
+    let _ = write!(report, "keyspace_hits:{}\r\n", lookups.hits);
+    let _ = write!(report, "keyspace_misses:{}\r\n", lookups.misses);
+    report
+}
+
+fn replication<S: Storage>(db: &Db<S>) -> String {
+    let replication = db.replication();
+    let mut report = String::from("# Replication\r\n");
+
+    if let Some(master) = replication.master() {
+        let status = match master.state {
+            LinkState::Connected => "up",
+            _ => "down",
+        };
+        report.push_str("role:slave\r\n");
+        let _ = write!(report, "master_host:{}\r\n", master.host);
+        let _ = write!(report, "master_port:{}\r\n", master.port);
+        let _ = write!(report, "master_link_status:{status}\r\n");
+        let _ = write!(report, "slave_repl_offset:{}\r\n", master.offset);
+    } else {
+        report.push_str("role:master\r\n");
+    }
+
+    let _ = write!(
+        report,
+        "connected_slaves:{}\r\n",
+        replication.replicas().len()
+    );
+    let _ = write!(report, "master_replid:{}\r\n", replication.replid());
+    let _ = write!(report, "master_repl_offset:{}\r\n", replication.offset());
+    report
+}
+
+#[cfg(test)]
+mod info_tests {
+    use super::*;
+    use crate::cmd::run;
+
+    fn report(db: &Db, args: &[&str]) -> String {
+        let FrameValue::BulkString(report) = run(db, args) else {
+            panic!("expected a bulk string");
+        };
+        String::from_utf8(report.to_vec()).unwrap()
+    }
+
+    #[test]
+    fn test_replication_section() {
+        let db = Db::new();
+
+        for args in [&["INFO"][..], &["INFO", "Replication"], &["INFO", "all"]] {
+            let report = report(&db, args);
+            assert!(report.contains("# Replication\r\n"), "{report}");
+            assert!(report.contains("\r\nrole:master\r\n"), "{report}");
+            assert!(report.contains("\r\nmaster_repl_offset:0\r\n"), "{report}");
+        }
+    }
+
+    #[test]
+    fn test_keyspace_hits_and_misses() {
+        let db = Db::new();
+        run(&db, &["SET", "key", "value"]);
+        run(&db, &["SADD", "set", "a", "b"]);
+
+        run(&db, &["GET", "key"]);
+        run(&db, &["GET", "missing"]);
+        // Each key a multi-key command reads is a lookup of its own
+        run(&db, &["SINTERCARD", "3", "set", "missing", "other"]);
+        run(&db, &["SMEMBERS", "set"]);
+        // Writes don't count
+        run(&db, &["SET", "missing", "value"]);
+
+        let report = report(&db, &["INFO", "stats"]);
+        assert!(report.starts_with("# Stats\r\n"), "{report}");
+        assert!(report.contains("\r\nkeyspace_hits:3\r\n"), "{report}");
+        assert!(report.contains("\r\nkeyspace_misses:3\r\n"), "{report}");
+    }
+
+    #[test]
+    fn test_unknown_section_is_empty() {
+        assert_eq!(report(&Db::new(), &["INFO", "nonsense"]), "");
+    }
+}
@@ -0,0 +1,106 @@
+use super::{CommandError, Parse, wrong_type};
+use crate::{
+    db::{Db, DbValue, Storage},
+    frame::FrameValue,
+};
+use bytes::Bytes;
+
+/// Gets the element at an index of a list, negative indices count from the tail
+pub struct LIndex {
+    key: Bytes,
+    index: i64,
+}
+
+impl LIndex {
+    pub fn parse_frames(parse: &mut Parse) -> Result<Self, CommandError> {
+        let key = parse.next_bytes()?;
+        let index = parse.next_int()?;
+        parse.finish()?;
+        Ok(Self { key, index })
+    }
+
+    pub fn apply<S: Storage>(self, db: &Db<S>) -> FrameValue {
+        let entries = db.read_key(&self.key);
+        let list = match entries.get_shared(&self.key) {
+            Some(DbValue::List(list)) => list,
+            Some(_) => return wrong_type(),
+            None => return FrameValue::NullBulkString,
+        };
+
+        match resolve_index(self.index, list.len()) {
+            Some(index) => FrameValue::BulkString(list[index].clone()),
+            None => FrameValue::NullBulkString,
+        }
+    }
+}
+
+/// Resolves a possibly negative index against a length
+///
+/// Returns `None` if it falls outside the list.
+pub fn resolve_index(index: i64, len: usize) -> Option<usize> {
+    let index = if index < 0 { len as i64 + index } else { index };
+    (0..len as i64).contains(&index).then_some(index as usize)
+}
+
+#[cfg(test)]
+mod lindex_tests {
+    use super::*;
+    use crate::cmd::run;
+
+    #[test]
+    fn test_positive_and_negative_index() {
+        let db = Db::new();
+        run(&db, &["RPUSH", "list", "a", "b", "c"]);
+
+        assert_eq!(
+            run(&db, &["LINDEX", "list", "0"]),
+            FrameValue::BulkString("a".into())
+        );
+        assert_eq!(
+            run(&db, &["LINDEX", "list", "2"]),
+            FrameValue::BulkString("c".into())
+        );
+        assert_eq!(
+            run(&db, &["LINDEX", "list", "-1"]),
+            FrameValue::BulkString("c".into())
+        );
+        assert_eq!(
+            run(&db, &["LINDEX", "list", "-3"]),
+            FrameValue::BulkString("a".into())
+        );
+        assert_eq!(
+            run(&db, &["LINDEX", "list", "3"]),
+            FrameValue::NullBulkString
+        );
+        assert_eq!(
+            run(&db, &["LINDEX", "list", "-4"]),
+            FrameValue::NullBulkString
+        );
+        assert_eq!(
+            run(&db, &["LINDEX", "missing", "0"]),
+            FrameValue::NullBulkString
+        );
+    }
+
+    #[test]
+    fn test_variadic_push() {
+        let db = Db::new();
+
+        assert_eq!(
+            run(&db, &["LPUSH", "list", "a", "b"]),
+            FrameValue::Integer(2)
+        );
+        assert_eq!(
+            run(&db, &["RPUSH", "list", "c", "d"]),
+            FrameValue::Integer(4)
+        );
+        assert_eq!(
+            run(&db, &["LINDEX", "list", "0"]),
+            FrameValue::BulkString("b".into())
+        );
+        assert_eq!(
+            run(&db, &["LINDEX", "list", "-1"]),
+            FrameValue::BulkString("d".into())
+        );
+    }
+}
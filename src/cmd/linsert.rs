@@ -0,0 +1,146 @@
+use super::{CommandError, Parse, are_equal, wrong_type};
+use crate::{
+    db::{Db, DbValue, Storage},
+    frame::FrameValue,
+};
+use bytes::Bytes;
+
+/// Inserts an element next to the first occurrence of a pivot in a list
+pub struct LInsert {
+    key: Bytes,
+    before_after: BeforeAfter,
+    pivot: Bytes,
+    element: Bytes,
+}
+
+/// Side of the pivot the element goes on
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum BeforeAfter {
+    Before,
+    After,
+}
+
+impl LInsert {
+    pub fn parse_frames(parse: &mut Parse) -> Result<Self, CommandError> {
+        let key = parse.next_bytes()?;
+        let side = parse.next_bytes()?;
+        let before_after = if are_equal(&side, b"BEFORE") {
+            BeforeAfter::Before
+        } else if are_equal(&side, b"AFTER") {
+            BeforeAfter::After
+        } else {
+            return Err(CommandError::Syntax);
+        };
+        let pivot = parse.next_bytes()?;
+        let element = parse.next_bytes()?;
+        parse.finish()?;
+
+        Ok(Self {
+            key,
+            before_after,
+            pivot,
+            element,
+        })
+    }
+
+    pub fn apply<S: Storage>(self, db: &Db<S>) -> FrameValue {
+        let mut entries = db.lock();
+        let list = match entries.get_mut(&self.key) {
+            Some(DbValue::List(list)) => list,
+            Some(_) => return wrong_type(),
+            None => return FrameValue::Integer(0),
+        };
+
+        let Some(index) = list.iter().position(|element| *element == self.pivot) else {
+            return FrameValue::Integer(-1);
+        };
+        let index = match self.before_after {
+            BeforeAfter::Before => index,
+            BeforeAfter::After => index + 1,
+        };
+        list.insert(index, self.element);
+
+        FrameValue::Integer(list.len() as i64)
+    }
+}
+
+#[cfg(test)]
+mod linsert_tests {
+    use super::*;
+    use crate::cmd::run;
+
+    fn elements(db: &Db, key: &str) -> Vec<Bytes> {
+        match db.lock().get(key.as_bytes()) {
+            Some(DbValue::List(list)) => list.iter().cloned().collect(),
+            _ => panic!("expected a list"),
+        }
+    }
+
+    #[test]
+    fn test_insert_before() {
+        let db = Db::new();
+        run(&db, &["RPUSH", "list", "a", "b", "b"]);
+
+        assert_eq!(
+            run(&db, &["LINSERT", "list", "BEFORE", "b", "x"]),
+            FrameValue::Integer(4)
+        );
+        assert_eq!(elements(&db, "list"), ["a", "x", "b", "b"]);
+    }
+
+    #[test]
+    fn test_insert_after() {
+        let db = Db::new();
+        run(&db, &["RPUSH", "list", "a", "b", "b"]);
+
+        assert_eq!(
+            run(&db, &["LINSERT", "list", "after", "b", "x"]),
+            FrameValue::Integer(4)
+        );
+        assert_eq!(
+            run(&db, &["LINSERT", "list", "AFTER", "b", "y"]),
+            FrameValue::Integer(5)
+        );
+        assert_eq!(elements(&db, "list"), ["a", "b", "y", "x", "b"]);
+    }
+
+    #[test]
+    fn test_pivot_not_found() {
+        let db = Db::new();
+        run(&db, &["RPUSH", "list", "a"]);
+
+        assert_eq!(
+            run(&db, &["LINSERT", "list", "BEFORE", "z", "x"]),
+            FrameValue::Integer(-1)
+        );
+        assert_eq!(elements(&db, "list"), ["a"]);
+    }
+
+    #[test]
+    fn test_missing_key() {
+        let db = Db::new();
+
+        assert_eq!(
+            run(&db, &["LINSERT", "missing", "BEFORE", "a", "x"]),
+            FrameValue::Integer(0)
+        );
+        assert!(db.lock().get(b"missing").is_none());
+    }
+
+    #[test]
+    fn test_errors() {
+        let db = Db::new();
+        run(&db, &["SET", "string", "value"]);
+
+        assert_eq!(
+            run(&db, &["LINSERT", "string", "BEFORE", "a", "x"]),
+            FrameValue::Error(
+                "WRONGTYPE Operation against a key holding the wrong kind of value".into()
+            )
+        );
+        assert_eq!(
+            run(&db, &["LINSERT", "list", "NEXTTO", "a", "x"]),
+            FrameValue::Error("ERR syntax error".into())
+        );
+    }
+}
@@ -0,0 +1,170 @@
+use super::{CommandError, Parse, push::ListEnd, wrong_type};
+use crate::{
+    db::{Db, DbValue, Storage},
+    frame::FrameValue,
+};
+use bytes::Bytes;
+
+/// Pops an element off one end of a list and pushes it onto an end of another,
+/// atomically
+///
+/// The source and destination may be the same list, which rotates it.
+pub struct LMove {
+    src: Bytes,
+    dst: Bytes,
+    src_end: ListEnd,
+    dst_end: ListEnd,
+}
+
+impl LMove {
+    pub fn parse_frames(parse: &mut Parse) -> Result<Self, CommandError> {
+        let src = parse.next_bytes()?;
+        let dst = parse.next_bytes()?;
+        let src_end = ListEnd::parse(&parse.next_bytes()?)?;
+        let dst_end = ListEnd::parse(&parse.next_bytes()?)?;
+        parse.finish()?;
+        Ok(Self {
+            src,
+            dst,
+            src_end,
+            dst_end,
+        })
+    }
+
+    /// `RPOPLPUSH`, which is `LMOVE` from the right to the left
+    pub fn parse_rpoplpush(parse: &mut Parse) -> Result<Self, CommandError> {
+        let src = parse.next_bytes()?;
+        let dst = parse.next_bytes()?;
+        parse.finish()?;
+        Ok(Self {
+            src,
+            dst,
+            src_end: ListEnd::Right,
+            dst_end: ListEnd::Left,
+        })
+    }
+
+    pub fn apply<S: Storage>(self, db: &Db<S>) -> FrameValue {
+        let mut entries = db.lock();
+
+        match entries.get(&self.src) {
+            Some(DbValue::List(_)) => {}
+            Some(_) => return wrong_type(),
+            None => return FrameValue::NullBulkString,
+        }
+        // Checked before popping, so a failed move leaves the source as it was
+        if let Some(dst) = entries.get(&self.dst)
+            && !matches!(dst, DbValue::List(_))
+        {
+            return wrong_type();
+        }
+
+        let Some(DbValue::List(src)) = entries.get_mut(&self.src) else {
+            unreachable!("source was checked to be a list");
+        };
+        let element = match self.src_end {
+            ListEnd::Left => src.pop_front(),
+            ListEnd::Right => src.pop_back(),
+        }
+        .expect("lists are removed once empty");
+        if src.is_empty() {
+            entries.remove(&self.src);
+        }
+
+        let DbValue::List(dst) =
+            entries.get_or_insert_with(self.dst, || DbValue::List(Default::default()))
+        else {
+            unreachable!("destination was checked to be a list");
+        };
+        match self.dst_end {
+            ListEnd::Left => dst.push_front(element.clone()),
+            ListEnd::Right => dst.push_back(element.clone()),
+        }
+
+        FrameValue::BulkString(element)
+    }
+}
+
+#[cfg(test)]
+mod lmove_tests {
+    use crate::{cmd::run, db::Db, frame::FrameValue};
+
+    fn list(db: &Db, key: &str) -> Vec<FrameValue> {
+        (0..)
+            .map(|index| run(db, &["LINDEX", key, &index.to_string()]))
+            .take_while(|element| *element != FrameValue::NullBulkString)
+            .collect()
+    }
+
+    fn elements(elements: &[&str]) -> Vec<FrameValue> {
+        elements
+            .iter()
+            .map(|element| FrameValue::BulkString(element.to_string().into()))
+            .collect()
+    }
+
+    #[test]
+    fn test_move_between_keys() {
+        let db = Db::new();
+        run(&db, &["RPUSH", "src", "a", "b", "c"]);
+        run(&db, &["RPUSH", "dst", "x"]);
+
+        assert_eq!(
+            run(&db, &["LMOVE", "src", "dst", "LEFT", "RIGHT"]),
+            FrameValue::BulkString("a".into())
+        );
+        assert_eq!(
+            run(&db, &["RPOPLPUSH", "src", "dst"]),
+            FrameValue::BulkString("c".into())
+        );
+        assert_eq!(list(&db, "src"), elements(&["b"]));
+        assert_eq!(list(&db, "dst"), elements(&["c", "x", "a"]));
+
+        // Moving the last element removes the source and creates the destination
+        run(&db, &["LMOVE", "src", "new", "right", "left"]);
+        assert_eq!(run(&db, &["DBSIZE"]), FrameValue::Integer(2));
+        assert_eq!(list(&db, "new"), elements(&["b"]));
+    }
+
+    #[test]
+    fn test_rotate_same_key() {
+        let db = Db::new();
+        run(&db, &["RPUSH", "list", "a", "b", "c"]);
+
+        assert_eq!(
+            run(&db, &["RPOPLPUSH", "list", "list"]),
+            FrameValue::BulkString("c".into())
+        );
+        assert_eq!(list(&db, "list"), elements(&["c", "a", "b"]));
+
+        run(&db, &["LMOVE", "list", "list", "LEFT", "RIGHT"]);
+        assert_eq!(list(&db, "list"), elements(&["a", "b", "c"]));
+
+        run(&db, &["RPUSH", "single", "x"]);
+        run(&db, &["LMOVE", "single", "single", "LEFT", "LEFT"]);
+        assert_eq!(list(&db, "single"), elements(&["x"]));
+    }
+
+    #[test]
+    fn test_empty_source() {
+        let db = Db::new();
+        run(&db, &["SET", "string", "value"]);
+
+        assert_eq!(
+            run(&db, &["LMOVE", "missing", "dst", "LEFT", "LEFT"]),
+            FrameValue::NullBulkString
+        );
+        assert_eq!(run(&db, &["DBSIZE"]), FrameValue::Integer(1));
+
+        run(&db, &["RPUSH", "list", "a"]);
+        assert!(matches!(
+            run(&db, &["RPOPLPUSH", "list", "string"]),
+            FrameValue::Error(e) if e.starts_with(b"WRONGTYPE")
+        ));
+        assert_eq!(list(&db, "list"), elements(&["a"]));
+        assert_eq!(
+            run(&db, &["LMOVE", "list", "dst", "UP", "LEFT"]),
+            FrameValue::Error("ERR syntax error".into())
+        );
+    }
+}
@@ -0,0 +1,177 @@
+use super::{CommandError, Parse, are_equal, wrong_type};
+use crate::{
+    db::{Db, DbValue, Storage},
+    frame::FrameValue,
+};
+use bytes::Bytes;
+
+/// Finds the indices at which an element occurs in a list
+///
+/// `RANK` picks which match to start from, counting from the tail when negative,
+/// `COUNT` asks for several matches and `MAXLEN` bounds how many elements are
+/// compared.
+pub struct LPos {
+    key: Bytes,
+    element: Bytes,
+    rank: Option<i64>,
+    count: Option<i64>,
+    maxlen: Option<i64>,
+}
+
+impl LPos {
+    pub fn parse_frames(parse: &mut Parse) -> Result<Self, CommandError> {
+        let key = parse.next_bytes()?;
+        let element = parse.next_bytes()?;
+        let (mut rank, mut count, mut maxlen) = (None, None, None);
+
+        // Options may be repeated, the last one wins
+        while let Some(option) = parse.next_optional_bytes()? {
+            let slot = match option.as_ref() {
+                option if are_equal(option, b"RANK") => &mut rank,
+                option if are_equal(option, b"COUNT") => &mut count,
+                option if are_equal(option, b"MAXLEN") => &mut maxlen,
+                _ => return Err(CommandError::Syntax),
+            };
+            *slot = Some(parse.next_int()?);
+        }
+
+        Ok(Self {
+            key,
+            element,
+            rank,
+            count,
+            maxlen,
+        })
+    }
+
+    pub fn apply<S: Storage>(self, db: &Db<S>) -> FrameValue {
+        let rank = self.rank.unwrap_or(1);
+        if rank == 0 {
+            return FrameValue::Error(
+                "ERR RANK can't be zero: use 1 to start from the first match, 2 from the second ... or use negative to start from the last match"
+                    .into(),
+            );
+        }
+        if self.count.is_some_and(|count| count < 0) {
+            return FrameValue::Error("ERR COUNT can't be negative".into());
+        }
+        if self.maxlen.is_some_and(|maxlen| maxlen < 0) {
+            return FrameValue::Error("ERR MAXLEN can't be negative".into());
+        }
+
+        let entries = db.read_key(&self.key);
+        let list = match entries.get_shared(&self.key) {
+            Some(DbValue::List(list)) => list,
+            Some(_) => return wrong_type(),
+            None if self.count.is_some() => return FrameValue::Array(vec![]),
+            None => return FrameValue::NullBulkString,
+        };
+
+        // Zero means no limit for both COUNT and MAXLEN
+        let scanned = match self.maxlen {
+            Some(maxlen) if maxlen > 0 => maxlen as usize,
+            _ => list.len(),
+        };
+        let wanted = match self.count {
+            Some(0) => usize::MAX,
+            Some(count) => count as usize,
+            None => 1,
+        };
+
+        let indexed = list.iter().enumerate();
+        let elements: Box<dyn Iterator<Item = _>> = if rank > 0 {
+            Box::new(indexed.take(scanned))
+        } else {
+            Box::new(indexed.rev().take(scanned))
+        };
+        let mut matches = elements
+            .filter(|(_, element)| **element == self.element)
+            .map(|(index, _)| FrameValue::Integer(index as i64))
+            .skip(rank.unsigned_abs() as usize - 1)
+            .take(wanted);
+
+        match self.count {
+            Some(_) => FrameValue::Array(matches.collect()),
+            None => matches.next().unwrap_or(FrameValue::NullBulkString),
+        }
+    }
+}
+
+#[cfg(test)]
+mod lpos_tests {
+    use crate::{cmd::run, db::Db, frame::FrameValue};
+
+    fn db_with_list() -> Db {
+        let db = Db::new();
+        run(
+            &db,
+            &["RPUSH", "list", "a", "b", "c", "1", "2", "3", "c", "c"],
+        );
+        db
+    }
+
+    fn indices(indices: &[i64]) -> FrameValue {
+        FrameValue::Array(indices.iter().copied().map(FrameValue::Integer).collect())
+    }
+
+    #[test]
+    fn test_first_match() {
+        let db = db_with_list();
+
+        assert_eq!(run(&db, &["LPOS", "list", "c"]), FrameValue::Integer(2));
+        assert_eq!(run(&db, &["LPOS", "list", "x"]), FrameValue::NullBulkString);
+        assert_eq!(
+            run(&db, &["LPOS", "missing", "c"]),
+            FrameValue::NullBulkString
+        );
+    }
+
+    #[test]
+    fn test_rank() {
+        let db = db_with_list();
+
+        assert_eq!(
+            run(&db, &["LPOS", "list", "c", "RANK", "2"]),
+            FrameValue::Integer(6)
+        );
+        assert_eq!(
+            run(&db, &["LPOS", "list", "c", "RANK", "-1"]),
+            FrameValue::Integer(7)
+        );
+        assert_eq!(
+            run(&db, &["LPOS", "list", "c", "RANK", "4"]),
+            FrameValue::NullBulkString
+        );
+        assert_eq!(
+            run(&db, &["LPOS", "list", "c", "RANK", "0"]),
+            FrameValue::Error("ERR RANK can't be zero: use 1 to start from the first match, 2 from the second ... or use negative to start from the last match".into())
+        );
+    }
+
+    #[test]
+    fn test_count() {
+        let db = db_with_list();
+
+        assert_eq!(
+            run(&db, &["LPOS", "list", "c", "COUNT", "2"]),
+            indices(&[2, 6])
+        );
+        assert_eq!(
+            run(&db, &["LPOS", "list", "c", "COUNT", "0"]),
+            indices(&[2, 6, 7])
+        );
+        assert_eq!(
+            run(&db, &["LPOS", "list", "c", "RANK", "-1", "COUNT", "2"]),
+            indices(&[7, 6])
+        );
+        assert_eq!(
+            run(&db, &["LPOS", "list", "c", "COUNT", "0", "MAXLEN", "7"]),
+            indices(&[2, 6])
+        );
+        assert_eq!(run(&db, &["LPOS", "list", "x", "COUNT", "0"]), indices(&[]));
+        assert_eq!(
+            run(&db, &["LPOS", "list", "c", "COUNT", "-1"]),
+            FrameValue::Error("ERR COUNT can't be negative".into())
+        );
+    }
+}
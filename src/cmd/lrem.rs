@@ -0,0 +1,126 @@
+use super::{CommandError, Parse, wrong_type};
+use crate::{
+    db::{Db, DbValue, Storage},
+    frame::FrameValue,
+};
+use bytes::Bytes;
+use std::collections::VecDeque;
+
+/// Removes elements equal to `value` from a list
+///
+/// A positive `count` removes up to that many starting from the head, a negative one
+/// from the tail, and zero removes every match.
+pub struct LRem {
+    key: Bytes,
+    count: i64,
+    value: Bytes,
+}
+
+impl LRem {
+    pub fn parse_frames(parse: &mut Parse) -> Result<Self, CommandError> {
+        let key = parse.next_bytes()?;
+        let count = parse.next_int()?;
+        let value = parse.next_bytes()?;
+        parse.finish()?;
+        Ok(Self { key, count, value })
+    }
+
+    pub fn apply<S: Storage>(self, db: &Db<S>) -> FrameValue {
+        let mut entries = db.lock();
+        let list = match entries.get_mut(&self.key) {
+            Some(DbValue::List(list)) => list,
+            Some(_) => return wrong_type(),
+            None => return FrameValue::Integer(0),
+        };
+
+        let limit = match self.count {
+            0 => usize::MAX,
+            count => count.unsigned_abs() as usize,
+        };
+        let mut removed = 0;
+
+        if self.count >= 0 {
+            list.retain(|element| {
+                let remove = removed < limit && *element == self.value;
+                removed += remove as usize;
+                !remove
+            });
+        } else {
+            let mut kept = VecDeque::with_capacity(list.len());
+            while let Some(element) = list.pop_back() {
+                if removed < limit && element == self.value {
+                    removed += 1;
+                } else {
+                    kept.push_front(element);
+                }
+            }
+            *list = kept;
+        }
+
+        if list.is_empty() {
+            entries.remove(&self.key);
+        }
+
+        FrameValue::Integer(removed as i64)
+    }
+}
+
+#[cfg(test)]
+mod lrem_tests {
+    use super::*;
+    use crate::cmd::run;
+
+    fn elements(db: &Db) -> Vec<FrameValue> {
+        (0..)
+            .map(|i| run(db, &["LINDEX", "list", &i.to_string()]))
+            .take_while(|frame| *frame != FrameValue::NullBulkString)
+            .collect()
+    }
+
+    fn bulk(values: &[&'static str]) -> Vec<FrameValue> {
+        values
+            .iter()
+            .map(|value| FrameValue::BulkString(Bytes::from(*value)))
+            .collect()
+    }
+
+    #[test]
+    fn test_remove_from_head() {
+        let db = Db::new();
+        run(&db, &["RPUSH", "list", "x", "a", "x", "b", "x"]);
+
+        assert_eq!(
+            run(&db, &["LREM", "list", "2", "x"]),
+            FrameValue::Integer(2)
+        );
+        assert_eq!(elements(&db), bulk(&["a", "b", "x"]));
+    }
+
+    #[test]
+    fn test_remove_from_tail() {
+        let db = Db::new();
+        run(&db, &["RPUSH", "list", "x", "a", "x", "b", "x"]);
+
+        assert_eq!(
+            run(&db, &["LREM", "list", "-2", "x"]),
+            FrameValue::Integer(2)
+        );
+        assert_eq!(elements(&db), bulk(&["x", "a", "b"]));
+    }
+
+    #[test]
+    fn test_remove_all() {
+        let db = Db::new();
+        run(&db, &["RPUSH", "list", "x", "x"]);
+
+        assert_eq!(
+            run(&db, &["LREM", "list", "0", "x"]),
+            FrameValue::Integer(2)
+        );
+        assert!(db.lock().get(&Bytes::from("list")).is_none());
+        assert_eq!(
+            run(&db, &["LREM", "list", "0", "x"]),
+            FrameValue::Integer(0)
+        );
+    }
+}
@@ -0,0 +1,80 @@
+use super::{CommandError, Parse, lindex::resolve_index, wrong_type};
+use crate::{
+    db::{Db, DbValue, Storage},
+    frame::FrameValue,
+};
+use bytes::Bytes;
+
+/// Replaces the element at an index of a list
+pub struct LSet {
+    key: Bytes,
+    index: i64,
+    value: Bytes,
+}
+
+impl LSet {
+    pub fn parse_frames(parse: &mut Parse) -> Result<Self, CommandError> {
+        let key = parse.next_bytes()?;
+        let index = parse.next_int()?;
+        let value = parse.next_bytes()?;
+        parse.finish()?;
+        Ok(Self { key, index, value })
+    }
+
+    pub fn apply<S: Storage>(self, db: &Db<S>) -> FrameValue {
+        let mut entries = db.lock();
+        let list = match entries.get_mut(&self.key) {
+            Some(DbValue::List(list)) => list,
+            Some(_) => return wrong_type(),
+            None => return FrameValue::Error("ERR no such key".into()),
+        };
+
+        match resolve_index(self.index, list.len()) {
+            Some(index) => {
+                list[index] = self.value;
+                FrameValue::SimpleString("OK".into())
+            }
+            None => FrameValue::Error("ERR index out of range".into()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod lset_tests {
+    use super::*;
+    use crate::cmd::run;
+
+    #[test]
+    fn test_set_element() {
+        let db = Db::new();
+        run(&db, &["RPUSH", "list", "a", "b", "c"]);
+
+        assert_eq!(
+            run(&db, &["LSET", "list", "-1", "z"]),
+            FrameValue::SimpleString("OK".into())
+        );
+        assert_eq!(
+            run(&db, &["LINDEX", "list", "2"]),
+            FrameValue::BulkString("z".into())
+        );
+    }
+
+    #[test]
+    fn test_out_of_range() {
+        let db = Db::new();
+        run(&db, &["RPUSH", "list", "a"]);
+
+        assert_eq!(
+            run(&db, &["LSET", "list", "1", "z"]),
+            FrameValue::Error("ERR index out of range".into())
+        );
+        assert_eq!(
+            run(&db, &["LSET", "list", "-2", "z"]),
+            FrameValue::Error("ERR index out of range".into())
+        );
+        assert_eq!(
+            run(&db, &["LSET", "missing", "0", "z"]),
+            FrameValue::Error("ERR no such key".into())
+        );
+    }
+}
@@ -0,0 +1,110 @@
+use super::{CommandError, Parse, wrong_type};
+use crate::{
+    db::{Db, DbValue, Storage},
+    frame::FrameValue,
+};
+use bytes::Bytes;
+
+/// Trims a list down to the inclusive range `start..=stop`
+pub struct LTrim {
+    key: Bytes,
+    start: i64,
+    stop: i64,
+}
+
+impl LTrim {
+    pub fn parse_frames(parse: &mut Parse) -> Result<Self, CommandError> {
+        let key = parse.next_bytes()?;
+        let start = parse.next_int()?;
+        let stop = parse.next_int()?;
+        parse.finish()?;
+        Ok(Self { key, start, stop })
+    }
+
+    pub fn apply<S: Storage>(self, db: &Db<S>) -> FrameValue {
+        let mut entries = db.lock();
+        let list = match entries.get_mut(&self.key) {
+            Some(DbValue::List(list)) => list,
+            Some(_) => return wrong_type(),
+            None => return FrameValue::SimpleString("OK".into()),
+        };
+
+        match list_range(self.start, self.stop, list.len()) {
+            Some((start, stop)) => {
+                list.truncate(stop + 1);
+                list.drain(..start);
+            }
+            None => {
+                entries.remove(&self.key);
+            }
+        }
+
+        FrameValue::SimpleString("OK".into())
+    }
+}
+
+/// Resolves an inclusive list range, where negative indices count from the tail
+///
+/// Unlike string ranges, a `stop` that still falls before the head after resolving
+/// makes the range empty. Returns `None` for an empty range.
+pub fn list_range(start: i64, stop: i64, len: usize) -> Option<(usize, usize)> {
+    let len = len as i64;
+    let start = if start < 0 { len + start } else { start }.max(0);
+    let stop = if stop < 0 { len + stop } else { stop }.min(len - 1);
+
+    if start > stop || start >= len {
+        None
+    } else {
+        Some((start as usize, stop as usize))
+    }
+}
+
+#[cfg(test)]
+mod ltrim_tests {
+    use super::*;
+    use crate::cmd::run;
+
+    #[test]
+    fn test_trim() {
+        let db = Db::new();
+        run(&db, &["RPUSH", "list", "a", "b", "c", "d"]);
+
+        assert_eq!(
+            run(&db, &["LTRIM", "list", "1", "-2"]),
+            FrameValue::SimpleString("OK".into())
+        );
+        assert_eq!(
+            run(&db, &["LINDEX", "list", "0"]),
+            FrameValue::BulkString("b".into())
+        );
+        assert_eq!(
+            run(&db, &["LINDEX", "list", "-1"]),
+            FrameValue::BulkString("c".into())
+        );
+        assert_eq!(
+            run(&db, &["LINDEX", "list", "2"]),
+            FrameValue::NullBulkString
+        );
+    }
+
+    #[test]
+    fn test_trim_to_empty_removes_key() {
+        let db = Db::new();
+        run(&db, &["RPUSH", "list", "a", "b"]);
+
+        assert_eq!(
+            run(&db, &["LTRIM", "list", "5", "10"]),
+            FrameValue::SimpleString("OK".into())
+        );
+        assert!(db.lock().get(&Bytes::from("list")).is_none());
+    }
+
+    #[test]
+    fn test_list_range() {
+        assert_eq!(list_range(0, -1, 3), Some((0, 2)));
+        assert_eq!(list_range(-100, 100, 3), Some((0, 2)));
+        assert_eq!(list_range(0, -100, 3), None);
+        assert_eq!(list_range(2, 1, 3), None);
+        assert_eq!(list_range(0, 0, 0), None);
+    }
+}
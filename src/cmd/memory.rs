@@ -0,0 +1,154 @@
+use super::{CommandError, Parse, are_equal, help};
+use crate::{
+    db::{Db, Storage},
+    frame::FrameValue,
+};
+use bytes::Bytes;
+
+/// `MEMORY` subcommands, reporting estimates of the memory used by the dataset
+pub enum MemorySubcommand {
+    /// Bytes used by a key and its value
+    Usage(Bytes),
+    /// Human readable assessment of the memory use
+    Doctor,
+    /// Memory metrics as name and value pairs
+    Stats,
+    Help,
+}
+
+/// Usage of each subcommand, for `MEMORY HELP`
+const HELP: &[&str] = &[
+    "DOCTOR",
+    "    Return memory problems reports.",
+    "STATS",
+    "    Return information about the memory usage of the server.",
+    "USAGE <key>",
+    "    Return memory in bytes used by <key> and its value.",
+];
+
+impl MemorySubcommand {
+    pub fn parse_frames(parse: &mut Parse) -> Result<Self, CommandError> {
+        let subcommand = parse.next_bytes()?;
+
+        let cmd = match subcommand.as_ref() {
+            sub if are_equal(sub, b"USAGE") => Self::Usage(parse.next_bytes()?),
+            sub if are_equal(sub, b"DOCTOR") => Self::Doctor,
+            sub if are_equal(sub, b"STATS") => Self::Stats,
+            sub if are_equal(sub, b"HELP") => Self::Help,
+            _ => return Err(CommandError::UnknownSubcommand("MEMORY", subcommand)),
+        };
+
+        parse.finish()?;
+        Ok(cmd)
+    }
+
+    pub fn apply<S: Storage>(self, db: &Db<S>) -> FrameValue {
+        let entries = db.read();
+
+        match self {
+            Self::Help => help("MEMORY", HELP),
+            Self::Usage(key) => match entries.memory_usage(&key) {
+                Some(bytes) => FrameValue::Integer(bytes as i64),
+                None => FrameValue::NullBulkString,
+            },
+            Self::Doctor => {
+                let stats = entries.memory_stats();
+                let report = if stats.keys == 0 {
+                    "The instance is empty, there is no memory use to assess.".to_string()
+                } else {
+                    format!(
+                        "No memory issues detected. {} keys use an estimated {} bytes.",
+                        stats.keys, stats.dataset_bytes
+                    )
+                };
+                FrameValue::BulkString(report.into())
+            }
+            Self::Stats => {
+                let stats = entries.memory_stats();
+                let metrics = [
+                    ("total.allocated", stats.allocated_bytes),
+                    ("keys.count", stats.keys),
+                    ("dataset.bytes", stats.dataset_bytes),
+                ];
+
+                FrameValue::Array(
+                    metrics
+                        .into_iter()
+                        .flat_map(|(name, value)| {
+                            [
+                                FrameValue::BulkString(name.into()),
+                                FrameValue::Integer(value as i64),
+                            ]
+                        })
+                        .collect(),
+                )
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod memory_tests {
+    use crate::{cmd::run, db::Db, frame::FrameValue};
+
+    fn usage(db: &Db, key: &str) -> i64 {
+        match run(db, &["MEMORY", "USAGE", key]) {
+            FrameValue::Integer(bytes) => bytes,
+            other => panic!("expected an integer, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_usage_grows_after_append() {
+        let db = Db::new();
+        run(&db, &["SET", "key", "value"]);
+        let before = usage(&db, "key");
+
+        run(&db, &["APPEND", "key", "more"]);
+        assert_eq!(usage(&db, "key"), before + 4);
+
+        assert_eq!(
+            run(&db, &["MEMORY", "USAGE", "missing"]),
+            FrameValue::NullBulkString
+        );
+    }
+
+    #[test]
+    fn test_usage_sums_container_elements() {
+        let db = Db::new();
+        run(&db, &["SADD", "set", "a"]);
+        let one = usage(&db, "set");
+
+        run(&db, &["SADD", "set", "bb"]);
+        assert!(usage(&db, "set") > one);
+    }
+
+    #[test]
+    fn test_stats_counts_keys() {
+        let db = Db::new();
+        run(&db, &["SET", "a", "1"]);
+        run(&db, &["SADD", "b", "x", "y"]);
+
+        let FrameValue::Array(stats) = run(&db, &["MEMORY", "STATS"]) else {
+            panic!("expected an array");
+        };
+        let keys = stats
+            .chunks(2)
+            .find(|pair| pair[0] == FrameValue::BulkString("keys.count".into()))
+            .map(|pair| pair[1].clone());
+        assert_eq!(keys, Some(FrameValue::Integer(2)));
+    }
+
+    #[test]
+    fn test_doctor() {
+        let db = Db::new();
+        assert!(matches!(
+            run(&db, &["MEMORY", "DOCTOR"]),
+            FrameValue::BulkString(_)
+        ));
+        assert!(matches!(
+            run(&db, &["MEMORY", "MALLOC-STATS"]),
+            FrameValue::Error(_)
+        ));
+    }
+}
@@ -1,23 +1,489 @@
-use crate::frame::{self, FrameValue};
+use crate::{
+    client::Client,
+    db::{Db, Storage, unix_time_of},
+    frame::FrameValue,
+};
 use bytes::Bytes;
+use std::{fmt, time::Instant};
 
+mod parse;
+use parse::Parse;
+
+mod acl;
+mod append;
+mod auth;
+mod bitcount;
+mod bitpos;
+mod client;
+mod command;
+mod dbsize;
+mod debug;
+mod del;
+mod dump;
+mod echo;
+mod expire;
+mod get;
+mod getex;
+mod glob;
+mod hdel;
+mod hello;
+mod hexists;
+mod hincrby;
+mod hkeys;
+mod hlen;
+mod hrandfield;
+mod hscan;
+mod hset;
+mod hvals;
+mod incr;
+mod incrbyfloat;
+mod info;
+mod lindex;
+mod linsert;
+mod lmove;
+mod lpos;
+mod lrem;
+mod lset;
+mod ltrim;
+mod memory;
+mod mset;
+mod object;
 mod ping;
+mod psync;
+mod publish;
+mod pubsub;
+mod push;
+mod quit;
+mod replconf;
+mod restore;
+mod role;
+mod sadd;
+mod scan;
+mod select;
+mod set;
+mod set_algebra;
+mod setstore;
+mod shutdown;
+mod sintercard;
+mod smembers;
+mod smismember;
+mod spop;
+mod srandmember;
+mod srem;
+mod sscan;
+mod subscribe;
+mod swapdb;
+mod table;
+mod wait;
+mod waitaof;
+mod zadd;
+mod zcard;
+mod zincrby;
+mod zrange;
+mod zrank;
+mod zrem;
+mod zscan;
+use acl::AclSubcommand;
+use append::Append;
+use auth::Auth;
+use bitcount::BitCount;
+use bitpos::BitPos;
+use client::ClientSubcommand;
+use command::CommandSubcommand;
+use dbsize::DbSize;
+use debug::DebugSubcommand;
+use del::Del;
+use dump::Dump;
+use echo::Echo;
+use expire::{Expire, ExpireAt, Ttl};
+use get::Get;
+use getex::GetEx;
+use hdel::HDel;
+use hello::Hello;
+use hexists::HExists;
+use hincrby::HIncrBy;
+use hkeys::HKeys;
+use hlen::HLen;
+use hrandfield::HRandField;
+use hscan::HScan;
+use hset::HSet;
+use hvals::HVals;
+use incr::Incr;
+use incrbyfloat::IncrByFloat;
+use info::Info;
+use lindex::LIndex;
+use linsert::LInsert;
+use lmove::LMove;
+use lpos::LPos;
+use lrem::LRem;
+use lset::LSet;
+use ltrim::LTrim;
+use memory::MemorySubcommand;
+use mset::MSet;
+use object::ObjectSubcommand;
 use ping::Ping;
-
-mod command_names {
-    pub const PING: &[u8] = b"PING";
-}
+use psync::PSync;
+use publish::Publish;
+use pubsub::PubSubSubcommand;
+use push::Push;
+use quit::Quit;
+use replconf::ReplConf;
+use restore::Restore;
+use role::Role;
+use sadd::SAdd;
+use scan::Scan;
+use select::Select;
+use set::Set;
+use setstore::SetStore;
+use shutdown::Shutdown;
+use sintercard::SInterCard;
+use smembers::SMembers;
+use smismember::SMIsMember;
+use spop::SPop;
+use srandmember::SRandMember;
+use srem::SRem;
+use sscan::SScan;
+use subscribe::{Subscribe, Unsubscribe};
+use swapdb::SwapDb;
+use wait::Wait;
+use waitaof::WaitAof;
+use zadd::ZAdd;
+use zcard::ZCard;
+use zincrby::ZIncrBy;
+use zrange::{ZRangeByLex, ZRangeByScore};
+use zrank::ZRank;
+use zrem::ZRem;
+use zscan::ZScan;
 
 pub enum Command {
     Ping(Ping),
-    Echo { msg: Bytes },
+    Quit(Quit),
+    Shutdown(Shutdown),
+    Echo(Echo),
+    Get(Get),
+    GetEx(GetEx),
+    Set(Set),
+    MSet(MSet),
+    Del(Del),
+    Expire(Expire),
+    PExpire(Expire),
+    ExpireAt(ExpireAt),
+    PExpireAt(ExpireAt),
+    Ttl(Ttl),
+    PTtl(Ttl),
+    DbSize(DbSize),
+    Scan(Scan),
+    BitCount(BitCount),
+    BitPos(BitPos),
+    SAdd(SAdd),
+    SMembers(SMembers),
+    SMIsMember(SMIsMember),
+    SScan(SScan),
+    SRandMember(SRandMember),
+    SPop(SPop),
+    SRem(SRem),
+    SInterStore(SetStore),
+    SUnionStore(SetStore),
+    SDiffStore(SetStore),
+    SInterCard(SInterCard),
+    HSet(HSet),
+    HDel(HDel),
+    HExists(HExists),
+    HLen(HLen),
+    HKeys(HKeys),
+    HScan(HScan),
+    HVals(HVals),
+    HRandField(HRandField),
+    HIncrBy(HIncrBy),
+    LPush(Push),
+    RPush(Push),
+    LPushX(Push),
+    RPushX(Push),
+    LIndex(LIndex),
+    LPos(LPos),
+    LMove(LMove),
+    RPopLPush(LMove),
+    LSet(LSet),
+    LInsert(LInsert),
+    LRem(LRem),
+    LTrim(LTrim),
+    Auth(Auth),
+    Hello(Hello),
+    Acl(AclSubcommand),
+    Client(ClientSubcommand),
+    Object(ObjectSubcommand),
+    Memory(MemorySubcommand),
+    Debug(DebugSubcommand),
+    Publish(Publish),
+    PubSub(PubSubSubcommand),
+    Subscribe(Subscribe),
+    Unsubscribe(Unsubscribe),
+    Introspect(CommandSubcommand),
+    Append(Append),
+    Incr(Incr),
+    IncrByFloat(IncrByFloat),
+    Dump(Dump),
+    Restore(Restore),
+    ReplConf(ReplConf),
+    PSync(PSync),
+    Role(Role),
+    Wait(Wait),
+    WaitAof(WaitAof),
+    ZAdd(ZAdd),
+    ZRangeByScore(ZRangeByScore),
+    ZRangeByLex(ZRangeByLex),
+    ZRem(ZRem),
+    ZCard(ZCard),
+    ZRank(ZRank),
+    ZRevRank(ZRank),
+    ZIncrBy(ZIncrBy),
+    ZScan(ZScan),
+    Info(Info),
+    Select(Select),
+    SwapDb(SwapDb),
+}
+
+/// What the connection loop does after running a command, besides replying
+pub enum CommandEffect {
+    /// Sends the reply and waits for the next command
+    Reply(FrameValue),
+    /// Sends the reply, then closes the connection
+    CloseAfterReply(FrameValue),
+    /// Sends an array reply a chunk at a time
+    ReplyArray(BulkArray),
+    /// Stops the server, closing the connection without a reply
+    Shutdown,
+}
+
+impl From<Result<BulkArray, FrameValue>> for CommandEffect {
+    fn from(reply: Result<BulkArray, FrameValue>) -> Self {
+        match reply {
+            Ok(array) => Self::ReplyArray(array),
+            Err(reply) => Self::Reply(reply),
+        }
+    }
+}
+
+/// Array reply of bulk strings, for commands listing whole collections
+///
+/// The strings are handles sharing their bytes with the keyspace, so taking
+/// them copies no payload, and no lock is held while the connection encodes
+/// them a chunk at a time. A [`FrameValue::Array`] of them is never built.
+pub struct BulkArray(Vec<Bytes>);
+
+impl BulkArray {
+    pub fn new(strings: Vec<Bytes>) -> Self {
+        Self(strings)
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// The elements of the reply, built as they are taken
+    pub fn into_frames(self) -> impl ExactSizeIterator<Item = FrameValue> + Send {
+        self.0.into_iter().map(FrameValue::BulkString)
+    }
+}
+
+/// Reply of a command listing a collection, as a single frame
+fn collected(reply: Result<BulkArray, FrameValue>) -> FrameValue {
+    reply.map_or_else(
+        |error| error,
+        |array| FrameValue::Array(array.into_frames().collect()),
+    )
 }
 
-enum CommandError {
-    FrameError(frame::FrameError),
-    InvalidArrayFrame(FrameValue),
-    InvalidCommand(FrameValue),
+#[derive(Debug)]
+pub enum CommandError {
+    /// A frame other than an array was sent as a command, holding the byte it
+    /// started with
+    NotAnArray(u8),
+    InvalidArrayFrame,
+    InvalidArgument,
     ExpectedBulkStringCommand,
+    UnknownCommand(Bytes),
+    UnknownSubcommand(&'static str, Bytes),
+    UnknownOption(&'static str, Bytes),
+    /// Raised by the argument parser for counts the arity doesn't rule out,
+    /// reported as [`CommandError::WrongArity`]
+    ArgumentCount,
+    WrongArity(Bytes),
+    Syntax,
+    NotInteger,
+}
+
+impl CommandError {
+    /// Whether the client is speaking something other than RESP commands, after
+    /// which the connection is closed rather than guessing where the next
+    /// command starts
+    pub fn is_protocol_error(&self) -> bool {
+        matches!(self, Self::NotAnArray(_))
+    }
+
+    /// Error reply sent back to the client
+    pub fn into_frame(self) -> FrameValue {
+        let msg = match self {
+            Self::NotAnArray(byte) => {
+                format!("ERR Protocol error: expected '*', got '{}'", byte as char)
+            }
+            Self::InvalidArrayFrame => {
+                "ERR Protocol error: expected an array of bulk strings".into()
+            }
+            Self::InvalidArgument | Self::ExpectedBulkStringCommand => {
+                "ERR Protocol error: expected a bulk string".into()
+            }
+            Self::UnknownCommand(name) => {
+                format!("ERR unknown command '{}'", String::from_utf8_lossy(&name))
+            }
+            Self::UnknownSubcommand(command, subcommand) => format!(
+                "ERR unknown subcommand '{}'. Try {} HELP.",
+                String::from_utf8_lossy(&subcommand),
+                command
+            ),
+            Self::UnknownOption(command, option) => format!(
+                "ERR Unrecognized {} option: {}",
+                command,
+                String::from_utf8_lossy(&option)
+            ),
+            Self::ArgumentCount => "ERR wrong number of arguments".into(),
+            Self::WrongArity(name) => format!(
+                "ERR wrong number of arguments for '{}' command",
+                String::from_utf8_lossy(&name).to_lowercase()
+            ),
+            Self::Syntax => "ERR syntax error".into(),
+            Self::NotInteger => "ERR value is not an integer or out of range".into(),
+        };
+        FrameValue::Error(msg.into())
+    }
+}
+
+/// Reply to `<command> HELP`, the usage `lines` of its subcommands between
+/// the header and footer Redis puts around them
+pub fn help(command: &str, lines: &[&str]) -> FrameValue {
+    let header = format!("{command} <subcommand> [<arg> [value] [opt] ...]. Subcommands are:");
+    let footer = ["HELP", "    Print this help."];
+
+    FrameValue::Array(
+        std::iter::once(header.into())
+            .chain(
+                lines
+                    .iter()
+                    .chain(&footer)
+                    .map(|line| line.to_string().into()),
+            )
+            .map(FrameValue::SimpleString)
+            .collect(),
+    )
+}
+
+/// Reply for a command run against a key holding another type of value
+pub fn wrong_type() -> FrameValue {
+    FrameValue::Error("WRONGTYPE Operation against a key holding the wrong kind of value".into())
+}
+
+/// Reply for a command that would store a string longer than
+/// [`Db::max_value_len`]
+pub fn string_too_long() -> FrameValue {
+    FrameValue::Error("ERR string exceeds maximum allowed size".into())
+}
+
+/// Whether `frame` invokes a command that modifies the keyspace
+pub fn is_write(frame: &FrameValue) -> bool {
+    spec_of(frame).is_some_and(|spec| spec.is_write())
+}
+
+/// Whether `frame` invokes a command a connection may run before authenticating
+pub fn allows_unauthenticated(frame: &FrameValue) -> bool {
+    spec_of(frame).is_some_and(|spec| spec.allows_unauthenticated())
+}
+
+/// Whether `frame` invokes a command that waits with a timeout of its own
+pub fn is_blocking(frame: &FrameValue) -> bool {
+    spec_of(frame).is_some_and(|spec| spec.is_blocking())
+}
+
+/// Whether `frame` invokes a command that only reads the keyspace
+pub fn is_readonly(frame: &FrameValue) -> bool {
+    spec_of(frame).is_some_and(|spec| spec.is_readonly())
+}
+
+/// Keys `frame` names, empty if it isn't a valid invocation of a known command
+pub fn keys(frame: &FrameValue) -> Vec<Bytes> {
+    let (Some(spec), FrameValue::Array(frames)) = (spec_of(frame), frame) else {
+        return Vec::new();
+    };
+    if !spec.accepts_argc(frames.len()) {
+        return Vec::new();
+    }
+
+    spec.key_positions(frames)
+        .filter_map(|position| match &frames[position] {
+            FrameValue::BulkString(key) => Some(key.clone()),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Formats `frame` the way MONITOR shows a command, each argument quoted and
+/// escaped, with the secrets a command takes shown as `(redacted)`
+///
+/// Everything that logs a command goes through here or [`redacted`], so no
+/// password is ever written out. Nothing is formatted unless the result is.
+pub fn describe(frame: &FrameValue) -> impl fmt::Display + '_ {
+    Described(frame)
+}
+
+/// Copy of `frame` with the secrets a command takes replaced by `(redacted)`,
+/// for logs that show commands as RESP
+pub fn redacted(frame: &FrameValue) -> FrameValue {
+    let redact = spec_of(frame).map_or(table::Redact::Nothing, |spec| spec.redact);
+    match frame {
+        FrameValue::Array(args) if redact != table::Redact::Nothing => FrameValue::Array(
+            (0..args.len())
+                .map(|position| match &args[position] {
+                    _ if redact.hides(args, position) => {
+                        FrameValue::BulkString("(redacted)".into())
+                    }
+                    arg => arg.clone(),
+                })
+                .collect(),
+        ),
+        frame => frame.clone(),
+    }
+}
+
+struct Described<'a>(&'a FrameValue);
+
+impl fmt::Display for Described<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let FrameValue::Array(args) = self.0 else {
+            return write!(f, "{:?}", self.0);
+        };
+        let redact = spec_of(self.0).map_or(table::Redact::Nothing, |spec| spec.redact);
+
+        for (position, arg) in args.iter().enumerate() {
+            if position > 0 {
+                f.write_str(" ")?;
+            }
+            match arg {
+                _ if redact.hides(args, position) => f.write_str("\"(redacted)\"")?,
+                FrameValue::BulkString(arg) => write!(f, "\"{}\"", arg.escape_ascii())?,
+                arg => write!(f, "{arg:?}")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+fn spec_of(frame: &FrameValue) -> Option<&'static table::CommandSpec> {
+    match frame {
+        FrameValue::Array(frames) => match frames.first() {
+            Some(FrameValue::BulkString(name)) => table::lookup(name),
+            _ => None,
+        },
+        _ => None,
+    }
 }
 
 #[inline]
@@ -27,22 +493,221 @@ fn are_equal(first: &[u8], second: &[u8]) -> bool {
 
 impl Command {
     pub fn from_frame(frame: FrameValue) -> Result<Self, CommandError> {
-        let mut frames_iter = match frame {
-            FrameValue::Array(frames) => frames.into_iter(),
-            _ => return Err(CommandError::InvalidArrayFrame(frame)),
+        let mut parse = match frame {
+            FrameValue::Array(frames) => Parse::new(frames),
+            FrameValue::NullBulkArray => return Err(CommandError::InvalidArrayFrame),
+            frame => return Err(CommandError::NotAnArray(frame.type_byte())),
         };
 
-        let command = match frames_iter.next() {
-            Some(FrameValue::BulkString(bytes)) => bytes,
-            _ => return Err(CommandError::ExpectedBulkStringCommand),
+        let command = match parse.next_bytes() {
+            Ok(bytes) => bytes,
+            Err(_) => return Err(CommandError::ExpectedBulkStringCommand),
         };
 
-        // use command_names::*;
-        // match command.as_ref() {
-        //     cmd if are_equal(cmd, PING) => {}
-        // }
-        Ok(Self::Echo {
-            msg: "hello".into(),
-        })
+        let Some(spec) = table::lookup(&command) else {
+            return Err(CommandError::UnknownCommand(command));
+        };
+        if !spec.accepts_argc(parse.remaining() + 1) {
+            return Err(CommandError::WrongArity(command));
+        }
+
+        match (spec.parse)(&mut parse) {
+            Err(CommandError::ArgumentCount) => Err(CommandError::WrongArity(command)),
+            result => result,
+        }
+    }
+
+    /// Like [`Command::apply`], but waits asynchronously where the command waits,
+    /// so the connection loop can cut it short, and tells the loop how the
+    /// command affects the connection
+    ///
+    /// Waiting happens here rather than in [`Command::apply`] so that no keyspace
+    /// lock is ever held across an `.await`.
+    pub async fn apply_async<S: Storage>(self, db: &Db<S>, client: &Client) -> CommandEffect {
+        match self {
+            Self::Debug(DebugSubcommand::Sleep(duration)) => {
+                tokio::time::sleep(duration).await;
+                CommandEffect::Reply(FrameValue::SimpleString("OK".into()))
+            }
+            Self::Wait(cmd) => CommandEffect::Reply(cmd.apply_async(db).await),
+            Self::WaitAof(cmd) => CommandEffect::Reply(cmd.apply_async(db).await),
+            Self::Quit(cmd) => CommandEffect::CloseAfterReply(cmd.apply()),
+            Self::Shutdown(cmd) => cmd.apply(),
+            Self::SMembers(cmd) => cmd.stream(db).into(),
+            Self::HKeys(cmd) => cmd.stream(db).into(),
+            Self::HVals(cmd) => cmd.stream(db).into(),
+            Self::ZRangeByScore(cmd) => cmd.stream(db).into(),
+            Self::ZRangeByLex(cmd) => cmd.stream(db).into(),
+            cmd => CommandEffect::Reply(cmd.apply(db, client)),
+        }
+    }
+
+    /// Executes a write command like [`Command::apply`], also returning what
+    /// replicas should apply to end up with the same data, `None` if nothing
+    /// changed
+    ///
+    /// That is `frame`, the command as it was sent, unless the command has a
+    /// random outcome replicas can't reproduce, or sets an expiry relative to
+    /// now. Those are sent as their effect.
+    pub fn apply_write<S: Storage>(
+        self,
+        db: &Db<S>,
+        client: &Client,
+        frame: FrameValue,
+    ) -> (FrameValue, Option<FrameValue>) {
+        match self {
+            Self::SPop(cmd) => cmd.apply(db),
+            Self::Expire(cmd) | Self::PExpire(cmd) => cmd.apply_write(db),
+            Self::GetEx(cmd) => cmd.apply_write(db, frame),
+            cmd => {
+                let reply = cmd.apply(db, client);
+                let propagated = (!matches!(reply, FrameValue::Error(_))).then_some(frame);
+                (reply, propagated)
+            }
+        }
+    }
+
+    /// Executes the command on behalf of `client`, producing the reply
+    pub fn apply<S: Storage>(self, db: &Db<S>, client: &Client) -> FrameValue {
+        match self {
+            Self::Ping(cmd) => cmd.apply(),
+            Self::Quit(cmd) => cmd.apply(),
+            Self::Echo(cmd) => cmd.apply(),
+            Self::Get(cmd) => cmd.apply(db),
+            Self::GetEx(cmd) => cmd.apply(db),
+            Self::Set(cmd) => cmd.apply(db),
+            Self::MSet(cmd) => cmd.apply(db),
+            Self::Del(cmd) => cmd.apply(db),
+            Self::Expire(cmd) | Self::PExpire(cmd) => cmd.apply(db),
+            Self::ExpireAt(cmd) | Self::PExpireAt(cmd) => cmd.apply(db),
+            Self::Ttl(cmd) | Self::PTtl(cmd) => cmd.apply(db),
+            Self::DbSize(cmd) => cmd.apply(db),
+            Self::Scan(cmd) => cmd.apply(db),
+            Self::BitCount(cmd) => cmd.apply(db),
+            Self::BitPos(cmd) => cmd.apply(db),
+            Self::SAdd(cmd) => cmd.apply(db),
+            Self::SMembers(cmd) => collected(cmd.stream(db)),
+            Self::SMIsMember(cmd) => cmd.apply(db),
+            Self::SScan(cmd) => cmd.apply(db),
+            Self::SRandMember(cmd) => cmd.apply(db),
+            Self::SPop(cmd) => cmd.apply(db).0,
+            Self::SRem(cmd) => cmd.apply(db),
+            Self::SInterStore(cmd) | Self::SUnionStore(cmd) | Self::SDiffStore(cmd) => {
+                cmd.apply(db)
+            }
+            Self::SInterCard(cmd) => cmd.apply(db),
+            Self::HSet(cmd) => cmd.apply(db),
+            Self::HDel(cmd) => cmd.apply(db),
+            Self::HExists(cmd) => cmd.apply(db),
+            Self::HLen(cmd) => cmd.apply(db),
+            Self::HKeys(cmd) => collected(cmd.stream(db)),
+            Self::HScan(cmd) => cmd.apply(db),
+            Self::HVals(cmd) => collected(cmd.stream(db)),
+            Self::HRandField(cmd) => cmd.apply(db),
+            Self::HIncrBy(cmd) => cmd.apply(db),
+            Self::LPush(cmd) | Self::RPush(cmd) | Self::LPushX(cmd) | Self::RPushX(cmd) => {
+                cmd.apply(db)
+            }
+            Self::LIndex(cmd) => cmd.apply(db),
+            Self::LPos(cmd) => cmd.apply(db),
+            Self::LMove(cmd) | Self::RPopLPush(cmd) => cmd.apply(db),
+            Self::LSet(cmd) => cmd.apply(db),
+            Self::LInsert(cmd) => cmd.apply(db),
+            Self::LRem(cmd) => cmd.apply(db),
+            Self::LTrim(cmd) => cmd.apply(db),
+            Self::Auth(cmd) => cmd.apply(db, client),
+            Self::Hello(cmd) => cmd.apply(db, client),
+            Self::Acl(cmd) => cmd.apply(db, client),
+            Self::Client(cmd) => cmd.apply(client),
+            Self::Object(cmd) => cmd.apply(db),
+            Self::Memory(cmd) => cmd.apply(db),
+            Self::Debug(cmd) => cmd.apply(db),
+            Self::Publish(cmd) => cmd.apply(db),
+            Self::PubSub(cmd) => cmd.apply(db),
+            Self::Subscribe(_) | Self::Unsubscribe(_) => {
+                unreachable!("subscriptions are managed by the connection loop")
+            }
+            Self::Introspect(cmd) => cmd.apply(),
+            Self::Append(cmd) => cmd.apply(db),
+            Self::Incr(cmd) => cmd.apply(db),
+            Self::IncrByFloat(cmd) => cmd.apply(db),
+            Self::Dump(cmd) => cmd.apply(db),
+            Self::Restore(cmd) => cmd.apply(db),
+            Self::ReplConf(cmd) => cmd.apply(db, client),
+            Self::PSync(_) => unreachable!("replicas are synchronised by the connection loop"),
+            Self::Role(cmd) => cmd.apply(db),
+            Self::Wait(cmd) => cmd.apply(db),
+            Self::WaitAof(cmd) => cmd.apply(db),
+            Self::ZAdd(cmd) => cmd.apply(db),
+            Self::ZRangeByScore(cmd) => collected(cmd.stream(db)),
+            Self::ZRangeByLex(cmd) => collected(cmd.stream(db)),
+            Self::ZRem(cmd) => cmd.apply(db),
+            Self::ZCard(cmd) => cmd.apply(db),
+            Self::ZRank(cmd) | Self::ZRevRank(cmd) => cmd.apply(db),
+            Self::ZIncrBy(cmd) => cmd.apply(db),
+            Self::ZScan(cmd) => cmd.apply(db),
+            Self::Info(cmd) => cmd.apply(db, client),
+            Self::SwapDb(cmd) => cmd.apply(db),
+            Self::Select(_) => {
+                unreachable!("the selected database is managed by the connection loop")
+            }
+            Self::Shutdown(_) => unreachable!("shutdown is carried out by the server"),
+        }
+    }
+}
+
+/// PEXPIREAT setting `key` to expire at `at`, which is how relative expiries
+/// are propagated, so that applying them later doesn't start the countdown over
+pub(crate) fn pexpireat_frame(key: Bytes, at: Instant) -> FrameValue {
+    let millis = unix_time_of(at).as_millis().to_string();
+    FrameValue::Array(vec![
+        FrameValue::BulkString("PEXPIREAT".into()),
+        FrameValue::BulkString(key),
+        FrameValue::BulkString(millis.into()),
+    ])
+}
+
+/// Array frame a client would send for `args`
+pub(crate) fn command_frame(args: &[&str]) -> FrameValue {
+    FrameValue::Array(
+        args.iter()
+            .map(|arg| FrameValue::BulkString(Bytes::copy_from_slice(arg.as_bytes())))
+            .collect(),
+    )
+}
+
+/// Parses `args` as a command and applies it to `db`
+#[cfg(test)]
+pub(crate) fn run<S: Storage>(db: &Db<S>, args: &[&str]) -> FrameValue {
+    run_frame(db, command_frame(args))
+}
+
+/// Parses `frame` as a command and applies it to `db`
+#[cfg(test)]
+pub(crate) fn run_frame<S: Storage>(db: &Db<S>, frame: FrameValue) -> FrameValue {
+    let client = crate::client::ClientList::default().register("test".into(), Default::default());
+    if !db.acl().requires_auth() {
+        client.set_user(crate::acl::DEFAULT_USER);
+    }
+    match Command::from_frame(frame) {
+        Ok(cmd) => cmd.apply(db, &client),
+        Err(e) => e.into_frame(),
     }
 }
+
+/// Bulk strings of an array reply, sorted to make unordered replies comparable
+#[cfg(test)]
+pub(crate) fn sorted_bulk_strings(frame: FrameValue) -> Vec<Bytes> {
+    let FrameValue::Array(frames) = frame else {
+        panic!("expected an array, got {frame:?}");
+    };
+    let mut values: Vec<Bytes> = frames
+        .into_iter()
+        .map(|frame| match frame {
+            FrameValue::BulkString(value) => value,
+            frame => panic!("expected a bulk string, got {frame:?}"),
+        })
+        .collect();
+    values.sort();
+    values
+}
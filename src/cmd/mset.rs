@@ -0,0 +1,62 @@
+use super::{CommandError, Parse, string_too_long};
+use crate::{
+    db::{Db, DbValue, Storage},
+    frame::FrameValue,
+};
+use bytes::Bytes;
+
+/// Sets the string values of several keys at once
+pub struct MSet {
+    pairs: Vec<(Bytes, Bytes)>,
+}
+
+impl MSet {
+    pub fn parse_frames(parse: &mut Parse) -> Result<Self, CommandError> {
+        if !parse.remaining().is_multiple_of(2) {
+            return Err(CommandError::ArgumentCount);
+        }
+
+        let mut pairs = Vec::with_capacity(parse.remaining() / 2);
+        while parse.remaining() > 0 {
+            pairs.push((parse.next_bytes()?, parse.next_bytes()?));
+        }
+        Ok(Self { pairs })
+    }
+
+    pub fn apply<S: Storage>(self, db: &Db<S>) -> FrameValue {
+        // Checked up front so that either every pair is set or none is
+        if self
+            .pairs
+            .iter()
+            .any(|(_, value)| value.len() > db.max_value_len())
+        {
+            return string_too_long();
+        }
+
+        let mut entries = db.lock();
+        for (key, value) in self.pairs {
+            entries.insert(key, DbValue::String(value));
+        }
+        FrameValue::SimpleString("OK".into())
+    }
+}
+
+#[cfg(test)]
+mod mset_tests {
+    use crate::{cmd::run, db::Db, frame::FrameValue};
+
+    #[test]
+    fn test_mset() {
+        let db = Db::new();
+
+        assert_eq!(
+            run(&db, &["MSET", "a", "1", "b", "2"]),
+            FrameValue::SimpleString("OK".into())
+        );
+        assert_eq!(run(&db, &["GET", "b"]), FrameValue::BulkString("2".into()));
+        assert_eq!(
+            run(&db, &["MSET", "a", "1", "b"]),
+            FrameValue::Error("ERR wrong number of arguments for 'mset' command".into())
+        );
+    }
+}
@@ -0,0 +1,340 @@
+use super::{CommandError, Parse, are_equal, help};
+use crate::{
+    db::{Db, DbValue, EncodingLimits, Storage},
+    frame::FrameValue,
+};
+use bytes::Bytes;
+
+/// `OBJECT` subcommands, inspecting a key without counting as an access
+pub enum ObjectSubcommand {
+    /// Seconds since the key was last accessed
+    IdleTime(Bytes),
+    /// Access frequency of the key under an LFU eviction policy
+    Freq(Bytes),
+    /// References held to the value of the key
+    RefCount(Bytes),
+    /// Internal representation Redis would pick for the value of the key
+    Encoding(Bytes),
+    Help,
+}
+
+/// Usage of each subcommand, for `OBJECT HELP`
+const HELP: &[&str] = &[
+    "ENCODING <key>",
+    "    Return the kind of internal representation used in order to store the value",
+    "    associated with a <key>.",
+    "FREQ <key>",
+    "    Return the access frequency index of the <key>. The returned integer is",
+    "    proportional to the logarithm of the recent access frequency of the key.",
+    "IDLETIME <key>",
+    "    Return the idle time of the <key>, that is the approximated number of",
+    "    seconds elapsed since the last access to the key.",
+    "REFCOUNT <key>",
+    "    Return the number of references of the value associated with the specified",
+    "    <key>.",
+];
+
+/// Strings holding integers below this share one object each in Redis
+const SHARED_INTEGERS: i64 = 10_000;
+
+/// Refcount Redis reports for shared objects, which are never freed
+const SHARED_REFCOUNT: i64 = i32::MAX as i64;
+
+/// Longest string Redis embeds in the object header
+const EMBSTR_MAX_LEN: usize = 44;
+
+/// Largest hash Redis keeps in a listpack, by fields and by length of any one
+/// field or value
+const HASH_MAX_LISTPACK_ENTRIES: usize = 128;
+const HASH_MAX_LISTPACK_VALUE: usize = 64;
+
+/// Largest sorted set Redis keeps in a listpack, by members and by length of
+/// any one member
+const ZSET_MAX_LISTPACK_ENTRIES: usize = 128;
+const ZSET_MAX_LISTPACK_VALUE: usize = 64;
+
+/// Bytes of listpack a list may take up for each negative size class
+const LISTPACK_SIZE_CLASSES: [usize; 5] = [4096, 8192, 16384, 32768, 65536];
+
+impl ObjectSubcommand {
+    pub fn parse_frames(parse: &mut Parse) -> Result<Self, CommandError> {
+        let subcommand = parse.next_bytes()?;
+
+        let cmd = match subcommand.as_ref() {
+            sub if are_equal(sub, b"IDLETIME") => Self::IdleTime(parse.next_bytes()?),
+            sub if are_equal(sub, b"FREQ") => Self::Freq(parse.next_bytes()?),
+            sub if are_equal(sub, b"REFCOUNT") => Self::RefCount(parse.next_bytes()?),
+            sub if are_equal(sub, b"ENCODING") => Self::Encoding(parse.next_bytes()?),
+            sub if are_equal(sub, b"HELP") => Self::Help,
+            _ => return Err(CommandError::UnknownSubcommand("OBJECT", subcommand)),
+        };
+
+        parse.finish()?;
+        Ok(cmd)
+    }
+
+    pub fn apply<S: Storage>(self, db: &Db<S>) -> FrameValue {
+        let entries = db.read();
+
+        match self {
+            Self::Help => help("OBJECT", HELP),
+            Self::IdleTime(key) => match entries.idle_time(&key) {
+                Some(idle) => FrameValue::Integer(idle.as_secs() as i64),
+                None => FrameValue::NullBulkString,
+            },
+            // There is no eviction, so no LFU policy can be selected to track frequencies
+            Self::Freq(key) => match entries.peek(&key) {
+                Some(_) => FrameValue::Error(
+                    "ERR An LFU maxmemory policy is not selected, access frequency not tracked."
+                        .into(),
+                ),
+                None => FrameValue::NullBulkString,
+            },
+            Self::RefCount(key) => match entries.peek(&key) {
+                Some(value) => FrameValue::Integer(refcount(value)),
+                None => FrameValue::NullBulkString,
+            },
+            Self::Encoding(key) => match entries.peek(&key) {
+                Some(value) => FrameValue::BulkString(encoding(value, db.encoding_limits()).into()),
+                None => FrameValue::NullBulkString,
+            },
+        }
+    }
+}
+
+/// References Redis would report to `value`
+///
+/// Values aren't shared here, but clients expect small integers to report the
+/// refcount of Redis's shared integer pool.
+pub fn refcount(value: &DbValue) -> i64 {
+    match value {
+        DbValue::String(value) if is_shared_integer(value) => SHARED_REFCOUNT,
+        _ => 1,
+    }
+}
+
+/// Encoding Redis would store `value` in, given the limits it was configured with
+pub fn encoding(value: &DbValue, limits: EncodingLimits) -> &'static str {
+    match value {
+        DbValue::String(value) if as_integer(value).is_some() => "int",
+        DbValue::String(value) if value.len() <= EMBSTR_MAX_LEN => "embstr",
+        DbValue::String(_) => "raw",
+        DbValue::List(items) => {
+            let fits = match limits.list_max_listpack_size {
+                size if size >= 0 => items.len() <= size as usize,
+                class => {
+                    let class = (class.unsigned_abs() as usize).min(LISTPACK_SIZE_CLASSES.len());
+                    listpack_size(items.iter()) <= LISTPACK_SIZE_CLASSES[class - 1]
+                }
+            };
+            if fits { "listpack" } else { "quicklist" }
+        }
+        DbValue::Set(members)
+            if members.len() <= limits.set_max_intset_entries
+                && members.iter().all(|member| as_integer(member).is_some()) =>
+        {
+            "intset"
+        }
+        DbValue::Set(_) => "hashtable",
+        DbValue::Hash(fields)
+            if fields.len() <= HASH_MAX_LISTPACK_ENTRIES
+                && fields.iter().all(|(field, value)| {
+                    field.len() <= HASH_MAX_LISTPACK_VALUE && value.len() <= HASH_MAX_LISTPACK_VALUE
+                }) =>
+        {
+            "listpack"
+        }
+        DbValue::Hash(_) => "hashtable",
+        DbValue::SortedSet(members)
+            if members.len() <= ZSET_MAX_LISTPACK_ENTRIES
+                && members
+                    .iter()
+                    .all(|(member, _)| member.len() <= ZSET_MAX_LISTPACK_VALUE) =>
+        {
+            "listpack"
+        }
+        DbValue::SortedSet(_) => "skiplist",
+    }
+}
+
+/// Roughly the bytes a listpack of `items` takes, each entry costing a byte of
+/// header and one of trailing length besides its contents
+fn listpack_size<'a>(items: impl Iterator<Item = &'a Bytes>) -> usize {
+    // Total length and entry count, then the end marker
+    6 + items.map(|item| item.len() + 2).sum::<usize>() + 1
+}
+
+/// The integer `value` holds, if it is written the way Redis would print it
+fn as_integer(value: &[u8]) -> Option<i64> {
+    std::str::from_utf8(value)
+        .ok()
+        .and_then(|value| value.parse::<i64>().ok().filter(|n| n.to_string() == value))
+}
+
+/// Whether Redis would store `value` as one of its shared integers, which
+/// requires it to be written the way Redis would print it
+fn is_shared_integer(value: &[u8]) -> bool {
+    as_integer(value).is_some_and(|n| (0..SHARED_INTEGERS).contains(&n))
+}
+
+#[cfg(test)]
+mod object_tests {
+    use crate::{
+        cmd::run,
+        db::{Db, EncodingLimits},
+        frame::FrameValue,
+    };
+    use std::{thread::sleep, time::Duration};
+
+    #[test]
+    fn test_idletime_after_access() {
+        let db = Db::new();
+        run(&db, &["SET", "key", "value"]);
+        sleep(Duration::from_millis(10));
+        run(&db, &["GET", "key"]);
+
+        assert_eq!(
+            run(&db, &["OBJECT", "IDLETIME", "key"]),
+            FrameValue::Integer(0)
+        );
+        assert_eq!(
+            run(&db, &["OBJECT", "IDLETIME", "missing"]),
+            FrameValue::NullBulkString
+        );
+    }
+
+    #[test]
+    fn test_freq_without_lfu_policy() {
+        let db = Db::new();
+        run(&db, &["SET", "key", "value"]);
+
+        assert!(matches!(
+            run(&db, &["OBJECT", "FREQ", "key"]),
+            FrameValue::Error(_)
+        ));
+        assert_eq!(
+            run(&db, &["OBJECT", "FREQ", "missing"]),
+            FrameValue::NullBulkString
+        );
+    }
+
+    #[test]
+    fn test_refcount() {
+        let db = Db::new();
+        run(&db, &["SET", "shared", "100"]);
+        run(&db, &["SET", "padded", "0100"]);
+        run(&db, &["SET", "large", "10000"]);
+        run(&db, &["SET", "text", "foo"]);
+
+        assert!(matches!(
+            run(&db, &["OBJECT", "REFCOUNT", "shared"]),
+            FrameValue::Integer(count) if count > 1
+        ));
+        for key in ["padded", "large", "text"] {
+            assert_eq!(
+                run(&db, &["OBJECT", "REFCOUNT", key]),
+                FrameValue::Integer(1)
+            );
+        }
+        assert_eq!(
+            run(&db, &["OBJECT", "REFCOUNT", "missing"]),
+            FrameValue::NullBulkString
+        );
+    }
+
+    #[test]
+    fn test_string_encoding() {
+        let db = Db::new();
+        run(&db, &["SET", "int", "-42"]);
+        run(&db, &["SET", "padded", "042"]);
+        run(&db, &["SET", "long", &"x".repeat(45)]);
+
+        for (key, encoding) in [("int", "int"), ("padded", "embstr"), ("long", "raw")] {
+            assert_eq!(
+                run(&db, &["OBJECT", "ENCODING", key]),
+                FrameValue::BulkString(encoding.into())
+            );
+        }
+        assert_eq!(
+            run(&db, &["OBJECT", "ENCODING", "missing"]),
+            FrameValue::NullBulkString
+        );
+    }
+
+    #[test]
+    fn test_set_encoding() {
+        let db = Db::new();
+        run(&db, &["SADD", "small", "1", "2", "-3"]);
+        run(&db, &["SADD", "mixed", "1", "two"]);
+        for member in 0..=512 {
+            run(&db, &["SADD", "large", &member.to_string()]);
+        }
+
+        assert_eq!(
+            run(&db, &["OBJECT", "ENCODING", "small"]),
+            FrameValue::BulkString("intset".into())
+        );
+        assert_eq!(
+            run(&db, &["OBJECT", "ENCODING", "mixed"]),
+            FrameValue::BulkString("hashtable".into())
+        );
+        assert_eq!(
+            run(&db, &["OBJECT", "ENCODING", "large"]),
+            FrameValue::BulkString("hashtable".into())
+        );
+    }
+
+    #[test]
+    fn test_list_encoding_follows_limits() {
+        let db = Db::new().with_encoding_limits(EncodingLimits {
+            list_max_listpack_size: 3,
+            ..EncodingLimits::default()
+        });
+        run(&db, &["RPUSH", "short", "a", "b", "c"]);
+        run(&db, &["RPUSH", "long", "a", "b", "c", "d"]);
+
+        assert_eq!(
+            run(&db, &["OBJECT", "ENCODING", "short"]),
+            FrameValue::BulkString("listpack".into())
+        );
+        assert_eq!(
+            run(&db, &["OBJECT", "ENCODING", "long"]),
+            FrameValue::BulkString("quicklist".into())
+        );
+
+        // By default lists are sized in bytes, 8 KiB of them
+        let db = Db::new();
+        run(&db, &["RPUSH", "list", &"x".repeat(4000)]);
+        run(&db, &["RPUSH", "list", &"x".repeat(4000)]);
+        assert_eq!(
+            run(&db, &["OBJECT", "ENCODING", "list"]),
+            FrameValue::BulkString("listpack".into())
+        );
+        run(&db, &["RPUSH", "list", &"x".repeat(4000)]);
+        assert_eq!(
+            run(&db, &["OBJECT", "ENCODING", "list"]),
+            FrameValue::BulkString("quicklist".into())
+        );
+    }
+
+    #[test]
+    fn test_help() {
+        let db = Db::new();
+
+        let FrameValue::Array(lines) = run(&db, &["OBJECT", "help"]) else {
+            panic!("OBJECT HELP should reply with an array");
+        };
+        let line = |i: usize| match &lines[i] {
+            FrameValue::SimpleString(line) => String::from_utf8_lossy(line).into_owned(),
+            frame => panic!("not a simple string: {frame:?}"),
+        };
+        assert!(line(0).starts_with("OBJECT <subcommand>"), "{}", line(0));
+        assert!(lines.contains(&FrameValue::SimpleString("ENCODING <key>".into())));
+        assert_eq!(line(lines.len() - 2), "HELP");
+
+        assert_eq!(
+            run(&db, &["OBJECT", "HELP", "extra"]),
+            FrameValue::Error("ERR wrong number of arguments for 'object' command".into())
+        );
+    }
+}
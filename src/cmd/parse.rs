@@ -0,0 +1,67 @@
+use super::CommandError;
+use crate::frame::FrameValue;
+use bytes::Bytes;
+use std::{str::from_utf8, vec};
+
+/// Cursor over the arguments of a command frame
+pub struct Parse {
+    frames: vec::IntoIter<FrameValue>,
+}
+
+impl Parse {
+    pub fn new(frames: Vec<FrameValue>) -> Self {
+        Self {
+            frames: frames.into_iter(),
+        }
+    }
+
+    /// Next argument as raw bytes
+    pub fn next_bytes(&mut self) -> Result<Bytes, CommandError> {
+        match self.frames.next() {
+            Some(FrameValue::BulkString(bytes)) => Ok(bytes),
+            Some(_) => Err(CommandError::InvalidArgument),
+            None => Err(CommandError::ArgumentCount),
+        }
+    }
+
+    /// Next argument parsed as a signed integer
+    pub fn next_int(&mut self) -> Result<i64, CommandError> {
+        let bytes = self.next_bytes()?;
+        from_utf8(&bytes)
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .ok_or(CommandError::NotInteger)
+    }
+
+    /// Next argument if there is one left
+    pub fn next_optional_bytes(&mut self) -> Result<Option<Bytes>, CommandError> {
+        if self.remaining() == 0 {
+            Ok(None)
+        } else {
+            self.next_bytes().map(Some)
+        }
+    }
+
+    /// All remaining arguments as raw bytes
+    pub fn rest_bytes(&mut self) -> Result<Vec<Bytes>, CommandError> {
+        let mut rest = Vec::with_capacity(self.remaining());
+        while self.remaining() > 0 {
+            rest.push(self.next_bytes()?);
+        }
+        Ok(rest)
+    }
+
+    /// Number of arguments left
+    pub fn remaining(&self) -> usize {
+        self.frames.len()
+    }
+
+    /// Ensures every argument was consumed
+    pub fn finish(&mut self) -> Result<(), CommandError> {
+        if self.remaining() == 0 {
+            Ok(())
+        } else {
+            Err(CommandError::ArgumentCount)
+        }
+    }
+}
@@ -1 +1,23 @@
-pub struct Ping;
+use super::{CommandError, Parse};
+use crate::frame::FrameValue;
+use bytes::Bytes;
+
+/// Replies with `PONG`, or echoes the message if one is given
+pub struct Ping {
+    msg: Option<Bytes>,
+}
+
+impl Ping {
+    pub fn parse_frames(parse: &mut Parse) -> Result<Self, CommandError> {
+        let msg = parse.next_optional_bytes()?;
+        parse.finish()?;
+        Ok(Self { msg })
+    }
+
+    pub fn apply(self) -> FrameValue {
+        match self.msg {
+            Some(msg) => FrameValue::BulkString(msg),
+            None => FrameValue::SimpleString("PONG".into()),
+        }
+    }
+}
@@ -0,0 +1,27 @@
+use super::{CommandError, Parse};
+use crate::{
+    db::{Db, Storage},
+    frame::FrameValue,
+};
+
+/// Asks to synchronise with this server as a replica
+///
+/// There is no replication backlog to continue from, so this always answers
+/// with a full resync. The connection loop follows the reply with a snapshot.
+pub struct PSync;
+
+impl PSync {
+    pub fn parse_frames(parse: &mut Parse) -> Result<Self, CommandError> {
+        // The replication id and offset the replica last saw
+        parse.next_bytes()?;
+        parse.next_int()?;
+        parse.finish()?;
+        Ok(Self)
+    }
+
+    /// Replies with the `offset` the replica's feed starts at
+    pub fn apply<S: Storage>(self, db: &Db<S>, offset: u64) -> FrameValue {
+        let replid = db.replication().replid();
+        FrameValue::SimpleString(format!("FULLRESYNC {replid} {offset}").into())
+    }
+}
@@ -0,0 +1,25 @@
+use super::{CommandError, Parse};
+use crate::{
+    db::{Db, Storage},
+    frame::FrameValue,
+};
+use bytes::Bytes;
+
+/// Posts a message to a channel, replying with the number of receivers
+pub struct Publish {
+    channel: Bytes,
+    message: Bytes,
+}
+
+impl Publish {
+    pub fn parse_frames(parse: &mut Parse) -> Result<Self, CommandError> {
+        let channel = parse.next_bytes()?;
+        let message = parse.next_bytes()?;
+        parse.finish()?;
+        Ok(Self { channel, message })
+    }
+
+    pub fn apply<S: Storage>(self, db: &Db<S>) -> FrameValue {
+        FrameValue::Integer(db.pubsub().publish(self.channel, self.message) as i64)
+    }
+}
@@ -0,0 +1,153 @@
+use super::{CommandError, Parse, are_equal, glob, help};
+use crate::{
+    db::{Db, Storage},
+    frame::FrameValue,
+};
+use bytes::Bytes;
+
+/// `PUBSUB` subcommands, which look into the pub/sub registry
+pub enum PubSubSubcommand {
+    /// Channels with subscribers, optionally only those matching a pattern
+    Channels(Option<Bytes>),
+    NumSub(Vec<Bytes>),
+    NumPat,
+    Help,
+}
+
+/// Usage of each subcommand, for `PUBSUB HELP`
+const HELP: &[&str] = &[
+    "CHANNELS [<pattern>]",
+    "    Return the currently active channels matching a <pattern> (default: '*').",
+    "NUMPAT",
+    "    Return number of subscriptions to patterns.",
+    "NUMSUB [<channel> ...]",
+    "    Return the number of subscribers for the specified channels, excluding",
+    "    pattern subscriptions(default: no channels).",
+];
+
+impl PubSubSubcommand {
+    pub fn parse_frames(parse: &mut Parse) -> Result<Self, CommandError> {
+        let subcommand = parse.next_bytes()?;
+
+        let cmd = match subcommand.as_ref() {
+            sub if are_equal(sub, b"CHANNELS") => Self::Channels(parse.next_optional_bytes()?),
+            sub if are_equal(sub, b"NUMSUB") => Self::NumSub(parse.rest_bytes()?),
+            sub if are_equal(sub, b"NUMPAT") => Self::NumPat,
+            sub if are_equal(sub, b"HELP") => Self::Help,
+            _ => return Err(CommandError::UnknownSubcommand("PUBSUB", subcommand)),
+        };
+
+        parse.finish()?;
+        Ok(cmd)
+    }
+
+    pub fn apply<S: Storage>(self, db: &Db<S>) -> FrameValue {
+        let pubsub = db.pubsub();
+        match self {
+            Self::Help => help("PUBSUB", HELP),
+            Self::Channels(pattern) => FrameValue::Array(
+                pubsub
+                    .channels()
+                    .into_iter()
+                    .filter(|channel| {
+                        pattern
+                            .as_ref()
+                            .is_none_or(|pattern| glob::matches(pattern, channel))
+                    })
+                    .map(FrameValue::BulkString)
+                    .collect(),
+            ),
+            Self::NumSub(channels) => FrameValue::Array(
+                channels
+                    .into_iter()
+                    .flat_map(|channel| {
+                        let count = pubsub.numsub(&channel) as i64;
+                        [FrameValue::BulkString(channel), FrameValue::Integer(count)]
+                    })
+                    .collect(),
+            ),
+            // PSUBSCRIBE isn't supported, so there are never pattern subscriptions
+            Self::NumPat => FrameValue::Integer(0),
+        }
+    }
+}
+
+#[cfg(test)]
+mod pubsub_tests {
+    use crate::{
+        cmd::{run, sorted_bulk_strings},
+        db::Db,
+        frame::FrameValue,
+        pubsub::OutputLimits,
+    };
+    use bytes::Bytes;
+    use std::time::Duration;
+
+    #[test]
+    fn test_introspection() {
+        let db = Db::new();
+        let limits = OutputLimits {
+            soft: usize::MAX,
+            hard: usize::MAX,
+            soft_duration: Duration::MAX,
+        };
+        let (mut first, _first_messages) = db.pubsub().subscriber(1, limits);
+        let (mut second, _second_messages) = db.pubsub().subscriber(2, limits);
+        first.subscribe("news.tech".into());
+        first.subscribe("weather".into());
+        second.subscribe("news.tech".into());
+        second.subscribe("news.sport".into());
+
+        let channels = |args: &[&str]| sorted_bulk_strings(run(&db, args));
+        assert_eq!(
+            channels(&["PUBSUB", "CHANNELS"]),
+            [
+                Bytes::from("news.sport"),
+                Bytes::from("news.tech"),
+                Bytes::from("weather")
+            ]
+        );
+        assert_eq!(
+            channels(&["PUBSUB", "CHANNELS", "news.*"]),
+            [Bytes::from("news.sport"), Bytes::from("news.tech")]
+        );
+        assert!(channels(&["PUBSUB", "CHANNELS", "nothing*"]).is_empty());
+
+        assert_eq!(
+            run(
+                &db,
+                &["PUBSUB", "NUMSUB", "news.tech", "weather", "missing"]
+            ),
+            FrameValue::Array(vec![
+                FrameValue::BulkString("news.tech".into()),
+                FrameValue::Integer(2),
+                FrameValue::BulkString("weather".into()),
+                FrameValue::Integer(1),
+                FrameValue::BulkString("missing".into()),
+                FrameValue::Integer(0),
+            ])
+        );
+        assert_eq!(run(&db, &["PUBSUB", "NUMSUB"]), FrameValue::Array(vec![]));
+        assert_eq!(run(&db, &["PUBSUB", "NUMPAT"]), FrameValue::Integer(0));
+
+        second.unsubscribe(&"news.sport".into());
+        assert_eq!(
+            channels(&["PUBSUB", "CHANNELS", "news.*"]),
+            [Bytes::from("news.tech")]
+        );
+    }
+
+    #[test]
+    fn test_errors() {
+        let db = Db::new();
+
+        assert_eq!(
+            run(&db, &["PUBSUB", "NUMPAT", "extra"]),
+            FrameValue::Error("ERR wrong number of arguments for 'pubsub' command".into())
+        );
+        assert_eq!(
+            run(&db, &["PUBSUB", "FOO"]),
+            FrameValue::Error("ERR unknown subcommand 'FOO'. Try PUBSUB HELP.".into())
+        );
+    }
+}
@@ -0,0 +1,18 @@
+use super::{CommandError, Parse};
+use crate::frame::FrameValue;
+
+/// Asks the server to close the connection once it has replied
+///
+/// Arguments are accepted and ignored, as Redis does.
+pub struct Quit;
+
+impl Quit {
+    pub fn parse_frames(parse: &mut Parse) -> Result<Self, CommandError> {
+        parse.rest_bytes()?;
+        Ok(Self)
+    }
+
+    pub fn apply(self) -> FrameValue {
+        FrameValue::SimpleString("OK".into())
+    }
+}
@@ -0,0 +1,71 @@
+use super::{CommandError, Parse, are_equal};
+use crate::{
+    client::Client,
+    db::{Db, Storage},
+    frame::FrameValue,
+};
+
+/// Configures the replication link, sent by a replica during the handshake
+///
+/// Only the listening port is remembered, for ROLE to report. Once the link is
+/// up, `ACK <offset>` goes from replica to master to report how much of the feed
+/// it has applied, and `GETACK *` the other way to ask for an `ACK` right away.
+pub struct ReplConf {
+    listening_port: Option<u16>,
+    ack: Option<u64>,
+    getack: bool,
+}
+
+impl ReplConf {
+    pub fn parse_frames(parse: &mut Parse) -> Result<Self, CommandError> {
+        if parse.remaining() == 0 || !parse.remaining().is_multiple_of(2) {
+            return Err(CommandError::Syntax);
+        }
+
+        let mut listening_port = None;
+        let mut ack = None;
+        let mut getack = false;
+        while let Some(option) = parse.next_optional_bytes()? {
+            if are_equal(&option, b"LISTENING-PORT") {
+                let port = parse.next_int()?;
+                listening_port = Some(u16::try_from(port).map_err(|_| CommandError::NotInteger)?);
+            } else if are_equal(&option, b"CAPA") {
+                parse.next_bytes()?;
+            } else if are_equal(&option, b"ACK") {
+                let offset = parse.next_int()?;
+                ack = Some(u64::try_from(offset).map_err(|_| CommandError::NotInteger)?);
+            } else if are_equal(&option, b"GETACK") {
+                parse.next_bytes()?;
+                getack = true;
+            } else {
+                return Err(CommandError::UnknownOption("REPLCONF", option));
+            }
+        }
+
+        Ok(Self {
+            listening_port,
+            ack,
+            getack,
+        })
+    }
+
+    /// Whether this is a replica acknowledging its offset, which gets no reply
+    pub fn is_ack(&self) -> bool {
+        self.ack.is_some()
+    }
+
+    /// Whether this is the master asking for an acknowledgement
+    pub fn is_getack(&self) -> bool {
+        self.getack
+    }
+
+    pub fn apply<S: Storage>(self, db: &Db<S>, client: &Client) -> FrameValue {
+        if let Some(port) = self.listening_port {
+            db.replication().announce_port(client.id(), port);
+        }
+        if let Some(offset) = self.ack {
+            db.replication().acknowledge(client.id(), offset);
+        }
+        FrameValue::SimpleString("OK".into())
+    }
+}
@@ -0,0 +1,258 @@
+use super::{CommandError, Parse, are_equal, expire::TimeUnit, string_too_long};
+use crate::{
+    db::{Db, Storage},
+    frame::FrameValue,
+    rdb::{self, DecodeError},
+};
+use bytes::Bytes;
+use std::time::Instant;
+
+/// Recreates a key from a DUMP payload
+///
+/// A TTL of 0 leaves the key without an expiry. Otherwise it is in milliseconds
+/// from now, or with `ABSTTL` a Unix time in milliseconds. A key that would
+/// already have expired isn't created, though `REPLACE` still removes the key it
+/// would have replaced.
+pub struct Restore {
+    key: Bytes,
+    ttl: i64,
+    payload: Bytes,
+    replace: bool,
+    absttl: bool,
+}
+
+impl Restore {
+    pub fn parse_frames(parse: &mut Parse) -> Result<Self, CommandError> {
+        let key = parse.next_bytes()?;
+        let ttl = parse.next_int()?;
+        let payload = parse.next_bytes()?;
+
+        let mut replace = false;
+        let mut absttl = false;
+        while let Some(option) = parse.next_optional_bytes()? {
+            if are_equal(&option, b"REPLACE") {
+                replace = true;
+            } else if are_equal(&option, b"ABSTTL") {
+                absttl = true;
+            } else {
+                return Err(CommandError::Syntax);
+            }
+        }
+
+        Ok(Self {
+            key,
+            ttl,
+            payload,
+            replace,
+            absttl,
+        })
+    }
+
+    pub fn apply<S: Storage>(self, db: &Db<S>) -> FrameValue {
+        if self.ttl < 0 {
+            return FrameValue::Error("ERR Invalid TTL value, must be >= 0".into());
+        }
+        let expires_at = if self.ttl == 0 {
+            None
+        } else {
+            let at = if self.absttl {
+                TimeUnit::Millis.instant_at(self.ttl)
+            } else {
+                TimeUnit::Millis
+                    .duration(self.ttl)
+                    .and_then(|ttl| Instant::now().checked_add(ttl))
+            };
+            match at {
+                Some(at) => Some(at),
+                None => {
+                    return FrameValue::Error(
+                        "ERR invalid expire time in 'restore' command".into(),
+                    );
+                }
+            }
+        };
+
+        let mut entries = db.lock();
+        if !self.replace && entries.exists(&self.key) {
+            return FrameValue::Error("BUSYKEY Target key name already exists.".into());
+        }
+
+        let value = match rdb::decode(&self.payload) {
+            Ok(value) => value,
+            Err(DecodeError::Version) => {
+                return FrameValue::Error("ERR DUMP payload version or checksum are wrong".into());
+            }
+            Err(DecodeError::BadData) => {
+                return FrameValue::Error("ERR Bad data format".into());
+            }
+        };
+
+        if value.longest_string() > db.max_value_len() {
+            return string_too_long();
+        }
+
+        if expires_at.is_some_and(|at| at <= Instant::now()) {
+            if self.replace {
+                entries.remove(&self.key);
+            }
+            return FrameValue::SimpleString("OK".into());
+        }
+        entries.insert(self.key.clone(), value);
+        entries.set_expiry(&self.key, expires_at);
+        FrameValue::SimpleString("OK".into())
+    }
+}
+
+#[cfg(test)]
+mod restore_tests {
+    use super::*;
+    use crate::{
+        cmd::{command_frame, run, run_frame, sorted_bulk_strings},
+        db::DbValue,
+    };
+    use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+    fn restore(db: &Db, key: &str, payload: Bytes, options: &[&str]) -> FrameValue {
+        restore_with_ttl(db, key, "0", payload, options)
+    }
+
+    fn restore_with_ttl(
+        db: &Db,
+        key: &str,
+        ttl: &str,
+        payload: Bytes,
+        options: &[&str],
+    ) -> FrameValue {
+        let mut args = vec!["RESTORE", key, ttl, ""];
+        args.extend(options);
+
+        // Payloads aren't valid UTF-8, so swap in the raw bytes afterwards
+        let FrameValue::Array(mut frames) = command_frame(&args) else {
+            unreachable!()
+        };
+        frames[3] = FrameValue::BulkString(payload);
+        run_frame(db, FrameValue::Array(frames))
+    }
+
+    fn dump(db: &Db, key: &str) -> Bytes {
+        match run(db, &["DUMP", key]) {
+            FrameValue::BulkString(payload) => payload,
+            frame => panic!("expected a bulk string, got {frame:?}"),
+        }
+    }
+
+    #[test]
+    fn test_round_trip_under_new_name() {
+        let db = Db::new();
+        run(&db, &["SADD", "set", "a", "b", "c"]);
+        run(&db, &["RPUSH", "list", "x", "y", "x"]);
+
+        let ok = FrameValue::SimpleString("OK".into());
+        assert_eq!(restore(&db, "set2", dump(&db, "set"), &[]), ok);
+        assert_eq!(restore(&db, "list2", dump(&db, "list"), &[]), ok);
+
+        assert_eq!(
+            sorted_bulk_strings(run(&db, &["SMEMBERS", "set2"])),
+            sorted_bulk_strings(run(&db, &["SMEMBERS", "set"]))
+        );
+        for index in ["0", "1", "2"] {
+            assert_eq!(
+                run(&db, &["LINDEX", "list2", index]),
+                run(&db, &["LINDEX", "list", index])
+            );
+        }
+        assert_eq!(run(&db, &["DUMP", "missing"]), FrameValue::NullBulkString);
+    }
+
+    #[test]
+    fn test_existing_key_needs_replace() {
+        let db = Db::new();
+        run(&db, &["SET", "a", "1"]);
+        run(&db, &["SET", "b", "2"]);
+        let payload = dump(&db, "a");
+
+        assert_eq!(
+            restore(&db, "b", payload.clone(), &[]),
+            FrameValue::Error("BUSYKEY Target key name already exists.".into())
+        );
+        assert_eq!(
+            restore(&db, "b", payload, &["REPLACE"]),
+            FrameValue::SimpleString("OK".into())
+        );
+        assert_eq!(run(&db, &["GET", "b"]), FrameValue::BulkString("1".into()));
+        assert_eq!(
+            restore(&db, "c", "garbage".into(), &[]),
+            FrameValue::Error("ERR DUMP payload version or checksum are wrong".into())
+        );
+    }
+
+    #[test]
+    fn test_ttl() {
+        let db = Db::new();
+        run(&db, &["SET", "key", "value"]);
+        let payload = dump(&db, "key");
+        let ok = FrameValue::SimpleString("OK".into());
+
+        assert_eq!(
+            restore_with_ttl(&db, "relative", "5000", payload.clone(), &[]),
+            ok
+        );
+        assert!(matches!(
+            run(&db, &["PTTL", "relative"]),
+            FrameValue::Integer(4900..=5000)
+        ));
+
+        let at = (SystemTime::now() + Duration::from_secs(100))
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_millis()
+            .to_string();
+        assert_eq!(
+            restore_with_ttl(&db, "absolute", &at, payload.clone(), &["ABSTTL"]),
+            ok
+        );
+        assert!(matches!(
+            run(&db, &["TTL", "absolute"]),
+            FrameValue::Integer(99 | 100)
+        ));
+
+        // Already expired, so nothing is created but the replaced key goes
+        assert_eq!(
+            restore_with_ttl(&db, "key", "1", payload.clone(), &["ABSTTL", "REPLACE"]),
+            ok
+        );
+        assert_eq!(run(&db, &["GET", "key"]), FrameValue::NullBulkString);
+        assert_eq!(
+            restore_with_ttl(&db, "key", "-1", payload, &[]),
+            FrameValue::Error("ERR Invalid TTL value, must be >= 0".into())
+        );
+    }
+
+    #[test]
+    fn test_strings_are_capped() {
+        let db = Db::new().with_max_value_len(3);
+        run(&db, &["RPUSH", "list", "abc", "abcd"]);
+
+        assert_eq!(
+            restore(&db, "copy", dump(&db, "list"), &[]),
+            FrameValue::Error("ERR string exceeds maximum allowed size".into())
+        );
+        assert_eq!(run(&db, &["DBSIZE"]), FrameValue::Integer(1));
+    }
+
+    #[test]
+    fn test_empty_container_is_refused() {
+        let db = Db::new();
+        let empty = rdb::encode(&DbValue::List(Default::default()));
+
+        assert_eq!(
+            restore(&db, "list", empty, &[]),
+            FrameValue::Error("ERR Bad data format".into())
+        );
+        assert_eq!(
+            run(&db, &["LMOVE", "list", "dest", "LEFT", "LEFT"]),
+            FrameValue::NullBulkString
+        );
+        assert_eq!(run(&db, &["DBSIZE"]), FrameValue::Integer(0));
+    }
+}
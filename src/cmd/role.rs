@@ -0,0 +1,67 @@
+use super::{CommandError, Parse};
+use crate::{
+    db::{Db, Storage},
+    frame::FrameValue,
+};
+
+/// Reports whether this server is a master or a replica, and of whom
+pub struct Role;
+
+impl Role {
+    pub fn parse_frames(parse: &mut Parse) -> Result<Self, CommandError> {
+        parse.finish()?;
+        Ok(Self)
+    }
+
+    pub fn apply<S: Storage>(self, db: &Db<S>) -> FrameValue {
+        let replication = db.replication();
+
+        if let Some(master) = replication.master() {
+            return FrameValue::Array(vec![
+                FrameValue::BulkString("slave".into()),
+                FrameValue::BulkString(master.host.into()),
+                FrameValue::Integer(master.port.into()),
+                FrameValue::BulkString(master.state.as_str().into()),
+                FrameValue::Integer(master.offset as i64),
+            ]);
+        }
+
+        let replicas = replication
+            .replicas()
+            .into_iter()
+            .map(|replica| {
+                FrameValue::Array(vec![
+                    FrameValue::BulkString(replica.ip.into()),
+                    FrameValue::BulkString(replica.listening_port.to_string().into()),
+                    FrameValue::BulkString(replica.ack_offset.to_string().into()),
+                ])
+            })
+            .collect();
+
+        FrameValue::Array(vec![
+            FrameValue::BulkString("master".into()),
+            FrameValue::Integer(replication.offset() as i64),
+            FrameValue::Array(replicas),
+        ])
+    }
+}
+
+#[cfg(test)]
+mod role_tests {
+    use super::*;
+    use crate::cmd::run;
+
+    #[test]
+    fn test_standalone_is_master() {
+        let db = Db::new();
+
+        assert_eq!(
+            run(&db, &["ROLE"]),
+            FrameValue::Array(vec![
+                FrameValue::BulkString("master".into()),
+                FrameValue::Integer(0),
+                FrameValue::Array(vec![]),
+            ])
+        );
+    }
+}
@@ -0,0 +1,36 @@
+use super::{CommandError, Parse, wrong_type};
+use crate::{
+    db::{Db, DbValue, Storage},
+    frame::FrameValue,
+};
+use bytes::Bytes;
+
+/// Adds members to a set, creating it if needed
+pub struct SAdd {
+    key: Bytes,
+    members: Vec<Bytes>,
+}
+
+impl SAdd {
+    pub fn parse_frames(parse: &mut Parse) -> Result<Self, CommandError> {
+        let key = parse.next_bytes()?;
+        let members = parse.rest_bytes()?;
+        Ok(Self { key, members })
+    }
+
+    pub fn apply<S: Storage>(self, db: &Db<S>) -> FrameValue {
+        let mut entries = db.lock();
+        let set = match entries.get_or_insert_with(self.key, || DbValue::Set(Default::default())) {
+            DbValue::Set(set) => set,
+            _ => return wrong_type(),
+        };
+
+        let added = self
+            .members
+            .into_iter()
+            .filter(|member| set.insert(member.clone()))
+            .count();
+
+        FrameValue::Integer(added as i64)
+    }
+}
@@ -0,0 +1,237 @@
+//! Cursor based iteration shared by the SCAN family
+//!
+//! Containers keep their elements ordered by scan position, as described in
+//! [`crate::dict`], and a cursor is the position to resume from. Elements
+//! present for the whole scan are returned at least once however the container
+//! grows or shrinks, though some may come back more than once.
+//!
+//! A page takes O(log N + `COUNT`) for N elements. `COUNT` is only a hint, as
+//! in Redis: elements sharing a position always come back in the same page, so
+//! that the cursor moves past them, which can make a page slightly larger.
+
+use super::{CommandError, Parse, are_equal, glob};
+use crate::{
+    db::{Db, Storage},
+    frame::FrameValue,
+};
+use bytes::Bytes;
+
+/// Elements scanned per call unless `COUNT` says otherwise
+const DEFAULT_COUNT: usize = 10;
+
+/// Types `SCAN TYPE` can filter keys by
+const TYPE_NAMES: &[&str] = &["string", "list", "set", "hash", "zset"];
+
+/// Arguments shared by the SCAN family, after the key if there is one
+pub struct ScanArgs {
+    cursor: Bytes,
+    pattern: Option<Bytes>,
+    count: i64,
+    /// Type of value to keep, which only SCAN itself accepts
+    type_name: Option<Bytes>,
+}
+
+/// Iterates over the keys of the selected database a page at a time
+///
+/// `TYPE` keeps only keys holding that type of value. Like `MATCH`, it is
+/// applied after a page has been picked, so a page may come back with fewer
+/// keys than `COUNT` or none at all while the scan goes on.
+pub struct Scan {
+    args: ScanArgs,
+}
+
+impl ScanArgs {
+    pub fn parse(parse: &mut Parse) -> Result<Self, CommandError> {
+        Self::parse_options(parse, false)
+    }
+
+    fn parse_options(parse: &mut Parse, accepts_type: bool) -> Result<Self, CommandError> {
+        let cursor = parse.next_bytes()?;
+        let mut pattern = None;
+        let mut count = DEFAULT_COUNT as i64;
+        let mut type_name = None;
+
+        while let Some(option) = parse.next_optional_bytes()? {
+            if are_equal(&option, b"MATCH") {
+                pattern = Some(parse.next_bytes()?);
+            } else if are_equal(&option, b"COUNT") {
+                count = parse.next_int()?;
+            } else if accepts_type && are_equal(&option, b"TYPE") {
+                type_name = Some(parse.next_bytes()?);
+            } else {
+                return Err(CommandError::Syntax);
+            }
+        }
+
+        Ok(Self {
+            cursor,
+            pattern,
+            count,
+            type_name,
+        })
+    }
+
+    /// Scans one page of the elements `scan` returns from a position on, in
+    /// scan order with their positions, each identified by the bytes `MATCH` is
+    /// applied to
+    ///
+    /// Replies with the cursor to continue from, 0 once the scan is complete,
+    /// and the elements turned into frames by `reply`.
+    pub fn page<T, I: Iterator<Item = (u64, T)>>(
+        &self,
+        scan: impl FnOnce(u64) -> I,
+        id: impl Fn(&T) -> &Bytes,
+        reply: impl Fn(T, &mut Vec<FrameValue>),
+    ) -> FrameValue {
+        let Some(cursor) = std::str::from_utf8(&self.cursor)
+            .ok()
+            .and_then(|cursor| cursor.parse::<u64>().ok())
+        else {
+            return FrameValue::Error("ERR invalid cursor".into());
+        };
+        if self.count < 1 {
+            return FrameValue::Error("ERR syntax error".into());
+        }
+
+        let count = self.count as usize;
+        let mut elements = scan(cursor).peekable();
+        let mut page = Vec::new();
+        let mut last = None;
+        while let Some((position, element)) =
+            elements.next_if(|(position, _)| page.len() < count || last == Some(*position))
+        {
+            last = Some(position);
+            page.push(element);
+        }
+        let next = elements.peek().map_or(0, |(position, _)| *position);
+
+        let mut frames = Vec::new();
+        for element in page {
+            let matched = self
+                .pattern
+                .as_ref()
+                .is_none_or(|pattern| glob::matches(pattern, id(&element)));
+            if matched {
+                reply(element, &mut frames);
+            }
+        }
+
+        FrameValue::Array(vec![
+            FrameValue::BulkString(next.to_string().into()),
+            FrameValue::Array(frames),
+        ])
+    }
+}
+
+impl Scan {
+    pub fn parse_frames(parse: &mut Parse) -> Result<Self, CommandError> {
+        Ok(Self {
+            args: ScanArgs::parse_options(parse, true)?,
+        })
+    }
+
+    pub fn apply<S: Storage>(self, db: &Db<S>) -> FrameValue {
+        let type_name = self.args.type_name.as_ref();
+        if let Some(name) = type_name
+            && !TYPE_NAMES
+                .iter()
+                .any(|known| are_equal(name, known.as_bytes()))
+        {
+            return FrameValue::Error("ERR unknown type name".into());
+        }
+
+        let entries = db.read();
+        self.args.page(
+            |cursor| {
+                entries
+                    .scan_from(cursor)
+                    .map(|(position, key, value)| (position, (key, value)))
+            },
+            |(key, _)| key,
+            |(key, value), frames| {
+                if type_name.is_none_or(|name| are_equal(name, value.type_name().as_bytes())) {
+                    frames.push(FrameValue::BulkString(key.clone()));
+                }
+            },
+        )
+    }
+}
+
+/// Reply for a scan of a key that doesn't exist
+pub fn empty_page() -> FrameValue {
+    FrameValue::Array(vec![
+        FrameValue::BulkString("0".into()),
+        FrameValue::Array(vec![]),
+    ])
+}
+
+#[cfg(test)]
+mod scan_tests {
+    use crate::{
+        cmd::{run, sorted_bulk_strings},
+        db::Db,
+        frame::FrameValue,
+    };
+    use bytes::Bytes;
+
+    /// Every key a scan with `options` returns, sorted
+    fn scan_all(db: &Db, options: &[&str]) -> Vec<Bytes> {
+        let mut found = Vec::new();
+        let mut cursor = "0".to_string();
+        loop {
+            let mut args = vec!["SCAN", &cursor, "COUNT", "3"];
+            args.extend(options);
+            let FrameValue::Array(reply) = run(db, &args) else {
+                panic!("expected an array");
+            };
+            let [FrameValue::BulkString(next), page] = <[_; 2]>::try_from(reply).unwrap() else {
+                panic!("expected a cursor and keys");
+            };
+            found.extend(sorted_bulk_strings(page));
+            cursor = String::from_utf8(next.to_vec()).unwrap();
+            if cursor == "0" {
+                break;
+            }
+        }
+        found.sort();
+        found
+    }
+
+    #[test]
+    fn test_type_filter() {
+        let db = Db::new();
+        run(&db, &["SET", "str:1", "a"]);
+        run(&db, &["SET", "str:2", "b"]);
+        run(&db, &["SET", "other", "c"]);
+        run(&db, &["SADD", "str:set", "a"]);
+        run(&db, &["HSET", "hash", "field", "value"]);
+        run(&db, &["RPUSH", "list", "a"]);
+        run(&db, &["ZADD", "zset", "1", "a"]);
+
+        assert_eq!(scan_all(&db, &[]).len(), 7);
+        assert_eq!(
+            scan_all(&db, &["TYPE", "string"]),
+            ["other", "str:1", "str:2"]
+        );
+        assert_eq!(
+            scan_all(&db, &["MATCH", "str:*", "TYPE", "STRING"]),
+            ["str:1", "str:2"]
+        );
+        assert_eq!(scan_all(&db, &["TYPE", "zset"]), ["zset"]);
+        assert!(scan_all(&db, &["TYPE", "list", "MATCH", "str:*"]).is_empty());
+    }
+
+    #[test]
+    fn test_errors() {
+        let db = Db::new();
+
+        assert_eq!(
+            run(&db, &["SCAN", "0", "TYPE", "stream"]),
+            FrameValue::Error("ERR unknown type name".into())
+        );
+        assert_eq!(
+            run(&db, &["SSCAN", "key", "0", "TYPE", "string"]),
+            FrameValue::Error("ERR syntax error".into())
+        );
+    }
+}
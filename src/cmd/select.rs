@@ -0,0 +1,75 @@
+use super::{CommandError, Parse};
+use crate::{
+    db::{Db, Storage},
+    frame::FrameValue,
+};
+
+/// Selects the logical database the connection's commands run against
+pub struct Select {
+    index: i64,
+}
+
+impl Select {
+    pub fn parse_frames(parse: &mut Parse) -> Result<Self, CommandError> {
+        let index = parse.next_int()?;
+        parse.finish()?;
+        Ok(Self { index })
+    }
+
+    /// Switches `db` over to the requested database
+    pub fn apply<S: Storage>(self, db: &mut Db<S>) -> FrameValue {
+        match usize::try_from(self.index)
+            .ok()
+            .and_then(|index| db.select(index))
+        {
+            Some(selected) => {
+                *db = selected;
+                FrameValue::SimpleString("OK".into())
+            }
+            None => out_of_range(),
+        }
+    }
+}
+
+/// Reply for a database index that doesn't exist
+pub fn out_of_range() -> FrameValue {
+    FrameValue::Error("ERR DB index is out of range".into())
+}
+
+#[cfg(test)]
+mod select_tests {
+    use super::*;
+    use crate::cmd::{Command, command_frame, run};
+
+    fn select(db: &mut Db, index: &str) -> FrameValue {
+        match Command::from_frame(command_frame(&["SELECT", index])) {
+            Ok(Command::Select(cmd)) => cmd.apply(db),
+            _ => panic!("expected SELECT"),
+        }
+    }
+
+    #[test]
+    fn test_databases_are_separate() {
+        let mut db = Db::new();
+        run(&db, &["SET", "key", "zero"]);
+
+        assert_eq!(select(&mut db, "1"), FrameValue::SimpleString("OK".into()));
+        assert_eq!(db.index(), 1);
+        assert_eq!(run(&db, &["GET", "key"]), FrameValue::NullBulkString);
+
+        assert_eq!(select(&mut db, "0"), FrameValue::SimpleString("OK".into()));
+        assert_eq!(
+            run(&db, &["GET", "key"]),
+            FrameValue::BulkString("zero".into())
+        );
+    }
+
+    #[test]
+    fn test_out_of_range() {
+        let mut db = Db::new();
+
+        assert_eq!(select(&mut db, "16"), out_of_range());
+        assert_eq!(select(&mut db, "-1"), out_of_range());
+        assert_eq!(db.index(), 0);
+    }
+}
@@ -0,0 +1,29 @@
+use super::{CommandError, Parse, string_too_long};
+use crate::{
+    db::{Db, DbValue, Storage},
+    frame::FrameValue,
+};
+use bytes::Bytes;
+
+/// Sets the string value of a key, overwriting whatever was stored there
+pub struct Set {
+    key: Bytes,
+    value: Bytes,
+}
+
+impl Set {
+    pub fn parse_frames(parse: &mut Parse) -> Result<Self, CommandError> {
+        let key = parse.next_bytes()?;
+        let value = parse.next_bytes()?;
+        parse.finish()?;
+        Ok(Self { key, value })
+    }
+
+    pub fn apply<S: Storage>(self, db: &Db<S>) -> FrameValue {
+        if self.value.len() > db.max_value_len() {
+            return string_too_long();
+        }
+        db.lock().insert(self.key, DbValue::String(self.value));
+        FrameValue::SimpleString("OK".into())
+    }
+}
@@ -0,0 +1,85 @@
+use super::wrong_type;
+use crate::{
+    db::{DbValue, Storage},
+    dict::Set,
+    frame::FrameValue,
+};
+use bytes::Bytes;
+
+/// Operation combining several sets into one
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SetOp {
+    Inter,
+    Union,
+    Diff,
+}
+
+/// Combines the sets stored at `keys` using `op`
+///
+/// Missing keys behave as empty sets. Returns the `WRONGTYPE` reply if any key
+/// holds something other than a set.
+pub fn combine<S: Storage>(entries: &S, keys: &[Bytes], op: SetOp) -> Result<Set, FrameValue> {
+    let empty = Set::default();
+    let sets = sets_at(entries, keys, &empty)?;
+
+    let Some((first, rest)) = sets.split_first() else {
+        return Ok(Set::default());
+    };
+
+    let result = match op {
+        SetOp::Inter => first
+            .iter()
+            .filter(|member| rest.iter().all(|set| set.contains(member)))
+            .cloned()
+            .collect(),
+        SetOp::Union => sets.iter().flat_map(|set| set.iter()).cloned().collect(),
+        SetOp::Diff => first
+            .iter()
+            .filter(|member| !rest.iter().any(|set| set.contains(member)))
+            .cloned()
+            .collect(),
+    };
+
+    Ok(result)
+}
+
+/// Counts the members of the intersection of the sets stored at `keys`, giving
+/// up once `limit` have been found
+///
+/// Walks the smallest set and never builds the intersection itself.
+pub fn inter_card<S: Storage>(
+    entries: &S,
+    keys: &[Bytes],
+    limit: usize,
+) -> Result<usize, FrameValue> {
+    let empty = Set::default();
+    let mut sets = sets_at(entries, keys, &empty)?;
+    sets.sort_unstable_by_key(|set| set.len());
+
+    let Some((smallest, rest)) = sets.split_first() else {
+        return Ok(0);
+    };
+
+    Ok(smallest
+        .iter()
+        .filter(|member| rest.iter().all(|set| set.contains(member)))
+        .take(limit)
+        .count())
+}
+
+/// Sets stored at `keys`, with `empty` standing in for missing keys
+fn sets_at<'a, S: Storage>(
+    entries: &'a S,
+    keys: &[Bytes],
+    empty: &'a Set,
+) -> Result<Vec<&'a Set>, FrameValue> {
+    let mut sets = Vec::with_capacity(keys.len());
+    for key in keys {
+        match entries.get_shared(key) {
+            Some(DbValue::Set(set)) => sets.push(set),
+            Some(_) => return Err(wrong_type()),
+            None => sets.push(empty),
+        }
+    }
+    Ok(sets)
+}
@@ -0,0 +1,113 @@
+use super::{
+    CommandError, Parse,
+    set_algebra::{SetOp, combine},
+};
+use crate::{
+    db::{Db, DbValue, Storage},
+    frame::FrameValue,
+};
+use bytes::Bytes;
+
+/// Stores the intersection, union or difference of sets under `destination`
+pub struct SetStore {
+    op: SetOp,
+    destination: Bytes,
+    keys: Vec<Bytes>,
+}
+
+impl SetStore {
+    pub fn parse_frames(parse: &mut Parse, op: SetOp) -> Result<Self, CommandError> {
+        let destination = parse.next_bytes()?;
+        let keys = parse.rest_bytes()?;
+        Ok(Self {
+            op,
+            destination,
+            keys,
+        })
+    }
+
+    pub fn apply<S: Storage>(self, db: &Db<S>) -> FrameValue {
+        let mut entries = db.lock();
+        let result = match combine(&*entries, &self.keys, self.op) {
+            Ok(result) => result,
+            Err(reply) => return reply,
+        };
+
+        let len = result.len();
+        if result.is_empty() {
+            entries.remove(&self.destination);
+        } else {
+            entries.insert(self.destination, DbValue::Set(result));
+        }
+
+        FrameValue::Integer(len as i64)
+    }
+}
+
+#[cfg(test)]
+mod setstore_tests {
+    use super::*;
+    use crate::cmd::{run, sorted_bulk_strings};
+
+    fn members(db: &Db, key: &str) -> Vec<Bytes> {
+        sorted_bulk_strings(run(db, &["SMEMBERS", key]))
+    }
+
+    fn db_with_sets() -> Db {
+        let db = Db::new();
+        run(&db, &["SADD", "a", "1", "2", "3"]);
+        run(&db, &["SADD", "b", "3", "4"]);
+        db
+    }
+
+    #[test]
+    fn test_sunionstore() {
+        let db = db_with_sets();
+
+        assert_eq!(
+            run(&db, &["SUNIONSTORE", "dest", "a", "b"]),
+            FrameValue::Integer(4)
+        );
+        assert_eq!(members(&db, "dest"), ["1", "2", "3", "4"]);
+    }
+
+    #[test]
+    fn test_sinterstore_and_sdiffstore() {
+        let db = db_with_sets();
+
+        assert_eq!(
+            run(&db, &["SINTERSTORE", "dest", "a", "b"]),
+            FrameValue::Integer(1)
+        );
+        assert_eq!(members(&db, "dest"), ["3"]);
+
+        assert_eq!(
+            run(&db, &["SDIFFSTORE", "dest", "a", "b"]),
+            FrameValue::Integer(2)
+        );
+        assert_eq!(members(&db, "dest"), ["1", "2"]);
+    }
+
+    #[test]
+    fn test_empty_result_deletes_destination() {
+        let db = db_with_sets();
+        run(&db, &["SET", "dest", "value"]);
+
+        assert_eq!(
+            run(&db, &["SINTERSTORE", "dest", "a", "missing"]),
+            FrameValue::Integer(0)
+        );
+        assert_eq!(run(&db, &["GET", "dest"]), FrameValue::NullBulkString);
+    }
+
+    #[test]
+    fn test_wrong_type() {
+        let db = db_with_sets();
+        run(&db, &["SET", "string", "value"]);
+
+        assert_eq!(
+            run(&db, &["SUNIONSTORE", "dest", "a", "string"]),
+            crate::cmd::wrong_type()
+        );
+    }
+}
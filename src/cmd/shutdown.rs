@@ -0,0 +1,32 @@
+use super::{CommandEffect, CommandError, Parse, are_equal};
+use crate::frame::FrameValue;
+
+/// Stops the server, closing every connection without a reply
+///
+/// There is no dump file to write, so `NOSAVE` is accepted for compatibility
+/// while `SAVE` is refused rather than stopping without the dump it asks for.
+pub struct Shutdown {
+    save: bool,
+}
+
+impl Shutdown {
+    pub fn parse_frames(parse: &mut Parse) -> Result<Self, CommandError> {
+        let save = match parse.next_optional_bytes()? {
+            Some(mode) if are_equal(&mode, b"SAVE") => true,
+            Some(mode) if are_equal(&mode, b"NOSAVE") => false,
+            Some(_) => return Err(CommandError::Syntax),
+            None => false,
+        };
+        parse.finish()?;
+        Ok(Self { save })
+    }
+
+    pub fn apply(self) -> CommandEffect {
+        if self.save {
+            return CommandEffect::Reply(FrameValue::Error(
+                "ERR SHUTDOWN SAVE is not supported, as there is no dump file to write".into(),
+            ));
+        }
+        CommandEffect::Shutdown
+    }
+}
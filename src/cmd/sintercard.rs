@@ -0,0 +1,143 @@
+use super::{CommandError, Parse, are_equal, set_algebra::inter_card};
+use crate::{
+    db::{Db, Storage},
+    frame::FrameValue,
+};
+use bytes::Bytes;
+
+/// Counts the members of the intersection of several sets, optionally stopping
+/// once `limit` of them have been found
+pub struct SInterCard {
+    numkeys: i64,
+    keys: Vec<Bytes>,
+    limit: Option<i64>,
+}
+
+impl SInterCard {
+    pub fn parse_frames(parse: &mut Parse) -> Result<Self, CommandError> {
+        let numkeys = parse.next_int()?;
+
+        // A `numkeys` out of range is reported by `apply`, once every argument is
+        // accounted for
+        let count = match usize::try_from(numkeys) {
+            Ok(count) if count > 0 && count <= parse.remaining() => count,
+            _ => {
+                let keys = parse.rest_bytes()?;
+                return Ok(Self {
+                    numkeys,
+                    keys,
+                    limit: None,
+                });
+            }
+        };
+        let keys = (0..count)
+            .map(|_| parse.next_bytes())
+            .collect::<Result<_, _>>()?;
+
+        let limit = match parse.next_optional_bytes()? {
+            Some(option) if are_equal(&option, b"LIMIT") => Some(parse.next_int()?),
+            Some(_) => return Err(CommandError::Syntax),
+            None => None,
+        };
+        if parse.remaining() > 0 {
+            return Err(CommandError::Syntax);
+        }
+
+        Ok(Self {
+            numkeys,
+            keys,
+            limit,
+        })
+    }
+
+    pub fn apply<S: Storage>(self, db: &Db<S>) -> FrameValue {
+        if self.numkeys <= 0 {
+            return FrameValue::Error("ERR numkeys should be greater than 0".into());
+        }
+        if self.keys.len() < self.numkeys as usize {
+            return FrameValue::Error(
+                "ERR Number of keys can't be greater than number of args".into(),
+            );
+        }
+        // A limit of 0 means no limit
+        let limit = match self.limit {
+            Some(limit) if limit < 0 => {
+                return FrameValue::Error("ERR LIMIT can't be negative".into());
+            }
+            Some(limit) if limit > 0 => limit as usize,
+            _ => usize::MAX,
+        };
+
+        match inter_card(&*db.read_keys(&self.keys), &self.keys, limit) {
+            Ok(count) => FrameValue::Integer(count as i64),
+            Err(reply) => reply,
+        }
+    }
+}
+
+#[cfg(test)]
+mod sintercard_tests {
+    use crate::{cmd::run, db::Db, frame::FrameValue};
+
+    fn db_with_sets() -> Db {
+        let db = Db::new();
+        run(&db, &["SADD", "a", "1", "2", "3", "4", "5"]);
+        run(&db, &["SADD", "b", "2", "3", "4", "5", "6"]);
+        run(&db, &["SADD", "c", "3", "4", "5", "6", "7"]);
+        db
+    }
+
+    #[test]
+    fn test_full_cardinality() {
+        let db = db_with_sets();
+
+        assert_eq!(
+            run(&db, &["SINTERCARD", "3", "a", "b", "c"]),
+            FrameValue::Integer(3)
+        );
+        assert_eq!(
+            run(&db, &["SINTERCARD", "2", "a", "b", "LIMIT", "0"]),
+            FrameValue::Integer(4)
+        );
+        assert_eq!(
+            run(&db, &["SINTERCARD", "2", "a", "missing"]),
+            FrameValue::Integer(0)
+        );
+    }
+
+    #[test]
+    fn test_limit_stops_early() {
+        let db = db_with_sets();
+
+        assert_eq!(
+            run(&db, &["SINTERCARD", "3", "a", "b", "c", "LIMIT", "2"]),
+            FrameValue::Integer(2)
+        );
+        assert_eq!(
+            run(&db, &["SINTERCARD", "1", "a", "limit", "10"]),
+            FrameValue::Integer(5)
+        );
+    }
+
+    #[test]
+    fn test_invalid_arguments() {
+        let db = db_with_sets();
+
+        assert_eq!(
+            run(&db, &["SINTERCARD", "3", "a", "b"]),
+            FrameValue::Error("ERR Number of keys can't be greater than number of args".into())
+        );
+        assert_eq!(
+            run(&db, &["SINTERCARD", "0", "a"]),
+            FrameValue::Error("ERR numkeys should be greater than 0".into())
+        );
+        assert_eq!(
+            run(&db, &["SINTERCARD", "1", "a", "b"]),
+            FrameValue::Error("ERR syntax error".into())
+        );
+        assert_eq!(
+            run(&db, &["SINTERCARD", "1", "a", "LIMIT", "-1"]),
+            FrameValue::Error("ERR LIMIT can't be negative".into())
+        );
+    }
+}
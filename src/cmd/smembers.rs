@@ -0,0 +1,27 @@
+use super::{BulkArray, CommandError, Parse, wrong_type};
+use crate::{
+    db::{Db, DbValue, Storage},
+    frame::FrameValue,
+};
+use bytes::Bytes;
+
+/// Lists every member of a set
+pub struct SMembers {
+    key: Bytes,
+}
+
+impl SMembers {
+    pub fn parse_frames(parse: &mut Parse) -> Result<Self, CommandError> {
+        let key = parse.next_bytes()?;
+        parse.finish()?;
+        Ok(Self { key })
+    }
+
+    pub fn stream<S: Storage>(self, db: &Db<S>) -> Result<BulkArray, FrameValue> {
+        match db.read_key(&self.key).get_shared(&self.key) {
+            Some(DbValue::Set(set)) => Ok(BulkArray::new(set.iter().cloned().collect())),
+            Some(_) => Err(wrong_type()),
+            None => Ok(BulkArray::new(Vec::new())),
+        }
+    }
+}
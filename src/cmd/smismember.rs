@@ -0,0 +1,81 @@
+use super::{CommandError, Parse, wrong_type};
+use crate::{
+    db::{Db, DbValue, Storage},
+    frame::FrameValue,
+};
+use bytes::Bytes;
+
+/// Checks whether each of several members is in a set, in the order asked
+pub struct SMIsMember {
+    key: Bytes,
+    members: Vec<Bytes>,
+}
+
+impl SMIsMember {
+    pub fn parse_frames(parse: &mut Parse) -> Result<Self, CommandError> {
+        let key = parse.next_bytes()?;
+        let members = parse.rest_bytes()?;
+        Ok(Self { key, members })
+    }
+
+    pub fn apply<S: Storage>(self, db: &Db<S>) -> FrameValue {
+        let entries = db.read_key(&self.key);
+        let set = match entries.get_shared(&self.key) {
+            Some(DbValue::Set(set)) => Some(set),
+            Some(_) => return wrong_type(),
+            None => None,
+        };
+
+        FrameValue::Array(
+            self.members
+                .iter()
+                .map(|member| {
+                    FrameValue::Integer(set.is_some_and(|set| set.contains(member)) as i64)
+                })
+                .collect(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod smismember_tests {
+    use super::*;
+    use crate::cmd::run;
+
+    #[test]
+    fn test_members_in_order() {
+        let db = Db::new();
+        run(&db, &["SADD", "set", "a", "c"]);
+
+        assert_eq!(
+            run(&db, &["SMISMEMBER", "set", "a", "b", "c", "a"]),
+            FrameValue::Array(vec![
+                FrameValue::Integer(1),
+                FrameValue::Integer(0),
+                FrameValue::Integer(1),
+                FrameValue::Integer(1),
+            ])
+        );
+        assert_eq!(
+            run(&db, &["SMISMEMBER", "missing", "a", "b"]),
+            FrameValue::Array(vec![FrameValue::Integer(0), FrameValue::Integer(0)])
+        );
+    }
+
+    #[test]
+    fn test_errors() {
+        let db = Db::new();
+        run(&db, &["SET", "string", "value"]);
+
+        assert_eq!(
+            run(&db, &["SMISMEMBER", "string", "a"]),
+            FrameValue::Error(
+                "WRONGTYPE Operation against a key holding the wrong kind of value".into()
+            )
+        );
+        assert_eq!(
+            run(&db, &["SMISMEMBER", "set"]),
+            FrameValue::Error("ERR wrong number of arguments for 'smismember' command".into())
+        );
+    }
+}
@@ -0,0 +1,135 @@
+use super::{CommandError, Parse, wrong_type};
+use crate::{
+    db::{Db, DbValue, Storage},
+    frame::FrameValue,
+    random::sample,
+};
+use bytes::Bytes;
+
+/// Removes and returns random members of a set, deleting the key once it is empty
+///
+/// Without a count a single member is returned, or null if the set is missing.
+/// Replicas get an `SREM` of the popped members instead of the `SPOP`, which
+/// would have them pop members of their own.
+pub struct SPop {
+    key: Bytes,
+    count: Option<usize>,
+}
+
+impl SPop {
+    pub fn parse_frames(parse: &mut Parse) -> Result<Self, CommandError> {
+        let key = parse.next_bytes()?;
+        let count = match parse.remaining() {
+            0 => None,
+            _ => Some(usize::try_from(parse.next_int()?).map_err(|_| CommandError::NotInteger)?),
+        };
+        parse.finish()?;
+        Ok(Self { key, count })
+    }
+
+    /// Pops the members, replying along with the `SREM` replicas should apply,
+    /// `None` if nothing was popped
+    pub fn apply<S: Storage>(self, db: &Db<S>) -> (FrameValue, Option<FrameValue>) {
+        let mut entries = db.lock();
+        let set = match entries.get_mut(&self.key) {
+            Some(DbValue::Set(set)) => set,
+            Some(_) => return (wrong_type(), None),
+            None if self.count.is_some() => return (FrameValue::Array(vec![]), None),
+            None => return (FrameValue::NullBulkString, None),
+        };
+
+        let mut rng = db.rng();
+        let popped: Vec<Bytes> = match self.count {
+            None => set.random(&mut rng).cloned().into_iter().collect(),
+            Some(count) => {
+                let count = count.min(i64::MAX as usize) as i64;
+                // Only negative counts are ever refused
+                sample(set.iter(), set.len(), count, &mut rng)
+                    .unwrap_or_default()
+                    .into_iter()
+                    .cloned()
+                    .collect()
+            }
+        };
+        drop(rng);
+        for member in &popped {
+            set.remove(member);
+        }
+
+        if set.is_empty() {
+            entries.remove(&self.key);
+        }
+
+        let propagated = (!popped.is_empty()).then(|| {
+            let srem = [Bytes::from("SREM"), self.key].into_iter();
+            FrameValue::Array(
+                srem.chain(popped.iter().cloned())
+                    .map(FrameValue::BulkString)
+                    .collect(),
+            )
+        });
+        let reply = match self.count {
+            Some(_) => FrameValue::Array(popped.into_iter().map(FrameValue::BulkString).collect()),
+            None => popped
+                .into_iter()
+                .next()
+                .map_or(FrameValue::NullBulkString, FrameValue::BulkString),
+        };
+        (reply, propagated)
+    }
+}
+
+#[cfg(test)]
+mod spop_tests {
+    use super::*;
+    use crate::cmd::{run, sorted_bulk_strings};
+
+    #[test]
+    fn test_single_pop() {
+        let db = Db::new();
+        run(&db, &["SADD", "set", "a", "b"]);
+
+        let FrameValue::BulkString(first) = run(&db, &["SPOP", "set"]) else {
+            panic!("expected a bulk string");
+        };
+        let rest = sorted_bulk_strings(run(&db, &["SMEMBERS", "set"]));
+        assert_eq!(rest.len(), 1);
+        assert_ne!(rest[0], first);
+
+        assert_eq!(
+            run(&db, &["SPOP", "set"]),
+            FrameValue::BulkString(rest[0].clone())
+        );
+        assert_eq!(run(&db, &["SPOP", "set"]), FrameValue::NullBulkString);
+        assert_eq!(run(&db, &["GET", "set"]), FrameValue::NullBulkString);
+    }
+
+    #[test]
+    fn test_count_pop() {
+        let db = Db::new();
+        run(&db, &["SADD", "set", "a", "b", "c", "d"]);
+
+        let mut popped = sorted_bulk_strings(run(&db, &["SPOP", "set", "3"]));
+        assert_eq!(popped.len(), 3);
+        popped.extend(sorted_bulk_strings(run(&db, &["SMEMBERS", "set"])));
+        popped.sort();
+        assert_eq!(popped, ["a", "b", "c", "d"]);
+    }
+
+    #[test]
+    fn test_count_larger_than_set() {
+        let db = Db::new();
+        run(&db, &["SADD", "set", "a", "b"]);
+
+        assert_eq!(
+            sorted_bulk_strings(run(&db, &["SPOP", "set", "10"])),
+            ["a", "b"]
+        );
+        assert_eq!(run(&db, &["SMEMBERS", "set"]), FrameValue::Array(vec![]));
+        assert_eq!(run(&db, &["SPOP", "set", "1"]), FrameValue::Array(vec![]));
+        assert_eq!(
+            run(&db, &["SPOP", "set", "-1"]),
+            FrameValue::Error("ERR value is not an integer or out of range".into())
+        );
+    }
+}
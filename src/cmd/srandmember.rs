@@ -0,0 +1,145 @@
+use super::{CommandError, Parse, wrong_type};
+use crate::{
+    db::{Db, DbValue, Storage},
+    dict::Set,
+    frame::FrameValue,
+    random::{out_of_range, sample},
+};
+use bytes::Bytes;
+
+/// Returns random members of a set without removing them
+///
+/// Without a count a single member is returned, or null if the set is missing.
+pub struct SRandMember {
+    key: Bytes,
+    count: Option<i64>,
+}
+
+impl SRandMember {
+    pub fn parse_frames(parse: &mut Parse) -> Result<Self, CommandError> {
+        let key = parse.next_bytes()?;
+        let count = match parse.remaining() {
+            0 => None,
+            _ => Some(parse.next_int()?),
+        };
+        parse.finish()?;
+        Ok(Self { key, count })
+    }
+
+    pub fn apply<S: Storage>(self, db: &Db<S>) -> FrameValue {
+        let entries = db.read_key(&self.key);
+        let empty = Set::default();
+        let set = match entries.get_shared(&self.key) {
+            Some(DbValue::Set(set)) => set,
+            Some(_) => return wrong_type(),
+            None => &empty,
+        };
+        let mut rng = db.rng();
+
+        match self.count {
+            Some(count) => match sample(set.iter(), set.len(), count, &mut rng) {
+                Some(picked) => FrameValue::Array(
+                    picked
+                        .into_iter()
+                        .map(|member| FrameValue::BulkString(member.clone()))
+                        .collect(),
+                ),
+                None => out_of_range(),
+            },
+            None => set
+                .random(&mut rng)
+                .map_or(FrameValue::NullBulkString, |member| {
+                    FrameValue::BulkString(member.clone())
+                }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod srandmember_tests {
+    use super::*;
+    use crate::cmd::{run, sorted_bulk_strings};
+
+    fn db_with_set() -> Db {
+        let db = Db::new();
+        run(&db, &["SADD", "set", "a", "b", "c", "d", "e"]);
+        db
+    }
+
+    #[test]
+    fn test_distinct_members() {
+        let db = db_with_set();
+
+        let picked = sorted_bulk_strings(run(&db, &["SRANDMEMBER", "set", "3"]));
+        assert_eq!(picked.len(), 3);
+        assert!(picked.windows(2).all(|pair| pair[0] != pair[1]));
+        assert!(
+            picked
+                .iter()
+                .all(|m| [&b"a"[..], b"b", b"c", b"d", b"e"].contains(&&m[..]))
+        );
+
+        let all = sorted_bulk_strings(run(&db, &["SRANDMEMBER", "set", "10"]));
+        assert_eq!(all, ["a", "b", "c", "d", "e"]);
+    }
+
+    #[test]
+    fn test_negative_count_allows_repeats() {
+        let db = db_with_set();
+
+        let picked = sorted_bulk_strings(run(&db, &["SRANDMEMBER", "set", "-20"]));
+        assert_eq!(picked.len(), 20);
+        assert!(
+            picked
+                .iter()
+                .all(|m| [&b"a"[..], b"b", b"c", b"d", b"e"].contains(&&m[..]))
+        );
+    }
+
+    #[test]
+    fn test_single_member_and_missing_key() {
+        let db = db_with_set();
+        run(&db, &["SET", "string", "value"]);
+
+        assert!(matches!(
+            run(&db, &["SRANDMEMBER", "set"]),
+            FrameValue::BulkString(_)
+        ));
+        assert_eq!(
+            run(&db, &["SRANDMEMBER", "missing"]),
+            FrameValue::NullBulkString
+        );
+        assert_eq!(
+            run(&db, &["SRANDMEMBER", "missing", "3"]),
+            FrameValue::Array(vec![])
+        );
+        assert_eq!(run(&db, &["SRANDMEMBER", "string"]), wrong_type());
+    }
+
+    #[test]
+    fn test_huge_negative_count() {
+        let db = db_with_set();
+
+        for count in [i64::MIN.to_string(), "-10000000000".to_string()] {
+            assert_eq!(run(&db, &["SRANDMEMBER", "set", &count]), out_of_range());
+            assert_eq!(
+                run(&db, &["SRANDMEMBER", "missing", &count]),
+                out_of_range()
+            );
+        }
+    }
+
+    #[test]
+    fn test_same_seed_same_picks() {
+        let picks = |seed| {
+            let db = Db::new().with_rng_seed(seed);
+            run(&db, &["SADD", "set", "a", "b", "c", "d", "e"]);
+            [
+                run(&db, &["SRANDMEMBER", "set"]),
+                run(&db, &["SRANDMEMBER", "set", "3"]),
+                run(&db, &["SRANDMEMBER", "set", "-3"]),
+            ]
+        };
+        assert_eq!(picks(42), picks(42));
+    }
+}
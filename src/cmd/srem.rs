@@ -0,0 +1,69 @@
+use super::{CommandError, Parse, wrong_type};
+use crate::{
+    db::{Db, DbValue, Storage},
+    frame::FrameValue,
+};
+use bytes::Bytes;
+
+/// Removes members from a set, deleting the key once it is empty
+pub struct SRem {
+    key: Bytes,
+    members: Vec<Bytes>,
+}
+
+impl SRem {
+    pub fn parse_frames(parse: &mut Parse) -> Result<Self, CommandError> {
+        let key = parse.next_bytes()?;
+        let members = parse.rest_bytes()?;
+        Ok(Self { key, members })
+    }
+
+    pub fn apply<S: Storage>(self, db: &Db<S>) -> FrameValue {
+        let mut entries = db.lock();
+        let set = match entries.get_mut(&self.key) {
+            Some(DbValue::Set(set)) => set,
+            Some(_) => return wrong_type(),
+            None => return FrameValue::Integer(0),
+        };
+
+        let removed = self
+            .members
+            .iter()
+            .filter(|member| set.remove(member))
+            .count();
+
+        if set.is_empty() {
+            entries.remove(&self.key);
+        }
+
+        FrameValue::Integer(removed as i64)
+    }
+}
+
+#[cfg(test)]
+mod srem_tests {
+    use super::*;
+    use crate::cmd::{run, sorted_bulk_strings};
+
+    #[test]
+    fn test_removing_every_member_removes_key() {
+        let db = Db::new();
+        run(&db, &["SADD", "set", "a", "b", "c"]);
+
+        assert_eq!(
+            run(&db, &["SREM", "set", "a", "missing", "a"]),
+            FrameValue::Integer(1)
+        );
+        assert_eq!(
+            sorted_bulk_strings(run(&db, &["SMEMBERS", "set"])),
+            ["b", "c"]
+        );
+
+        assert_eq!(run(&db, &["SREM", "set", "b", "c"]), FrameValue::Integer(2));
+        assert!(db.lock().get(b"set").is_none());
+        assert_eq!(run(&db, &["SREM", "set", "b"]), FrameValue::Integer(0));
+
+        run(&db, &["SET", "string", "value"]);
+        assert_eq!(run(&db, &["SREM", "string", "a"]), wrong_type());
+    }
+}
@@ -0,0 +1,93 @@
+use super::{
+    CommandError, Parse,
+    scan::{ScanArgs, empty_page},
+    wrong_type,
+};
+use crate::{
+    db::{Db, DbValue, Storage},
+    frame::FrameValue,
+};
+use bytes::Bytes;
+
+/// Iterates over the members of a set a page at a time
+pub struct SScan {
+    key: Bytes,
+    args: ScanArgs,
+}
+
+impl SScan {
+    pub fn parse_frames(parse: &mut Parse) -> Result<Self, CommandError> {
+        let key = parse.next_bytes()?;
+        let args = ScanArgs::parse(parse)?;
+        Ok(Self { key, args })
+    }
+
+    pub fn apply<S: Storage>(self, db: &Db<S>) -> FrameValue {
+        match db.read_key(&self.key).get_shared(&self.key) {
+            Some(DbValue::Set(set)) => self.args.page(
+                |cursor| set.scan_from(cursor),
+                |member| member,
+                |member, frames| frames.push(FrameValue::BulkString(member.clone())),
+            ),
+            Some(_) => wrong_type(),
+            None => empty_page(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod sscan_tests {
+    use crate::{
+        cmd::{run, sorted_bulk_strings},
+        db::Db,
+        frame::FrameValue,
+    };
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_scan_to_completion() {
+        let db = Db::new();
+        let members: Vec<_> = (0..50).map(|i| i.to_string()).collect();
+        let mut args = vec!["SADD", "set"];
+        args.extend(members.iter().map(String::as_str));
+        run(&db, &args);
+
+        let mut found = HashSet::new();
+        let mut cursor = "0".to_string();
+        loop {
+            let FrameValue::Array(reply) = run(&db, &["SSCAN", "set", &cursor, "COUNT", "8"])
+            else {
+                panic!("expected an array");
+            };
+            let [FrameValue::BulkString(next), FrameValue::Array(page)] = &reply[..] else {
+                panic!("expected a cursor and members");
+            };
+            assert!(page.len() <= 8);
+            found.extend(sorted_bulk_strings(FrameValue::Array(page.clone())));
+            cursor = String::from_utf8(next.to_vec()).unwrap();
+            if cursor == "0" {
+                break;
+            }
+        }
+
+        assert_eq!(found.len(), 50);
+    }
+
+    #[test]
+    fn test_count_holds_for_large_sets() {
+        let db = Db::new();
+        let members: Vec<_> = (0..10_000).map(|i| i.to_string()).collect();
+        let mut args = vec!["SADD", "set"];
+        args.extend(members.iter().map(String::as_str));
+        run(&db, &args);
+
+        let FrameValue::Array(reply) = run(&db, &["SSCAN", "set", "0", "COUNT", "5"]) else {
+            panic!("expected an array");
+        };
+        let [FrameValue::BulkString(next), FrameValue::Array(page)] = &reply[..] else {
+            panic!("expected a cursor and members");
+        };
+        assert_eq!(page.len(), 5);
+        assert_ne!(next, "0");
+    }
+}
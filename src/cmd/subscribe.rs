@@ -0,0 +1,70 @@
+use super::{CommandError, Parse};
+use crate::{frame::FrameValue, pubsub::Subscriber};
+use bytes::Bytes;
+
+/// Subscribes the connection to one or more channels
+///
+/// Unlike other commands this replies with one confirmation per channel.
+pub struct Subscribe {
+    channels: Vec<Bytes>,
+}
+
+/// Unsubscribes the connection from the given channels, or all of them if none
+/// are given
+pub struct Unsubscribe {
+    channels: Vec<Bytes>,
+}
+
+impl Subscribe {
+    pub fn parse_frames(parse: &mut Parse) -> Result<Self, CommandError> {
+        let channels = parse.rest_bytes()?;
+        Ok(Self { channels })
+    }
+
+    pub fn apply(self, subscriber: &mut Subscriber) -> Vec<FrameValue> {
+        self.channels
+            .into_iter()
+            .map(|channel| {
+                let count = subscriber.subscribe(channel.clone());
+                confirmation("subscribe", FrameValue::BulkString(channel), count)
+            })
+            .collect()
+    }
+}
+
+impl Unsubscribe {
+    pub fn parse_frames(parse: &mut Parse) -> Result<Self, CommandError> {
+        Ok(Self {
+            channels: parse.rest_bytes()?,
+        })
+    }
+
+    pub fn apply(self, subscriber: &mut Subscriber) -> Vec<FrameValue> {
+        let channels = match self.channels.is_empty() {
+            true => subscriber.channels(),
+            false => self.channels,
+        };
+
+        if channels.is_empty() {
+            return vec![confirmation("unsubscribe", FrameValue::NullBulkString, 0)];
+        }
+
+        channels
+            .into_iter()
+            .map(|channel| {
+                let count = subscriber.unsubscribe(&channel);
+                confirmation("unsubscribe", FrameValue::BulkString(channel), count)
+            })
+            .collect()
+    }
+}
+
+/// Confirmation of a change to the subscriptions, pushed to RESP3 clients so
+/// they can tell it apart from the replies to commands sent in between
+fn confirmation(kind: &'static str, channel: FrameValue, count: usize) -> FrameValue {
+    FrameValue::Push(vec![
+        FrameValue::BulkString(kind.into()),
+        channel,
+        FrameValue::Integer(count as i64),
+    ])
+}
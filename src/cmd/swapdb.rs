@@ -0,0 +1,78 @@
+use super::{CommandError, Parse, select::out_of_range};
+use crate::{
+    db::{Db, Storage},
+    frame::FrameValue,
+};
+
+/// Swaps the contents of two logical databases
+pub struct SwapDb {
+    index1: i64,
+    index2: i64,
+}
+
+impl SwapDb {
+    pub fn parse_frames(parse: &mut Parse) -> Result<Self, CommandError> {
+        let index1 = parse.next_int()?;
+        let index2 = parse.next_int()?;
+        parse.finish()?;
+        Ok(Self { index1, index2 })
+    }
+
+    pub fn apply<S: Storage>(self, db: &Db<S>) -> FrameValue {
+        let (Ok(index1), Ok(index2)) = (usize::try_from(self.index1), usize::try_from(self.index2))
+        else {
+            return out_of_range();
+        };
+
+        if db.swap(index1, index2) {
+            FrameValue::SimpleString("OK".into())
+        } else {
+            out_of_range()
+        }
+    }
+}
+
+#[cfg(test)]
+mod swapdb_tests {
+    use crate::{cmd::run, db::Db, frame::FrameValue};
+
+    #[test]
+    fn test_swap_moves_keys() {
+        let db = Db::new();
+        let other = db.select(1).unwrap();
+        run(&db, &["SET", "key", "value"]);
+        run(&other, &["SADD", "set", "member"]);
+
+        assert_eq!(
+            run(&db, &["SWAPDB", "0", "1"]),
+            FrameValue::SimpleString("OK".into())
+        );
+        assert_eq!(
+            run(&other, &["GET", "key"]),
+            FrameValue::BulkString("value".into())
+        );
+        assert_eq!(run(&db, &["GET", "key"]), FrameValue::NullBulkString);
+        assert_eq!(
+            run(&db, &["SMEMBERS", "set"]),
+            FrameValue::Array(vec![FrameValue::BulkString("member".into())])
+        );
+    }
+
+    #[test]
+    fn test_out_of_range() {
+        let db = Db::new();
+
+        assert_eq!(
+            run(&db, &["SWAPDB", "0", "16"]),
+            FrameValue::Error("ERR DB index is out of range".into())
+        );
+        assert_eq!(
+            run(&db, &["SWAPDB", "-1", "0"]),
+            FrameValue::Error("ERR DB index is out of range".into())
+        );
+        assert_eq!(
+            run(&db, &["SWAPDB", "0", "zero"]),
+            FrameValue::Error("ERR value is not an integer or out of range".into())
+        );
+    }
+}
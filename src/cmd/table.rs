@@ -0,0 +1,664 @@
+use super::{
+    Command, CommandError, Parse,
+    acl::AclSubcommand,
+    append::Append,
+    are_equal,
+    auth::Auth,
+    bitcount::BitCount,
+    bitpos::BitPos,
+    client::ClientSubcommand,
+    command::CommandSubcommand,
+    dbsize::DbSize,
+    debug::DebugSubcommand,
+    del::Del,
+    dump::Dump,
+    echo::Echo,
+    expire::{Expire, ExpireAt, TimeUnit, Ttl},
+    get::Get,
+    getex::GetEx,
+    hdel::HDel,
+    hello::Hello,
+    hexists::HExists,
+    hincrby::HIncrBy,
+    hkeys::HKeys,
+    hlen::HLen,
+    hrandfield::HRandField,
+    hscan::HScan,
+    hset::HSet,
+    hvals::HVals,
+    incr::Incr,
+    incrbyfloat::IncrByFloat,
+    info::Info,
+    lindex::LIndex,
+    linsert::LInsert,
+    lmove::LMove,
+    lpos::LPos,
+    lrem::LRem,
+    lset::LSet,
+    ltrim::LTrim,
+    memory::MemorySubcommand,
+    mset::MSet,
+    object::ObjectSubcommand,
+    ping::Ping,
+    psync::PSync,
+    publish::Publish,
+    pubsub::PubSubSubcommand,
+    push::{ListEnd, Push},
+    quit::Quit,
+    replconf::ReplConf,
+    restore::Restore,
+    role::Role,
+    sadd::SAdd,
+    scan::Scan,
+    select::Select,
+    set::Set,
+    set_algebra::SetOp,
+    setstore::SetStore,
+    shutdown::Shutdown,
+    sintercard::SInterCard,
+    smembers::SMembers,
+    smismember::SMIsMember,
+    spop::SPop,
+    srandmember::SRandMember,
+    srem::SRem,
+    sscan::SScan,
+    subscribe::{Subscribe, Unsubscribe},
+    swapdb::SwapDb,
+    wait::Wait,
+    waitaof::WaitAof,
+    zadd::ZAdd,
+    zcard::ZCard,
+    zincrby::ZIncrBy,
+    zrange::{ZRangeByLex, ZRangeByScore},
+    zrank::{RankOrder, ZRank},
+    zrem::ZRem,
+    zscan::ZScan,
+};
+use crate::frame::FrameValue;
+use std::{collections::HashMap, sync::LazyLock};
+
+/// Describes a command: how to parse it and what `COMMAND INFO` reports about it
+///
+/// Parsing yields a [`Command`], which is then executed by [`Command::apply`].
+pub struct CommandSpec {
+    pub name: &'static str,
+    /// Number of arguments including the command name, negative meaning "at least"
+    pub arity: i64,
+    pub flags: &'static [&'static str],
+    pub first_key: i64,
+    pub last_key: i64,
+    pub step: i64,
+    /// Position of the argument counting the keys that follow it, for commands
+    /// whose keys move with that count and so have no fixed range
+    pub numkeys: Option<usize>,
+    /// Parses the arguments following the command name
+    pub parse: fn(&mut Parse) -> Result<Command, CommandError>,
+    /// Arguments hidden wherever the command is logged
+    pub redact: Redact,
+}
+
+/// Which arguments of a command hold secrets, replaced by `(redacted)` when
+/// the command is shown
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Redact {
+    Nothing,
+    /// Every argument after the command name, as AUTH takes
+    Arguments,
+    /// The username and password following an `AUTH` option, as HELLO takes
+    AuthOption,
+}
+
+impl Redact {
+    /// Whether the argument at `position` of `args`, the command name included,
+    /// is hidden
+    pub fn hides(self, args: &[FrameValue], position: usize) -> bool {
+        match self {
+            Self::Nothing => false,
+            Self::Arguments => position > 0,
+            Self::AuthOption => (position.saturating_sub(2).max(2)..position).any(|option| {
+                matches!(&args[option], FrameValue::BulkString(arg) if are_equal(arg, b"AUTH"))
+            }),
+        }
+    }
+}
+
+impl CommandSpec {
+    /// Whether the command modifies the keyspace
+    pub fn is_write(&self) -> bool {
+        self.flags.contains(&"write")
+    }
+
+    /// Whether the command only reads the keyspace
+    pub fn is_readonly(&self) -> bool {
+        self.flags.contains(&"readonly")
+    }
+
+    /// Whether the command can run before the connection has authenticated
+    pub fn allows_unauthenticated(&self) -> bool {
+        self.flags.contains(&"no-auth")
+    }
+
+    /// Whether the command waits on other clients, bounded by a timeout of its own
+    pub fn is_blocking(&self) -> bool {
+        self.flags.contains(&"blocking")
+    }
+
+    /// Whether `argc` arguments, the command name included, satisfy the arity
+    pub fn accepts_argc(&self, argc: usize) -> bool {
+        let argc = argc as i64;
+        if self.arity < 0 {
+            argc >= -self.arity
+        } else {
+            argc == self.arity
+        }
+    }
+
+    /// Positions of the keys among `args`, the command name included
+    ///
+    /// Keys counted by a `numkeys` argument come after those of the fixed
+    /// range, none if the count isn't a number.
+    pub fn key_positions(&self, args: &[FrameValue]) -> impl Iterator<Item = usize> {
+        let argc = args.len();
+        let (first, step) = (self.first_key.max(0), self.step.max(1));
+        // A negative last key counts back from the end of the arguments
+        let last = match self.last_key {
+            last if last < 0 => argc as i64 + last,
+            last => last,
+        };
+
+        let movable = self.numkeys.and_then(|position| {
+            let FrameValue::BulkString(numkeys) = args.get(position)? else {
+                return None;
+            };
+            let numkeys: usize = std::str::from_utf8(numkeys).ok()?.parse().ok()?;
+            Some(position + 1..position.saturating_add(numkeys + 1).min(argc))
+        });
+
+        (first..=last)
+            .step_by(step as usize)
+            .take_while(move |&position| first > 0 && position < argc as i64)
+            .map(|position| position as usize)
+            .chain(movable.into_iter().flatten())
+    }
+}
+
+const WRITE: &[&str] = &["write"];
+const READONLY: &[&str] = &["readonly"];
+const PUBSUB: &[&str] = &["pubsub"];
+const NONE: &[&str] = &[];
+const NO_AUTH: &[&str] = &["no-auth"];
+const BLOCKING: &[&str] = &["blocking"];
+const READONLY_MOVABLEKEYS: &[&str] = &["readonly", "movablekeys"];
+
+const NO_KEYS: (i64, i64, i64) = (0, 0, 0);
+const FIRST_KEY: (i64, i64, i64) = (1, 1, 1);
+const ALL_KEYS: (i64, i64, i64) = (1, -1, 1);
+
+const fn spec(
+    name: &'static str,
+    arity: i64,
+    flags: &'static [&'static str],
+    (first_key, last_key, step): (i64, i64, i64),
+    parse: fn(&mut Parse) -> Result<Command, CommandError>,
+) -> CommandSpec {
+    CommandSpec {
+        name,
+        arity,
+        flags,
+        first_key,
+        last_key,
+        step,
+        numkeys: None,
+        parse,
+        redact: Redact::Nothing,
+    }
+}
+
+impl CommandSpec {
+    /// Hides the arguments `redact` selects wherever the command is logged
+    const fn redacting(self, redact: Redact) -> Self {
+        Self { redact, ..self }
+    }
+
+    /// Takes the keys from the count at argument `position` onwards, which
+    /// the flags should mark as `movablekeys`
+    const fn counting_keys_at(self, position: usize) -> Self {
+        Self {
+            numkeys: Some(position),
+            ..self
+        }
+    }
+}
+
+pub static COMMAND_TABLE: &[CommandSpec] = &[
+    spec("ping", -1, NONE, NO_KEYS, |parse| {
+        Ping::parse_frames(parse).map(Command::Ping)
+    }),
+    spec("quit", -1, NO_AUTH, NO_KEYS, |parse| {
+        Quit::parse_frames(parse).map(Command::Quit)
+    }),
+    spec("shutdown", -1, NONE, NO_KEYS, |parse| {
+        Shutdown::parse_frames(parse).map(Command::Shutdown)
+    }),
+    spec("echo", 2, NONE, NO_KEYS, |parse| {
+        Echo::parse_frames(parse).map(Command::Echo)
+    }),
+    spec("command", -1, NONE, NO_KEYS, |parse| {
+        CommandSubcommand::parse_frames(parse).map(Command::Introspect)
+    }),
+    spec("auth", -2, NO_AUTH, NO_KEYS, |parse| {
+        Auth::parse_frames(parse).map(Command::Auth)
+    })
+    .redacting(Redact::Arguments),
+    spec("hello", -1, NO_AUTH, NO_KEYS, |parse| {
+        Hello::parse_frames(parse).map(Command::Hello)
+    })
+    .redacting(Redact::AuthOption),
+    spec("acl", -2, NONE, NO_KEYS, |parse| {
+        AclSubcommand::parse_frames(parse).map(Command::Acl)
+    }),
+    spec("client", -2, NONE, NO_KEYS, |parse| {
+        ClientSubcommand::parse_frames(parse).map(Command::Client)
+    }),
+    spec("object", -2, READONLY, (2, 2, 1), |parse| {
+        ObjectSubcommand::parse_frames(parse).map(Command::Object)
+    }),
+    spec("memory", -2, READONLY, (2, 2, 1), |parse| {
+        MemorySubcommand::parse_frames(parse).map(Command::Memory)
+    }),
+    spec("debug", -2, NONE, NO_KEYS, |parse| {
+        DebugSubcommand::parse_frames(parse).map(Command::Debug)
+    }),
+    spec("get", 2, READONLY, FIRST_KEY, |parse| {
+        Get::parse_frames(parse).map(Command::Get)
+    }),
+    spec("getex", -2, WRITE, FIRST_KEY, |parse| {
+        GetEx::parse_frames(parse).map(Command::GetEx)
+    }),
+    spec("set", -3, WRITE, FIRST_KEY, |parse| {
+        Set::parse_frames(parse).map(Command::Set)
+    }),
+    spec("mset", -3, WRITE, (1, -1, 2), |parse| {
+        MSet::parse_frames(parse).map(Command::MSet)
+    }),
+    spec("del", -2, WRITE, ALL_KEYS, |parse| {
+        Del::parse_frames(parse).map(Command::Del)
+    }),
+    spec("expire", 3, WRITE, FIRST_KEY, |parse| {
+        Expire::parse_frames(parse, TimeUnit::Seconds).map(Command::Expire)
+    }),
+    spec("pexpire", 3, WRITE, FIRST_KEY, |parse| {
+        Expire::parse_frames(parse, TimeUnit::Millis).map(Command::PExpire)
+    }),
+    spec("expireat", 3, WRITE, FIRST_KEY, |parse| {
+        ExpireAt::parse_frames(parse, TimeUnit::Seconds).map(Command::ExpireAt)
+    }),
+    spec("pexpireat", 3, WRITE, FIRST_KEY, |parse| {
+        ExpireAt::parse_frames(parse, TimeUnit::Millis).map(Command::PExpireAt)
+    }),
+    spec("ttl", 2, READONLY, FIRST_KEY, |parse| {
+        Ttl::parse_frames(parse, TimeUnit::Seconds).map(Command::Ttl)
+    }),
+    spec("pttl", 2, READONLY, FIRST_KEY, |parse| {
+        Ttl::parse_frames(parse, TimeUnit::Millis).map(Command::PTtl)
+    }),
+    spec("dbsize", 1, READONLY, NO_KEYS, |parse| {
+        DbSize::parse_frames(parse).map(Command::DbSize)
+    }),
+    spec("scan", -2, READONLY, NO_KEYS, |parse| {
+        Scan::parse_frames(parse).map(Command::Scan)
+    }),
+    spec("append", 3, WRITE, FIRST_KEY, |parse| {
+        Append::parse_frames(parse).map(Command::Append)
+    }),
+    spec("incr", 2, WRITE, FIRST_KEY, |parse| {
+        Incr::parse_frames(parse).map(Command::Incr)
+    }),
+    spec("incrbyfloat", 3, WRITE, FIRST_KEY, |parse| {
+        IncrByFloat::parse_frames(parse).map(Command::IncrByFloat)
+    }),
+    spec("dump", 2, READONLY, FIRST_KEY, |parse| {
+        Dump::parse_frames(parse).map(Command::Dump)
+    }),
+    spec("restore", -4, WRITE, FIRST_KEY, |parse| {
+        Restore::parse_frames(parse).map(Command::Restore)
+    }),
+    spec("bitcount", -2, READONLY, FIRST_KEY, |parse| {
+        BitCount::parse_frames(parse).map(Command::BitCount)
+    }),
+    spec("bitpos", -3, READONLY, FIRST_KEY, |parse| {
+        BitPos::parse_frames(parse).map(Command::BitPos)
+    }),
+    spec("sadd", -3, WRITE, FIRST_KEY, |parse| {
+        SAdd::parse_frames(parse).map(Command::SAdd)
+    }),
+    spec("smembers", 2, READONLY, FIRST_KEY, |parse| {
+        SMembers::parse_frames(parse).map(Command::SMembers)
+    }),
+    spec("smismember", -3, READONLY, FIRST_KEY, |parse| {
+        SMIsMember::parse_frames(parse).map(Command::SMIsMember)
+    }),
+    spec("sscan", -3, READONLY, FIRST_KEY, |parse| {
+        SScan::parse_frames(parse).map(Command::SScan)
+    }),
+    spec("srandmember", -2, READONLY, FIRST_KEY, |parse| {
+        SRandMember::parse_frames(parse).map(Command::SRandMember)
+    }),
+    spec("spop", -2, WRITE, FIRST_KEY, |parse| {
+        SPop::parse_frames(parse).map(Command::SPop)
+    }),
+    spec("srem", -3, WRITE, FIRST_KEY, |parse| {
+        SRem::parse_frames(parse).map(Command::SRem)
+    }),
+    spec("sinterstore", -3, WRITE, ALL_KEYS, |parse| {
+        SetStore::parse_frames(parse, SetOp::Inter).map(Command::SInterStore)
+    }),
+    spec("sunionstore", -3, WRITE, ALL_KEYS, |parse| {
+        SetStore::parse_frames(parse, SetOp::Union).map(Command::SUnionStore)
+    }),
+    spec("sdiffstore", -3, WRITE, ALL_KEYS, |parse| {
+        SetStore::parse_frames(parse, SetOp::Diff).map(Command::SDiffStore)
+    }),
+    spec("sintercard", -3, READONLY_MOVABLEKEYS, NO_KEYS, |parse| {
+        SInterCard::parse_frames(parse).map(Command::SInterCard)
+    })
+    .counting_keys_at(1),
+    spec("hset", -4, WRITE, FIRST_KEY, |parse| {
+        HSet::parse_frames(parse).map(Command::HSet)
+    }),
+    spec("hdel", -3, WRITE, FIRST_KEY, |parse| {
+        HDel::parse_frames(parse).map(Command::HDel)
+    }),
+    spec("hexists", 3, READONLY, FIRST_KEY, |parse| {
+        HExists::parse_frames(parse).map(Command::HExists)
+    }),
+    spec("hlen", 2, READONLY, FIRST_KEY, |parse| {
+        HLen::parse_frames(parse).map(Command::HLen)
+    }),
+    spec("hkeys", 2, READONLY, FIRST_KEY, |parse| {
+        HKeys::parse_frames(parse).map(Command::HKeys)
+    }),
+    spec("hscan", -3, READONLY, FIRST_KEY, |parse| {
+        HScan::parse_frames(parse).map(Command::HScan)
+    }),
+    spec("hvals", 2, READONLY, FIRST_KEY, |parse| {
+        HVals::parse_frames(parse).map(Command::HVals)
+    }),
+    spec("hrandfield", -2, READONLY, FIRST_KEY, |parse| {
+        HRandField::parse_frames(parse).map(Command::HRandField)
+    }),
+    spec("hincrby", 4, WRITE, FIRST_KEY, |parse| {
+        HIncrBy::parse_frames(parse).map(Command::HIncrBy)
+    }),
+    spec("lpush", -3, WRITE, FIRST_KEY, |parse| {
+        Push::parse_frames(parse, ListEnd::Left).map(Command::LPush)
+    }),
+    spec("rpush", -3, WRITE, FIRST_KEY, |parse| {
+        Push::parse_frames(parse, ListEnd::Right).map(Command::RPush)
+    }),
+    spec("lpushx", -3, WRITE, FIRST_KEY, |parse| {
+        Push::parse_pushx(parse, ListEnd::Left).map(Command::LPushX)
+    }),
+    spec("rpushx", -3, WRITE, FIRST_KEY, |parse| {
+        Push::parse_pushx(parse, ListEnd::Right).map(Command::RPushX)
+    }),
+    spec("lindex", 3, READONLY, FIRST_KEY, |parse| {
+        LIndex::parse_frames(parse).map(Command::LIndex)
+    }),
+    spec("lpos", -3, READONLY, FIRST_KEY, |parse| {
+        LPos::parse_frames(parse).map(Command::LPos)
+    }),
+    spec("lmove", 5, WRITE, (1, 2, 1), |parse| {
+        LMove::parse_frames(parse).map(Command::LMove)
+    }),
+    spec("rpoplpush", 3, WRITE, (1, 2, 1), |parse| {
+        LMove::parse_rpoplpush(parse).map(Command::RPopLPush)
+    }),
+    spec("lset", 4, WRITE, FIRST_KEY, |parse| {
+        LSet::parse_frames(parse).map(Command::LSet)
+    }),
+    spec("linsert", 5, WRITE, FIRST_KEY, |parse| {
+        LInsert::parse_frames(parse).map(Command::LInsert)
+    }),
+    spec("lrem", 4, WRITE, FIRST_KEY, |parse| {
+        LRem::parse_frames(parse).map(Command::LRem)
+    }),
+    spec("ltrim", 4, WRITE, FIRST_KEY, |parse| {
+        LTrim::parse_frames(parse).map(Command::LTrim)
+    }),
+    spec("zadd", -4, WRITE, FIRST_KEY, |parse| {
+        ZAdd::parse_frames(parse).map(Command::ZAdd)
+    }),
+    spec("zincrby", 4, WRITE, FIRST_KEY, |parse| {
+        ZIncrBy::parse_frames(parse).map(Command::ZIncrBy)
+    }),
+    spec("zrangebyscore", -4, READONLY, FIRST_KEY, |parse| {
+        ZRangeByScore::parse_frames(parse).map(Command::ZRangeByScore)
+    }),
+    spec("zrangebylex", -4, READONLY, FIRST_KEY, |parse| {
+        ZRangeByLex::parse_frames(parse).map(Command::ZRangeByLex)
+    }),
+    spec("zrem", -3, WRITE, FIRST_KEY, |parse| {
+        ZRem::parse_frames(parse).map(Command::ZRem)
+    }),
+    spec("zcard", 2, READONLY, FIRST_KEY, |parse| {
+        ZCard::parse_frames(parse).map(Command::ZCard)
+    }),
+    spec("zrank", 3, READONLY, FIRST_KEY, |parse| {
+        ZRank::parse_frames(parse, RankOrder::Ascending).map(Command::ZRank)
+    }),
+    spec("zrevrank", 3, READONLY, FIRST_KEY, |parse| {
+        ZRank::parse_frames(parse, RankOrder::Descending).map(Command::ZRevRank)
+    }),
+    spec("zscan", -3, READONLY, FIRST_KEY, |parse| {
+        ZScan::parse_frames(parse).map(Command::ZScan)
+    }),
+    spec("publish", 3, PUBSUB, NO_KEYS, |parse| {
+        Publish::parse_frames(parse).map(Command::Publish)
+    }),
+    spec("pubsub", -2, PUBSUB, NO_KEYS, |parse| {
+        PubSubSubcommand::parse_frames(parse).map(Command::PubSub)
+    }),
+    spec("subscribe", -2, PUBSUB, NO_KEYS, |parse| {
+        Subscribe::parse_frames(parse).map(Command::Subscribe)
+    }),
+    spec("unsubscribe", -1, PUBSUB, NO_KEYS, |parse| {
+        Unsubscribe::parse_frames(parse).map(Command::Unsubscribe)
+    }),
+    spec("replconf", -1, NONE, NO_KEYS, |parse| {
+        ReplConf::parse_frames(parse).map(Command::ReplConf)
+    }),
+    spec("psync", 3, NONE, NO_KEYS, |parse| {
+        PSync::parse_frames(parse).map(Command::PSync)
+    }),
+    spec("role", 1, NONE, NO_KEYS, |parse| {
+        Role::parse_frames(parse).map(Command::Role)
+    }),
+    spec("wait", 3, BLOCKING, NO_KEYS, |parse| {
+        Wait::parse_frames(parse).map(Command::Wait)
+    }),
+    spec("waitaof", 4, BLOCKING, NO_KEYS, |parse| {
+        WaitAof::parse_frames(parse).map(Command::WaitAof)
+    }),
+    spec("info", -1, NONE, NO_KEYS, |parse| {
+        Info::parse_frames(parse).map(Command::Info)
+    }),
+    spec("select", 2, NONE, NO_KEYS, |parse| {
+        Select::parse_frames(parse).map(Command::Select)
+    }),
+    spec("swapdb", 3, WRITE, NO_KEYS, |parse| {
+        SwapDb::parse_frames(parse).map(Command::SwapDb)
+    }),
+];
+
+/// [`COMMAND_TABLE`] keyed by uppercase command name
+static REGISTRY: LazyLock<HashMap<Box<[u8]>, &'static CommandSpec>> = LazyLock::new(|| {
+    COMMAND_TABLE
+        .iter()
+        .map(|spec| (spec.name.to_ascii_uppercase().into_bytes().into(), spec))
+        .collect()
+});
+
+/// Looks up a command by name, ignoring case
+pub fn lookup(name: &[u8]) -> Option<&'static CommandSpec> {
+    REGISTRY.get(name.to_ascii_uppercase().as_slice()).copied()
+}
+
+#[cfg(test)]
+mod table_tests {
+    use super::*;
+
+    #[test]
+    fn test_every_entry_round_trips_its_name() {
+        assert_eq!(
+            REGISTRY.len(),
+            COMMAND_TABLE.len(),
+            "duplicate command names"
+        );
+
+        for spec in COMMAND_TABLE {
+            assert_eq!(spec.name, spec.name.to_ascii_lowercase());
+            for name in [spec.name.to_ascii_uppercase(), spec.name.to_string()] {
+                let found = lookup(name.as_bytes()).unwrap();
+                assert!(
+                    std::ptr::eq(found, spec),
+                    "{name} resolved to {}",
+                    found.name
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_arity_is_checked_before_parsing() {
+        use crate::{cmd::run, db::Db, frame::FrameValue};
+
+        let db = Db::new();
+
+        assert_eq!(
+            run(&db, &["GET"]),
+            FrameValue::Error("ERR wrong number of arguments for 'get' command".into())
+        );
+        assert_eq!(
+            run(&db, &["SET", "key"]),
+            FrameValue::Error("ERR wrong number of arguments for 'set' command".into())
+        );
+        assert_eq!(
+            run(&db, &["sadd", "key"]),
+            FrameValue::Error("ERR wrong number of arguments for 'sadd' command".into())
+        );
+        // Counts the arity allows can still be wrong for the command
+        assert_eq!(
+            run(&db, &["HSET", "key", "field", "value", "other"]),
+            FrameValue::Error("ERR wrong number of arguments for 'hset' command".into())
+        );
+    }
+
+    #[test]
+    fn test_readonly_commands_share_the_lock() {
+        use crate::{cmd::run, db::Db, frame::FrameValue};
+        use std::{collections::HashSet, sync::mpsc, thread, time::Duration};
+
+        // One invocation of every readonly command
+        const INVOCATIONS: &[&[&str]] = &[
+            &["OBJECT", "IDLETIME", "string"],
+            &["MEMORY", "USAGE", "string"],
+            &["GET", "string"],
+            &["TTL", "string"],
+            &["PTTL", "string"],
+            &["DBSIZE"],
+            &["SCAN", "0", "TYPE", "string"],
+            &["DUMP", "string"],
+            &["BITCOUNT", "string"],
+            &["BITPOS", "string", "1"],
+            &["SMEMBERS", "set"],
+            &["SMISMEMBER", "set", "a"],
+            &["SSCAN", "set", "0"],
+            &["SRANDMEMBER", "set"],
+            &["SINTERCARD", "1", "set"],
+            &["HEXISTS", "hash", "field"],
+            &["HLEN", "hash"],
+            &["HKEYS", "hash"],
+            &["HSCAN", "hash", "0"],
+            &["HVALS", "hash"],
+            &["HRANDFIELD", "hash"],
+            &["LINDEX", "list", "0"],
+            &["LPOS", "list", "a"],
+            &["ZRANGEBYSCORE", "zset", "-inf", "+inf"],
+            &["ZRANGEBYLEX", "zset", "-", "+"],
+            &["ZCARD", "zset"],
+            &["ZSCAN", "zset", "0"],
+            &["ZRANK", "zset", "a"],
+            &["ZREVRANK", "zset", "a"],
+        ];
+        let readonly: HashSet<String> = COMMAND_TABLE
+            .iter()
+            .filter(|spec| spec.flags.contains(&"readonly"))
+            .map(|spec| spec.name.to_string())
+            .collect();
+        let invoked: HashSet<String> = INVOCATIONS
+            .iter()
+            .map(|args| args[0].to_ascii_lowercase())
+            .collect();
+        assert_eq!(
+            invoked, readonly,
+            "every readonly command needs an invocation"
+        );
+
+        let db = Db::new();
+        run(&db, &["SET", "string", "value"]);
+        run(&db, &["SADD", "set", "a"]);
+        run(&db, &["HSET", "hash", "field", "value"]);
+        run(&db, &["RPUSH", "list", "a"]);
+        run(&db, &["ZADD", "zset", "1", "a"]);
+
+        // Holding a shared lock blocks any command taking the exclusive one, in
+        // which case the lock is let go so the command can finish
+        let mut reader = Some(db.read());
+        let mut exclusive = Vec::new();
+        thread::scope(|scope| {
+            for args in INVOCATIONS {
+                let (tx, rx) = mpsc::channel();
+                let db = &db;
+                scope.spawn(move || tx.send(run(db, args)).unwrap());
+
+                let reply = match rx.recv_timeout(Duration::from_secs(1)) {
+                    Ok(reply) => reply,
+                    Err(_) => {
+                        exclusive.push(args[0]);
+                        reader = None;
+                        rx.recv().unwrap()
+                    }
+                };
+                assert!(
+                    !matches!(reply, FrameValue::Error(_)),
+                    "{args:?} failed: {reply:?}"
+                );
+            }
+        });
+        assert!(
+            exclusive.is_empty(),
+            "took the exclusive lock: {exclusive:?}"
+        );
+    }
+
+    #[test]
+    fn test_secrets_are_redacted() {
+        use crate::cmd::{command_frame, describe};
+
+        let shown = |args: &[&str]| describe(&command_frame(args)).to_string();
+
+        assert_eq!(
+            shown(&["AUTH", "default", "hunter2"]),
+            r#""AUTH" "(redacted)" "(redacted)""#
+        );
+        assert_eq!(
+            shown(&["hello", "3", "auth", "default", "hunter2", "SETNAME", "app"]),
+            r#""hello" "3" "auth" "(redacted)" "(redacted)" "SETNAME" "app""#
+        );
+        assert_eq!(
+            shown(&["SET", "key", "two words\n"]),
+            r#""SET" "key" "two words\n""#
+        );
+    }
+}
@@ -0,0 +1,112 @@
+use super::{CommandError, Parse};
+use crate::{
+    db::{Db, Storage},
+    frame::FrameValue,
+};
+use std::time::Duration;
+
+/// Waits until enough replicas have acknowledged every write made so far, or
+/// until the timeout, and reports how many did
+///
+/// A timeout of 0 waits for as long as it takes.
+pub struct Wait {
+    replicas: i64,
+    timeout: i64,
+}
+
+impl Wait {
+    pub fn parse_frames(parse: &mut Parse) -> Result<Self, CommandError> {
+        let replicas = parse.next_int()?;
+        let timeout = parse.next_int()?;
+        parse.finish()?;
+        Ok(Self { replicas, timeout })
+    }
+
+    /// Reports how many replicas have acknowledged every write so far, without
+    /// waiting for any more to
+    pub fn apply<S: Storage>(self, db: &Db<S>) -> FrameValue {
+        if self.timeout < 0 {
+            return negative_timeout();
+        }
+        let replication = db.replication();
+        FrameValue::Integer(replication.acknowledged(replication.offset()) as i64)
+    }
+
+    pub async fn apply_async<S: Storage>(self, db: &Db<S>) -> FrameValue {
+        if self.timeout < 0 {
+            return negative_timeout();
+        }
+
+        let replication = db.replication();
+        let offset = replication.offset();
+        let wanted = self.replicas.max(0) as usize;
+        if replication.acknowledged(offset) < wanted {
+            replication.request_acks();
+        }
+
+        let acked = replication.wait_for_acks(offset, wanted);
+        let acked = match self.timeout {
+            0 => acked.await,
+            timeout => tokio::time::timeout(Duration::from_millis(timeout as u64), acked)
+                .await
+                .unwrap_or_else(|_| replication.acknowledged(offset)),
+        };
+        FrameValue::Integer(acked as i64)
+    }
+}
+
+fn negative_timeout() -> FrameValue {
+    FrameValue::Error("ERR timeout is negative".into())
+}
+
+#[cfg(test)]
+mod wait_tests {
+    use super::*;
+    use crate::cmd::{Command, command_frame};
+
+    async fn wait(db: &Db, args: &[&str]) -> FrameValue {
+        let Ok(Command::Wait(cmd)) = Command::from_frame(command_frame(args)) else {
+            panic!("expected WAIT");
+        };
+        cmd.apply_async(db).await
+    }
+
+    #[tokio::test]
+    async fn test_without_replicas() {
+        let db = Db::new();
+
+        assert_eq!(wait(&db, &["WAIT", "0", "0"]).await, FrameValue::Integer(0));
+        assert_eq!(
+            wait(&db, &["WAIT", "1", "20"]).await,
+            FrameValue::Integer(0)
+        );
+        assert_eq!(
+            wait(&db, &["WAIT", "1", "-1"]).await,
+            FrameValue::Error("ERR timeout is negative".into())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_counts_acknowledged_replicas() {
+        let db = Db::new();
+        let replication = db.replication();
+        let (_feed, ..) = replication.attach(1, "127.0.0.1".into(), || ());
+        let (_feed, ..) = replication.attach(2, "127.0.0.1".into(), || ());
+        replication.execute(0, || {
+            (
+                FrameValue::SimpleString("OK".into()),
+                Some(command_frame(&["SET", "key", "value"])),
+            )
+        });
+
+        let waiting = tokio::spawn({
+            let db = db.clone();
+            async move { wait(&db, &["WAIT", "1", "0"]).await }
+        });
+        tokio::task::yield_now().await;
+        // Past the GETACK sent on behalf of the waiting client too
+        replication.acknowledge(1, replication.offset());
+
+        assert_eq!(waiting.await.unwrap(), FrameValue::Integer(1));
+    }
+}
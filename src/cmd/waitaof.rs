@@ -0,0 +1,122 @@
+use super::{CommandError, Parse};
+use crate::{
+    db::{Db, Storage},
+    frame::FrameValue,
+};
+use std::time::Duration;
+
+/// Waits until writes so far are fsynced to the append only file locally and on
+/// replicas, and reports how many did
+///
+/// Asking for a local fsync is an error unless `appendonly` is on. Replicas
+/// don't report fsyncs of their own files, so as in Redis when theirs are off,
+/// asking for replicas waits out the timeout. A timeout of 0 waits for as long
+/// as it takes.
+pub struct WaitAof {
+    local: i64,
+    replicas: i64,
+    timeout: i64,
+}
+
+impl WaitAof {
+    pub fn parse_frames(parse: &mut Parse) -> Result<Self, CommandError> {
+        let local = parse.next_int()?;
+        let replicas = parse.next_int()?;
+        let timeout = parse.next_int()?;
+        parse.finish()?;
+        Ok(Self {
+            local,
+            replicas,
+            timeout,
+        })
+    }
+
+    /// Reports whether writes so far are fsynced locally, without waiting for
+    /// them to be
+    pub fn apply<S: Storage>(self, db: &Db<S>) -> FrameValue {
+        let replication = db.replication();
+        self.check(db)
+            .unwrap_or_else(|| local_reply(replication.aof_fsynced(replication.offset())))
+    }
+
+    pub async fn apply_async<S: Storage>(self, db: &Db<S>) -> FrameValue {
+        if let Some(error) = self.check(db) {
+            return error;
+        }
+
+        let replication = db.replication();
+        let offset = replication.offset();
+        let wait = async {
+            if self.replicas > 0 {
+                std::future::pending::<()>().await;
+            } else if self.local > 0 {
+                replication.wait_for_aof_fsync(offset).await;
+            }
+        };
+        match self.timeout {
+            0 => wait.await,
+            timeout => {
+                let _ = tokio::time::timeout(Duration::from_millis(timeout as u64), wait).await;
+            }
+        }
+        local_reply(replication.aof_fsynced(offset))
+    }
+
+    /// Error reply for arguments that can't be waited on
+    fn check<S: Storage>(&self, db: &Db<S>) -> Option<FrameValue> {
+        if self.timeout < 0 {
+            return Some(FrameValue::Error("ERR timeout is negative".into()));
+        }
+        if self.local > 0 && db.replication().aof_fsync_policy().is_none() {
+            return Some(FrameValue::Error(
+                "ERR WAITAOF cannot be used when numlocal is set but appendonly is disabled."
+                    .into(),
+            ));
+        }
+        None
+    }
+}
+
+/// Reply counting the local append only file if it was `fsynced`, and no
+/// replicas
+fn local_reply(fsynced: Option<bool>) -> FrameValue {
+    reply(fsynced.unwrap_or(false) as i64, 0)
+}
+
+/// Number of local and replica append only files that fsynced
+fn reply(local: i64, replicas: i64) -> FrameValue {
+    FrameValue::Array(vec![
+        FrameValue::Integer(local),
+        FrameValue::Integer(replicas),
+    ])
+}
+
+#[cfg(test)]
+mod waitaof_tests {
+    use super::*;
+    use crate::cmd::{Command, command_frame};
+
+    async fn waitaof(args: &[&str]) -> FrameValue {
+        let Ok(Command::WaitAof(cmd)) = Command::from_frame(command_frame(args)) else {
+            panic!("expected WAITAOF");
+        };
+        cmd.apply_async(&Db::new()).await
+    }
+
+    #[tokio::test]
+    async fn test_without_append_only_file() {
+        assert_eq!(waitaof(&["WAITAOF", "0", "0", "0"]).await, reply(0, 0));
+        assert_eq!(waitaof(&["WAITAOF", "0", "1", "20"]).await, reply(0, 0));
+        assert_eq!(
+            waitaof(&["WAITAOF", "1", "0", "0"]).await,
+            FrameValue::Error(
+                "ERR WAITAOF cannot be used when numlocal is set but appendonly is disabled."
+                    .into()
+            )
+        );
+        assert_eq!(
+            waitaof(&["WAITAOF", "0", "0", "-1"]).await,
+            FrameValue::Error("ERR timeout is negative".into())
+        );
+    }
+}
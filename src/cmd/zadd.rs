@@ -0,0 +1,338 @@
+use super::{
+    CommandError, Parse, are_equal,
+    incrbyfloat::{not_a_float, parse_float},
+    wrong_type,
+};
+use crate::{
+    db::{Db, DbValue, Storage},
+    frame::FrameValue,
+    sorted_set::format_score,
+};
+use bytes::Bytes;
+
+/// Adds members to a sorted set or updates their scores, creating it if needed
+///
+/// Flags come before the score and member pairs. `NX` only adds new members and
+/// `XX` only updates existing ones, while `GT` and `LT` only update a score if
+/// the new one is greater or less, never stopping new members from being added.
+/// `CH` counts updated members along with added ones in the reply. `INCR` adds
+/// the score to the member's instead, replying with the result, or nil if the
+/// other flags stopped it.
+pub struct ZAdd {
+    key: Bytes,
+    flags: Flags,
+    /// Scores, still unparsed, and their members
+    pairs: Vec<(Bytes, Bytes)>,
+}
+
+#[derive(Default)]
+struct Flags {
+    nx: bool,
+    xx: bool,
+    gt: bool,
+    lt: bool,
+    ch: bool,
+    incr: bool,
+}
+
+impl ZAdd {
+    pub fn parse_frames(parse: &mut Parse) -> Result<Self, CommandError> {
+        let key = parse.next_bytes()?;
+        let mut args = parse.rest_bytes()?.into_iter().peekable();
+
+        let mut flags = Flags::default();
+        while let Some(arg) = args.peek() {
+            let flag = match arg {
+                arg if are_equal(arg, b"NX") => &mut flags.nx,
+                arg if are_equal(arg, b"XX") => &mut flags.xx,
+                arg if are_equal(arg, b"GT") => &mut flags.gt,
+                arg if are_equal(arg, b"LT") => &mut flags.lt,
+                arg if are_equal(arg, b"CH") => &mut flags.ch,
+                arg if are_equal(arg, b"INCR") => &mut flags.incr,
+                _ => break,
+            };
+            *flag = true;
+            args.next();
+        }
+
+        let args: Vec<_> = args.collect();
+        if args.is_empty() || args.len() % 2 != 0 {
+            return Err(CommandError::Syntax);
+        }
+        let pairs = args
+            .chunks_exact(2)
+            .map(|pair| (pair[0].clone(), pair[1].clone()))
+            .collect();
+
+        Ok(Self { key, flags, pairs })
+    }
+
+    /// `ZADD key INCR delta member`, which is what `ZINCRBY` does
+    pub fn incr(key: Bytes, delta: Bytes, member: Bytes) -> Self {
+        Self {
+            key,
+            flags: Flags {
+                incr: true,
+                ..Flags::default()
+            },
+            pairs: vec![(delta, member)],
+        }
+    }
+
+    pub fn apply<S: Storage>(self, db: &Db<S>) -> FrameValue {
+        let Flags {
+            nx,
+            xx,
+            gt,
+            lt,
+            ch,
+            incr,
+        } = self.flags;
+        if incr && self.pairs.len() > 1 {
+            return FrameValue::Error(
+                "ERR INCR option supports a single increment-element pair".into(),
+            );
+        }
+        if nx && xx {
+            return FrameValue::Error(
+                "ERR XX and NX options at the same time are not compatible".into(),
+            );
+        }
+        if (gt || lt) && nx || gt && lt {
+            return FrameValue::Error(
+                "ERR GT, LT, and/or NX options at the same time are not compatible".into(),
+            );
+        }
+
+        // Every score is checked before anything is written
+        let Some(pairs) = self
+            .pairs
+            .into_iter()
+            .map(|(score, member)| Some((parse_float(&score)?, member)))
+            .collect::<Option<Vec<_>>>()
+        else {
+            return not_a_float();
+        };
+
+        let mut entries = db.lock();
+        match entries.get(&self.key) {
+            Some(DbValue::SortedSet(_)) => {}
+            Some(_) => return wrong_type(),
+            // Nothing would be added, so the key isn't created
+            None if xx => {
+                return if incr {
+                    FrameValue::NullBulkString
+                } else {
+                    FrameValue::Integer(0)
+                };
+            }
+            None => {}
+        }
+        let DbValue::SortedSet(members) =
+            entries.get_or_insert_with(self.key, || DbValue::SortedSet(Default::default()))
+        else {
+            unreachable!("the key was checked to hold a sorted set");
+        };
+
+        let (mut added, mut updated) = (0, 0);
+        // Score of the last member written, which INCR replies with
+        let mut written = None;
+        for (score, member) in pairs {
+            match members.score(&member) {
+                Some(current) => {
+                    if nx {
+                        continue;
+                    }
+                    let score = if incr { current + score } else { score };
+                    if score.is_nan() {
+                        return FrameValue::Error(
+                            "ERR resulting score is not a number (NaN)".into(),
+                        );
+                    }
+                    if gt && score <= current || lt && score >= current {
+                        continue;
+                    }
+                    if score != current {
+                        members.insert(member, score);
+                        updated += 1;
+                    }
+                    written = Some(score);
+                }
+                None if xx => {}
+                None => {
+                    members.insert(member, score);
+                    added += 1;
+                    written = Some(score);
+                }
+            }
+        }
+
+        if incr {
+            written.map_or(FrameValue::NullBulkString, |score| {
+                FrameValue::BulkString(format_score(score))
+            })
+        } else if ch {
+            FrameValue::Integer(added + updated)
+        } else {
+            FrameValue::Integer(added)
+        }
+    }
+}
+
+#[cfg(test)]
+mod zadd_tests {
+    use super::*;
+    use crate::cmd::run;
+
+    /// Score of `member` in the sorted set at `key`
+    fn score(db: &Db, key: &str, member: &str) -> Option<f64> {
+        match db.lock().get(key.as_bytes()) {
+            Some(DbValue::SortedSet(members)) => members.score(member.as_bytes()),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn test_adds_and_updates() {
+        let db = Db::new();
+
+        assert_eq!(
+            run(&db, &["ZADD", "key", "1", "a", "2", "b"]),
+            FrameValue::Integer(2)
+        );
+        assert_eq!(
+            run(&db, &["ZADD", "key", "3", "a", "-inf", "c"]),
+            FrameValue::Integer(1)
+        );
+        assert_eq!(score(&db, "key", "a"), Some(3.0));
+        assert_eq!(score(&db, "key", "c"), Some(f64::NEG_INFINITY));
+    }
+
+    #[test]
+    fn test_gt_only_moves_scores_up() {
+        let db = Db::new();
+        run(&db, &["ZADD", "key", "5", "a", "5", "b"]);
+
+        assert_eq!(
+            run(
+                &db,
+                &["ZADD", "key", "GT", "CH", "7", "a", "3", "b", "1", "c"]
+            ),
+            FrameValue::Integer(2)
+        );
+        assert_eq!(score(&db, "key", "a"), Some(7.0));
+        assert_eq!(score(&db, "key", "b"), Some(5.0));
+        // New members are added whatever their score
+        assert_eq!(score(&db, "key", "c"), Some(1.0));
+
+        run(&db, &["ZADD", "key", "LT", "6", "a", "9", "b"]);
+        assert_eq!(score(&db, "key", "a"), Some(6.0));
+        assert_eq!(score(&db, "key", "b"), Some(5.0));
+    }
+
+    #[test]
+    fn test_ch_counts_updates() {
+        let db = Db::new();
+        run(&db, &["ZADD", "key", "1", "a", "2", "b"]);
+
+        // Rewriting a score with the same value isn't a change
+        assert_eq!(
+            run(&db, &["ZADD", "key", "ch", "10", "a", "2", "b", "3", "c"]),
+            FrameValue::Integer(2)
+        );
+        assert_eq!(
+            run(&db, &["ZADD", "key", "20", "a", "4", "d"]),
+            FrameValue::Integer(1)
+        );
+    }
+
+    #[test]
+    fn test_nx_and_xx() {
+        let db = Db::new();
+        run(&db, &["ZADD", "key", "1", "a"]);
+
+        assert_eq!(
+            run(&db, &["ZADD", "key", "NX", "5", "a", "2", "b"]),
+            FrameValue::Integer(1)
+        );
+        assert_eq!(score(&db, "key", "a"), Some(1.0));
+
+        assert_eq!(
+            run(&db, &["ZADD", "key", "XX", "CH", "5", "a", "3", "c"]),
+            FrameValue::Integer(1)
+        );
+        assert_eq!(score(&db, "key", "a"), Some(5.0));
+        assert_eq!(score(&db, "key", "c"), None);
+
+        assert_eq!(
+            run(&db, &["ZADD", "missing", "XX", "1", "a"]),
+            FrameValue::Integer(0)
+        );
+        assert!(db.lock().get(b"missing").is_none());
+    }
+
+    #[test]
+    fn test_incr() {
+        let db = Db::new();
+
+        assert_eq!(
+            run(&db, &["ZADD", "key", "INCR", "1.5", "a"]),
+            FrameValue::BulkString("1.5".into())
+        );
+        assert_eq!(
+            run(&db, &["ZADD", "key", "INCR", "2", "a"]),
+            FrameValue::BulkString("3.5".into())
+        );
+        assert_eq!(
+            run(&db, &["ZADD", "key", "INCR", "GT", "-1", "a"]),
+            FrameValue::NullBulkString
+        );
+        assert_eq!(
+            run(&db, &["ZADD", "key", "INCR", "NX", "1", "a"]),
+            FrameValue::NullBulkString
+        );
+        run(&db, &["ZADD", "key", "inf", "b"]);
+        assert_eq!(
+            run(&db, &["ZADD", "key", "INCR", "-inf", "b"]),
+            FrameValue::Error("ERR resulting score is not a number (NaN)".into())
+        );
+    }
+
+    #[test]
+    fn test_errors() {
+        let db = Db::new();
+        run(&db, &["SET", "string", "value"]);
+
+        for (args, error) in [
+            (&["ZADD", "key", "1", "a", "2"][..], "ERR syntax error"),
+            (&["ZADD", "key", "NX", "XX"], "ERR syntax error"),
+            (
+                &["ZADD", "key", "NX", "XX", "1", "a"],
+                "ERR XX and NX options at the same time are not compatible",
+            ),
+            (
+                &["ZADD", "key", "GT", "LT", "1", "a"],
+                "ERR GT, LT, and/or NX options at the same time are not compatible",
+            ),
+            (
+                &["ZADD", "key", "NX", "GT", "1", "a"],
+                "ERR GT, LT, and/or NX options at the same time are not compatible",
+            ),
+            (
+                &["ZADD", "key", "INCR", "1", "a", "2", "b"],
+                "ERR INCR option supports a single increment-element pair",
+            ),
+            (
+                &["ZADD", "key", "1", "a", "nan", "b"],
+                "ERR value is not a valid float",
+            ),
+            (
+                &["ZADD", "string", "1", "a"],
+                "WRONGTYPE Operation against a key holding the wrong kind of value",
+            ),
+        ] {
+            assert_eq!(run(&db, args), FrameValue::Error(error.into()), "{args:?}");
+        }
+        assert!(db.lock().get(b"key").is_none());
+    }
+}
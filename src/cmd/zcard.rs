@@ -0,0 +1,44 @@
+use super::{CommandError, Parse, wrong_type};
+use crate::{
+    db::{Db, DbValue, Storage},
+    frame::FrameValue,
+};
+use bytes::Bytes;
+
+/// Counts the members of a sorted set
+pub struct ZCard {
+    key: Bytes,
+}
+
+impl ZCard {
+    pub fn parse_frames(parse: &mut Parse) -> Result<Self, CommandError> {
+        let key = parse.next_bytes()?;
+        parse.finish()?;
+        Ok(Self { key })
+    }
+
+    pub fn apply<S: Storage>(self, db: &Db<S>) -> FrameValue {
+        match db.read_key(&self.key).get_shared(&self.key) {
+            Some(DbValue::SortedSet(members)) => FrameValue::Integer(members.len() as i64),
+            Some(_) => wrong_type(),
+            None => FrameValue::Integer(0),
+        }
+    }
+}
+
+#[cfg(test)]
+mod zcard_tests {
+    use super::*;
+    use crate::cmd::run;
+
+    #[test]
+    fn test_cardinality() {
+        let db = Db::new();
+        run(&db, &["ZADD", "key", "1", "a", "2", "b", "3", "a"]);
+        run(&db, &["SET", "string", "value"]);
+
+        assert_eq!(run(&db, &["ZCARD", "key"]), FrameValue::Integer(2));
+        assert_eq!(run(&db, &["ZCARD", "missing"]), FrameValue::Integer(0));
+        assert_eq!(run(&db, &["ZCARD", "string"]), wrong_type());
+    }
+}
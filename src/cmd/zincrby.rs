@@ -0,0 +1,79 @@
+use super::{CommandError, Parse, zadd::ZAdd};
+use crate::{
+    db::{Db, Storage},
+    frame::FrameValue,
+};
+use bytes::Bytes;
+
+/// Adds to the score of a member of a sorted set, which starts from 0 if the
+/// member is absent, replying with the new score
+///
+/// This is `ZADD` with the `INCR` flag, so a sum that isn't a number is refused.
+pub struct ZIncrBy {
+    key: Bytes,
+    delta: Bytes,
+    member: Bytes,
+}
+
+impl ZIncrBy {
+    pub fn parse_frames(parse: &mut Parse) -> Result<Self, CommandError> {
+        let key = parse.next_bytes()?;
+        let delta = parse.next_bytes()?;
+        let member = parse.next_bytes()?;
+        parse.finish()?;
+        Ok(Self { key, delta, member })
+    }
+
+    pub fn apply<S: Storage>(self, db: &Db<S>) -> FrameValue {
+        ZAdd::incr(self.key, self.delta, self.member).apply(db)
+    }
+}
+
+#[cfg(test)]
+mod zincrby_tests {
+    use super::*;
+    use crate::cmd::run;
+
+    #[test]
+    fn test_increment_absent_member() {
+        let db = Db::new();
+
+        assert_eq!(
+            run(&db, &["ZINCRBY", "key", "2.5", "a"]),
+            FrameValue::BulkString("2.5".into())
+        );
+        assert_eq!(run(&db, &["ZCARD", "key"]), FrameValue::Integer(1));
+    }
+
+    #[test]
+    fn test_increment_reorders_members() {
+        let db = Db::new();
+        run(&db, &["ZADD", "key", "1", "a", "2", "b", "3", "c"]);
+
+        assert_eq!(
+            run(&db, &["ZINCRBY", "key", "5", "a"]),
+            FrameValue::BulkString("6".into())
+        );
+        assert_eq!(run(&db, &["ZRANK", "key", "a"]), FrameValue::Integer(2));
+        assert_eq!(
+            run(&db, &["ZINCRBY", "key", "-10", "c"]),
+            FrameValue::BulkString("-7".into())
+        );
+        assert_eq!(run(&db, &["ZRANK", "key", "c"]), FrameValue::Integer(0));
+    }
+
+    #[test]
+    fn test_errors() {
+        let db = Db::new();
+        run(&db, &["ZADD", "key", "+inf", "a"]);
+
+        assert_eq!(
+            run(&db, &["ZINCRBY", "key", "-inf", "a"]),
+            FrameValue::Error("ERR resulting score is not a number (NaN)".into())
+        );
+        assert_eq!(
+            run(&db, &["ZINCRBY", "key", "one", "a"]),
+            FrameValue::Error("ERR value is not a valid float".into())
+        );
+    }
+}
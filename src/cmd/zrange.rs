@@ -0,0 +1,325 @@
+use super::{BulkArray, CommandError, Parse, are_equal, incrbyfloat::parse_float, wrong_type};
+use crate::{
+    db::{Db, DbValue, Storage},
+    frame::FrameValue,
+    sorted_set::format_score,
+};
+use bytes::Bytes;
+use std::ops::Bound;
+
+/// Lists the members of a sorted set with scores between `min` and `max`
+///
+/// A bound is inclusive unless prefixed by `(`, and may be `-inf` or `+inf`.
+/// `WITHSCORES` follows each member with its score, `LIMIT` skips `offset`
+/// members and returns at most `count`, all of the rest if it is negative.
+pub struct ZRangeByScore {
+    key: Bytes,
+    min: Bytes,
+    max: Bytes,
+    withscores: bool,
+    limit: Option<(i64, i64)>,
+}
+
+/// Lists the members of a sorted set between `min` and `max` in byte order,
+/// which is only meaningful if every member has the same score
+///
+/// A bound is `[` for inclusive or `(` for exclusive followed by the member,
+/// or `-` and `+` for the lowest and highest possible. `LIMIT` works as in
+/// [`ZRangeByScore`].
+pub struct ZRangeByLex {
+    key: Bytes,
+    min: Bytes,
+    max: Bytes,
+    limit: Option<(i64, i64)>,
+}
+
+impl ZRangeByScore {
+    pub fn parse_frames(parse: &mut Parse) -> Result<Self, CommandError> {
+        let key = parse.next_bytes()?;
+        let min = parse.next_bytes()?;
+        let max = parse.next_bytes()?;
+
+        let (mut withscores, mut limit) = (false, None);
+        while let Some(option) = parse.next_optional_bytes()? {
+            if are_equal(&option, b"WITHSCORES") {
+                withscores = true;
+            } else if are_equal(&option, b"LIMIT") && parse.remaining() >= 2 {
+                limit = Some((parse.next_int()?, parse.next_int()?));
+            } else {
+                return Err(CommandError::Syntax);
+            }
+        }
+
+        Ok(Self {
+            key,
+            min,
+            max,
+            withscores,
+            limit,
+        })
+    }
+
+    pub fn stream<S: Storage>(self, db: &Db<S>) -> Result<BulkArray, FrameValue> {
+        let (Some(min), Some(max)) = (score_bound(&self.min), score_bound(&self.max)) else {
+            return Err(FrameValue::Error("ERR min or max is not a float".into()));
+        };
+
+        let entries = db.read_key(&self.key);
+        let members = match entries.get_shared(&self.key) {
+            Some(DbValue::SortedSet(members)) => members,
+            Some(_) => return Err(wrong_type()),
+            None => return Ok(BulkArray::new(Vec::new())),
+        };
+
+        Ok(reply(
+            members.range_by_score(min, max),
+            self.limit,
+            self.withscores,
+        ))
+    }
+}
+
+impl ZRangeByLex {
+    pub fn parse_frames(parse: &mut Parse) -> Result<Self, CommandError> {
+        let key = parse.next_bytes()?;
+        let min = parse.next_bytes()?;
+        let max = parse.next_bytes()?;
+
+        let mut limit = None;
+        while let Some(option) = parse.next_optional_bytes()? {
+            if are_equal(&option, b"LIMIT") && parse.remaining() >= 2 {
+                limit = Some((parse.next_int()?, parse.next_int()?));
+            } else {
+                return Err(CommandError::Syntax);
+            }
+        }
+
+        Ok(Self {
+            key,
+            min,
+            max,
+            limit,
+        })
+    }
+
+    pub fn stream<S: Storage>(self, db: &Db<S>) -> Result<BulkArray, FrameValue> {
+        let (Some(min), Some(max)) = (lex_bound(&self.min), lex_bound(&self.max)) else {
+            return Err(FrameValue::Error(
+                "ERR min or max not valid string range item".into(),
+            ));
+        };
+
+        let entries = db.read_key(&self.key);
+        let members = match entries.get_shared(&self.key) {
+            Some(DbValue::SortedSet(members)) => members,
+            Some(_) => return Err(wrong_type()),
+            None => return Ok(BulkArray::new(Vec::new())),
+        };
+
+        // `+` as the minimum or `-` as the maximum leaves nothing in range
+        let (min, max) = match (min, max) {
+            (LexBound::Max, _) | (_, LexBound::Min) => return Ok(BulkArray::new(Vec::new())),
+            (min, max) => (min.into_bound(), max.into_bound()),
+        };
+        Ok(reply(members.range_by_lex(min, max), self.limit, false))
+    }
+}
+
+/// Parses a score bound, `(` making it exclusive
+fn score_bound(bytes: &[u8]) -> Option<Bound<f64>> {
+    match bytes.strip_prefix(b"(") {
+        Some(score) => parse_float(score).map(Bound::Excluded),
+        None => parse_float(bytes).map(Bound::Included),
+    }
+}
+
+/// Bound of a lexicographic range
+enum LexBound {
+    /// `-`, below every member
+    Min,
+    /// `+`, above every member
+    Max,
+    Included(Bytes),
+    Excluded(Bytes),
+}
+
+impl LexBound {
+    fn into_bound(self) -> Bound<Bytes> {
+        match self {
+            Self::Min | Self::Max => Bound::Unbounded,
+            Self::Included(member) => Bound::Included(member),
+            Self::Excluded(member) => Bound::Excluded(member),
+        }
+    }
+}
+
+fn lex_bound(bytes: &Bytes) -> Option<LexBound> {
+    match bytes.first()? {
+        b'-' if bytes.len() == 1 => Some(LexBound::Min),
+        b'+' if bytes.len() == 1 => Some(LexBound::Max),
+        b'[' => Some(LexBound::Included(bytes.slice(1..))),
+        b'(' => Some(LexBound::Excluded(bytes.slice(1..))),
+        _ => None,
+    }
+}
+
+/// Members in range after applying `LIMIT`, each followed by its score if asked
+///
+/// A negative offset selects nothing, as in Redis.
+fn reply<'a>(
+    range: impl Iterator<Item = (&'a Bytes, f64)>,
+    limit: Option<(i64, i64)>,
+    withscores: bool,
+) -> BulkArray {
+    let (offset, count) = match limit {
+        Some((offset, _)) if offset < 0 => return BulkArray::new(Vec::new()),
+        Some((offset, count)) => (
+            offset as usize,
+            usize::try_from(count).unwrap_or(usize::MAX),
+        ),
+        None => (0, usize::MAX),
+    };
+
+    let mut strings = Vec::new();
+    for (member, score) in range.skip(offset).take(count) {
+        strings.push(member.clone());
+        if withscores {
+            strings.push(format_score(score));
+        }
+    }
+    BulkArray::new(strings)
+}
+
+#[cfg(test)]
+mod zrange_tests {
+    use super::*;
+    use crate::cmd::run;
+
+    fn array(items: &[&str]) -> FrameValue {
+        FrameValue::Array(
+            items
+                .iter()
+                .map(|item| FrameValue::BulkString(Bytes::copy_from_slice(item.as_bytes())))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn test_score_range() {
+        let db = Db::new();
+        run(
+            &db,
+            &["ZADD", "key", "1", "a", "2", "b", "2.5", "c", "3", "d"],
+        );
+
+        assert_eq!(
+            run(&db, &["ZRANGEBYSCORE", "key", "2", "3"]),
+            array(&["b", "c", "d"])
+        );
+        assert_eq!(
+            run(&db, &["ZRANGEBYSCORE", "key", "(1", "(3"]),
+            array(&["b", "c"])
+        );
+        assert_eq!(
+            run(&db, &["ZRANGEBYSCORE", "key", "(2", "+inf", "WITHSCORES"]),
+            array(&["c", "2.5", "d", "3"])
+        );
+        assert_eq!(
+            run(&db, &["ZRANGEBYSCORE", "key", "-inf", "(1"]),
+            array(&[])
+        );
+        assert_eq!(
+            run(&db, &["ZRANGEBYSCORE", "missing", "-inf", "+inf"]),
+            array(&[])
+        );
+    }
+
+    #[test]
+    fn test_limit() {
+        let db = Db::new();
+        run(
+            &db,
+            &["ZADD", "key", "1", "a", "2", "b", "3", "c", "4", "d"],
+        );
+
+        assert_eq!(
+            run(
+                &db,
+                &["ZRANGEBYSCORE", "key", "-inf", "+inf", "LIMIT", "1", "2"]
+            ),
+            array(&["b", "c"])
+        );
+        assert_eq!(
+            run(
+                &db,
+                &["ZRANGEBYSCORE", "key", "-inf", "+inf", "limit", "2", "-1"]
+            ),
+            array(&["c", "d"])
+        );
+        assert_eq!(
+            run(
+                &db,
+                &["ZRANGEBYSCORE", "key", "-inf", "+inf", "LIMIT", "-1", "2"]
+            ),
+            array(&[])
+        );
+        assert_eq!(
+            run(&db, &["ZRANGEBYLEX", "key", "-", "+", "LIMIT", "3", "5"]),
+            array(&["d"])
+        );
+    }
+
+    #[test]
+    fn test_lex_range() {
+        let db = Db::new();
+        run(
+            &db,
+            &["ZADD", "key", "0", "a", "0", "b", "0", "c", "0", "d"],
+        );
+
+        assert_eq!(
+            run(&db, &["ZRANGEBYLEX", "key", "[a", "(c"]),
+            array(&["a", "b"])
+        );
+        assert_eq!(
+            run(&db, &["ZRANGEBYLEX", "key", "(a", "+"]),
+            array(&["b", "c", "d"])
+        );
+        assert_eq!(
+            run(&db, &["ZRANGEBYLEX", "key", "-", "[b"]),
+            array(&["a", "b"])
+        );
+        assert_eq!(run(&db, &["ZRANGEBYLEX", "key", "+", "-"]), array(&[]));
+    }
+
+    #[test]
+    fn test_errors() {
+        let db = Db::new();
+        run(&db, &["SET", "string", "value"]);
+
+        for (args, error) in [
+            (
+                &["ZRANGEBYSCORE", "key", "one", "2"][..],
+                "ERR min or max is not a float",
+            ),
+            (
+                &["ZRANGEBYLEX", "key", "a", "[c"],
+                "ERR min or max not valid string range item",
+            ),
+            (
+                &["ZRANGEBYLEX", "key", "-", "+", "WITHSCORES"],
+                "ERR syntax error",
+            ),
+            (
+                &["ZRANGEBYSCORE", "key", "0", "1", "LIMIT", "1"],
+                "ERR syntax error",
+            ),
+            (
+                &["ZRANGEBYSCORE", "string", "0", "1"],
+                "WRONGTYPE Operation against a key holding the wrong kind of value",
+            ),
+        ] {
+            assert_eq!(run(&db, args), FrameValue::Error(error.into()), "{args:?}");
+        }
+    }
+}
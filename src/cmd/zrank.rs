@@ -0,0 +1,85 @@
+use super::{CommandError, Parse, wrong_type};
+use crate::{
+    db::{Db, DbValue, Storage},
+    frame::FrameValue,
+};
+use bytes::Bytes;
+
+/// Finds the 0-based position of a member in a sorted set, nil if it is absent
+pub struct ZRank {
+    key: Bytes,
+    member: Bytes,
+    order: RankOrder,
+}
+
+/// Which end of the sorted set ranks count from
+#[derive(Clone, Copy)]
+pub enum RankOrder {
+    /// From the lowest score, as `ZRANK` does
+    Ascending,
+    /// From the highest score, as `ZREVRANK` does
+    Descending,
+}
+
+impl ZRank {
+    pub fn parse_frames(parse: &mut Parse, order: RankOrder) -> Result<Self, CommandError> {
+        let key = parse.next_bytes()?;
+        let member = parse.next_bytes()?;
+        parse.finish()?;
+        Ok(Self { key, member, order })
+    }
+
+    pub fn apply<S: Storage>(self, db: &Db<S>) -> FrameValue {
+        let entries = db.read_key(&self.key);
+        let members = match entries.get_shared(&self.key) {
+            Some(DbValue::SortedSet(members)) => members,
+            Some(_) => return wrong_type(),
+            None => return FrameValue::NullBulkString,
+        };
+
+        match (members.rank(&self.member), self.order) {
+            (Some(rank), RankOrder::Ascending) => FrameValue::Integer(rank as i64),
+            (Some(rank), RankOrder::Descending) => {
+                FrameValue::Integer((members.len() - 1 - rank) as i64)
+            }
+            (None, _) => FrameValue::NullBulkString,
+        }
+    }
+}
+
+#[cfg(test)]
+mod zrank_tests {
+    use super::*;
+    use crate::cmd::run;
+
+    #[test]
+    fn test_rank_follows_score_updates() {
+        let db = Db::new();
+        run(&db, &["ZADD", "key", "1", "a", "2", "b", "3", "c"]);
+
+        assert_eq!(run(&db, &["ZRANK", "key", "a"]), FrameValue::Integer(0));
+        assert_eq!(run(&db, &["ZREVRANK", "key", "a"]), FrameValue::Integer(2));
+
+        run(&db, &["ZADD", "key", "5", "a"]);
+        assert_eq!(run(&db, &["ZRANK", "key", "a"]), FrameValue::Integer(2));
+        assert_eq!(run(&db, &["ZRANK", "key", "b"]), FrameValue::Integer(0));
+        assert_eq!(run(&db, &["ZREVRANK", "key", "a"]), FrameValue::Integer(0));
+    }
+
+    #[test]
+    fn test_absent_member() {
+        let db = Db::new();
+        run(&db, &["ZADD", "key", "1", "a"]);
+        run(&db, &["SET", "string", "value"]);
+
+        assert_eq!(
+            run(&db, &["ZRANK", "key", "missing"]),
+            FrameValue::NullBulkString
+        );
+        assert_eq!(
+            run(&db, &["ZREVRANK", "missing", "a"]),
+            FrameValue::NullBulkString
+        );
+        assert_eq!(run(&db, &["ZRANK", "string", "a"]), wrong_type());
+    }
+}
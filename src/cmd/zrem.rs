@@ -0,0 +1,62 @@
+use super::{CommandError, Parse, wrong_type};
+use crate::{
+    db::{Db, DbValue, Storage},
+    frame::FrameValue,
+};
+use bytes::Bytes;
+
+/// Removes members from a sorted set, deleting the key once it is empty
+pub struct ZRem {
+    key: Bytes,
+    members: Vec<Bytes>,
+}
+
+impl ZRem {
+    pub fn parse_frames(parse: &mut Parse) -> Result<Self, CommandError> {
+        let key = parse.next_bytes()?;
+        let members = parse.rest_bytes()?;
+        Ok(Self { key, members })
+    }
+
+    pub fn apply<S: Storage>(self, db: &Db<S>) -> FrameValue {
+        let mut entries = db.lock();
+        let members = match entries.get_mut(&self.key) {
+            Some(DbValue::SortedSet(members)) => members,
+            Some(_) => return wrong_type(),
+            None => return FrameValue::Integer(0),
+        };
+
+        let removed = self
+            .members
+            .iter()
+            .filter(|member| members.remove(member).is_some())
+            .count();
+
+        if members.is_empty() {
+            entries.remove(&self.key);
+        }
+
+        FrameValue::Integer(removed as i64)
+    }
+}
+
+#[cfg(test)]
+mod zrem_tests {
+    use super::*;
+    use crate::cmd::run;
+
+    #[test]
+    fn test_removing_last_member_deletes_key() {
+        let db = Db::new();
+        run(&db, &["ZADD", "key", "1", "a", "2", "b"]);
+
+        assert_eq!(
+            run(&db, &["ZREM", "key", "a", "missing"]),
+            FrameValue::Integer(1)
+        );
+        assert_eq!(run(&db, &["ZCARD", "key"]), FrameValue::Integer(1));
+        assert_eq!(run(&db, &["ZREM", "key", "b", "b"]), FrameValue::Integer(1));
+        assert!(db.lock().get(b"key").is_none());
+        assert_eq!(run(&db, &["ZREM", "key", "a"]), FrameValue::Integer(0));
+    }
+}
@@ -0,0 +1,124 @@
+use super::{
+    CommandError, Parse,
+    scan::{ScanArgs, empty_page},
+    wrong_type,
+};
+use crate::{
+    db::{Db, DbValue, Storage},
+    frame::FrameValue,
+    sorted_set::format_score,
+};
+use bytes::Bytes;
+
+/// Iterates over the members of a sorted set a page at a time, replying with
+/// each member followed by its score
+pub struct ZScan {
+    key: Bytes,
+    args: ScanArgs,
+}
+
+impl ZScan {
+    pub fn parse_frames(parse: &mut Parse) -> Result<Self, CommandError> {
+        let key = parse.next_bytes()?;
+        let args = ScanArgs::parse(parse)?;
+        Ok(Self { key, args })
+    }
+
+    pub fn apply<S: Storage>(self, db: &Db<S>) -> FrameValue {
+        match db.read_key(&self.key).get_shared(&self.key) {
+            Some(DbValue::SortedSet(members)) => self.args.page(
+                |cursor| {
+                    members
+                        .scan_from(cursor)
+                        .map(|(position, member, score)| (position, (member, score)))
+                },
+                |(member, _)| member,
+                |(member, score), frames| {
+                    frames.push(FrameValue::BulkString(member.clone()));
+                    frames.push(FrameValue::BulkString(format_score(score)));
+                },
+            ),
+            Some(_) => wrong_type(),
+            None => empty_page(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod zscan_tests {
+    use crate::{cmd::run, db::Db, frame::FrameValue};
+    use bytes::Bytes;
+    use std::collections::HashMap;
+
+    /// Runs ZSCAN with `options` from cursor 0 until it comes back to 0
+    fn scan_all(db: &Db, key: &str, options: &[&str]) -> HashMap<Bytes, Bytes> {
+        let mut found = HashMap::new();
+        let mut cursor = "0".to_string();
+        loop {
+            let mut args = vec!["ZSCAN", key, &cursor];
+            args.extend_from_slice(options);
+            let FrameValue::Array(reply) = run(db, &args) else {
+                panic!("expected an array");
+            };
+            let [FrameValue::BulkString(next), FrameValue::Array(elements)] = &reply[..] else {
+                panic!("expected a cursor and elements");
+            };
+            for pair in elements.chunks(2) {
+                let [
+                    FrameValue::BulkString(member),
+                    FrameValue::BulkString(score),
+                ] = pair
+                else {
+                    panic!("expected member and score");
+                };
+                found.insert(member.clone(), score.clone());
+            }
+
+            cursor = String::from_utf8(next.to_vec()).unwrap();
+            if cursor == "0" {
+                return found;
+            }
+        }
+    }
+
+    #[test]
+    fn test_members_with_scores() {
+        let db = Db::new();
+        for i in 0..50 {
+            let score = format!("{i}.5");
+            run(&db, &["ZADD", "zset", &score, &format!("member:{i}")]);
+        }
+
+        let found = scan_all(&db, "zset", &["COUNT", "7"]);
+        assert_eq!(found.len(), 50);
+        assert_eq!(found[&Bytes::from("member:42")], Bytes::from("42.5"));
+
+        let found = scan_all(&db, "zset", &["MATCH", "member:1?"]);
+        let mut members: Vec<_> = found.into_keys().collect();
+        members.sort();
+        assert_eq!(
+            members,
+            (10..20)
+                .map(|i| Bytes::from(format!("member:{i}")))
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_missing_key_and_errors() {
+        let db = Db::new();
+        run(&db, &["SET", "string", "value"]);
+
+        assert_eq!(
+            run(&db, &["ZSCAN", "missing", "0"]),
+            FrameValue::Array(vec![
+                FrameValue::BulkString("0".into()),
+                FrameValue::Array(vec![]),
+            ])
+        );
+        assert_eq!(
+            run(&db, &["ZSCAN", "string", "0"]),
+            crate::cmd::wrong_type()
+        );
+    }
+}
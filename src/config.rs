@@ -0,0 +1,141 @@
+use crate::{
+    connection::{DEFAULT_READ_CAPACITY, DEFAULT_WRITE_CAPACITY},
+    db::{DATABASES, EncodingLimits, PROTO_MAX_BULK_LEN},
+    frame::MAX_ARRAY_LEN,
+    log::LogLevel,
+};
+use std::{io, path::PathBuf, time::Duration};
+
+/// Server settings, defaulting to the same values as Redis
+#[derive(Clone, Debug)]
+pub struct Config {
+    /// Length of the queue of connections waiting to be accepted
+    pub tcp_backlog: u32,
+    /// Idle time before keepalive probes are sent, `None` disables them
+    pub tcp_keepalive: Option<Duration>,
+    /// Closes connections that send nothing for `tcp_keepalive` times this
+    /// factor, `None` leaves dead peers to the OS keepalive alone
+    ///
+    /// Subscribers and replicas are exempt, as they may legitimately stay silent.
+    pub idle_timeout_factor: Option<u32>,
+    /// Initial size of each connection's read buffer
+    pub read_buffer_size: usize,
+    /// Size of each connection's write buffer
+    pub write_buffer_size: usize,
+    /// Bytes a subscriber may have waiting for up to
+    /// `pubsub_output_soft_duration` before it is disconnected
+    pub pubsub_output_soft_limit: usize,
+    pub pubsub_output_soft_duration: Duration,
+    /// Bytes a subscriber may have waiting before it is disconnected
+    pub pubsub_output_hard_limit: usize,
+    /// Master to replicate from as a host and port, `None` when this is a master
+    pub replicaof: Option<(String, u16)>,
+    /// Whether a replica rejects writes from its own clients
+    pub replica_read_only: bool,
+    /// Time a command may spend waiting before it is cut short with an error,
+    /// `None` for no limit
+    ///
+    /// Commands run atomically between their waits, so only the waiting can be
+    /// cut short. Blocking commands are bounded by their own timeout instead.
+    pub command_timeout: Option<Duration>,
+    /// Longest the server keeps existing connections open once asked to shut down,
+    /// refusing writes on them
+    pub shutdown_timeout: Duration,
+    /// Number of logical databases clients can SELECT from
+    pub databases: usize,
+    /// Password of the default user, `None` lets connections in without one
+    pub requirepass: Option<String>,
+    /// Logs the raw RESP bytes every connection reads and writes, each chunk
+    /// cut off after this many bytes, `None` to log nothing
+    ///
+    /// Lines go to the server log at the debug level, so they only show with
+    /// [`Config::loglevel`] at [`LogLevel::Debug`].
+    pub protocol_log: Option<usize>,
+    /// File the server log is appended to, `None` logs to stdout
+    pub logfile: Option<PathBuf>,
+    /// Least severe level that gets logged
+    pub loglevel: LogLevel,
+    /// File the server writes its process id to while it runs, `None` for none
+    pub pidfile: Option<PathBuf>,
+    /// Longest string clients may send or store
+    ///
+    /// Bulk strings declaring more are rejected as a protocol error as soon as
+    /// their header is read, and commands building longer values, like APPEND,
+    /// fail.
+    pub proto_max_bulk_len: usize,
+    /// Most arguments a command may have, or elements any array a client sends
+    /// may declare, checked before any of them are read
+    pub max_array_len: usize,
+    /// After replying to a malformed frame, skips to the next line starting
+    /// with `*` and carries on rather than closing the connection
+    pub protocol_resync: bool,
+    /// Longest list, in entries or if negative in a size class, reported as a
+    /// listpack rather than a quicklist
+    pub list_max_listpack_size: i64,
+    /// Largest set of integers reported as an intset rather than a hashtable
+    pub set_max_intset_entries: usize,
+    /// Appends every write command to [`Config::appendfilename`], which is
+    /// replayed on startup
+    pub appendonly: bool,
+    /// Append only file, relative to the working directory
+    pub appendfilename: PathBuf,
+    /// When writes to the append only file are fsynced
+    pub appendfsync: AppendFsync,
+}
+
+/// How often the append only file is fsynced, named as in Redis
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AppendFsync {
+    /// After every write command, before it is replied to
+    Always,
+    /// Once a second, in the background
+    EverySec,
+    /// Whenever the OS decides to
+    No,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            tcp_backlog: 511,
+            tcp_keepalive: Some(Duration::from_secs(300)),
+            idle_timeout_factor: None,
+            read_buffer_size: DEFAULT_READ_CAPACITY,
+            write_buffer_size: DEFAULT_WRITE_CAPACITY,
+            pubsub_output_soft_limit: 8 * 1024 * 1024,
+            pubsub_output_soft_duration: Duration::from_secs(60),
+            pubsub_output_hard_limit: 32 * 1024 * 1024,
+            replicaof: None,
+            replica_read_only: true,
+            command_timeout: None,
+            shutdown_timeout: Duration::from_secs(10),
+            databases: DATABASES,
+            requirepass: None,
+            protocol_log: None,
+            logfile: None,
+            loglevel: LogLevel::Notice,
+            pidfile: None,
+            proto_max_bulk_len: PROTO_MAX_BULK_LEN,
+            max_array_len: MAX_ARRAY_LEN,
+            protocol_resync: false,
+            list_max_listpack_size: EncodingLimits::default().list_max_listpack_size,
+            set_max_intset_entries: EncodingLimits::default().set_max_intset_entries,
+            appendonly: false,
+            appendfilename: PathBuf::from("appendonly.aof"),
+            appendfsync: AppendFsync::EverySec,
+        }
+    }
+}
+
+impl Config {
+    /// Checks the settings make sense together, before a server is started with them
+    pub fn validate(&self) -> io::Result<()> {
+        if self.databases < 1 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "databases must be at least 1",
+            ));
+        }
+        Ok(())
+    }
+}
@@ -1,22 +1,1037 @@
-use bytes::BytesMut;
-use tokio::{io::BufWriter, net::TcpStream};
+use crate::{
+    cmd,
+    frame::{self, Frame, FrameError, FrameValue},
+    log,
+};
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+use std::{
+    io::{self, IoSlice, Write},
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+    },
+};
+use tokio::{
+    io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufWriter},
+    net::TcpStream,
+};
+use tokio_util::codec::{Decoder, Encoder, Framed};
 
-pub struct Connection {
-    stream: BufWriter<TcpStream>,
+pub const DEFAULT_READ_CAPACITY: usize = 4 * 1024;
+pub const DEFAULT_WRITE_CAPACITY: usize = 8 * 1024;
+
+/// Bulk strings at least this long skip the encode buffer and are written
+/// straight from their `Bytes`
+const VECTORED_THRESHOLD: usize = 64 * 1024;
+
+/// Bytes of a streamed array encoded before they are written out, and the
+/// encoded size from which arrays are streamed rather than encoded whole
+const STREAMING_CHUNK: usize = 16 * 1024;
+
+/// Frame level wrapper around a byte stream
+///
+/// Generic over the stream so tests can run it over an in-memory pipe.
+pub struct Connection<S = TcpStream> {
+    stream: BufWriter<S>,
     buffer: BytesMut,
+    codec: Frame,
+    /// Frame decoded by [`Connection::peek_frame`], handed out by the next read
+    peeked: Option<FrameValue>,
+    stats: Arc<ConnectionStats>,
+    protocol_log: Option<ProtocolLog>,
+}
+
+/// Alternative to [`Connection`] built on [`Framed`], which is a `Stream` of
+/// decoded frames and a `Sink` of frames to send
+///
+/// Use it where frames need to flow through stream and sink combinators or other
+/// tokio ecosystem code. The server sticks to [`Connection`], which counts
+/// traffic for `CLIENT INFO`, lets callers choose when to flush and can read
+/// the non-frame RDB payload of a resync.
+pub type FramedConnection<S = TcpStream> = Framed<S, Frame>;
+
+/// Wraps `stream` in a [`FramedConnection`] with the default read buffer size
+pub fn framed<S: AsyncRead + AsyncWrite>(stream: S) -> FramedConnection<S> {
+    Framed::with_capacity(stream, Frame::new(), DEFAULT_READ_CAPACITY)
+}
+
+/// Traffic counters of a connection, shared with the client registry
+#[derive(Debug, Default)]
+pub struct ConnectionStats {
+    commands: AtomicU64,
+    net_input: AtomicU64,
+    net_output: AtomicU64,
+}
+
+/// Point in time copy of [`ConnectionStats`]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct StatsSnapshot {
+    pub commands: u64,
+    pub net_input: u64,
+    pub net_output: u64,
+}
+
+impl std::ops::Add for StatsSnapshot {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self {
+            commands: self.commands + other.commands,
+            net_input: self.net_input + other.net_input,
+            net_output: self.net_output + other.net_output,
+        }
+    }
+}
+
+impl ConnectionStats {
+    pub fn snapshot(&self) -> StatsSnapshot {
+        StatsSnapshot {
+            commands: self.commands.load(Ordering::Relaxed),
+            net_input: self.net_input.load(Ordering::Relaxed),
+            net_output: self.net_output.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Debug log of the raw bytes a connection reads and writes
+///
+/// Each chunk is written as one escaped line, cut off after `limit` bytes so
+/// large payloads don't flood the log. What is read is logged a frame at a
+/// time, re-encoded with the secrets of commands like `AUTH` redacted.
+pub struct ProtocolLog {
+    label: String,
+    limit: usize,
+    /// Where lines go instead of the server log
+    out: Option<Box<dyn Write + Send>>,
+}
+
+impl ProtocolLog {
+    /// Logs to the server log at [`LogLevel::Debug`](crate::log::LogLevel::Debug),
+    /// tagging every line with `label`
+    pub fn new(label: String, limit: usize) -> Self {
+        Self {
+            label,
+            limit,
+            out: None,
+        }
+    }
+
+    /// Writes lines to `out` rather than the server log
+    #[cfg(test)]
+    pub fn with_output(label: String, limit: usize, out: impl Write + Send + 'static) -> Self {
+        Self {
+            label,
+            limit,
+            out: Some(Box::new(out)),
+        }
+    }
+
+    /// Logs a frame that was read, with its secrets redacted
+    fn record_frame(&mut self, arrow: &str, frame: &FrameValue) {
+        let mut buf = BytesMut::new();
+        if Frame::new().encode(cmd::redacted(frame), &mut buf).is_ok() {
+            self.record(arrow, &[&buf]);
+        }
+    }
+
+    /// Logs bytes split over `parts`, `arrow` showing which way they went
+    fn record(&mut self, arrow: &str, parts: &[&[u8]]) {
+        let len: usize = parts.iter().map(|part| part.len()).sum();
+        let mut shown = Vec::with_capacity(len.min(self.limit));
+        for part in parts {
+            let room = self.limit - shown.len();
+            shown.extend_from_slice(&part[..part.len().min(room)]);
+        }
+
+        let mut line = format!("[resp {}] {arrow} \"{}\"", self.label, shown.escape_ascii());
+        if len > shown.len() {
+            line += &format!(" ... ({} more bytes)", len - shown.len());
+        }
+        match &mut self.out {
+            // Losing a debug line is not worth failing the connection over
+            Some(out) => {
+                let _ = writeln!(out, "{line}");
+            }
+            None => log!(Debug, "{line}"),
+        }
+    }
 }
 
-impl Connection {
-    pub fn new(stream: TcpStream) -> Self {
+impl<S: AsyncRead + AsyncWrite + Unpin> Connection<S> {
+    /// Creates a connection with the default buffer sizes
+    pub fn new(stream: S) -> Self {
+        Self::with_capacity(stream, DEFAULT_READ_CAPACITY, DEFAULT_WRITE_CAPACITY)
+    }
+
+    /// Creates a connection with read and write buffers of the given sizes
+    ///
+    /// The read buffer still grows past `read_capacity` to fit larger frames.
+    pub fn with_capacity(stream: S, read_capacity: usize, write_capacity: usize) -> Self {
         Self {
-            stream: BufWriter::new(stream),
-            buffer: BytesMut::with_capacity(4 * 1024),
+            stream: BufWriter::with_capacity(write_capacity, stream),
+            buffer: BytesMut::with_capacity(read_capacity),
+            codec: Frame::new(),
+            peeked: None,
+            stats: Arc::default(),
+            protocol_log: None,
+        }
+    }
+
+    /// Starts logging the raw bytes read and written from here on
+    pub fn log_protocol(&mut self, log: ProtocolLog) {
+        self.protocol_log = Some(log);
+    }
+
+    /// Rejects frames read from here on that declare arrays longer than `max`
+    pub fn limit_array_len(&mut self, max: usize) {
+        self.codec = self.codec.with_max_array_len(max);
+    }
+
+    /// Rejects frames read from here on that declare bulk strings longer than
+    /// `max` bytes
+    pub fn limit_bulk_len(&mut self, max: usize) {
+        self.codec = self.codec.with_max_bulk_len(max);
+    }
+
+    pub fn stats(&self) -> &Arc<ConnectionStats> {
+        &self.stats
+    }
+
+    /// Counts a command as processed on this connection
+    pub fn record_command(&self) {
+        self.stats.commands.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Tries to decode a frame out of the bytes read so far
+    pub fn parse_frame(&mut self) -> Result<Option<FrameValue>, FrameError> {
+        if let Some(frame) = self.peeked.take() {
+            return Ok(Some(frame));
+        }
+        let frame = self.codec.decode(&mut self.buffer)?;
+        Ok(self.log_read(frame))
+    }
+
+    /// The next frame if it has been read in full already, as it is when a
+    /// client pipelines commands, without taking it
+    ///
+    /// A frame that fails to parse is left for the next read to report.
+    pub fn peek_frame(&mut self) -> Option<&FrameValue> {
+        if self.peeked.is_none() {
+            self.peeked = self.parse_frame().unwrap_or(None);
+        }
+        self.peeked.as_ref()
+    }
+
+    fn log_read(&mut self, frame: Option<FrameValue>) -> Option<FrameValue> {
+        if let (Some(log), Some(frame)) = (&mut self.protocol_log, &frame) {
+            log.record_frame("<<", frame);
+        }
+        frame
+    }
+
+    /// Discards buffered bytes up to the next line that starts with `*`, where
+    /// a command may begin after a frame that failed to parse
+    ///
+    /// The first byte is always discarded, so a frame that failed at its start
+    /// is never parsed again. Returns `false`, having discarded everything, if
+    /// no such line has been read yet.
+    pub fn resync(&mut self) -> bool {
+        let start = self
+            .buffer
+            .windows(2)
+            .skip(1)
+            .position(|window| window == b"\n*")
+            .map(|position| position + 2);
+
+        match start {
+            Some(start) => {
+                self.buffer.advance(start);
+                true
+            }
+            None => {
+                self.buffer.clear();
+                false
+            }
+        }
+    }
+
+    /// Reads a single frame from the stream
+    ///
+    /// Returns `None` if the peer closed the connection on a frame boundary, and
+    /// [`FrameError::UnexpectedEnd`] if it closed it partway through a frame.
+    pub async fn read_frame(&mut self) -> Result<Option<FrameValue>, FrameError> {
+        loop {
+            if let Some(frame) = self.parse_frame()? {
+                return Ok(Some(frame));
+            }
+
+            let read = self.stream.read_buf(&mut self.buffer).await?;
+            self.stats
+                .net_input
+                .fetch_add(read as u64, Ordering::Relaxed);
+
+            if read == 0 {
+                let frame = self.codec.decode_eof(&mut self.buffer)?;
+                return Ok(self.log_read(frame));
+            }
+        }
+    }
+
+    /// Reads the RDB payload a master sends after agreeing to a full resync
+    pub async fn read_rdb(&mut self) -> Result<Bytes, FrameError> {
+        loop {
+            if let Some(payload) = self.parse_rdb()? {
+                return Ok(payload);
+            }
+
+            let read = self.stream.read_buf(&mut self.buffer).await?;
+            self.stats
+                .net_input
+                .fetch_add(read as u64, Ordering::Relaxed);
+
+            if read == 0 {
+                return Err(io::Error::from(io::ErrorKind::ConnectionReset).into());
+            }
+        }
+    }
+
+    /// Tries to split a `$<len>\r\n<payload>` off the bytes read so far
+    fn parse_rdb(&mut self) -> Result<Option<Bytes>, FrameError> {
+        let Some(end) = self.buffer.windows(2).position(|w| w == b"\r\n") else {
+            return Ok(None);
+        };
+        if self.buffer[0] != b'$' {
+            return Err(FrameError::UnknownStartingByte);
+        }
+
+        let len = std::str::from_utf8(&self.buffer[1..end])
+            .ok()
+            .and_then(|len| len.parse::<i64>().ok())
+            .ok_or(FrameError::IntParseFailure)?;
+        let len = usize::try_from(len).map_err(|_| FrameError::BadBulkStringSize(len))?;
+
+        if self.buffer.len() < end + 2 + len {
+            return Ok(None);
+        }
+        self.buffer.advance(end + 2);
+        Ok(Some(self.buffer.split_to(len).freeze()))
+    }
+
+    /// Writes a single frame and flushes it to the stream
+    pub async fn write_frame(&mut self, frame: FrameValue) -> Result<(), FrameError> {
+        self.write_frames([frame]).await
+    }
+
+    /// Writes several frames and flushes them to the stream once at the end
+    pub async fn write_frames(
+        &mut self,
+        frames: impl IntoIterator<Item = FrameValue>,
+    ) -> Result<(), FrameError> {
+        self.buffer_frames(frames).await?;
+        self.flush().await?;
+        Ok(())
+    }
+
+    /// Writes frames into the write buffer without flushing it, so replies to
+    /// pipelined commands can share a write
+    ///
+    /// The buffer is still written out whenever it fills up.
+    pub async fn buffer_frames(
+        &mut self,
+        frames: impl IntoIterator<Item = FrameValue>,
+    ) -> Result<(), FrameError> {
+        for frame in frames {
+            let written = self.buffer_frame(frame).await?;
+            self.stats
+                .net_output
+                .fetch_add(written as u64, Ordering::Relaxed);
+        }
+        Ok(())
+    }
+
+    /// Writes an array of `len` elements into the write buffer, encoding each
+    /// element as `elements` yields it, and returns its encoded length
+    ///
+    /// Unlike encoding the whole array first, only a chunk of it is encoded at a
+    /// time, so a large reply doesn't take up its memory twice, and replies too
+    /// large to encode whole can still be sent. If `elements` doesn't yield
+    /// exactly `len` frames, the stream no longer lines up with the header and
+    /// this fails with [`FrameError::BadBulkArraySize`] holding the count, after
+    /// which the connection can't be used.
+    pub async fn write_array_streaming(
+        &mut self,
+        len: usize,
+        elements: impl IntoIterator<Item = FrameValue>,
+    ) -> Result<usize, FrameError> {
+        let written = self.buffer_array_streaming(len, elements).await?;
+        self.stats
+            .net_output
+            .fetch_add(written as u64, Ordering::Relaxed);
+        Ok(written)
+    }
+
+    /// [`Connection::write_array_streaming`] without counting what it writes in
+    /// the connection's stats, which [`Connection::buffer_frames`] does itself
+    async fn buffer_array_streaming(
+        &mut self,
+        len: usize,
+        elements: impl IntoIterator<Item = FrameValue>,
+    ) -> Result<usize, FrameError> {
+        // Elements are encoded in batches, each written out once it fills a
+        // chunk, so the cost per element stays that of encoding it
+        let mut chunk = BytesMut::with_capacity(STREAMING_CHUNK);
+        chunk.put_u8(b'*');
+        chunk.put_slice(len.to_string().as_bytes());
+        chunk.put_slice(b"\r\n");
+
+        let (mut written, mut count) = (0, 0);
+        for element in elements {
+            count += 1;
+            if count > len {
+                return Err(FrameError::BadBulkArraySize(count as i64));
+            }
+            match element {
+                FrameValue::BulkString(payload) if payload.len() >= VECTORED_THRESHOLD => {
+                    written += self.write_chunk(&mut chunk).await?;
+                    written += self.write_bulk_vectored(payload).await?;
+                }
+                element => {
+                    Frame::new().encode(element, &mut chunk)?;
+                    if chunk.len() >= STREAMING_CHUNK {
+                        written += self.write_chunk(&mut chunk).await?;
+                    }
+                }
+            }
+        }
+        written += self.write_chunk(&mut chunk).await?;
+        if count != len {
+            return Err(FrameError::BadBulkArraySize(count as i64));
+        }
+        Ok(written)
+    }
+
+    /// Writes out and empties a batch of encoded frames, returning its length
+    async fn write_chunk(&mut self, chunk: &mut BytesMut) -> Result<usize, FrameError> {
+        if let Some(log) = &mut self.protocol_log {
+            log.record(">>", &[chunk]);
         }
+        self.write_all(chunk).await?;
+        let len = chunk.len();
+        chunk.clear();
+        Ok(len)
     }
 
-    pub async fn parse_frame() {}
+    /// Writes a frame into the write buffer without flushing it, returning its
+    /// encoded length
+    async fn buffer_frame(&mut self, frame: FrameValue) -> Result<usize, FrameError> {
+        let len = frame.len();
+        match frame {
+            FrameValue::BulkString(payload) if payload.len() >= VECTORED_THRESHOLD => {
+                self.write_bulk_vectored(payload).await
+            }
+            // Large replies, like a whole collection, are encoded a chunk at a time
+            FrameValue::Array(elements) if len >= STREAMING_CHUNK => {
+                self.buffer_array_streaming(elements.len(), elements).await
+            }
+            _ if len > frame::MAX => Err(frame::too_large(len)),
+            frame => {
+                let mut buf = BytesMut::with_capacity(len);
+                Frame::new().encode(frame, &mut buf)?;
+                if let Some(log) = &mut self.protocol_log {
+                    log.record(">>", &[&buf]);
+                }
+                self.write_all(&buf).await?;
+                Ok(buf.len())
+            }
+        }
+    }
+
+    /// Writes a large bulk string as a header, payload and trailer chain, so the
+    /// payload goes to the stream without being copied into an encode buffer
+    async fn write_bulk_vectored(&mut self, payload: Bytes) -> Result<usize, FrameError> {
+        let header = format!("${}\r\n", payload.len());
+        let len = header.len() + payload.len() + 2;
+        if let Some(log) = &mut self.protocol_log {
+            log.record(">>", &[header.as_bytes(), &payload, b"\r\n"]);
+        }
+
+        let mut slices = [
+            IoSlice::new(header.as_bytes()),
+            IoSlice::new(&payload),
+            IoSlice::new(b"\r\n"),
+        ];
+        let mut remaining = &mut slices[..];
+        while !remaining.is_empty() {
+            match self.stream.write_vectored(remaining).await {
+                Ok(0) => return Err(io::Error::from(io::ErrorKind::WriteZero).into()),
+                Ok(n) => IoSlice::advance_slices(&mut remaining, n),
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e.into()),
+            }
+        }
+
+        Ok(len)
+    }
+
+    /// Writes all of `buf` however many rounds the stream takes, resuming from
+    /// where a short or interrupted write left off
+    ///
+    /// Unlike `AsyncWriteExt::write_all`, an interrupted write is retried rather
+    /// than failing the frame partway through. Bytes the stream accepted are
+    /// never sent again, so a later call picks up cleanly after an error too.
+    async fn write_all(&mut self, mut buf: &[u8]) -> io::Result<()> {
+        while !buf.is_empty() {
+            match self.stream.write(buf).await {
+                Ok(0) => return Err(io::ErrorKind::WriteZero.into()),
+                Ok(n) => buf = &buf[n..],
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(())
+    }
+
+    /// Flushes the write buffer, retrying if the stream is interrupted
+    ///
+    /// The buffer drops only what the stream accepted, so a retry sends the
+    /// rest exactly once.
+    pub async fn flush(&mut self) -> io::Result<()> {
+        loop {
+            match self.stream.flush().await {
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                result => return result,
+            }
+        }
+    }
 
-    pub async fn read_frame() {}
+    /// Writes an RDB payload as a replica expects it during a resync
+    ///
+    /// This looks like a bulk string, but without the trailing CRLF.
+    pub async fn write_rdb(&mut self, payload: &[u8]) -> Result<(), FrameError> {
+        let mut buf = BytesMut::new();
+        buf.put_u8(b'$');
+        buf.put_slice(payload.len().to_string().as_bytes());
+        buf.put_slice(b"\r\n");
+        buf.put_slice(payload);
 
-    pub async fn write_frame() {}
+        self.write_all(&buf).await?;
+        self.flush().await?;
+        self.stats
+            .net_output
+            .fetch_add(buf.len() as u64, Ordering::Relaxed);
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod connection_tests {
+    use super::*;
+    use std::{
+        pin::Pin,
+        sync::Mutex,
+        task::{Context, Poll},
+    };
+    use tokio::io::{ReadBuf, duplex};
+
+    /// Log output that the test can read back
+    #[derive(Clone, Default)]
+    struct SharedLog(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for SharedLog {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            Write::write(&mut *self.0.lock().unwrap(), buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl SharedLog {
+        fn lines(&self) -> Vec<String> {
+            String::from_utf8(self.0.lock().unwrap().clone())
+                .unwrap()
+                .lines()
+                .map(str::to_owned)
+                .collect()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_protocol_log() {
+        let (client, server) = duplex(1024);
+        let mut client = Connection::new(client);
+        let mut server = Connection::new(server);
+        let log = SharedLog::default();
+        server.log_protocol(ProtocolLog::with_output("test".into(), 64, log.clone()));
+
+        client
+            .write_frame(FrameValue::Array(vec![FrameValue::BulkString(
+                "PING".into(),
+            )]))
+            .await
+            .unwrap();
+        server.read_frame().await.unwrap();
+        server
+            .write_frame(FrameValue::SimpleString("PONG".into()))
+            .await
+            .unwrap();
+
+        assert_eq!(
+            log.lines(),
+            [
+                r#"[resp test] << "*1\r\n$4\r\nPING\r\n""#,
+                r#"[resp test] >> "+PONG\r\n""#,
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_protocol_log_redacts_secrets() {
+        let (client, server) = duplex(1024);
+        let mut client = Connection::new(client);
+        let mut server = Connection::new(server);
+        let log = SharedLog::default();
+        server.log_protocol(ProtocolLog::with_output("test".into(), 256, log.clone()));
+
+        for args in [
+            &["AUTH", "default", "hunter2"][..],
+            &["HELLO", "3", "AUTH", "default", "hunter2"],
+        ] {
+            client.write_frame(cmd::command_frame(args)).await.unwrap();
+            server.read_frame().await.unwrap();
+        }
+
+        let lines = log.lines();
+        assert_eq!(lines.len(), 2);
+        assert!(
+            lines.iter().all(|line| !line.contains("hunter2")),
+            "{lines:?}"
+        );
+        assert_eq!(
+            lines[0],
+            r#"[resp test] << "*3\r\n$4\r\nAUTH\r\n$10\r\n(redacted)\r\n$10\r\n(redacted)\r\n""#
+        );
+    }
+
+    #[tokio::test]
+    async fn test_protocol_log_truncates() {
+        let (client, server) = duplex(1024 * 1024);
+        let mut client = Connection::new(client);
+        let mut server = Connection::new(server);
+        let log = SharedLog::default();
+        server.log_protocol(ProtocolLog::with_output("test".into(), 9, log.clone()));
+
+        let payload = Bytes::from(vec![b'x'; VECTORED_THRESHOLD]);
+        server
+            .write_frame(FrameValue::BulkString(payload))
+            .await
+            .unwrap();
+        client.read_frame().await.unwrap();
+
+        assert_eq!(
+            log.lines(),
+            [r#"[resp test] >> "$65536\r\nx" ... (65537 more bytes)"#]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_resync_skips_to_next_command() {
+        let (mut client, server) = duplex(1024);
+        let mut server = Connection::new(server);
+
+        client
+            .write_all(b"?garbage\r\nmore *garbage\r\n*1\r\n$4\r\nPING\r\n")
+            .await
+            .unwrap();
+        assert!(server.read_frame().await.is_err());
+
+        assert!(server.resync());
+        assert_eq!(
+            server.read_frame().await.unwrap(),
+            Some(FrameValue::Array(vec![FrameValue::BulkString(
+                "PING".into()
+            )]))
+        );
+
+        // Nothing buffered looks like the start of a command
+        client.write_all(b"?garbage\r\n").await.unwrap();
+        assert!(server.read_frame().await.is_err());
+        assert!(!server.resync());
+        assert!(server.buffer.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_pipelined_batch_with_small_buffers() {
+        let (client, server) = duplex(64 * 1024);
+        let mut client = Connection::new(client);
+        let mut server = Connection::with_capacity(server, 16, 16);
+
+        let mut batch = BytesMut::new();
+        for i in 0..1000 {
+            let frame = FrameValue::Array(vec![
+                FrameValue::BulkString("ECHO".into()),
+                FrameValue::BulkString(i.to_string().into()),
+            ]);
+            Frame::new().encode(frame, &mut batch).unwrap();
+        }
+        client.stream.write_all(&batch).await.unwrap();
+        client.stream.flush().await.unwrap();
+
+        for i in 0..1000 {
+            let Some(FrameValue::Array(mut args)) = server.read_frame().await.unwrap() else {
+                panic!("expected an array");
+            };
+            assert_eq!(args[1], FrameValue::BulkString(i.to_string().into()));
+            server.write_frame(args.remove(1)).await.unwrap();
+        }
+
+        for i in 0..1000 {
+            assert_eq!(
+                client.read_frame().await.unwrap(),
+                Some(FrameValue::BulkString(i.to_string().into()))
+            );
+        }
+    }
+
+    /// Stream that counts the flushes reaching it
+    struct CountFlushes<S> {
+        inner: S,
+        flushes: usize,
+    }
+
+    impl<S: AsyncRead + Unpin> AsyncRead for CountFlushes<S> {
+        fn poll_read(
+            mut self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+            buf: &mut ReadBuf<'_>,
+        ) -> Poll<io::Result<()>> {
+            Pin::new(&mut self.inner).poll_read(cx, buf)
+        }
+    }
+
+    impl<S: AsyncWrite + Unpin> AsyncWrite for CountFlushes<S> {
+        fn poll_write(
+            mut self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+            buf: &[u8],
+        ) -> Poll<io::Result<usize>> {
+            Pin::new(&mut self.inner).poll_write(cx, buf)
+        }
+
+        fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+            self.flushes += 1;
+            Pin::new(&mut self.inner).poll_flush(cx)
+        }
+
+        fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+            Pin::new(&mut self.inner).poll_shutdown(cx)
+        }
+    }
+
+    /// Stream that accepts at most a few bytes per write and is interrupted
+    /// every other time it is written to or flushed
+    struct Choppy<S> {
+        inner: S,
+        calls: usize,
+    }
+
+    impl<S> Choppy<S> {
+        fn interrupted(&mut self) -> bool {
+            self.calls += 1;
+            self.calls.is_multiple_of(2)
+        }
+    }
+
+    impl<S: AsyncWrite + Unpin> AsyncWrite for Choppy<S> {
+        fn poll_write(
+            mut self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+            buf: &[u8],
+        ) -> Poll<io::Result<usize>> {
+            if self.interrupted() {
+                return Poll::Ready(Err(io::ErrorKind::Interrupted.into()));
+            }
+            let len = buf.len().min(3);
+            Pin::new(&mut self.inner).poll_write(cx, &buf[..len])
+        }
+
+        fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+            if self.interrupted() {
+                return Poll::Ready(Err(io::ErrorKind::Interrupted.into()));
+            }
+            Pin::new(&mut self.inner).poll_flush(cx)
+        }
+
+        fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+            Pin::new(&mut self.inner).poll_shutdown(cx)
+        }
+    }
+
+    impl<S: AsyncRead + Unpin> AsyncRead for Choppy<S> {
+        fn poll_read(
+            mut self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+            buf: &mut ReadBuf<'_>,
+        ) -> Poll<io::Result<()>> {
+            Pin::new(&mut self.inner).poll_read(cx, buf)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_large_frame_written_over_many_rounds() {
+        let (client, server) = duplex(16);
+        let mut client = Connection::new(client);
+        let mut server = Connection::with_capacity(
+            Choppy {
+                inner: server,
+                calls: 0,
+            },
+            16,
+            16,
+        );
+
+        let frames = vec![
+            FrameValue::Array(
+                (0..500)
+                    .map(|i| FrameValue::BulkString(format!("element {i}").into()))
+                    .collect(),
+            ),
+            FrameValue::BulkString(Bytes::from(vec![b'x'; VECTORED_THRESHOLD + 7])),
+            FrameValue::SimpleString("OK".into()),
+        ];
+        let expected = frames.clone();
+        let writer = tokio::spawn(async move {
+            for frame in frames {
+                server.write_frame(frame).await.unwrap();
+            }
+        });
+
+        for frame in expected {
+            assert_eq!(client.read_frame().await.unwrap(), Some(frame));
+        }
+        writer.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_write_frames_flushes_once() {
+        let (client, server) = duplex(1024);
+        let mut client = Connection::new(client);
+        let mut server = Connection::new(CountFlushes {
+            inner: server,
+            flushes: 0,
+        });
+
+        let frames = [
+            FrameValue::SimpleString("OK".into()),
+            FrameValue::Integer(42),
+            FrameValue::BulkString("value".into()),
+        ];
+        server.write_frames(frames.clone()).await.unwrap();
+        assert_eq!(server.stream.get_ref().flushes, 1);
+
+        for frame in frames {
+            assert_eq!(client.read_frame().await.unwrap(), Some(frame));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_framed_send_and_next() {
+        use futures_core::Stream;
+        use futures_sink::Sink;
+        use std::{future::poll_fn, pin::Pin};
+
+        let (client, server) = duplex(1024);
+        let mut client = framed(client);
+        let mut server = Connection::new(server);
+
+        let ping = FrameValue::Array(vec![FrameValue::BulkString("PING".into())]);
+        poll_fn(|cx| Pin::new(&mut client).poll_ready(cx))
+            .await
+            .unwrap();
+        Pin::new(&mut client).start_send(ping.clone()).unwrap();
+        poll_fn(|cx| Pin::new(&mut client).poll_flush(cx))
+            .await
+            .unwrap();
+
+        assert_eq!(server.read_frame().await.unwrap(), Some(ping));
+        server
+            .write_frame(FrameValue::SimpleString("PONG".into()))
+            .await
+            .unwrap();
+
+        let reply = poll_fn(|cx| Pin::new(&mut client).poll_next(cx)).await;
+        assert_eq!(
+            reply.unwrap().unwrap(),
+            FrameValue::SimpleString("PONG".into())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_large_bulk_string_round_trip() {
+        let (client, server) = duplex(64 * 1024);
+        let mut client = Connection::new(client);
+        let mut server = Connection::new(server);
+
+        let payload = Bytes::from((0..1024 * 1024).map(|i| i as u8).collect::<Vec<_>>());
+        let expected = payload.clone();
+        let writer = tokio::spawn(async move {
+            server
+                .write_frame(FrameValue::BulkString(payload))
+                .await
+                .unwrap();
+            server
+        });
+
+        assert_eq!(
+            client.read_frame().await.unwrap(),
+            Some(FrameValue::BulkString(expected))
+        );
+        let server = writer.await.unwrap();
+        assert_eq!(
+            server.stats.net_output.load(Ordering::Relaxed),
+            1024 * 1024 + "$1048576\r\n\r\n".len() as u64
+        );
+    }
+
+    #[tokio::test]
+    async fn test_write_array_streaming() {
+        let (client, server) = duplex(1024);
+        let mut client = Connection::new(client);
+        let mut server = Connection::with_capacity(server, 16, 16);
+
+        let mut elements = vec![
+            FrameValue::BulkString("a".into()),
+            FrameValue::Integer(2),
+            FrameValue::Array(vec![FrameValue::SimpleString("nested".into())]),
+            FrameValue::BulkString(Bytes::from(vec![b'x'; VECTORED_THRESHOLD])),
+            FrameValue::NullBulkString,
+        ];
+        // Enough to be written out over several chunks
+        elements.extend((0..STREAMING_CHUNK as i64).map(FrameValue::Integer));
+        let expected = FrameValue::Array(elements);
+        let encoded_len = expected.len() as u64;
+
+        let writer = tokio::spawn({
+            let expected = expected.clone();
+            async move {
+                server.write_frame(expected).await.unwrap();
+                server
+            }
+        });
+
+        assert_eq!(client.read_frame().await.unwrap(), Some(expected));
+        let server = writer.await.unwrap();
+        assert_eq!(server.stats.net_output.load(Ordering::Relaxed), encoded_len);
+    }
+
+    #[tokio::test]
+    async fn test_streamed_array_must_match_its_length() {
+        let mut connection =
+            Connection::new(tokio::io::join(tokio::io::empty(), tokio::io::sink()));
+        let elements = || (0..3).map(FrameValue::Integer);
+
+        assert!(matches!(
+            connection.write_array_streaming(2, elements()).await,
+            Err(FrameError::BadBulkArraySize(3))
+        ));
+        assert!(matches!(
+            connection.write_array_streaming(4, elements()).await,
+            Err(FrameError::BadBulkArraySize(3))
+        ));
+        assert_eq!(
+            connection
+                .write_array_streaming(3, elements())
+                .await
+                .unwrap(),
+            "*3\r\n:0\r\n:1\r\n:2\r\n".len()
+        );
+    }
+
+    /// Compares replying with a whole 100k element list, as `LRANGE list 0 -1`
+    /// would, by encoding the array whole and by writing it as replies are
+    /// written, which streams it out in chunks
+    ///
+    /// Run with `cargo test --release -- --ignored --nocapture bench_`.
+    #[tokio::test]
+    #[ignore]
+    async fn bench_list_reply_streaming() {
+        use crate::{
+            cmd::run,
+            db::{Db, DbValue, Storage},
+        };
+        use std::time::Instant;
+
+        const ELEMENTS: usize = 100_000;
+        const ROUNDS: u32 = 20;
+        let db = Db::new();
+        let mut args = vec!["RPUSH".to_string(), "list".to_string()];
+        args.extend((0..ELEMENTS).map(|i| format!("element:{i}")));
+        run(&db, &args.iter().map(String::as_str).collect::<Vec<_>>());
+        let mut connection =
+            Connection::new(tokio::io::join(tokio::io::empty(), tokio::io::sink()));
+
+        let reply = || match db.lock().get(b"list") {
+            Some(DbValue::List(items)) => {
+                FrameValue::Array(items.iter().cloned().map(FrameValue::BulkString).collect())
+            }
+            _ => unreachable!("the list was just pushed"),
+        };
+
+        let start = Instant::now();
+        for _ in 0..ROUNDS {
+            let mut buf = BytesMut::new();
+            Frame::new().encode(reply(), &mut buf).unwrap();
+            connection.write_all(&buf).await.unwrap();
+            connection.flush().await.unwrap();
+        }
+        let encoded = start.elapsed() / ROUNDS;
+
+        let start = Instant::now();
+        for _ in 0..ROUNDS {
+            connection.write_frame(reply()).await.unwrap();
+        }
+        let streamed = start.elapsed() / ROUNDS;
+
+        println!(
+            "List of {ELEMENTS} elements: encoded whole {encoded:?}, streamed {streamed:?} per reply"
+        );
+    }
+
+    /// Compares the copying and vectored write paths for a 1 MiB value
+    ///
+    /// Run with `cargo test --release -- --ignored --nocapture bench_`.
+    #[tokio::test]
+    #[ignore]
+    async fn bench_large_bulk_string_write() {
+        use std::time::Instant;
+
+        const ROUNDS: u32 = 500;
+        let payload = Bytes::from(vec![b'x'; 1024 * 1024]);
+        let mut connection =
+            Connection::new(tokio::io::join(tokio::io::empty(), tokio::io::sink()));
+
+        let start = Instant::now();
+        for _ in 0..ROUNDS {
+            let mut buf = BytesMut::new();
+            Frame::new()
+                .encode(FrameValue::BulkString(payload.clone()), &mut buf)
+                .unwrap();
+            connection.stream.write_all(&buf).await.unwrap();
+            connection.stream.flush().await.unwrap();
+        }
+        let copied = start.elapsed() / ROUNDS;
+
+        let start = Instant::now();
+        for _ in 0..ROUNDS {
+            connection
+                .write_frame(FrameValue::BulkString(payload.clone()))
+                .await
+                .unwrap();
+        }
+        let vectored = start.elapsed() / ROUNDS;
+
+        println!("1 MiB bulk string: copied {copied:?}, vectored {vectored:?} per write");
+    }
 }
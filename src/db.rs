@@ -0,0 +1,1092 @@
+use crate::{
+    acl::Acl,
+    dict::{Dict, Set},
+    pubsub::PubSub,
+    random::Rng,
+    replication::Replication,
+    sorted_set::SortedSet,
+};
+use bytes::Bytes;
+use std::{
+    collections::VecDeque,
+    sync::{
+        Arc, LazyLock, Mutex, MutexGuard, RwLock, RwLockReadGuard, RwLockWriteGuard,
+        atomic::{AtomicBool, AtomicU64, Ordering},
+    },
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+/// Number of logical databases unless configured otherwise
+pub const DATABASES: usize = 16;
+/// Longest string value clients may store by default, as in Redis
+pub const PROTO_MAX_BULK_LEN: usize = 512 * 1024 * 1024;
+
+/// Keys with an expiry checked per database in each round of the active
+/// expire cycle
+const ACTIVE_EXPIRE_KEYS_PER_ROUND: usize = 20;
+/// Time after which the active expire cycle stops starting new rounds, a
+/// quarter of the interval it runs at as in Redis
+const ACTIVE_EXPIRE_TIME_BUDGET: Duration = Duration::from_millis(25);
+
+/// Sizes up to which Redis keeps values in its compact encodings
+///
+/// Values are stored the same way whatever their size, these only decide the
+/// encoding `OBJECT ENCODING` reports.
+#[derive(Clone, Copy, Debug)]
+pub struct EncodingLimits {
+    /// Entries a list may hold as a single listpack, or if negative its size
+    /// class, -1 for 4 KiB doubling up to -5 for 64 KiB
+    pub list_max_listpack_size: i64,
+    /// Members a set of integers may hold as an intset
+    pub set_max_intset_entries: usize,
+}
+
+impl Default for EncodingLimits {
+    fn default() -> Self {
+        Self {
+            list_max_listpack_size: -2,
+            set_max_intset_entries: 512,
+        }
+    }
+}
+
+/// Keyspaces of the logical databases, indexed by database number
+pub type SharedDbs<S = Keyspace> = Arc<[RwLock<S>]>;
+
+/// Shared handle to the logical databases, pub/sub channels, users and replication
+/// state
+///
+/// Cloning is cheap, every connection holds its own handle to the same state.
+/// Each handle has one database selected, which is what [`Db::lock`] returns.
+/// Databases are stored in [`Keyspace`]s unless another [`Storage`] is chosen.
+pub struct Db<S = Keyspace> {
+    databases: SharedDbs<S>,
+    index: usize,
+    /// Whether expired keys are swept in the background rather than only
+    /// removed when accessed
+    active_expire: Arc<AtomicBool>,
+    pubsub: PubSub,
+    acl: Acl,
+    replication: Replication,
+    /// Longest string value a command may store
+    max_value_len: usize,
+    encoding_limits: EncodingLimits,
+    /// Generator behind the commands picking random elements, shared by all
+    /// connections
+    rng: Arc<Mutex<Rng>>,
+}
+
+/// Values that can be stored against a key
+#[derive(Debug, PartialEq)]
+pub enum DbValue {
+    String(Bytes),
+    Set(Set),
+    Hash(Dict<Bytes>),
+    List(VecDeque<Bytes>),
+    SortedSet(SortedSet),
+}
+
+/// Keys and their values along with per-key metadata
+///
+/// Lookups through [`Keyspace::get`] and friends count as an access of the key,
+/// [`Keyspace::peek`] does not. Keys past their expiry are treated as missing, and
+/// removed by the lookups taking `&mut self`.
+#[derive(Default)]
+pub struct Keyspace {
+    entries: Dict<Entry>,
+    /// Keys that have an expiry, so the active expire cycle only visits those
+    volatile: Vec<Bytes>,
+    /// Where in `volatile` the active expire cycle carries on from
+    expire_cursor: usize,
+    /// Lookups through [`Keyspace::get_shared`] that found their key
+    hits: AtomicU64,
+    /// Lookups through [`Keyspace::get_shared`] that didn't
+    misses: AtomicU64,
+}
+
+struct Entry {
+    value: DbValue,
+    last_access: LastAccess,
+    expires_at: Option<Instant>,
+    /// Index of the key in [`Keyspace::volatile`], set whenever `expires_at` is
+    volatile_index: Option<usize>,
+}
+
+/// Reference point for [`LastAccess`], which can't store an `Instant` atomically
+static CLOCK_START: LazyLock<Instant> = LazyLock::new(Instant::now);
+
+/// When an entry was last accessed, updatable through a shared reference so
+/// reads under a shared lock still count as accesses
+struct LastAccess(AtomicU64);
+
+impl LastAccess {
+    fn now() -> Self {
+        Self(AtomicU64::new(Self::ticks()))
+    }
+
+    fn touch(&self) {
+        self.0.store(Self::ticks(), Ordering::Relaxed);
+    }
+
+    fn elapsed(&self) -> Duration {
+        Duration::from_nanos(Self::ticks().saturating_sub(self.0.load(Ordering::Relaxed)))
+    }
+
+    /// Nanoseconds since [`CLOCK_START`]
+    fn ticks() -> u64 {
+        CLOCK_START.elapsed().as_nanos() as u64
+    }
+}
+
+/// A key as seen by `DEBUG OBJECT`, whether or not it has expired
+pub struct EntryInfo<'a> {
+    pub value: &'a DbValue,
+    pub idle: Duration,
+    /// Past its expiry but not removed yet
+    pub expired: bool,
+}
+
+/// Memory estimates for the keyspace, as reported by `MEMORY STATS`
+pub struct MemoryStats {
+    pub keys: usize,
+    /// Bytes used by keys, values and their entries
+    pub dataset_bytes: usize,
+    /// Dataset bytes plus spare capacity allocated by the keyspace
+    pub allocated_bytes: usize,
+}
+
+/// Key lookups by read commands, as reported by `INFO stats`
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct LookupStats {
+    pub hits: u64,
+    pub misses: u64,
+}
+
+/// Instant at which the Unix time `since_epoch` falls, `None` if it is too far
+/// off to represent
+///
+/// Instants have no relation to the wall clock, so this measures how far the
+/// time is from the current system time and applies that gap to the current
+/// instant. A time that has already passed maps to now, which counts as expired
+/// straight away.
+pub fn instant_at_unix(since_epoch: Duration) -> Option<Instant> {
+    let target = UNIX_EPOCH.checked_add(since_epoch)?;
+    let now = Instant::now();
+    match target.duration_since(SystemTime::now()) {
+        Ok(remaining) => now.checked_add(remaining),
+        Err(_) => Some(now),
+    }
+}
+
+/// Unix time at which `at` falls, the reverse of [`instant_at_unix`]
+pub fn unix_time_of(at: Instant) -> Duration {
+    let now = SystemTime::now();
+    let time = match at.checked_duration_since(Instant::now()) {
+        Some(remaining) => now.checked_add(remaining),
+        None => now.checked_sub(Instant::now().duration_since(at)),
+    };
+    time.and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+        .unwrap_or_default()
+}
+
+impl Entry {
+    fn new(value: DbValue) -> Self {
+        Self {
+            value,
+            last_access: LastAccess::now(),
+            expires_at: None,
+            volatile_index: None,
+        }
+    }
+
+    fn is_expired(&self) -> bool {
+        self.expires_at.is_some_and(|at| at <= Instant::now())
+    }
+
+    fn memory_usage(&self, key: &[u8]) -> usize {
+        const OVERHEAD: usize = std::mem::size_of::<Bytes>() + std::mem::size_of::<Entry>();
+
+        OVERHEAD + key.len() + self.value.memory_usage()
+    }
+}
+
+impl DbValue {
+    /// Name of the value's type as Redis reports it, which `SCAN TYPE` takes
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Self::String(_) => "string",
+            Self::Set(_) => "set",
+            Self::Hash(_) => "hash",
+            Self::List(_) => "list",
+            Self::SortedSet(_) => "zset",
+        }
+    }
+
+    /// Rough estimate of the bytes held by the value, counting each element's
+    /// payload plus the handle it is stored behind
+    pub fn memory_usage(&self) -> usize {
+        const ELEMENT: usize = std::mem::size_of::<Bytes>();
+
+        match self {
+            Self::String(bytes) => bytes.len(),
+            Self::Set(members) => members.iter().map(|member| ELEMENT + member.len()).sum(),
+            Self::Hash(fields) => fields
+                .iter()
+                .map(|(field, value)| 2 * ELEMENT + field.len() + value.len())
+                .sum(),
+            Self::List(items) => items.iter().map(|item| ELEMENT + item.len()).sum(),
+            // Each member is held by both the score map and the ordered set
+            Self::SortedSet(members) => members
+                .iter()
+                .map(|(member, _)| 2 * (ELEMENT + size_of::<f64>()) + member.len())
+                .sum(),
+        }
+    }
+
+    /// Length of the longest string held by the value, whether it is the value
+    /// itself or an element, field or member of it
+    pub fn longest_string(&self) -> usize {
+        match self {
+            Self::String(bytes) => bytes.len(),
+            Self::Set(members) => members.iter().map(Bytes::len).max().unwrap_or(0),
+            Self::Hash(fields) => fields
+                .iter()
+                .map(|(field, value)| field.len().max(value.len()))
+                .max()
+                .unwrap_or(0),
+            Self::List(items) => items.iter().map(Bytes::len).max().unwrap_or(0),
+            Self::SortedSet(members) => members
+                .iter()
+                .map(|(member, _)| member.len())
+                .max()
+                .unwrap_or(0),
+        }
+    }
+}
+
+// Not derived, which would needlessly require the storage to be `Clone`
+impl<S> Clone for Db<S> {
+    fn clone(&self) -> Self {
+        Self {
+            databases: self.databases.clone(),
+            index: self.index,
+            active_expire: self.active_expire.clone(),
+            pubsub: self.pubsub.clone(),
+            acl: self.acl.clone(),
+            replication: self.replication.clone(),
+            max_value_len: self.max_value_len,
+            encoding_limits: self.encoding_limits,
+            rng: self.rng.clone(),
+        }
+    }
+}
+
+impl<S: Storage> Default for Db<S> {
+    fn default() -> Self {
+        Self::with_databases(DATABASES)
+    }
+}
+
+impl Db {
+    /// Databases in the default number, the server sizes them from its config
+    #[cfg(test)]
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl<S: Storage> Db<S> {
+    /// Creates `count` empty logical databases, of which at least one is needed
+    pub fn with_databases(count: usize) -> Self {
+        assert!(count >= 1, "at least one database is needed");
+        Self {
+            databases: (0..count).map(|_| RwLock::default()).collect(),
+            index: 0,
+            active_expire: Arc::new(AtomicBool::new(true)),
+            pubsub: PubSub::default(),
+            acl: Acl::default(),
+            replication: Replication::default(),
+            max_value_len: PROTO_MAX_BULK_LEN,
+            encoding_limits: EncodingLimits::default(),
+            rng: Arc::new(Mutex::new(Rng::from_entropy())),
+        }
+    }
+
+    /// Replaces the users clients authenticate as
+    pub fn with_acl(self, acl: Acl) -> Self {
+        Self { acl, ..self }
+    }
+
+    /// Caps the length of the string values commands may store
+    pub fn with_max_value_len(self, max_value_len: usize) -> Self {
+        Self {
+            max_value_len,
+            ..self
+        }
+    }
+
+    pub fn max_value_len(&self) -> usize {
+        self.max_value_len
+    }
+
+    /// Replaces the sizes up to which values report a compact encoding
+    pub fn with_encoding_limits(self, encoding_limits: EncodingLimits) -> Self {
+        Self {
+            encoding_limits,
+            ..self
+        }
+    }
+
+    pub fn encoding_limits(&self) -> EncodingLimits {
+        self.encoding_limits
+    }
+
+    /// Reseeds the generator behind the commands picking random elements, so
+    /// their picks can be reproduced
+    #[cfg(test)]
+    pub fn with_rng_seed(self, seed: u64) -> Self {
+        Self {
+            rng: Arc::new(Mutex::new(Rng::seeded(seed))),
+            ..self
+        }
+    }
+
+    /// Generator the commands picking random elements draw from
+    pub fn rng(&self) -> MutexGuard<'_, Rng> {
+        self.rng.lock().unwrap()
+    }
+
+    /// Locks the selected keyspace exclusively for the duration of a single
+    /// command
+    ///
+    /// This is a std lock rather than an async one: commands hold it only while
+    /// they run synchronously and never across an `.await`, so waiting on it is
+    /// brief and an async lock would only add overhead. Commands that wait, like
+    /// `DEBUG SLEEP`, do so in [`Command::apply_async`] without holding it.
+    ///
+    /// [`Command::apply_async`]: crate::cmd::Command::apply_async
+    pub fn lock(&self) -> RwLockWriteGuard<'_, S> {
+        self.databases[self.index].write().unwrap()
+    }
+
+    /// Locks the selected keyspace for reading, shared with other readers
+    ///
+    /// Commands flagged `readonly` in the command table take this lock, the rest
+    /// take [`Db::lock`].
+    pub fn read(&self) -> RwLockReadGuard<'_, S> {
+        self.databases[self.index].read().unwrap()
+    }
+
+    /// Like [`Db::read`], but first removes any of `keys` that have expired
+    ///
+    /// Readers can't remove keys themselves, so this briefly takes the exclusive
+    /// lock, though only when there is an expired key to remove.
+    pub fn read_keys(&self, keys: &[impl AsRef<[u8]>]) -> RwLockReadGuard<'_, S> {
+        let keyspace = self.read();
+        let is_expired = |key: &[u8]| keyspace.inspect(key).is_some_and(|entry| entry.expired);
+        if !keys.iter().any(|key| is_expired(key.as_ref())) {
+            return keyspace;
+        }
+        drop(keyspace);
+
+        let mut keyspace = self.lock();
+        for key in keys {
+            keyspace.purge_if_expired(key.as_ref());
+        }
+        drop(keyspace);
+        self.read()
+    }
+
+    /// [`Db::read_keys`] for a single key
+    pub fn read_key(&self, key: &[u8]) -> RwLockReadGuard<'_, S> {
+        self.read_keys(&[key])
+    }
+
+    /// Number of the selected database
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// Handle to the same state with database `index` selected, if it exists
+    pub fn select(&self, index: usize) -> Option<Self> {
+        (index < self.databases.len()).then(|| Self {
+            index,
+            ..self.clone()
+        })
+    }
+
+    /// Swaps the keyspaces of databases `a` and `b`, returning false if either
+    /// doesn't exist
+    ///
+    /// Handles with either database selected see the other's keys afterwards.
+    pub fn swap(&self, a: usize, b: usize) -> bool {
+        if a >= self.databases.len() || b >= self.databases.len() {
+            return false;
+        }
+        if a != b {
+            // Always lock the lower index first so concurrent swaps can't deadlock
+            let (low, high) = (a.min(b), a.max(b));
+            let mut low = self.databases[low].write().unwrap();
+            let mut high = self.databases[high].write().unwrap();
+            std::mem::swap(&mut *low, &mut *high);
+        }
+        true
+    }
+
+    /// Locks every keyspace, in index order like [`Db::swap`] so the two can't
+    /// deadlock
+    pub fn lock_all(&self) -> Vec<RwLockWriteGuard<'_, S>> {
+        self.databases
+            .iter()
+            .map(|keyspace| keyspace.write().unwrap())
+            .collect()
+    }
+
+    /// Read locks every keyspace, in index order like [`Db::lock_all`]
+    pub fn read_all(&self) -> Vec<RwLockReadGuard<'_, S>> {
+        self.databases
+            .iter()
+            .map(|keyspace| keyspace.read().unwrap())
+            .collect()
+    }
+
+    /// Key lookups by read commands across every database
+    ///
+    /// The counts live with each keyspace and are summed here, so swapping or
+    /// flushing databases leaves the totals as they were.
+    pub fn lookup_stats(&self) -> LookupStats {
+        self.databases
+            .iter()
+            .map(|keyspace| keyspace.read().unwrap().lookup_stats())
+            .fold(LookupStats::default(), |total, stats| LookupStats {
+                hits: total.hits + stats.hits,
+                misses: total.misses + stats.misses,
+            })
+    }
+
+    /// Turns the background sweep of expired keys on or off
+    pub fn set_active_expire(&self, enabled: bool) {
+        self.active_expire.store(enabled, Ordering::Relaxed);
+    }
+
+    /// Removes expired keys of every database a few at a time, unless active
+    /// expiry is off
+    ///
+    /// As in Redis, each database gets rounds checking
+    /// [`ACTIVE_EXPIRE_KEYS_PER_ROUND`] of its keys with an expiry, for as long
+    /// as more than a quarter of a round turns out to have expired. Only the
+    /// first round of each database runs once [`ACTIVE_EXPIRE_TIME_BUDGET`] is
+    /// spent, and the lock is released between rounds so commands aren't held
+    /// up by a large sweep.
+    pub fn active_expire_cycle(&self) {
+        if !self.active_expire.load(Ordering::Relaxed) {
+            return;
+        }
+        let deadline = Instant::now() + ACTIVE_EXPIRE_TIME_BUDGET;
+        for keyspace in self.databases.iter() {
+            loop {
+                let (checked, expired) = keyspace
+                    .write()
+                    .unwrap()
+                    .purge_expired_sample(ACTIVE_EXPIRE_KEYS_PER_ROUND);
+                if expired * 4 <= checked || Instant::now() >= deadline {
+                    break;
+                }
+            }
+        }
+    }
+
+    pub fn pubsub(&self) -> &PubSub {
+        &self.pubsub
+    }
+
+    pub fn acl(&self) -> &Acl {
+        &self.acl
+    }
+
+    pub fn replication(&self) -> &Replication {
+        &self.replication
+    }
+}
+
+/// Store of a single logical database, holding its keys, values and expiries
+///
+/// [`Keyspace`] is the in-memory store the server uses. Commands only go
+/// through this trait, so a [`Db`] can be backed by another implementation.
+pub trait Storage: Default + Send + 'static {
+    /// Gets the value of `key`, counting it as an access
+    fn get(&mut self, key: &[u8]) -> Option<&DbValue>;
+
+    fn get_mut(&mut self, key: &[u8]) -> Option<&mut DbValue>;
+
+    /// Gets the value of `key`, inserting the one built by `f` if it is missing
+    fn get_or_insert_with(&mut self, key: Bytes, f: impl FnOnce() -> DbValue) -> &mut DbValue;
+
+    /// Gets the value of `key` through a shared reference, counting it as an
+    /// access where the store can record one without `&mut self`
+    ///
+    /// Unlike [`Storage::get`], an expired key is hidden but not removed.
+    fn get_shared(&self, key: &[u8]) -> Option<&DbValue> {
+        self.peek(key)
+    }
+
+    /// Gets the value of `key` without counting it as an access
+    fn peek(&self, key: &[u8]) -> Option<&DbValue>;
+
+    /// Whether `key` exists, without counting it as an access
+    fn exists(&self, key: &[u8]) -> bool {
+        self.peek(key).is_some()
+    }
+
+    /// Stores `value` against `key`, replacing any previous value and its expiry
+    fn insert(&mut self, key: Bytes, value: DbValue);
+
+    fn remove(&mut self, key: &[u8]) -> Option<DbValue>;
+
+    /// Number of keys, including expired ones that haven't been removed yet
+    fn len(&self) -> usize;
+
+    /// Expiry of `key`, `None` if the key is missing and `Some(None)` if it never
+    /// expires
+    fn expiry(&self, key: &[u8]) -> Option<Option<Instant>>;
+
+    /// Sets or clears the expiry of `key`, returning false if it is missing
+    fn set_expiry(&mut self, key: &[u8], expires_at: Option<Instant>) -> bool;
+
+    /// Looks `key` up without counting an access or removing it if it expired
+    fn inspect(&self, key: &[u8]) -> Option<EntryInfo<'_>>;
+
+    /// Removes `key` if it has expired
+    fn purge_if_expired(&mut self, key: &[u8]);
+
+    /// Checks up to `count` of the keys with an expiry, removing those that
+    /// have expired
+    ///
+    /// Successive calls should go on from where the previous one stopped, so
+    /// that every key with an expiry is eventually checked. Returns how many
+    /// keys were checked and how many of them were removed.
+    fn purge_expired_sample(&mut self, count: usize) -> (usize, usize);
+
+    /// Keys that haven't expired, with their values and expiries
+    fn iter(&self) -> impl Iterator<Item = (&Bytes, &DbValue, Option<Instant>)>;
+
+    /// Keys that haven't expired from scan position `cursor` on, in scan order,
+    /// each with its position and value
+    ///
+    /// Positions are those of [`crate::dict::position`].
+    fn scan_from(&self, cursor: u64) -> impl Iterator<Item = (u64, &Bytes, &DbValue)>;
+
+    /// Removes every key
+    fn clear(&mut self);
+
+    /// Estimated bytes used by `key`, its value and the entry holding them
+    fn memory_usage(&self, key: &[u8]) -> Option<usize>;
+
+    /// Estimates of the memory used by the whole store
+    fn memory_stats(&self) -> MemoryStats;
+
+    /// Time since `key` was last accessed
+    fn idle_time(&self, key: &[u8]) -> Option<Duration>;
+
+    /// How many lookups through [`Storage::get_shared`] found their key, and
+    /// how many didn't, for stores that count them
+    fn lookup_stats(&self) -> LookupStats {
+        LookupStats::default()
+    }
+}
+
+impl Storage for Keyspace {
+    fn get(&mut self, key: &[u8]) -> Option<&DbValue> {
+        self.get_mut(key).map(|value| &*value)
+    }
+
+    fn get_mut(&mut self, key: &[u8]) -> Option<&mut DbValue> {
+        self.purge_if_expired(key);
+        self.entries.get_mut(key).map(|entry| {
+            entry.last_access.touch();
+            &mut entry.value
+        })
+    }
+
+    fn get_or_insert_with(&mut self, key: Bytes, f: impl FnOnce() -> DbValue) -> &mut DbValue {
+        self.purge_if_expired(&key);
+        let entry = self.entries.get_or_insert_with(key, || Entry::new(f()));
+        entry.last_access.touch();
+        &mut entry.value
+    }
+
+    fn get_shared(&self, key: &[u8]) -> Option<&DbValue> {
+        let Some(entry) = self.live_entry(key) else {
+            self.misses.fetch_add(1, Ordering::Relaxed);
+            return None;
+        };
+        self.hits.fetch_add(1, Ordering::Relaxed);
+        entry.last_access.touch();
+        Some(&entry.value)
+    }
+
+    fn peek(&self, key: &[u8]) -> Option<&DbValue> {
+        self.live_entry(key).map(|entry| &entry.value)
+    }
+
+    fn insert(&mut self, key: Bytes, value: DbValue) {
+        if let Some(previous) = self.entries.insert(key, Entry::new(value)) {
+            self.untrack_expiry(previous.volatile_index);
+        }
+    }
+
+    fn remove(&mut self, key: &[u8]) -> Option<DbValue> {
+        self.purge_if_expired(key);
+        self.remove_entry(key).map(|entry| entry.value)
+    }
+
+    fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    fn expiry(&self, key: &[u8]) -> Option<Option<Instant>> {
+        self.live_entry(key).map(|entry| entry.expires_at)
+    }
+
+    fn set_expiry(&mut self, key: &[u8], expires_at: Option<Instant>) -> bool {
+        self.purge_if_expired(key);
+        let Some(entry) = self.entries.get_mut(key) else {
+            return false;
+        };
+        entry.expires_at = expires_at;
+        match (expires_at, entry.volatile_index) {
+            (Some(_), None) => {
+                entry.volatile_index = Some(self.volatile.len());
+                self.volatile.push(Bytes::copy_from_slice(key));
+            }
+            (None, Some(_)) => {
+                let index = entry.volatile_index.take();
+                self.untrack_expiry(index);
+            }
+            _ => {}
+        }
+        true
+    }
+
+    fn inspect(&self, key: &[u8]) -> Option<EntryInfo<'_>> {
+        self.entries.get(key).map(|entry| EntryInfo {
+            value: &entry.value,
+            idle: entry.last_access.elapsed(),
+            expired: entry.is_expired(),
+        })
+    }
+
+    fn purge_if_expired(&mut self, key: &[u8]) {
+        if self.entries.get(key).is_some_and(Entry::is_expired) {
+            self.remove_entry(key);
+        }
+    }
+
+    fn purge_expired_sample(&mut self, count: usize) -> (usize, usize) {
+        let checked = count.min(self.volatile.len());
+        let mut expired = 0;
+        for _ in 0..checked {
+            if self.expire_cursor >= self.volatile.len() {
+                self.expire_cursor = 0;
+            }
+            let key = &self.volatile[self.expire_cursor];
+            if self.entries[key].is_expired() {
+                // The last key takes its place, and is checked next
+                let key = key.clone();
+                self.remove_entry(&key);
+                expired += 1;
+            } else {
+                self.expire_cursor += 1;
+            }
+        }
+        (checked, expired)
+    }
+
+    fn iter(&self) -> impl Iterator<Item = (&Bytes, &DbValue, Option<Instant>)> {
+        self.entries
+            .iter()
+            .filter(|(_, entry)| !entry.is_expired())
+            .map(|(key, entry)| (key, &entry.value, entry.expires_at))
+    }
+
+    fn scan_from(&self, cursor: u64) -> impl Iterator<Item = (u64, &Bytes, &DbValue)> {
+        self.entries
+            .scan_from(cursor)
+            .filter(|(_, _, entry)| !entry.is_expired())
+            .map(|(position, key, entry)| (position, key, &entry.value))
+    }
+
+    fn clear(&mut self) {
+        self.entries.clear();
+        self.volatile.clear();
+        self.expire_cursor = 0;
+    }
+
+    fn memory_usage(&self, key: &[u8]) -> Option<usize> {
+        self.live_entry(key).map(|entry| entry.memory_usage(key))
+    }
+
+    fn memory_stats(&self) -> MemoryStats {
+        let dataset_bytes = self
+            .entries
+            .iter()
+            .map(|(key, entry)| entry.memory_usage(key))
+            .sum();
+        // Slots the map has allocated but not filled yet
+        let spare = self.entries.capacity() - self.entries.len();
+
+        MemoryStats {
+            keys: self.entries.len(),
+            dataset_bytes,
+            allocated_bytes: dataset_bytes + spare * std::mem::size_of::<(Bytes, Entry)>(),
+        }
+    }
+
+    fn idle_time(&self, key: &[u8]) -> Option<Duration> {
+        self.live_entry(key)
+            .map(|entry| entry.last_access.elapsed())
+    }
+
+    fn lookup_stats(&self) -> LookupStats {
+        LookupStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+        }
+    }
+}
+
+impl Keyspace {
+    fn live_entry(&self, key: &[u8]) -> Option<&Entry> {
+        self.entries.get(key).filter(|entry| !entry.is_expired())
+    }
+
+    /// Removes `key` whether or not it has expired, along with its expiry
+    fn remove_entry(&mut self, key: &[u8]) -> Option<Entry> {
+        let entry = self.entries.remove(key)?;
+        self.untrack_expiry(entry.volatile_index);
+        Some(entry)
+    }
+
+    /// Drops the key at `index` of [`Keyspace::volatile`], whose entry has
+    /// already lost its expiry or been removed
+    fn untrack_expiry(&mut self, index: Option<usize>) {
+        let Some(index) = index else {
+            return;
+        };
+        self.volatile.swap_remove(index);
+        if let Some(moved) = self.volatile.get(index) {
+            self.entries.get_mut(moved).unwrap().volatile_index = Some(index);
+        }
+    }
+}
+
+#[cfg(test)]
+mod db_tests {
+    use super::*;
+    use crate::{cmd::run, dict::position, frame::FrameValue};
+    use std::{collections::BTreeMap, thread::sleep};
+
+    #[test]
+    fn test_access_resets_idle_time() {
+        let mut keyspace = Keyspace::default();
+        keyspace.insert("key".into(), DbValue::String("value".into()));
+        sleep(Duration::from_millis(20));
+
+        assert!(keyspace.idle_time(b"key").unwrap() >= Duration::from_millis(20));
+        keyspace.peek(b"key");
+        assert!(keyspace.idle_time(b"key").unwrap() >= Duration::from_millis(20));
+
+        keyspace.get(b"key");
+        assert!(keyspace.idle_time(b"key").unwrap() < Duration::from_millis(20));
+        assert!(keyspace.idle_time(b"missing").is_none());
+    }
+
+    #[test]
+    fn test_expired_keys_are_removed_on_access() {
+        let mut keyspace = Keyspace::default();
+        keyspace.insert("key".into(), DbValue::String("value".into()));
+        keyspace.set_expiry(b"key", Some(Instant::now()));
+
+        assert!(keyspace.peek(b"key").is_none());
+        assert!(keyspace.inspect(b"key").unwrap().expired);
+        assert_eq!(keyspace.len(), 1);
+
+        assert!(keyspace.get(b"key").is_none());
+        assert_eq!(keyspace.len(), 0);
+    }
+
+    #[test]
+    fn test_insert_clears_expiry() {
+        let mut keyspace = Keyspace::default();
+        let later = Instant::now() + Duration::from_secs(60);
+        keyspace.insert("key".into(), DbValue::String("value".into()));
+        keyspace.set_expiry(b"key", Some(later));
+        assert_eq!(keyspace.expiry(b"key"), Some(Some(later)));
+
+        keyspace.insert("key".into(), DbValue::String("other".into()));
+        assert_eq!(keyspace.expiry(b"key"), Some(None));
+        assert_eq!(keyspace.expiry(b"missing"), None);
+    }
+
+    #[test]
+    fn test_expire_sample_only_checks_keys_with_an_expiry() {
+        let mut keyspace = Keyspace::default();
+        for i in 0..100 {
+            keyspace.insert(format!("key:{i}").into(), DbValue::String("value".into()));
+        }
+        let later = Instant::now() + Duration::from_secs(60);
+        for i in 0..30 {
+            let expires_at = if i < 5 { later } else { Instant::now() };
+            keyspace.set_expiry(format!("key:{i}").as_bytes(), Some(expires_at));
+        }
+        // Keys losing their expiry, one way or another, are no longer checked
+        keyspace.set_expiry(b"key:0", None);
+        keyspace.insert("key:1".into(), DbValue::String("other".into()));
+        keyspace.remove(b"key:2");
+
+        let (checked, mut expired) = keyspace.purge_expired_sample(20);
+        assert_eq!(checked, 20);
+        loop {
+            let (checked, removed) = keyspace.purge_expired_sample(20);
+            expired += removed;
+            if removed == 0 {
+                assert_eq!(checked, 2);
+                break;
+            }
+        }
+        assert_eq!(expired, 25);
+        assert_eq!(keyspace.len(), 74);
+        assert!(keyspace.peek(b"key:0").is_some());
+        assert!(keyspace.peek(b"key:1").is_some());
+        assert!(keyspace.peek(b"key:4").is_some());
+
+        keyspace.clear();
+        assert_eq!(keyspace.purge_expired_sample(20), (0, 0));
+    }
+
+    #[test]
+    fn test_active_expire_cycle_repeats_while_keys_keep_expiring() {
+        let db = Db::new();
+        for i in 0..1000 {
+            let key = format!("key:{i}");
+            run(&db, &["SET", &key, "value"]);
+            run(&db, &["PEXPIRE", &key, "1"]);
+        }
+        run(&db, &["SET", "kept", "value"]);
+        run(&db, &["EXPIRE", "kept", "60"]);
+        sleep(Duration::from_millis(5));
+
+        db.active_expire_cycle();
+        assert_eq!(db.read().len(), 1);
+    }
+
+    /// Ordered store with none of the bookkeeping of [`Keyspace`], expired keys
+    /// are simply hidden until someone removes them
+    #[derive(Default)]
+    struct OrderedStorage {
+        entries: BTreeMap<Bytes, (DbValue, Option<Instant>)>,
+    }
+
+    impl OrderedStorage {
+        fn live(&self, key: &[u8]) -> bool {
+            matches!(self.entries.get(key), Some((_, expiry)) if !expiry.is_some_and(|at| at <= Instant::now()))
+        }
+    }
+
+    impl Storage for OrderedStorage {
+        fn get(&mut self, key: &[u8]) -> Option<&DbValue> {
+            self.get_mut(key).map(|value| &*value)
+        }
+
+        fn get_mut(&mut self, key: &[u8]) -> Option<&mut DbValue> {
+            self.purge_if_expired(key);
+            self.entries.get_mut(key).map(|(value, _)| value)
+        }
+
+        fn get_or_insert_with(&mut self, key: Bytes, f: impl FnOnce() -> DbValue) -> &mut DbValue {
+            self.purge_if_expired(&key);
+            &mut self.entries.entry(key).or_insert_with(|| (f(), None)).0
+        }
+
+        fn peek(&self, key: &[u8]) -> Option<&DbValue> {
+            self.live(key).then(|| &self.entries[key].0)
+        }
+
+        fn insert(&mut self, key: Bytes, value: DbValue) {
+            self.entries.insert(key, (value, None));
+        }
+
+        fn remove(&mut self, key: &[u8]) -> Option<DbValue> {
+            self.purge_if_expired(key);
+            self.entries.remove(key).map(|(value, _)| value)
+        }
+
+        fn len(&self) -> usize {
+            self.entries.len()
+        }
+
+        fn expiry(&self, key: &[u8]) -> Option<Option<Instant>> {
+            self.live(key).then(|| self.entries[key].1)
+        }
+
+        fn set_expiry(&mut self, key: &[u8], expires_at: Option<Instant>) -> bool {
+            self.purge_if_expired(key);
+            self.entries
+                .get_mut(key)
+                .map(|(_, expiry)| *expiry = expires_at)
+                .is_some()
+        }
+
+        fn inspect(&self, key: &[u8]) -> Option<EntryInfo<'_>> {
+            self.entries.get(key).map(|(value, _)| EntryInfo {
+                value,
+                idle: Duration::ZERO,
+                expired: !self.live(key),
+            })
+        }
+
+        fn purge_if_expired(&mut self, key: &[u8]) {
+            if !self.live(key) {
+                self.entries.remove(key);
+            }
+        }
+
+        fn purge_expired_sample(&mut self, count: usize) -> (usize, usize) {
+            let now = Instant::now();
+            let volatile: Vec<_> = self
+                .entries
+                .iter()
+                .filter(|(_, (_, expiry))| expiry.is_some())
+                .take(count)
+                .map(|(key, (_, expiry))| (key.clone(), expiry.is_some_and(|at| at <= now)))
+                .collect();
+            let mut expired = 0;
+            for (key, _) in volatile.iter().filter(|(_, expired)| *expired) {
+                self.entries.remove(key);
+                expired += 1;
+            }
+            (volatile.len(), expired)
+        }
+
+        fn iter(&self) -> impl Iterator<Item = (&Bytes, &DbValue, Option<Instant>)> {
+            self.entries
+                .iter()
+                .filter(|(key, _)| self.live(key))
+                .map(|(key, (value, expiry))| (key, value, *expiry))
+        }
+
+        fn scan_from(&self, cursor: u64) -> impl Iterator<Item = (u64, &Bytes, &DbValue)> {
+            let mut keys: Vec<_> = self
+                .iter()
+                .map(|(key, value, _)| (position(key), key, value))
+                .filter(|(position, ..)| *position >= cursor)
+                .collect();
+            keys.sort_by_key(|(position, ..)| *position);
+            keys.into_iter()
+        }
+
+        fn clear(&mut self) {
+            self.entries.clear();
+        }
+
+        fn memory_usage(&self, key: &[u8]) -> Option<usize> {
+            self.live(key).then_some(0)
+        }
+
+        fn memory_stats(&self) -> MemoryStats {
+            MemoryStats {
+                keys: self.entries.len(),
+                dataset_bytes: 0,
+                allocated_bytes: 0,
+            }
+        }
+
+        fn idle_time(&self, key: &[u8]) -> Option<Duration> {
+            self.live(key).then_some(Duration::ZERO)
+        }
+    }
+
+    #[test]
+    fn test_commands_run_against_other_storage() {
+        let db = Db::<OrderedStorage>::default();
+
+        assert_eq!(
+            run(&db, &["SET", "b", "2"]),
+            FrameValue::SimpleString("OK".into())
+        );
+        assert_eq!(run(&db, &["RPUSH", "a", "x", "y"]), FrameValue::Integer(2));
+        assert_eq!(run(&db, &["GET", "b"]), FrameValue::BulkString("2".into()));
+        assert_eq!(
+            run(&db, &["LINDEX", "a", "-1"]),
+            FrameValue::BulkString("y".into())
+        );
+        assert_eq!(run(&db, &["DBSIZE"]), FrameValue::Integer(2));
+
+        assert_eq!(run(&db, &["PEXPIRE", "b", "1"]), FrameValue::Integer(1));
+        sleep(Duration::from_millis(5));
+        assert_eq!(run(&db, &["GET", "b"]), FrameValue::NullBulkString);
+        assert_eq!(run(&db, &["DEL", "a"]), FrameValue::Integer(1));
+        assert_eq!(run(&db, &["DBSIZE"]), FrameValue::Integer(0));
+    }
+
+    #[test]
+    fn test_concurrent_writes_serialize() {
+        const THREADS: usize = 8;
+        const INCREMENTS: usize = 500;
+
+        let db = Db::new();
+        std::thread::scope(|scope| {
+            for _ in 0..THREADS {
+                scope.spawn(|| {
+                    for _ in 0..INCREMENTS {
+                        run(&db, &["INCR", "counter"]);
+                        run(&db, &["GET", "counter"]);
+                    }
+                });
+            }
+        });
+
+        assert_eq!(
+            run(&db, &["GET", "counter"]),
+            FrameValue::BulkString((THREADS * INCREMENTS).to_string().into())
+        );
+    }
+
+    /// Compares concurrent GETs under the shared lock with the same reads taking
+    /// the exclusive lock, as every command did before readonly commands shared it.
+    ///
+    /// Run with `cargo test --release -- --ignored --nocapture bench_`.
+    #[test]
+    #[ignore]
+    fn bench_concurrent_get() {
+        const THREADS: usize = 8;
+        const READS: usize = 100_000;
+
+        let db = Db::new();
+        run(&db, &["SET", "key", "value"]);
+
+        let measure = |read: &(dyn Fn() + Sync)| {
+            let start = Instant::now();
+            std::thread::scope(|scope| {
+                for _ in 0..THREADS {
+                    scope.spawn(|| (0..READS).for_each(|_| read()));
+                }
+            });
+            start.elapsed()
+        };
+
+        let shared = measure(&|| {
+            db.read_key(b"key").get_shared(b"key").unwrap();
+        });
+        let exclusive = measure(&|| {
+            db.lock().get(b"key").unwrap();
+        });
+
+        let rate = |elapsed: Duration| (THREADS * READS) as f64 / elapsed.as_secs_f64();
+        println!(
+            "{THREADS} threads: shared {:.0} GET/s, exclusive {:.0} GET/s",
+            rate(shared),
+            rate(exclusive)
+        );
+    }
+}
@@ -0,0 +1,249 @@
+//! Hash tables that can be scanned a page at a time, the containers behind the
+//! keyspace, sets, hashes and sorted sets
+//!
+//! Redis walks its hash tables bucket by bucket to scan them, which the
+//! standard maps don't expose. Instead each key gets a position from a fixed
+//! hash of it, and an ordered index of the positions stands in for the buckets,
+//! so a scan resumes from a position in O(log N) whatever the size of the table.
+//! The same index picks random keys.
+
+use crate::random::Rng;
+use bytes::Bytes;
+use std::{
+    borrow::Borrow,
+    collections::{BTreeSet, HashMap, hash_map},
+    hash::{DefaultHasher, Hash, Hasher},
+    ops::{Bound, Index},
+};
+
+/// Where `key` falls in the scan order, never 0 as that ends a scan
+pub fn position(key: &[u8]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    key.hash(&mut hasher);
+    hasher.finish().max(1)
+}
+
+/// Map from byte strings to `V`, indexed by scan position
+///
+/// Lookups go through the map, scans through the index, so every key is held
+/// by both. Iterating walks the index too, so the same keys always come out in
+/// the same order.
+#[derive(Clone, Debug)]
+pub struct Dict<V> {
+    map: HashMap<Bytes, V>,
+    order: BTreeSet<(u64, Bytes)>,
+}
+
+impl<V> Default for Dict<V> {
+    fn default() -> Self {
+        Self {
+            map: HashMap::new(),
+            order: BTreeSet::new(),
+        }
+    }
+}
+
+impl<V: PartialEq> PartialEq for Dict<V> {
+    fn eq(&self, other: &Self) -> bool {
+        self.map == other.map
+    }
+}
+
+impl<V> Dict<V> {
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    /// Slots allocated for keys, filled or not
+    pub fn capacity(&self) -> usize {
+        self.map.capacity()
+    }
+
+    pub fn get(&self, key: &[u8]) -> Option<&V> {
+        self.map.get(key)
+    }
+
+    pub fn get_mut(&mut self, key: &[u8]) -> Option<&mut V> {
+        self.map.get_mut(key)
+    }
+
+    pub fn contains_key(&self, key: &[u8]) -> bool {
+        self.map.contains_key(key)
+    }
+
+    /// Sets the value of `key`, returning the one it replaced if it was present
+    pub fn insert(&mut self, key: Bytes, value: V) -> Option<V> {
+        match self.map.entry(key) {
+            hash_map::Entry::Occupied(mut entry) => Some(entry.insert(value)),
+            hash_map::Entry::Vacant(entry) => {
+                self.order
+                    .insert((position(entry.key()), entry.key().clone()));
+                entry.insert(value);
+                None
+            }
+        }
+    }
+
+    /// Value of `key`, inserting what `f` returns first if it is missing
+    pub fn get_or_insert_with(&mut self, key: Bytes, f: impl FnOnce() -> V) -> &mut V {
+        match self.map.entry(key) {
+            hash_map::Entry::Occupied(entry) => entry.into_mut(),
+            hash_map::Entry::Vacant(entry) => {
+                self.order
+                    .insert((position(entry.key()), entry.key().clone()));
+                entry.insert(f())
+            }
+        }
+    }
+
+    pub fn remove(&mut self, key: &[u8]) -> Option<V> {
+        self.remove_entry(key).map(|(_, value)| value)
+    }
+
+    /// Removes `key`, returning the stored key along with its value
+    pub fn remove_entry(&mut self, key: &[u8]) -> Option<(Bytes, V)> {
+        let (key, value) = self.map.remove_entry(key)?;
+        self.order.remove(&(position(&key), key.clone()));
+        Some((key, value))
+    }
+
+    pub fn clear(&mut self) {
+        self.map.clear();
+        self.order.clear();
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &Bytes> {
+        self.order.iter().map(|(_, key)| key)
+    }
+
+    pub fn values(&self) -> impl Iterator<Item = &V> {
+        self.keys().map(|key| &self.map[key])
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&Bytes, &V)> {
+        self.keys().map(|key| (key, &self.map[key]))
+    }
+
+    /// Random entry, `None` if there are none
+    ///
+    /// Takes the first key at or after a random position, so like Redis' own
+    /// pick it isn't quite uniform: keys after wider gaps come up more often.
+    pub fn random(&self, rng: &mut Rng) -> Option<(&Bytes, &V)> {
+        let (_, key) = self
+            .order
+            .range((
+                Bound::Included((rng.next_u64(), Bytes::new())),
+                Bound::Unbounded,
+            ))
+            .next()
+            .or_else(|| self.order.first())?;
+        Some((key, &self.map[key]))
+    }
+
+    /// Entries from scan position `cursor` on, in scan order, each with its
+    /// position
+    pub fn scan_from(&self, cursor: u64) -> impl Iterator<Item = (u64, &Bytes, &V)> {
+        self.order
+            .range((Bound::Included((cursor, Bytes::new())), Bound::Unbounded))
+            .map(|(position, key)| (*position, key, &self.map[key]))
+    }
+}
+
+impl<V, K: Borrow<[u8]> + ?Sized> Index<&K> for Dict<V> {
+    type Output = V;
+
+    fn index(&self, key: &K) -> &V {
+        &self.map[key.borrow()]
+    }
+}
+
+impl<V> FromIterator<(Bytes, V)> for Dict<V> {
+    fn from_iter<I: IntoIterator<Item = (Bytes, V)>>(iter: I) -> Self {
+        let mut dict = Self::default();
+        for (key, value) in iter {
+            dict.insert(key, value);
+        }
+        dict
+    }
+}
+
+/// Set of byte strings, indexed by scan position like [`Dict`]
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Set(Dict<()>);
+
+impl Set {
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn contains(&self, member: &[u8]) -> bool {
+        self.0.contains_key(member)
+    }
+
+    /// Adds `member`, returning whether it was missing
+    pub fn insert(&mut self, member: Bytes) -> bool {
+        self.0.insert(member, ()).is_none()
+    }
+
+    /// Removes `member`, returning whether it was present
+    pub fn remove(&mut self, member: &[u8]) -> bool {
+        self.0.remove(member).is_some()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Bytes> {
+        self.0.keys()
+    }
+
+    /// Random member as picked by [`Dict::random`], `None` if there are none
+    pub fn random(&self, rng: &mut Rng) -> Option<&Bytes> {
+        self.0.random(rng).map(|(member, ())| member)
+    }
+
+    /// Members from scan position `cursor` on, in scan order, each with its
+    /// position
+    pub fn scan_from(&self, cursor: u64) -> impl Iterator<Item = (u64, &Bytes)> {
+        self.0
+            .scan_from(cursor)
+            .map(|(position, member, ())| (position, member))
+    }
+}
+
+impl FromIterator<Bytes> for Set {
+    fn from_iter<I: IntoIterator<Item = Bytes>>(iter: I) -> Self {
+        Self(iter.into_iter().map(|member| (member, ())).collect())
+    }
+}
+
+#[cfg(test)]
+mod dict_tests {
+    use super::*;
+
+    #[test]
+    fn test_index_follows_the_map() {
+        let mut dict: Dict<u32> = (0..100).map(|i| (Bytes::from(i.to_string()), i)).collect();
+        assert_eq!(dict.insert("7".into(), 70), Some(7));
+        assert_eq!(dict.remove(b"8".as_slice()), Some(8));
+        assert_eq!(dict.remove(b"8".as_slice()), None);
+        *dict.get_or_insert_with("100".into(), || 0) += 1;
+
+        let scanned: Vec<_> = dict.scan_from(0).collect();
+        assert_eq!(scanned.len(), dict.len());
+        assert!(scanned.is_sorted_by_key(|(position, ..)| *position));
+        for (position, key, value) in scanned {
+            assert_eq!(position, super::position(key));
+            assert_eq!(dict.get(key), Some(value));
+        }
+
+        // Resuming from a position skips everything before it
+        let (middle, ..) = dict.scan_from(0).nth(50).unwrap();
+        assert_eq!(dict.scan_from(middle).count(), dict.len() - 50);
+    }
+}
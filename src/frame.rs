@@ -1,27 +1,93 @@
 #![allow(dead_code)]
 
+use crate::db::PROTO_MAX_BULK_LEN;
 use bytes::{Bytes, BytesMut};
-use memchr::memchr;
+use memchr::{memchr, memchr_iter};
 use std::str::from_utf8;
 use tokio_util::codec::{Decoder, Encoder};
 
-const MAX: usize = 8 * 1024 * 1024; // 8 MiB
+/// Largest frame a reply is encoded into whole, larger ones being bulk strings
+/// or arrays written out a piece at a time
+pub(crate) const MAX: usize = 8 * 1024 * 1024; // 8 MiB
 
-pub struct Frame;
+/// Arrays nested deeper than this are rejected rather than risking the stack
+const MAX_DEPTH: usize = 512;
+
+/// Most elements an array may declare unless the codec is built with
+/// [`Frame::with_max_array_len`]
+pub const MAX_ARRAY_LEN: usize = 1024 * 1024;
+
+/// Most elements reserved for up front, larger arrays growing as they are read
+const RESERVE_CHUNK: usize = 4096;
+
+/// RESP codec
+///
+/// Lines must end in `\r\n` unless the codec is built with [`Frame::lenient`].
+/// Arrays, pushes and attributes declaring more than [`MAX_ARRAY_LEN`] elements,
+/// and bulk strings declaring more than [`PROTO_MAX_BULK_LEN`] bytes, are
+/// rejected as soon as their header is read.
+#[derive(Clone, Copy, Debug)]
+pub struct Frame {
+    newlines: Newlines,
+    max_array_len: usize,
+    max_bulk_len: usize,
+}
+
+/// Line terminators the decoder accepts
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+enum Newlines {
+    /// Only `\r\n`
+    #[default]
+    Strict,
+    /// `\r\n` or a bare `\n`, for hand-rolled clients that leave out the `\r`
+    Lenient,
+}
+
+impl Default for Frame {
+    fn default() -> Self {
+        Self {
+            newlines: Newlines::default(),
+            max_array_len: MAX_ARRAY_LEN,
+            max_bulk_len: PROTO_MAX_BULK_LEN,
+        }
+    }
+}
+
+impl Frame {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Codec that also accepts lines terminated by a bare `\n`
+    pub fn lenient() -> Self {
+        Self {
+            newlines: Newlines::Lenient,
+            ..Self::default()
+        }
+    }
+
+    /// Replaces the most elements a decoded array may declare
+    pub fn with_max_array_len(self, max_array_len: usize) -> Self {
+        Self {
+            max_array_len,
+            ..self
+        }
+    }
+
+    /// Replaces the most bytes a decoded bulk string may declare
+    pub fn with_max_bulk_len(self, max_bulk_len: usize) -> Self {
+        Self {
+            max_bulk_len,
+            ..self
+        }
+    }
+}
 
 impl Encoder<FrameValue> for Frame {
     type Error = FrameError;
 
     fn encode(&mut self, item: FrameValue, dst: &mut BytesMut) -> Result<(), Self::Error> {
         let len = item.len();
-
-        if len > MAX {
-            return Err(FrameError::IOError(std::io::Error::new(
-                std::io::ErrorKind::InvalidData,
-                format!("FrameValue of length {} is too large.", len),
-            )));
-        }
-
         dst.reserve(len);
         item.value(dst);
 
@@ -29,6 +95,14 @@ impl Encoder<FrameValue> for Frame {
     }
 }
 
+/// Error for a frame too large to encode whole, over [`MAX`] bytes
+pub(crate) fn too_large(len: usize) -> FrameError {
+    FrameError::IOError(std::io::Error::new(
+        std::io::ErrorKind::InvalidData,
+        format!("FrameValue of length {} is too large.", len),
+    ))
+}
+
 impl Decoder for Frame {
     type Item = FrameValue;
     type Error = FrameError;
@@ -38,7 +112,7 @@ impl Decoder for Frame {
             return Ok(None);
         }
 
-        match FrameBufSlice::parse(src, 0)? {
+        match FrameBufSlice::parse(src, 0, 0, *self)? {
             Some((pos, buf_slice)) => {
                 let framable_data = src.split_to(pos);
                 Ok(Some(buf_slice.value(&framable_data.freeze())))
@@ -46,10 +120,20 @@ impl Decoder for Frame {
             None => Ok(None),
         }
     }
+
+    /// Like `decode`, but for when no more bytes will arrive, so a
+    /// partial frame left in `src` is an error rather than a reason to wait
+    fn decode_eof(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        match self.decode(src)? {
+            Some(frame) => Ok(Some(frame)),
+            None if src.is_empty() => Ok(None),
+            None => Err(FrameError::UnexpectedEnd),
+        }
+    }
 }
 
 /// Actual data types for frame
-#[derive(Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq)]
 pub enum FrameValue {
     SimpleString(Bytes),
     BulkString(Bytes),
@@ -58,9 +142,57 @@ pub enum FrameValue {
     Array(Vec<FrameValue>),
     NullBulkString,
     NullBulkArray,
+    /// RESP3 null, which replaces both RESP2 nulls
+    Null,
+    /// RESP3 metadata about `value` that clients may ignore
+    Attribute {
+        attrs: Vec<(FrameValue, FrameValue)>,
+        value: Box<FrameValue>,
+    },
+    /// RESP3 out of band data, such as invalidation messages, that isn't a
+    /// reply to any command
+    Push(Vec<FrameValue>),
 }
 
 impl FrameValue {
+    /// Adapts the frame to the protocol version a client negotiated
+    ///
+    /// Replies are built with RESP2 nulls, which RESP3 clients get as [`Null`]
+    /// instead. This is the one place the choice is made, so every null reply
+    /// agrees with the connection's protocol.
+    ///
+    /// [`Null`]: FrameValue::Null
+    pub fn for_protocol(self, protocol: u8) -> Self {
+        match self {
+            Self::NullBulkString | Self::NullBulkArray if protocol >= 3 => Self::Null,
+            Self::Array(frames) if protocol >= 3 => Self::Array(
+                frames
+                    .into_iter()
+                    .map(|frame| frame.for_protocol(protocol))
+                    .collect(),
+            ),
+            // RESP2 has no push type, so pushes go as the arrays Redis sends then
+            Self::Push(frames) => {
+                let frames = frames
+                    .into_iter()
+                    .map(|frame| frame.for_protocol(protocol))
+                    .collect();
+                if protocol >= 3 {
+                    Self::Push(frames)
+                } else {
+                    Self::Array(frames)
+                }
+            }
+            // RESP2 has no way to send attributes, so only the value goes
+            Self::Attribute { value, .. } if protocol < 3 => value.for_protocol(protocol),
+            Self::Attribute { attrs, value } => Self::Attribute {
+                attrs,
+                value: Box::new(value.for_protocol(protocol)),
+            },
+            frame => frame,
+        }
+    }
+
     fn value(self, dst: &mut BytesMut) {
         match self {
             Self::SimpleString(bytes) => {
@@ -91,6 +223,9 @@ impl FrameValue {
             Self::NullBulkArray => {
                 dst.extend_from_slice(b"*-1\r\n");
             }
+            Self::Null => {
+                dst.extend_from_slice(b"_\r\n");
+            }
             Self::Array(frames) => {
                 dst.extend_from_slice(b"*");
                 dst.extend_from_slice(frames.len().to_string().as_bytes());
@@ -99,10 +234,48 @@ impl FrameValue {
                     frame.value(dst);
                 });
             }
+            Self::Push(frames) => {
+                dst.extend_from_slice(b">");
+                dst.extend_from_slice(frames.len().to_string().as_bytes());
+                dst.extend_from_slice(b"\r\n");
+                frames.into_iter().for_each(|frame| {
+                    frame.value(dst);
+                });
+            }
+            Self::Attribute { attrs, value } => {
+                dst.extend_from_slice(b"|");
+                dst.extend_from_slice(attrs.len().to_string().as_bytes());
+                dst.extend_from_slice(b"\r\n");
+                attrs.into_iter().for_each(|(key, value)| {
+                    key.value(dst);
+                    value.value(dst);
+                });
+                value.value(dst);
+            }
+        }
+    }
+
+    /// Byte the encoded frame starts with, which tells its type
+    pub fn type_byte(&self) -> u8 {
+        match self {
+            Self::SimpleString(_) => b'+',
+            Self::BulkString(_) | Self::NullBulkString => b'$',
+            Self::Error(_) => b'-',
+            Self::Integer(_) => b':',
+            Self::Array(_) | Self::NullBulkArray => b'*',
+            Self::Null => b'_',
+            Self::Attribute { .. } => b'|',
+            Self::Push(_) => b'>',
         }
     }
 
-    fn len(&self) -> usize {
+    /// Number of bytes the frame is encoded as
+    ///
+    /// This must match what [`FrameValue::value`] writes exactly, as the encoder
+    /// reserves this much up front and the size limit is checked against it.
+    // No encoded frame is empty, so there is no `is_empty` to go with it
+    #[allow(clippy::len_without_is_empty)]
+    pub fn len(&self) -> usize {
         match self {
             Self::BulkString(bytes) => {
                 let len = bytes.len();
@@ -110,12 +283,22 @@ impl FrameValue {
             }
             Self::SimpleString(bytes) | Self::Error(bytes) => 1 + bytes.len() + 2,
             Self::NullBulkString | Self::NullBulkArray => 5,
+            Self::Null => 3,
             Self::Integer(num) => 1 + int_len(*num) + 2,
-            Self::Array(frames) => {
+            Self::Array(frames) | Self::Push(frames) => {
                 1 + int_len(frames.len() as i64)
                     + 2
                     + frames.iter().map(|frame| frame.len()).sum::<usize>()
             }
+            Self::Attribute { attrs, value } => {
+                1 + int_len(attrs.len() as i64)
+                    + 2
+                    + attrs
+                        .iter()
+                        .map(|(key, value)| key.len() + value.len())
+                        .sum::<usize>()
+                    + value.len()
+            }
         }
     }
 }
@@ -131,6 +314,9 @@ enum FrameBufSlice {
     Integer(i64),
     Array(Vec<FrameBufSlice>),
     NullBulkArray,
+    Null,
+    Attribute(Vec<(FrameBufSlice, FrameBufSlice)>, Box<FrameBufSlice>),
+    Push(Vec<FrameBufSlice>),
 }
 
 impl FrameBufSlice {
@@ -146,46 +332,101 @@ impl FrameBufSlice {
             }
             Self::NullBulkString => FrameValue::NullBulkString,
             Self::NullBulkArray => FrameValue::NullBulkArray,
+            Self::Null => FrameValue::Null,
+            Self::Attribute(attrs, value) => FrameValue::Attribute {
+                attrs: attrs
+                    .into_iter()
+                    .map(|(key, value)| (key.value(buf), value.value(buf)))
+                    .collect(),
+                value: Box::new(value.value(buf)),
+            },
+            Self::Push(frames) => {
+                FrameValue::Push(frames.into_iter().map(|frame| frame.value(buf)).collect())
+            }
         }
     }
 
-    /// Parses into a RESP type
-    fn parse(buf: &BytesMut, pos: usize) -> Result<Option<(usize, Self)>, FrameError> {
+    /// Parses into a RESP type, `depth` being the number of enclosing arrays
+    fn parse(
+        buf: &BytesMut,
+        pos: usize,
+        depth: usize,
+        codec: Frame,
+    ) -> Result<Option<(usize, Self)>, FrameError> {
         if buf.len() <= pos {
             return Ok(None);
         }
 
+        let newlines = codec.newlines;
         match buf[pos] {
-            b'+' => Self::get_simple_string(buf, pos + 1),
-            b'-' => Self::get_error(buf, pos + 1),
-            b':' => Self::get_int(buf, pos + 1),
-            b'$' => Self::get_bulk_string(buf, pos + 1),
-            b'*' => Self::get_array(buf, pos + 1),
+            b'+' => Self::get_simple_string(buf, pos + 1, newlines),
+            b'-' => Self::get_error(buf, pos + 1, newlines),
+            b':' => Self::get_int(buf, pos + 1, newlines),
+            b'$' => Self::get_bulk_string(buf, pos + 1, codec),
+            b'*' => Self::get_array(buf, pos + 1, depth, codec),
+            b'_' => Self::get_null(buf, pos + 1, newlines),
+            b'|' => Self::get_attribute(buf, pos + 1, depth, codec),
+            b'>' => Self::get_push(buf, pos + 1, depth, codec),
             _ => Err(FrameError::UnknownStartingByte),
         }
     }
 
     /// Wraps returned word buffer slice into RESP simple string type
-    fn get_simple_string(buf: &BytesMut, pos: usize) -> Result<Option<(usize, Self)>, FrameError> {
-        Ok(word(buf, pos).map(|(pos, word)| (pos, FrameBufSlice::SimpleString(word))))
+    fn get_simple_string(
+        buf: &BytesMut,
+        pos: usize,
+        newlines: Newlines,
+    ) -> Result<Option<(usize, Self)>, FrameError> {
+        Ok(word(buf, pos, newlines).map(|(pos, word)| (pos, FrameBufSlice::SimpleString(word))))
     }
 
     /// Wraps returned word buffer slice into RESP error type
-    fn get_error(buf: &BytesMut, pos: usize) -> Result<Option<(usize, Self)>, FrameError> {
-        Ok(word(buf, pos).map(|(pos, word)| (pos, FrameBufSlice::Error(word))))
+    fn get_error(
+        buf: &BytesMut,
+        pos: usize,
+        newlines: Newlines,
+    ) -> Result<Option<(usize, Self)>, FrameError> {
+        Ok(word(buf, pos, newlines).map(|(pos, word)| (pos, FrameBufSlice::Error(word))))
     }
 
     /// Wraps returned word buffer slice into RESP integer type
-    fn get_int(buf: &BytesMut, pos: usize) -> Result<Option<(usize, Self)>, FrameError> {
-        Ok(get_int(buf, pos)?.map(|(end, i)| (end, Self::Integer(i))))
+    fn get_int(
+        buf: &BytesMut,
+        pos: usize,
+        newlines: Newlines,
+    ) -> Result<Option<(usize, Self)>, FrameError> {
+        Ok(get_int(buf, pos, newlines)?.map(|(end, i)| (end, Self::Integer(i))))
     }
 
-    fn get_bulk_string(buf: &BytesMut, pos: usize) -> Result<Option<(usize, Self)>, FrameError> {
-        match get_int(buf, pos)? {
+    /// Checks the RESP3 null type has nothing between `_` and the line end
+    fn get_null(
+        buf: &BytesMut,
+        pos: usize,
+        newlines: Newlines,
+    ) -> Result<Option<(usize, Self)>, FrameError> {
+        match word(buf, pos, newlines) {
+            Some((end, word)) if word.as_slice(buf).is_empty() => Ok(Some((end, Self::Null))),
+            Some(_) => Err(FrameError::BadNull),
+            None => Ok(None),
+        }
+    }
+
+    fn get_bulk_string(
+        buf: &BytesMut,
+        pos: usize,
+        codec: Frame,
+    ) -> Result<Option<(usize, Self)>, FrameError> {
+        let newlines = codec.newlines;
+        match get_int(buf, pos, newlines)? {
             Some((end, -1)) => Ok(Some((end, FrameBufSlice::NullBulkString))),
-            Some((end, size)) if size >= 0 => {
+            Some((end, size)) if size >= 0 && size as u64 <= codec.max_bulk_len as u64 => {
                 let end_string_pos = end + size as usize;
-                if end_string_pos + 2 > buf.len() {
+                if newlines == Newlines::Lenient && buf.get(end_string_pos) == Some(&b'\n') {
+                    Ok(Some((
+                        end_string_pos + 1,
+                        FrameBufSlice::BulkString(BufSlice(end, end_string_pos)),
+                    )))
+                } else if end_string_pos + 2 > buf.len() {
                     Ok(None)
                 } else if buf[end_string_pos] == b'\r' && buf[end_string_pos + 1] == b'\n' {
                     Ok(Some((
@@ -201,14 +442,24 @@ impl FrameBufSlice {
         }
     }
 
-    fn get_array(buf: &BytesMut, pos: usize) -> Result<Option<(usize, Self)>, FrameError> {
-        match get_int(buf, pos)? {
+    fn get_array(
+        buf: &BytesMut,
+        pos: usize,
+        depth: usize,
+        codec: Frame,
+    ) -> Result<Option<(usize, Self)>, FrameError> {
+        if depth >= MAX_DEPTH {
+            return Err(FrameError::NestingTooDeep);
+        }
+
+        match get_int(buf, pos, codec.newlines)? {
             Some((end, -1)) => Ok(Some((end, FrameBufSlice::NullBulkArray))),
-            Some((end, size)) if size >= 0 => {
+            Some((end, size)) if size >= 0 && size as u64 <= codec.max_array_len as u64 => {
                 let mut cur_pos = end;
-                let mut values = Vec::with_capacity(size as usize);
+                let mut values =
+                    Vec::with_capacity(initial_capacity(size as usize, buf.len() - end));
                 for _ in 0..size {
-                    match Self::parse(buf, cur_pos)? {
+                    match Self::parse(buf, cur_pos, depth + 1, codec)? {
                         Some((new_pos, value)) => {
                             cur_pos = new_pos;
                             values.push(value);
@@ -222,6 +473,70 @@ impl FrameBufSlice {
             None => Ok(None),
         }
     }
+
+    /// Parses a push, which is laid out like an array but can't be null
+    fn get_push(
+        buf: &BytesMut,
+        pos: usize,
+        depth: usize,
+        codec: Frame,
+    ) -> Result<Option<(usize, Self)>, FrameError> {
+        match Self::get_array(buf, pos, depth, codec)? {
+            Some((end, FrameBufSlice::Array(values))) => {
+                Ok(Some((end, FrameBufSlice::Push(values))))
+            }
+            Some(_) => Err(FrameError::BadBulkArraySize(-1)),
+            None => Ok(None),
+        }
+    }
+
+    /// Parses `count` key-value pairs followed by the value they describe,
+    /// which all nest one level deeper
+    fn get_attribute(
+        buf: &BytesMut,
+        pos: usize,
+        depth: usize,
+        codec: Frame,
+    ) -> Result<Option<(usize, Self)>, FrameError> {
+        if depth >= MAX_DEPTH {
+            return Err(FrameError::NestingTooDeep);
+        }
+
+        let (mut cur_pos, count) = match get_int(buf, pos, codec.newlines)? {
+            Some((end, count)) if count >= 0 && count as u64 <= codec.max_array_len as u64 => {
+                (end, count)
+            }
+            Some((_end, bad_size)) => return Err(FrameError::BadAttributeSize(bad_size)),
+            None => return Ok(None),
+        };
+        // Each pair takes at least two elements' worth of bytes
+        let mut attrs =
+            Vec::with_capacity(initial_capacity(count as usize, (buf.len() - cur_pos) / 2));
+        for _ in 0..count {
+            let Some((key_end, key)) = Self::parse(buf, cur_pos, depth + 1, codec)? else {
+                return Ok(None);
+            };
+            let Some((value_end, value)) = Self::parse(buf, key_end, depth + 1, codec)? else {
+                return Ok(None);
+            };
+            cur_pos = value_end;
+            attrs.push((key, value));
+        }
+
+        match Self::parse(buf, cur_pos, depth + 1, codec)? {
+            Some((end, value)) => Ok(Some((end, Self::Attribute(attrs, Box::new(value))))),
+            None => Ok(None),
+        }
+    }
+}
+
+/// Capacity to reserve for `declared` elements with `remaining` bytes buffered
+///
+/// The declared size is trusted only as far as the bytes received could back
+/// it, an element taking at least two bytes, and never beyond [`RESERVE_CHUNK`]
+/// so a short header can't make the server allocate gigabytes.
+fn initial_capacity(declared: usize, remaining: usize) -> usize {
+    declared.min(remaining / 2).min(RESERVE_CHUNK)
 }
 
 /// Error types while parsing a buffer for RESP
@@ -233,6 +548,17 @@ pub enum FrameError {
     IOError(std::io::Error),
     BadBulkStringSize(i64),
     BadBulkArraySize(i64),
+    BadAttributeSize(i64),
+    NestingTooDeep,
+    BadNull,
+}
+
+impl FrameError {
+    /// Whether the peer sent bytes that aren't valid RESP, as opposed to the
+    /// underlying stream failing
+    pub fn is_protocol_error(&self) -> bool {
+        !matches!(self, Self::IOError(_))
+    }
 }
 
 impl From<std::io::Error> for FrameError {
@@ -259,27 +585,38 @@ impl BufSlice {
 /// Get a word from `buf` starting at `pos`
 ///
 /// Returns `None` if valid word is not found.
-fn word(buf: &BytesMut, pos: usize) -> Option<(usize, BufSlice)> {
+fn word(buf: &BytesMut, pos: usize, newlines: Newlines) -> Option<(usize, BufSlice)> {
     // Reached the end of buffer, so can't make a word
     if buf.len() <= pos {
         return None;
     }
 
-    // Find position of b'\r'
-    // memchr is fast
-    memchr(b'\r', &buf[pos..]).and_then(|end| {
-        // Ensure that buffer has b'\n'
-        if pos + end + 1 < buf.len() && buf[pos + end + 1] == b'\n' {
-            Some((pos + end + 2, BufSlice(pos, pos + end)))
-        } else {
-            // Received till b'\r' from client, the next byte b'\n' was never received
-            None
-        }
-    })
+    if newlines == Newlines::Lenient {
+        // The line ends at the first b'\n', dropping a b'\r' right before it
+        return memchr(b'\n', &buf[pos..]).map(|end| {
+            let word_end = if end > 0 && buf[pos + end - 1] == b'\r' {
+                pos + end - 1
+            } else {
+                pos + end
+            };
+            (pos + end + 1, BufSlice(pos, word_end))
+        });
+    }
+
+    // A b'\r' not followed by b'\n' is part of the word, so the search resumes
+    // right after it. One at the end of the buffer may still get its b'\n'.
+    let line = &buf[pos..];
+    memchr_iter(b'\r', line)
+        .find(|&end| line.get(end + 1) == Some(&b'\n'))
+        .map(|end| (pos + end + 2, BufSlice(pos, pos + end)))
 }
 
-fn get_int(buf: &BytesMut, pos: usize) -> Result<Option<(usize, i64)>, FrameError> {
-    match word(buf, pos) {
+fn get_int(
+    buf: &BytesMut,
+    pos: usize,
+    newlines: Newlines,
+) -> Result<Option<(usize, i64)>, FrameError> {
+    match word(buf, pos, newlines) {
         Some((end, buf_slice)) => {
             let i = from_utf8(buf_slice.as_slice(buf))
                 .map_err(|_| FrameError::IntParseFailure)?
@@ -325,7 +662,7 @@ mod frame_tests {
 
     #[test]
     fn test_simple_string_type() {
-        let mut decoder = Frame;
+        let mut decoder = Frame::new();
 
         let mut buffer = BytesMut::from("+Simple String\r\n");
         let expected_len = buffer.len();
@@ -339,7 +676,7 @@ mod frame_tests {
 
     #[test]
     fn test_error_type() {
-        let mut decoder = Frame;
+        let mut decoder = Frame::new();
 
         let mut buffer = BytesMut::from("-Error\r\n");
         let expected_len = buffer.len();
@@ -353,7 +690,7 @@ mod frame_tests {
 
     #[test]
     fn test_integer_type() {
-        let mut decoder = Frame;
+        let mut decoder = Frame::new();
 
         let mut buffer = BytesMut::from(":1334\r\n");
         let expected_len = buffer.len();
@@ -365,9 +702,101 @@ mod frame_tests {
         assert_eq!(result, FrameValue::Integer(1334));
     }
 
+    #[test]
+    fn test_null_type() {
+        let mut decoder = Frame::new();
+
+        let mut buffer = BytesMut::from("_\r\n");
+        let result = decoder.decode(&mut buffer).unwrap().unwrap();
+        assert_eq!(result.len(), 3);
+        assert_eq!(result, FrameValue::Null);
+
+        let mut buffer = BytesMut::from("_x\r\n");
+        assert!(decoder.decode(&mut buffer).is_err());
+    }
+
+    #[test]
+    fn test_nulls_for_protocol() {
+        let reply = FrameValue::Array(vec![
+            FrameValue::NullBulkArray,
+            FrameValue::Array(vec![FrameValue::NullBulkString]),
+        ]);
+
+        assert_eq!(reply.clone().for_protocol(2), reply);
+        assert_eq!(
+            reply.for_protocol(3),
+            FrameValue::Array(vec![
+                FrameValue::Null,
+                FrameValue::Array(vec![FrameValue::Null]),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_attribute_type() {
+        let mut buffer = BytesMut::from("|1\r\n+key-popularity\r\n:42\r\n$5\r\nHello\r\n");
+        let wire = buffer.clone();
+        let expected = FrameValue::Attribute {
+            attrs: vec![(
+                FrameValue::SimpleString("key-popularity".into()),
+                FrameValue::Integer(42),
+            )],
+            value: Box::new(FrameValue::BulkString("Hello".into())),
+        };
+
+        let result = Frame::new().decode(&mut buffer).unwrap().unwrap();
+        assert_eq!(result, expected);
+        assert_eq!(expected.len(), wire.len());
+
+        let mut encoded = BytesMut::new();
+        Frame::new().encode(expected.clone(), &mut encoded).unwrap();
+        assert_eq!(encoded, wire);
+
+        // RESP2 clients only get the value
+        assert_eq!(
+            expected.for_protocol(2),
+            FrameValue::BulkString("Hello".into())
+        );
+        assert!(matches!(
+            Frame::new().decode(&mut BytesMut::from("|-1\r\n+OK\r\n")),
+            Err(FrameError::BadAttributeSize(-1))
+        ));
+    }
+
+    #[test]
+    fn test_push_type() {
+        let mut buffer = BytesMut::from(">2\r\n$10\r\ninvalidate\r\n*1\r\n$3\r\nkey\r\n");
+        let wire = buffer.clone();
+        let expected = FrameValue::Push(vec![
+            FrameValue::BulkString("invalidate".into()),
+            FrameValue::Array(vec![FrameValue::BulkString("key".into())]),
+        ]);
+
+        let result = Frame::new().decode(&mut buffer).unwrap().unwrap();
+        assert_eq!(result, expected);
+        assert_eq!(expected.len(), wire.len());
+
+        let mut encoded = BytesMut::new();
+        Frame::new().encode(expected.clone(), &mut encoded).unwrap();
+        assert_eq!(encoded, wire);
+
+        // RESP2 clients get an ordinary array
+        assert_eq!(
+            expected.for_protocol(2),
+            FrameValue::Array(vec![
+                FrameValue::BulkString("invalidate".into()),
+                FrameValue::Array(vec![FrameValue::BulkString("key".into())]),
+            ])
+        );
+        assert!(matches!(
+            Frame::new().decode(&mut BytesMut::from(">-1\r\n")),
+            Err(FrameError::BadBulkArraySize(-1))
+        ));
+    }
+
     #[test]
     fn test_bulk_string_type() {
-        let mut decoder = Frame;
+        let mut decoder = Frame::new();
 
         let mut buffer = BytesMut::from("$5\r\nHello\r\n");
         let expected_len = buffer.len();
@@ -381,7 +810,7 @@ mod frame_tests {
 
     #[test]
     fn test_array_type() {
-        let mut decoder = Frame;
+        let mut decoder = Frame::new();
 
         let mut buffer = BytesMut::from("*2\r\n*3\r\n:1\r\n:2\r\n:3\r\n*2\r\n+Hello\r\n-World\r\n");
         let expected_len = buffer.len();
@@ -407,7 +836,7 @@ mod frame_tests {
 
     #[test]
     fn test_encoder() {
-        let mut encoder = Frame;
+        let mut encoder = Frame::new();
 
         let frame = FrameValue::Array(vec![
             FrameValue::Array(vec![
@@ -427,4 +856,427 @@ mod frame_tests {
         let val = b"*2\r\n*3\r\n:1\r\n:2\r\n:3\r\n*2\r\n+Hello\r\n-World\r\n";
         assert_eq!(buffer.as_ref(), val);
     }
+
+    #[test]
+    fn test_empty_bulk_string() {
+        let mut buffer = BytesMut::from("$0\r\n\r\n");
+        let frame = Frame::new().decode(&mut buffer).unwrap().unwrap();
+
+        assert_eq!(frame, FrameValue::BulkString(Bytes::new()));
+        assert_eq!(frame.len(), 6);
+        assert!(buffer.is_empty());
+
+        Frame::new().encode(frame, &mut buffer).unwrap();
+        assert_eq!(buffer.as_ref(), b"$0\r\n\r\n");
+
+        // The terminator of an empty bulk string may not have arrived yet
+        let mut buffer = BytesMut::from("$0\r\n\r");
+        assert_eq!(Frame::new().decode(&mut buffer).unwrap(), None);
+    }
+
+    #[test]
+    fn test_empty_array() {
+        let mut buffer = BytesMut::from("*0\r\n");
+        let frame = Frame::new().decode(&mut buffer).unwrap().unwrap();
+
+        assert_eq!(frame, FrameValue::Array(vec![]));
+        assert_eq!(frame.len(), 4);
+        assert!(buffer.is_empty());
+
+        Frame::new().encode(frame, &mut buffer).unwrap();
+        assert_eq!(buffer.as_ref(), b"*0\r\n");
+    }
+
+    #[test]
+    fn test_reservation_is_bounded() {
+        let declared = i32::MAX as usize;
+        assert_eq!(initial_capacity(declared, 13), 6);
+        assert_eq!(initial_capacity(declared, 1 << 30), RESERVE_CHUNK);
+        assert_eq!(initial_capacity(3, 1 << 30), 3);
+
+        // Arrays longer than a chunk still decode whole
+        let len = RESERVE_CHUNK * 3 + 1;
+        let mut buffer = BytesMut::from(format!("*{len}\r\n{}", ":1\r\n".repeat(len)).as_str());
+        assert_eq!(
+            Frame::new().decode(&mut buffer).unwrap(),
+            Some(FrameValue::Array(vec![FrameValue::Integer(1); len]))
+        );
+    }
+
+    #[test]
+    fn test_declared_array_len_is_capped() {
+        // Rejected from the header alone, before any element arrives
+        assert!(matches!(
+            Frame::new().decode(&mut BytesMut::from("*2000000000\r\n")),
+            Err(FrameError::BadBulkArraySize(2_000_000_000))
+        ));
+        assert!(matches!(
+            Frame::new().decode(&mut BytesMut::from("|2000000000\r\n")),
+            Err(FrameError::BadAttributeSize(2_000_000_000))
+        ));
+
+        let mut codec = Frame::new().with_max_array_len(2);
+        let mut buffer = BytesMut::from("*2\r\n:1\r\n:2\r\n*3\r\n");
+        assert_eq!(
+            codec.decode(&mut buffer).unwrap(),
+            Some(FrameValue::Array(vec![
+                FrameValue::Integer(1),
+                FrameValue::Integer(2),
+            ]))
+        );
+        assert!(matches!(
+            codec.decode(&mut buffer),
+            Err(FrameError::BadBulkArraySize(3))
+        ));
+    }
+
+    #[test]
+    fn test_declared_bulk_len_is_capped() {
+        // Rejected from the header alone, so the buffer never grows to fit it
+        assert!(matches!(
+            Frame::new().decode(&mut BytesMut::from("$9999999999\r\n")),
+            Err(FrameError::BadBulkStringSize(9_999_999_999))
+        ));
+
+        let mut codec = Frame::new().with_max_bulk_len(3);
+        let mut buffer = BytesMut::from("$3\r\nabc\r\n$4\r\n");
+        assert_eq!(
+            codec.decode(&mut buffer).unwrap(),
+            Some(FrameValue::BulkString("abc".into()))
+        );
+        assert!(matches!(
+            codec.decode(&mut buffer),
+            Err(FrameError::BadBulkStringSize(4))
+        ));
+    }
+
+    #[test]
+    fn test_strict_newlines() {
+        let mut buffer = BytesMut::from("+OK\n");
+        assert_eq!(Frame::new().decode(&mut buffer).unwrap(), None);
+        assert_eq!(buffer.len(), 4);
+    }
+
+    #[test]
+    fn test_lenient_newlines() {
+        let mut decoder = Frame::lenient();
+
+        let mut buffer = BytesMut::from("+OK\n:-5\n+CRLF\r\n$5\nHello\n*2\n$1\na\r\n:1\n");
+        assert_eq!(
+            decoder.decode(&mut buffer).unwrap(),
+            Some(FrameValue::SimpleString("OK".into()))
+        );
+        assert_eq!(
+            decoder.decode(&mut buffer).unwrap(),
+            Some(FrameValue::Integer(-5))
+        );
+        assert_eq!(
+            decoder.decode(&mut buffer).unwrap(),
+            Some(FrameValue::SimpleString("CRLF".into()))
+        );
+        assert_eq!(
+            decoder.decode(&mut buffer).unwrap(),
+            Some(FrameValue::BulkString("Hello".into()))
+        );
+        assert_eq!(
+            decoder.decode(&mut buffer).unwrap(),
+            Some(FrameValue::Array(vec![
+                FrameValue::BulkString("a".into()),
+                FrameValue::Integer(1),
+            ]))
+        );
+        assert!(buffer.is_empty());
+
+        // A line is only complete once its b'\n' arrives
+        let mut buffer = BytesMut::from("+OK\r");
+        assert_eq!(decoder.decode(&mut buffer).unwrap(), None);
+    }
+
+    #[test]
+    fn test_lone_carriage_returns() {
+        let mut buffer = BytesMut::from("+a\rb\r\r\n:1\r\n");
+        assert_eq!(
+            Frame::new().decode(&mut buffer).unwrap(),
+            Some(FrameValue::SimpleString("a\rb\r".into()))
+        );
+        assert_eq!(
+            Frame::new().decode(&mut buffer).unwrap(),
+            Some(FrameValue::Integer(1))
+        );
+
+        // The line isn't over until a b'\r' is followed by b'\n'
+        for partial in ["+a\rb", "+a\rb\r"] {
+            let mut buffer = BytesMut::from(partial);
+            assert_eq!(
+                Frame::new().decode(&mut buffer).unwrap(),
+                None,
+                "{partial:?}"
+            );
+        }
+
+        let mut buffer = BytesMut::from(":1\r2\r\n");
+        assert!(matches!(
+            Frame::new().decode(&mut buffer),
+            Err(FrameError::IntParseFailure)
+        ));
+    }
+
+    /// Decodes a 1 MiB simple string with a lone b'\r' every other byte, next to
+    /// one without any, to check the scan stays linear.
+    ///
+    /// Run with `cargo test --release -- --ignored --nocapture bench_`.
+    #[test]
+    #[ignore]
+    fn bench_word_with_lone_carriage_returns() {
+        const LEN: usize = 1 << 20;
+
+        let measure = |body: &[u8]| {
+            let mut frame = Vec::with_capacity(LEN + 3);
+            frame.push(b'+');
+            frame.extend_from_slice(body);
+            frame.extend_from_slice(b"\r\n");
+            let mut buffer = BytesMut::from(&frame[..]);
+
+            let start = std::time::Instant::now();
+            let decoded = Frame::new().decode(&mut buffer).unwrap();
+            let elapsed = start.elapsed();
+            assert!(matches!(decoded, Some(FrameValue::SimpleString(s)) if s.len() == LEN));
+            elapsed
+        };
+
+        let plain = measure(&vec![b'a'; LEN]);
+        let carriage_returns = measure(&b"a\r".repeat(LEN / 2));
+        println!("1 MiB word: plain {plain:?}, lone b'\\r' every other byte {carriage_returns:?}");
+    }
+
+    #[test]
+    fn test_truncated_frame_at_eof() {
+        let mut buffer = BytesMut::from("$5\r\nHel");
+        assert_eq!(Frame::new().decode(&mut buffer).unwrap(), None);
+        assert!(matches!(
+            Frame::new().decode_eof(&mut buffer),
+            Err(FrameError::UnexpectedEnd)
+        ));
+
+        let mut buffer = BytesMut::from("$5\r\nHello\r\n");
+        assert_eq!(
+            Frame::new().decode_eof(&mut buffer).unwrap(),
+            Some(FrameValue::BulkString("Hello".into()))
+        );
+        assert_eq!(Frame::new().decode_eof(&mut buffer).unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_framed_read_to_eof() {
+        use futures_core::Stream;
+        use std::{future::poll_fn, pin::Pin};
+        use tokio_util::codec::FramedRead;
+
+        let stream: &[u8] = b"$5\r\nHello\r\n$5\r\nHel";
+        let mut frames = FramedRead::new(stream, Frame::new());
+
+        let first = poll_fn(|cx| Pin::new(&mut frames).poll_next(cx)).await;
+        assert_eq!(
+            first.unwrap().unwrap(),
+            FrameValue::BulkString("Hello".into())
+        );
+        let second = poll_fn(|cx| Pin::new(&mut frames).poll_next(cx)).await;
+        assert!(matches!(second.unwrap(), Err(FrameError::UnexpectedEnd)));
+    }
+
+    #[test]
+    fn test_deeply_nested_arrays() {
+        let mut buffer = BytesMut::from("*1\r\n".repeat(100_000).as_str());
+        assert!(matches!(
+            Frame::new().decode(&mut buffer),
+            Err(FrameError::NestingTooDeep)
+        ));
+
+        let mut buffer = BytesMut::from("*1\r\n".repeat(MAX_DEPTH - 1).as_str());
+        buffer.extend_from_slice(b"*0\r\n");
+        assert!(Frame::new().decode(&mut buffer).unwrap().is_some());
+    }
+
+    #[test]
+    fn test_huge_declared_array_size() {
+        // Lifting the cap must still not reserve room for every declared element
+        let mut codec = Frame::new().with_max_array_len(usize::MAX);
+        let mut buffer = BytesMut::from(format!("*{}\r\n:1\r\n", i64::MAX).as_str());
+        assert_eq!(codec.decode(&mut buffer).unwrap(), None);
+    }
+}
+
+/// Randomised tests standing in for a fuzzer, driven by a fixed seed so that
+/// failures reproduce
+#[cfg(test)]
+mod frame_fuzz_tests {
+    use super::*;
+
+    /// Inputs the mutations start from, taken from the tests above
+    const SEED_CORPUS: &[&[u8]] = &[
+        b"+Simple String\r\n",
+        b"-Error\r\n",
+        b":1334\r\n",
+        b"$5\r\nHello\r\n",
+        b"$-1\r\n",
+        b"*-1\r\n",
+        b"*2\r\n*3\r\n:1\r\n:2\r\n:3\r\n*2\r\n+Hello\r\n-World\r\n",
+    ];
+
+    /// Bytes that steer random input towards something the decoder recognises
+    const ALPHABET: &[u8] = b"+-:$*\r\n0123456789-";
+
+    /// xorshift64*, good enough to explore inputs and needs no dependencies
+    struct Rng(u64);
+
+    impl Rng {
+        fn new(seed: u64) -> Self {
+            Self(seed.max(1))
+        }
+
+        fn next(&mut self) -> u64 {
+            self.0 ^= self.0 >> 12;
+            self.0 ^= self.0 << 25;
+            self.0 ^= self.0 >> 27;
+            self.0.wrapping_mul(0x2545_F491_4F6C_DD1D)
+        }
+
+        /// Uniform-ish value in `0..n`
+        fn below(&mut self, n: usize) -> usize {
+            (self.next() % n as u64) as usize
+        }
+
+        fn byte(&mut self) -> u8 {
+            if self.below(4) == 0 {
+                self.next() as u8
+            } else {
+                ALPHABET[self.below(ALPHABET.len())]
+            }
+        }
+
+        fn bytes(&mut self, max_len: usize) -> Vec<u8> {
+            let len = self.below(max_len + 1);
+            (0..len).map(|_| self.next() as u8).collect()
+        }
+    }
+
+    /// Arbitrary frame, nesting arrays at most `depth` levels deep
+    fn arbitrary_frame(rng: &mut Rng, depth: usize) -> FrameValue {
+        // Simple strings and errors can't contain line breaks
+        let line = |rng: &mut Rng| -> Bytes {
+            let mut bytes = rng.bytes(16);
+            bytes.retain(|&b| b != b'\r' && b != b'\n');
+            bytes.into()
+        };
+
+        match rng.below(if depth == 0 { 7 } else { 10 }) {
+            0 => FrameValue::SimpleString(line(rng)),
+            1 => FrameValue::Error(line(rng)),
+            2 => FrameValue::Integer(match rng.below(3) {
+                0 => rng.next() as i64,
+                1 => rng.below(100) as i64 - 50,
+                _ => [i64::MIN, i64::MAX, 0, -1][rng.below(4)],
+            }),
+            3 => FrameValue::BulkString(rng.bytes(32).into()),
+            4 => FrameValue::NullBulkString,
+            5 => FrameValue::NullBulkArray,
+            6 => FrameValue::Null,
+            7 => {
+                let len = rng.below(5);
+                FrameValue::Array((0..len).map(|_| arbitrary_frame(rng, depth - 1)).collect())
+            }
+            8 => {
+                let len = rng.below(5);
+                FrameValue::Push((0..len).map(|_| arbitrary_frame(rng, depth - 1)).collect())
+            }
+            _ => {
+                let len = rng.below(3);
+                FrameValue::Attribute {
+                    attrs: (0..len)
+                        .map(|_| {
+                            (
+                                arbitrary_frame(rng, depth - 1),
+                                arbitrary_frame(rng, depth - 1),
+                            )
+                        })
+                        .collect(),
+                    value: Box::new(arbitrary_frame(rng, depth - 1)),
+                }
+            }
+        }
+    }
+
+    /// Applies a few random edits to `input`
+    fn mutate(rng: &mut Rng, input: &[u8]) -> Vec<u8> {
+        let mut input = input.to_vec();
+        for _ in 0..rng.below(4) + 1 {
+            let pos = rng.below(input.len() + 1);
+            match rng.below(4) {
+                0 if pos < input.len() => input[pos] = rng.byte(),
+                1 if pos < input.len() => {
+                    input.remove(pos);
+                }
+                2 => input.truncate(pos),
+                _ => input.insert(pos, rng.byte()),
+            }
+        }
+        input
+    }
+
+    /// Decodes everything in `input` the way a connection would, checking the
+    /// decoder makes progress whenever it returns a frame
+    fn decode_all(input: &[u8]) {
+        let mut buffer = BytesMut::from(input);
+        loop {
+            let before = buffer.len();
+            match Frame::new().decode(&mut buffer) {
+                Ok(Some(_)) => assert!(buffer.len() < before, "no progress on {input:?}"),
+                Ok(None) | Err(_) => break,
+            }
+        }
+    }
+
+    #[test]
+    fn test_decode_never_panics() {
+        let mut rng = Rng::new(0x5EED);
+
+        for _ in 0..20_000 {
+            let input = match rng.below(3) {
+                0 => (0..rng.below(64)).map(|_| rng.byte()).collect(),
+                _ => {
+                    let seed = SEED_CORPUS[rng.below(SEED_CORPUS.len())];
+                    mutate(&mut rng, seed)
+                }
+            };
+            decode_all(&input);
+        }
+    }
+
+    #[test]
+    fn test_encode_decode_round_trip() {
+        let mut rng = Rng::new(0xF4A3E);
+
+        for _ in 0..5_000 {
+            let frame = arbitrary_frame(&mut rng, 4);
+            let mut buffer = BytesMut::new();
+            Frame::new().encode(frame.clone(), &mut buffer).unwrap();
+
+            assert_eq!(Frame::new().decode(&mut buffer).unwrap(), Some(frame));
+            assert!(buffer.is_empty());
+        }
+    }
+
+    #[test]
+    fn test_len_matches_encoding() {
+        let mut rng = Rng::new(0x1E4);
+
+        for _ in 0..5_000 {
+            let frame = arbitrary_frame(&mut rng, 4);
+            let len = frame.len();
+            let mut buffer = BytesMut::new();
+            Frame::new().encode(frame.clone(), &mut buffer).unwrap();
+
+            assert_eq!(len, buffer.len(), "{frame:?}");
+        }
+    }
 }
@@ -1,7 +1,26 @@
+// Keyspaces sit behind std mutexes, see `Db::lock`
+#![deny(clippy::await_holding_lock)]
+
+pub mod config;
+pub mod log;
 pub mod server;
 
+mod acl;
+mod aof;
+mod client;
 mod cmd;
 mod connection;
+mod db;
+mod dict;
 mod frame;
+mod pubsub;
+mod random;
+mod rdb;
+mod replica;
+mod replication;
+mod sorted_set;
+
+pub use connection::{FramedConnection, framed};
+pub use frame::{Frame, FrameError, FrameValue};
 
 pub const DEFAULT_PORT: u16 = 7878;
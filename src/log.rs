@@ -0,0 +1,160 @@
+//! Server log, written to stdout or appended to a file
+//!
+//! Lines are logged with the [`log!`](crate::log!) macro. Until [`init`] is
+//! called they go to stdout at [`LogLevel::Notice`].
+
+use crate::config::Config;
+use std::{
+    fmt,
+    fs::OpenOptions,
+    io::{self, Write},
+    sync::{Mutex, OnceLock},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+static LOGGER: OnceLock<Logger> = OnceLock::new();
+
+/// How much gets logged, from most to least verbose, named as in Redis
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum LogLevel {
+    Debug,
+    Verbose,
+    Notice,
+    Warning,
+}
+
+impl LogLevel {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "debug" => Some(Self::Debug),
+            "verbose" => Some(Self::Verbose),
+            "notice" => Some(Self::Notice),
+            "warning" => Some(Self::Warning),
+            _ => None,
+        }
+    }
+
+    /// Character Redis marks lines of this level with
+    fn mark(self) -> char {
+        match self {
+            Self::Debug => '.',
+            Self::Verbose => '-',
+            Self::Notice => '*',
+            Self::Warning => '#',
+        }
+    }
+}
+
+/// Writes lines at or above a level, each flushed as soon as it is written
+pub struct Logger {
+    level: LogLevel,
+    out: Mutex<Box<dyn Write + Send>>,
+}
+
+impl Logger {
+    /// Logs to [`Config::logfile`], appending to it, or to stdout if there is none
+    pub fn open(config: &Config) -> io::Result<Self> {
+        let out: Box<dyn Write + Send> = match &config.logfile {
+            Some(path) => Box::new(OpenOptions::new().create(true).append(true).open(path)?),
+            None => Box::new(io::stdout()),
+        };
+        Ok(Self::with_output(config.loglevel, out))
+    }
+
+    pub fn with_output(level: LogLevel, out: impl Write + Send + 'static) -> Self {
+        Self {
+            level,
+            out: Mutex::new(Box::new(out)),
+        }
+    }
+
+    pub fn log(&self, level: LogLevel, message: fmt::Arguments) {
+        if level < self.level {
+            return;
+        }
+
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        let mut out = self.out.lock().unwrap();
+        // A log that can't be written to has nowhere to report that either
+        let _ = writeln!(
+            out,
+            "{}:M {}.{:03} {} {message}",
+            std::process::id(),
+            now.as_secs(),
+            now.subsec_millis(),
+            level.mark(),
+        )
+        .and_then(|()| out.flush());
+    }
+}
+
+/// Makes `logger` the one every later [`log!`](crate::log!) writes to
+///
+/// Only the first call has any effect, later loggers are handed back.
+pub fn init(logger: Logger) -> Result<(), Logger> {
+    LOGGER.set(logger)
+}
+
+#[doc(hidden)]
+pub fn write(level: LogLevel, message: fmt::Arguments) {
+    LOGGER
+        .get_or_init(|| Logger::with_output(LogLevel::Notice, io::stdout()))
+        .log(level, message);
+}
+
+/// Logs a line at a [`LogLevel`], formatting the rest like `println!`
+///
+/// ```ignore
+/// log!(Warning, "Replication error: {e:?}");
+/// ```
+#[macro_export]
+macro_rules! log {
+    ($level:ident, $($arg:tt)+) => {
+        $crate::log::write($crate::log::LogLevel::$level, format_args!($($arg)+))
+    };
+}
+
+#[cfg(test)]
+mod log_tests {
+    use super::*;
+    use std::{fs, path::PathBuf};
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("mini-redis-{}-{name}", std::process::id()))
+    }
+
+    #[test]
+    fn test_parse_level() {
+        assert_eq!(LogLevel::parse("VERBOSE"), Some(LogLevel::Verbose));
+        assert_eq!(LogLevel::parse("warning"), Some(LogLevel::Warning));
+        assert_eq!(LogLevel::parse("info"), None);
+    }
+
+    #[test]
+    fn test_logs_to_file() {
+        let path = temp_path("logfile.log");
+        let _ = fs::remove_file(&path);
+        let config = Config {
+            logfile: Some(path.clone()),
+            loglevel: LogLevel::Verbose,
+            ..Config::default()
+        };
+
+        let logger = Logger::open(&config).unwrap();
+        logger.log(LogLevel::Debug, format_args!("hidden"));
+        logger.log(LogLevel::Verbose, format_args!("first"));
+        drop(logger);
+        // Reopening appends rather than truncating
+        let logger = Logger::open(&config).unwrap();
+        logger.log(LogLevel::Warning, format_args!("second"));
+
+        let log = fs::read_to_string(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        let lines: Vec<_> = log.lines().collect();
+        assert_eq!(lines.len(), 2, "{log}");
+        assert!(lines[0].ends_with(" - first"), "{log}");
+        assert!(lines[1].ends_with(" # second"), "{log}");
+    }
+}
@@ -0,0 +1,387 @@
+use crate::frame::FrameValue;
+use bytes::Bytes;
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+    },
+    time::{Duration, Instant},
+};
+use tokio::sync::mpsc::{UnboundedReceiver, UnboundedSender, unbounded_channel};
+
+/// Queues of the connections subscribed to a channel, keyed by client id
+type Subscribers = HashMap<u64, Arc<Outbox>>;
+
+/// Channel invalidations are published on for connections tracking keys with
+/// `CLIENT TRACKING ON REDIRECT`, as in Redis
+const INVALIDATE_CHANNEL: &[u8] = b"__redis__:invalidate";
+
+/// Registry of channel subscriptions, shared by every connection
+///
+/// Each subscribed connection owns a queue that published messages are pushed to.
+/// Publishing holds the registry lock while it queues a message for every
+/// subscriber, so all subscribers of a channel see its messages in one order,
+/// which keeps the order each publisher published them in. No message is ever
+/// skipped: a subscriber too slow to keep up is disconnected instead.
+#[derive(Clone, Default)]
+pub struct PubSub {
+    channels: Arc<Mutex<HashMap<Bytes, Subscribers>>>,
+    tracking: Arc<Mutex<Tracking>>,
+}
+
+/// Keys read by connections with `CLIENT TRACKING` on
+///
+/// A key is forgotten once it has been invalidated, so a connection is only told
+/// about it again after reading it again, as in Redis.
+#[derive(Default)]
+struct Tracking {
+    /// Connections that read each key
+    keys: HashMap<Bytes, HashSet<u64>>,
+    clients: HashMap<u64, TrackingClient>,
+}
+
+struct TrackingClient {
+    outbox: Arc<Outbox>,
+    /// Client whose subscription to [`INVALIDATE_CHANNEL`] gets the
+    /// invalidations, `None` to push them to the connection itself
+    redirect: Option<u64>,
+    /// Keys the connection read that haven't been invalidated yet
+    keys: HashSet<Bytes>,
+}
+
+/// Subscriptions held by a single connection
+pub struct Subscriber {
+    id: u64,
+    pubsub: PubSub,
+    channels: HashSet<Bytes>,
+    outbox: Arc<Outbox>,
+    tracking: bool,
+}
+
+/// Bounds on how far a subscriber may fall behind, in bytes of message payload
+/// waiting to be written, like the Redis `client-output-buffer-limit`
+///
+/// A subscriber is disconnected as soon as more than `hard` bytes would be
+/// waiting, or once more than `soft` bytes have been waiting for longer than
+/// `soft_duration`.
+#[derive(Clone, Copy, Debug)]
+pub struct OutputLimits {
+    pub soft: usize,
+    pub hard: usize,
+    pub soft_duration: Duration,
+}
+
+/// Items delivered to a subscribed connection
+#[derive(Clone)]
+pub enum Message {
+    Published {
+        frame: FrameValue,
+        size: usize,
+    },
+    /// A tracked key changed, which only RESP3 can tell apart from a reply
+    Invalidated {
+        frame: FrameValue,
+        size: usize,
+    },
+    /// The subscriber fell too far behind and has been dropped
+    Overflowed,
+}
+
+/// Queue of a subscribed connection along with how far behind it is
+///
+/// The channel itself is unbounded, the limits bound it by bytes instead.
+struct Outbox {
+    sender: UnboundedSender<Message>,
+    limits: OutputLimits,
+    /// Bytes queued but not yet written to the connection
+    queued: AtomicUsize,
+    /// When the queue last went over the soft limit, `None` while it is under
+    over_soft_since: Mutex<Option<Instant>>,
+    overflowed: AtomicBool,
+}
+
+enum Offer {
+    Queued,
+    Skipped,
+    Overflowed,
+}
+
+impl PubSub {
+    /// Creates the subscription state of connection `id`, along with the queue its
+    /// messages are delivered to
+    pub fn subscriber(
+        &self,
+        id: u64,
+        limits: OutputLimits,
+    ) -> (Subscriber, UnboundedReceiver<Message>) {
+        let (sender, receiver) = unbounded_channel();
+        let subscriber = Subscriber {
+            id,
+            pubsub: self.clone(),
+            channels: HashSet::new(),
+            tracking: false,
+            outbox: Arc::new(Outbox {
+                sender,
+                limits,
+                queued: AtomicUsize::new(0),
+                over_soft_since: Mutex::new(None),
+                overflowed: AtomicBool::new(false),
+            }),
+        };
+        (subscriber, receiver)
+    }
+
+    /// Delivers `message` to every subscriber of `channel`, returning how many got it
+    ///
+    /// Subscribers disconnected for going over their output limits don't count.
+    pub fn publish(&self, channel: Bytes, message: Bytes) -> usize {
+        let mut channels = self.channels.lock().unwrap();
+        let Some(subscribers) = channels.get_mut(&channel) else {
+            return 0;
+        };
+
+        let size = channel.len() + message.len();
+        // Sent as an array to RESP2 subscribers when it is written out
+        let frame = FrameValue::Push(vec![
+            FrameValue::BulkString("message".into()),
+            FrameValue::BulkString(channel.clone()),
+            FrameValue::BulkString(message),
+        ]);
+
+        let mut receivers = 0;
+        let message = Message::Published { frame, size };
+        subscribers.retain(|_, outbox| match outbox.offer(&message) {
+            Offer::Queued => {
+                receivers += 1;
+                true
+            }
+            Offer::Skipped => true,
+            Offer::Overflowed => false,
+        });
+        if subscribers.is_empty() {
+            channels.remove(&channel);
+        }
+
+        receivers
+    }
+
+    /// Tells every connection tracking any of `keys` that they were modified,
+    /// with an `invalidate` push per key
+    ///
+    /// Connections tracking with a redirect have it published instead, to the
+    /// redirect client only, as a message on [`INVALIDATE_CHANNEL`]. It is lost
+    /// if that client isn't subscribed to it.
+    pub fn invalidate(&self, keys: &[Bytes]) {
+        let mut tracking = self.tracking.lock().unwrap();
+        for key in keys {
+            let Some(readers) = tracking.keys.remove(key) else {
+                continue;
+            };
+
+            let size = key.len();
+            let invalidated = FrameValue::Array(vec![FrameValue::BulkString(key.clone())]);
+            let pushed = Message::Invalidated {
+                frame: FrameValue::Push(vec![
+                    FrameValue::BulkString("invalidate".into()),
+                    invalidated.clone(),
+                ]),
+                size,
+            };
+            let published = Message::Published {
+                frame: FrameValue::Push(vec![
+                    FrameValue::BulkString("message".into()),
+                    FrameValue::BulkString(Bytes::from_static(INVALIDATE_CHANNEL)),
+                    invalidated,
+                ]),
+                size,
+            };
+            for id in readers {
+                let Some(client) = tracking.clients.get_mut(&id) else {
+                    continue;
+                };
+                client.keys.remove(key);
+                // One that overflows is disconnected, and stops tracking as its
+                // subscriber is dropped
+                match client.redirect {
+                    None => {
+                        client.outbox.offer(&pushed);
+                    }
+                    Some(target) => {
+                        let channels = self.channels.lock().unwrap();
+                        if let Some(outbox) = channels
+                            .get(INVALIDATE_CHANNEL)
+                            .and_then(|subscribers| subscribers.get(&target))
+                        {
+                            outbox.offer(&published);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Channels with at least one subscriber
+    pub fn channels(&self) -> Vec<Bytes> {
+        self.channels.lock().unwrap().keys().cloned().collect()
+    }
+
+    /// Number of connections subscribed to `channel`
+    pub fn numsub(&self, channel: &[u8]) -> usize {
+        self.channels
+            .lock()
+            .unwrap()
+            .get(channel)
+            .map_or(0, HashMap::len)
+    }
+}
+
+impl Outbox {
+    fn offer(&self, message: &Message) -> Offer {
+        let size = match message {
+            Message::Published { size, .. } | Message::Invalidated { size, .. } => *size,
+            Message::Overflowed => 0,
+        };
+        if self.overflowed.load(Ordering::Relaxed) {
+            return Offer::Overflowed;
+        }
+
+        let queued = self.queued.load(Ordering::Relaxed) + size;
+        if queued > self.limits.hard || self.over_soft_limit_too_long(queued) {
+            self.overflowed.store(true, Ordering::Relaxed);
+            let _ = self.sender.send(Message::Overflowed);
+            return Offer::Overflowed;
+        }
+
+        if self.sender.send(message.clone()).is_err() {
+            // The connection is gone and about to unsubscribe
+            return Offer::Skipped;
+        }
+        self.queued.fetch_add(size, Ordering::Relaxed);
+        Offer::Queued
+    }
+
+    /// Whether `queued` bytes are over the soft limit, and the queue has been
+    /// for longer than allowed
+    fn over_soft_limit_too_long(&self, queued: usize) -> bool {
+        let mut since = self.over_soft_since.lock().unwrap();
+        if queued <= self.limits.soft {
+            *since = None;
+            return false;
+        }
+        since.get_or_insert_with(Instant::now).elapsed() > self.limits.soft_duration
+    }
+}
+
+impl Subscriber {
+    /// Subscribes to `channel`, returning the number of channels now subscribed to
+    pub fn subscribe(&mut self, channel: Bytes) -> usize {
+        if self.channels.insert(channel.clone()) {
+            self.pubsub
+                .channels
+                .lock()
+                .unwrap()
+                .entry(channel)
+                .or_default()
+                .insert(self.id, self.outbox.clone());
+        }
+        self.channels.len()
+    }
+
+    /// Unsubscribes from `channel`, returning the number of channels still subscribed to
+    pub fn unsubscribe(&mut self, channel: &Bytes) -> usize {
+        if self.channels.remove(channel) {
+            let mut channels = self.pubsub.channels.lock().unwrap();
+            if let Some(subscribers) = channels.get_mut(channel) {
+                subscribers.remove(&self.id);
+                if subscribers.is_empty() {
+                    channels.remove(channel);
+                }
+            }
+        }
+        self.channels.len()
+    }
+
+    /// Records that a message of `size` bytes was written to the connection
+    pub fn delivered(&self, size: usize) {
+        self.outbox.queued.fetch_sub(size, Ordering::Relaxed);
+    }
+
+    /// Turns `CLIENT TRACKING` on or off, forgetting the keys read so far either way
+    ///
+    /// With a `redirect` client, invalidations go to its subscription to
+    /// [`INVALIDATE_CHANNEL`] rather than to this connection.
+    pub fn set_tracking(&mut self, on: bool, redirect: Option<u64>) {
+        let mut tracking = self.pubsub.tracking.lock().unwrap();
+        if let Some(client) = tracking.clients.remove(&self.id) {
+            forget(&mut tracking.keys, self.id, client.keys);
+        }
+        if on {
+            tracking.clients.insert(
+                self.id,
+                TrackingClient {
+                    outbox: self.outbox.clone(),
+                    redirect,
+                    keys: HashSet::new(),
+                },
+            );
+        }
+        self.tracking = on;
+    }
+
+    pub fn is_tracking(&self) -> bool {
+        self.tracking
+    }
+
+    /// Records that the connection read `keys`, so that it is told once they change
+    pub fn track(&self, keys: Vec<Bytes>) {
+        if !self.tracking {
+            return;
+        }
+
+        let mut tracking = self.pubsub.tracking.lock().unwrap();
+        let tracking = &mut *tracking;
+        let Some(client) = tracking.clients.get_mut(&self.id) else {
+            return;
+        };
+        for key in keys {
+            if client.keys.insert(key.clone()) {
+                tracking.keys.entry(key).or_default().insert(self.id);
+            }
+        }
+    }
+
+    pub fn is_subscribed(&self) -> bool {
+        !self.channels.is_empty()
+    }
+
+    /// Channels currently subscribed to
+    pub fn channels(&self) -> Vec<Bytes> {
+        self.channels.iter().cloned().collect()
+    }
+}
+
+impl Drop for Subscriber {
+    /// Leaves every channel and stops tracking, so a connection that goes away
+    /// for any reason stops being published to
+    fn drop(&mut self) {
+        for channel in self.channels() {
+            self.unsubscribe(&channel);
+        }
+        if self.tracking {
+            self.set_tracking(false, None);
+        }
+    }
+}
+
+/// Drops connection `id` from the readers of `keys`
+fn forget(readers: &mut HashMap<Bytes, HashSet<u64>>, id: u64, keys: HashSet<Bytes>) {
+    for key in keys {
+        if let Some(ids) = readers.get_mut(&key) {
+            ids.remove(&id);
+            if ids.is_empty() {
+                readers.remove(&key);
+            }
+        }
+    }
+}
@@ -0,0 +1,165 @@
+use crate::frame::{FrameValue, MAX_ARRAY_LEN};
+use std::{
+    collections::HashSet,
+    hash::{BuildHasher, RandomState},
+};
+
+/// Most indices a negative count may pick, as each one becomes an element of
+/// the reply however small the collection is
+const MAX_REPEATED: u64 = MAX_ARRAY_LEN as u64;
+
+/// Small xorshift64* generator for the random sampling commands
+///
+/// Not suitable for anything security sensitive, it only needs to be cheap and
+/// reproducible from a seed.
+#[derive(Debug)]
+pub struct Rng(u64);
+
+impl Rng {
+    pub fn seeded(seed: u64) -> Self {
+        // The all zero state would only ever produce zeros
+        Self(seed.max(1))
+    }
+
+    /// Generator seeded from the process' random hasher keys
+    pub fn from_entropy() -> Self {
+        Self::seeded(RandomState::new().hash_one(0u64))
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+
+    /// Uniformish value in `0..n`, `n` must not be 0
+    pub fn below(&mut self, n: usize) -> usize {
+        (self.next_u64() % n as u64) as usize
+    }
+}
+
+/// Picks elements of a collection of `len` elements, which `elements` walks,
+/// following the Redis count semantics of SRANDMEMBER and HRANDFIELD
+///
+/// A positive `count` picks that many distinct elements, or all of them if the
+/// collection is smaller. A negative one picks `-count` elements that may
+/// repeat, and is `None` if that is more than the reply could reasonably hold.
+///
+/// The indices are drawn first, distinct ones with Floyd's algorithm, so
+/// `elements` is walked once and only as far as the last index drawn.
+pub fn sample<T: Clone>(
+    elements: impl Iterator<Item = T>,
+    len: usize,
+    count: i64,
+    rng: &mut Rng,
+) -> Option<Vec<T>> {
+    if count < 0 && count.unsigned_abs() > MAX_REPEATED {
+        return None;
+    }
+    if len == 0 {
+        return Some(vec![]);
+    }
+
+    // Indices to pick, each with where it goes in the result
+    let mut picks: Vec<(usize, usize)> = if count < 0 {
+        (0..count.unsigned_abs() as usize)
+            .map(|slot| (rng.below(len), slot))
+            .collect()
+    } else if count as usize >= len {
+        return Some(elements.take(len).collect());
+    } else {
+        let count = count as usize;
+        let mut picked = HashSet::with_capacity(count);
+        for j in len - count..len {
+            let i = rng.below(j + 1);
+            if !picked.insert(i) {
+                picked.insert(j);
+            }
+        }
+        // Sorted, as the order of the set would differ from run to run
+        let mut picked: Vec<_> = picked.into_iter().collect();
+        picked.sort_unstable();
+        picked
+            .into_iter()
+            .enumerate()
+            .map(|(slot, i)| (i, slot))
+            .collect()
+    };
+    picks.sort_unstable();
+
+    let walked = picks.last().map_or(0, |(i, _)| i + 1);
+    let mut picked = vec![None; picks.len()];
+    let mut picks = picks.into_iter().peekable();
+    for (i, element) in elements.take(walked).enumerate() {
+        while let Some((_, slot)) = picks.next_if(|(index, _)| *index == i) {
+            picked[slot] = Some(element.clone());
+        }
+    }
+    Some(picked.into_iter().flatten().collect())
+}
+
+/// Reply to a count [`sample`] turned down
+pub fn out_of_range() -> FrameValue {
+    FrameValue::Error("ERR value is out of range".into())
+}
+
+#[cfg(test)]
+mod random_tests {
+    use super::*;
+
+    #[test]
+    fn test_positive_count_is_distinct() {
+        let mut rng = Rng::seeded(7);
+
+        let picked = sample(0..10, 10, 4, &mut rng).unwrap();
+        assert_eq!(picked.len(), 4);
+        assert_eq!(picked.iter().collect::<HashSet<_>>().len(), 4);
+        assert!(picked.iter().all(|&i| i < 10));
+
+        let mut all = sample(0..10, 10, 25, &mut rng).unwrap();
+        all.sort();
+        assert_eq!(all, (0..10).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_negative_count_repeats() {
+        let mut rng = Rng::seeded(7);
+
+        let picked = sample(0..2, 2, -50, &mut rng).unwrap();
+        assert_eq!(picked.len(), 50);
+        assert!(picked.iter().all(|&i| i < 2));
+        assert!(picked.contains(&0) && picked.contains(&1));
+
+        assert_eq!(sample(0..0, 0, -5, &mut rng), Some(Vec::<usize>::new()));
+    }
+
+    #[test]
+    fn test_huge_negative_count_is_refused() {
+        let mut rng = Rng::seeded(7);
+
+        assert_eq!(sample(0..2, 2, i64::MIN, &mut rng), None);
+        assert_eq!(sample(0..0, 0, -10_000_000_000, &mut rng), None);
+        assert_eq!(
+            sample(0..2, 2, i64::MAX, &mut rng).map(|picked| picked.len()),
+            Some(2)
+        );
+    }
+
+    #[test]
+    fn test_elements_are_walked_once() {
+        let mut rng = Rng::seeded(7);
+        let mut walked = 0;
+
+        let picked = sample((0..1000).inspect(|_| walked += 1), 1000, 5, &mut rng).unwrap();
+        assert_eq!(walked, picked.iter().max().unwrap() + 1);
+    }
+
+    #[test]
+    fn test_same_seed_same_sample() {
+        assert_eq!(
+            sample(0..100, 100, 10, &mut Rng::seeded(42)),
+            sample(0..100, 100, 10, &mut Rng::seeded(42))
+        );
+    }
+}
@@ -0,0 +1,403 @@
+//! Binary encoding of single values, shared by DUMP/RESTORE and persistence
+//!
+//! A payload is a type byte followed by the value's contents, then a 2 byte
+//! format version and an 8 byte CRC-64 of everything before it. Every length is
+//! a little endian `u32`:
+//!
+//! - string: length, bytes
+//! - list and set: element count, then each element as a string
+//! - hash: pair count, then each field and value as strings
+//! - sorted set: member count, then each member as a string followed by its
+//!   score as a little endian `f64`
+//!
+//! A snapshot of the whole keyspace starts with the RDB file magic. Each
+//! database holding keys follows as a select opcode and its index, then its
+//! keys. A key is an optional expire opcode with a Unix time in milliseconds,
+//! then the value's type byte, the key as a string and the value's contents.
+//! An EOF opcode and a CRC-64 of everything before it close the snapshot.
+
+use crate::{
+    db::{DbValue, Storage, instant_at_unix, unix_time_of},
+    dict::{Dict, Set},
+    sorted_set::SortedSet,
+};
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+use std::{
+    collections::VecDeque,
+    ops::{Deref, DerefMut},
+    time::{Duration, Instant},
+};
+
+pub const VERSION: u16 = 1;
+
+/// Version of the RDB file format that snapshots claim to be
+const SNAPSHOT_VERSION: &[u8] = b"REDIS0011";
+const OPCODE_EXPIRETIME_MS: u8 = 0xFC;
+const OPCODE_SELECTDB: u8 = 0xFE;
+const OPCODE_EOF: u8 = 0xFF;
+
+const TYPE_STRING: u8 = 0;
+const TYPE_LIST: u8 = 1;
+const TYPE_SET: u8 = 2;
+const TYPE_HASH: u8 = 4;
+const TYPE_ZSET_2: u8 = 5;
+
+const TRAILER_LEN: usize = 2 + 8;
+
+/// Serializes a value into a self-contained payload
+pub fn encode(value: &DbValue) -> Bytes {
+    let mut buf = BytesMut::new();
+    buf.put_u8(type_of(value));
+    put_value(&mut buf, value);
+
+    buf.put_u16_le(VERSION);
+    let checksum = crc64(&buf);
+    buf.put_u64_le(checksum);
+    buf.freeze()
+}
+
+/// Why a payload couldn't be decoded
+#[derive(Debug, PartialEq)]
+pub enum DecodeError {
+    /// The version or checksum don't match
+    Version,
+    /// The checksum matches but the contents are malformed
+    BadData,
+}
+
+/// Deserializes a payload produced by [`encode`]
+pub fn decode(payload: &[u8]) -> Result<DbValue, DecodeError> {
+    let body_len = payload
+        .len()
+        .checked_sub(TRAILER_LEN)
+        .ok_or(DecodeError::Version)?;
+    let (body, mut trailer) = payload.split_at(body_len + 2);
+    let checksum = trailer.get_u64_le();
+    if crc64(body) != checksum {
+        return Err(DecodeError::Version);
+    }
+
+    let (mut body, mut version) = body.split_at(body_len);
+    if version.get_u16_le() != VERSION {
+        return Err(DecodeError::Version);
+    }
+    if body.is_empty() {
+        return Err(DecodeError::BadData);
+    }
+
+    let value_type = body.get_u8();
+    get_value(&mut body, value_type)
+        .filter(|_| body.is_empty())
+        .ok_or(DecodeError::BadData)
+}
+
+/// Serializes every key of `databases`, which are indexed by database number
+///
+/// Keys that have expired are left out.
+pub fn snapshot<S: Storage>(databases: &[impl Deref<Target = S>]) -> Bytes {
+    let mut buf = BytesMut::new();
+    buf.put_slice(SNAPSHOT_VERSION);
+
+    for (index, keyspace) in databases.iter().enumerate() {
+        let mut keys = keyspace.iter().peekable();
+        if keys.peek().is_none() {
+            continue;
+        }
+
+        buf.put_u8(OPCODE_SELECTDB);
+        buf.put_u32_le(index as u32);
+        for (key, value, expires_at) in keys {
+            if let Some(at) = expires_at {
+                buf.put_u8(OPCODE_EXPIRETIME_MS);
+                buf.put_u64_le(unix_time_of(at).as_millis() as u64);
+            }
+            buf.put_u8(type_of(value));
+            put_string(&mut buf, key);
+            put_value(&mut buf, value);
+        }
+    }
+
+    buf.put_u8(OPCODE_EOF);
+    let checksum = crc64(&buf);
+    buf.put_u64_le(checksum);
+    buf.freeze()
+}
+
+/// Replaces the contents of `databases` with the keys of a [`snapshot`]
+///
+/// Returns false, leaving `databases` untouched, if the snapshot is malformed
+/// or holds a database that doesn't exist. Keys that expired since the
+/// snapshot was taken are skipped.
+pub fn load<S: Storage>(payload: &[u8], databases: &mut [impl DerefMut<Target = S>]) -> bool {
+    let Some(keys) = parse_snapshot(payload) else {
+        return false;
+    };
+    if keys.iter().any(|(index, ..)| *index >= databases.len()) {
+        return false;
+    }
+
+    databases.iter_mut().for_each(|keyspace| keyspace.clear());
+    let now = Instant::now();
+    for (index, key, value, expires_at) in keys {
+        if expires_at.is_some_and(|at| at <= now) {
+            continue;
+        }
+        let keyspace = &mut databases[index];
+        keyspace.insert(key.clone(), value);
+        keyspace.set_expiry(&key, expires_at);
+    }
+    true
+}
+
+/// A key read from a snapshot: its database, name, value and expiry
+type SnapshotKey = (usize, Bytes, DbValue, Option<Instant>);
+
+fn parse_snapshot(payload: &[u8]) -> Option<Vec<SnapshotKey>> {
+    let body_len = payload.len().checked_sub(8)?;
+    let (mut body, mut trailer) = payload.split_at(body_len);
+    if crc64(body) != trailer.get_u64_le() || !body.starts_with(SNAPSHOT_VERSION) {
+        return None;
+    }
+    body.advance(SNAPSHOT_VERSION.len());
+
+    let mut keys = Vec::new();
+    let mut index = 0;
+    let mut expires_at = None;
+    loop {
+        if body.is_empty() {
+            return None;
+        }
+        match body.get_u8() {
+            OPCODE_EOF => break,
+            OPCODE_SELECTDB => index = get_len(&mut body)?,
+            OPCODE_EXPIRETIME_MS => {
+                let millis = (body.remaining() >= 8).then(|| body.get_u64_le())?;
+                expires_at = Some(instant_at_unix(Duration::from_millis(millis))?);
+            }
+            value_type => {
+                let key = get_string(&mut body)?;
+                let value = get_value(&mut body, value_type)?;
+                keys.push((index, key, value, expires_at.take()));
+            }
+        }
+    }
+
+    body.is_empty().then_some(keys)
+}
+
+fn type_of(value: &DbValue) -> u8 {
+    match value {
+        DbValue::String(_) => TYPE_STRING,
+        DbValue::List(_) => TYPE_LIST,
+        DbValue::Set(_) => TYPE_SET,
+        DbValue::Hash(_) => TYPE_HASH,
+        DbValue::SortedSet(_) => TYPE_ZSET_2,
+    }
+}
+
+/// Writes the contents of `value`, without its type byte
+fn put_value(buf: &mut BytesMut, value: &DbValue) {
+    match value {
+        DbValue::String(bytes) => put_string(buf, bytes),
+        DbValue::List(list) => {
+            buf.put_u32_le(list.len() as u32);
+            list.iter().for_each(|item| put_string(buf, item));
+        }
+        DbValue::Set(set) => {
+            buf.put_u32_le(set.len() as u32);
+            set.iter().for_each(|member| put_string(buf, member));
+        }
+        DbValue::Hash(hash) => {
+            buf.put_u32_le(hash.len() as u32);
+            for (field, value) in hash.iter() {
+                put_string(buf, field);
+                put_string(buf, value);
+            }
+        }
+        DbValue::SortedSet(members) => {
+            buf.put_u32_le(members.len() as u32);
+            for (member, score) in members.iter() {
+                put_string(buf, member);
+                buf.put_f64_le(score);
+            }
+        }
+    }
+}
+
+/// Reads the contents of a value of type `value_type`
+///
+/// Containers are removed once empty, so one holding nothing is malformed.
+fn get_value(buf: &mut &[u8], value_type: u8) -> Option<DbValue> {
+    let value = match value_type {
+        TYPE_STRING => DbValue::String(get_string(buf)?),
+        TYPE_LIST => {
+            let len = get_container_len(buf)?;
+            let list = (0..len)
+                .map(|_| get_string(buf))
+                .collect::<Option<VecDeque<_>>>()?;
+            DbValue::List(list)
+        }
+        TYPE_SET => {
+            let len = get_container_len(buf)?;
+            let set = (0..len).map(|_| get_string(buf)).collect::<Option<Set>>()?;
+            DbValue::Set(set)
+        }
+        TYPE_HASH => {
+            let len = get_container_len(buf)?;
+            let hash = (0..len)
+                .map(|_| Some((get_string(buf)?, get_string(buf)?)))
+                .collect::<Option<Dict<_>>>()?;
+            DbValue::Hash(hash)
+        }
+        TYPE_ZSET_2 => {
+            let len = get_container_len(buf)?;
+            let members = (0..len)
+                .map(|_| Some((get_string(buf)?, get_score(buf)?)))
+                .collect::<Option<SortedSet>>()?;
+            DbValue::SortedSet(members)
+        }
+        _ => return None,
+    };
+    Some(value)
+}
+
+/// An RDB snapshot holding no keys
+#[cfg(test)]
+pub fn empty_snapshot() -> Bytes {
+    let mut buf = BytesMut::new();
+    buf.put_slice(SNAPSHOT_VERSION);
+    buf.put_u8(OPCODE_EOF);
+    let checksum = crc64(&buf);
+    buf.put_u64_le(checksum);
+    buf.freeze()
+}
+
+fn put_string(buf: &mut BytesMut, bytes: &[u8]) {
+    buf.put_u32_le(bytes.len() as u32);
+    buf.put_slice(bytes);
+}
+
+fn get_len(buf: &mut &[u8]) -> Option<usize> {
+    (buf.remaining() >= 4).then(|| buf.get_u32_le() as usize)
+}
+
+/// Number of elements of a container, which is never 0
+fn get_container_len(buf: &mut &[u8]) -> Option<usize> {
+    get_len(buf).filter(|&len| len > 0)
+}
+
+fn get_string(buf: &mut &[u8]) -> Option<Bytes> {
+    let len = get_len(buf)?;
+    (buf.remaining() >= len).then(|| buf.copy_to_bytes(len))
+}
+
+fn get_score(buf: &mut &[u8]) -> Option<f64> {
+    (buf.remaining() >= 8)
+        .then(|| buf.get_f64_le())
+        .filter(|score| !score.is_nan())
+}
+
+/// CRC-64 with the Jones polynomial, as used by Redis
+fn crc64(bytes: &[u8]) -> u64 {
+    const POLY: u64 = 0x95AC_9329_AC4B_C9B5;
+
+    bytes.iter().fold(0, |mut crc, &byte| {
+        crc ^= byte as u64;
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ POLY
+            } else {
+                crc >> 1
+            };
+        }
+        crc
+    })
+}
+
+#[cfg(test)]
+mod rdb_tests {
+    use super::*;
+    use crate::db::Keyspace;
+
+    #[test]
+    fn test_crc64() {
+        assert_eq!(crc64(b"123456789"), 0xE9C6_D914_C4B8_D9CA);
+    }
+
+    #[test]
+    fn test_rejects_corrupt_payloads() {
+        let payload = encode(&DbValue::String("value".into()));
+        assert_eq!(decode(&payload), Ok(DbValue::String("value".into())));
+
+        let mut corrupt = payload.to_vec();
+        corrupt[2] ^= 1;
+        assert_eq!(decode(&corrupt), Err(DecodeError::Version));
+        assert_eq!(
+            decode(&payload[..payload.len() - 1]),
+            Err(DecodeError::Version)
+        );
+        assert_eq!(decode(b""), Err(DecodeError::Version));
+        assert_eq!(
+            decode(&encode(&DbValue::List(VecDeque::new()))),
+            Err(DecodeError::BadData)
+        );
+    }
+
+    #[test]
+    fn test_snapshot_round_trip() {
+        let mut databases = [Keyspace::default(), Keyspace::default()];
+        databases[0].insert("string".into(), DbValue::String("value".into()));
+        databases[0].insert(
+            "list".into(),
+            DbValue::List(["a", "b"].map(Bytes::from).into()),
+        );
+        databases[1].insert(
+            "hash".into(),
+            DbValue::Hash([("field".into(), "value".into())].into_iter().collect()),
+        );
+        databases[1].insert(
+            "zset".into(),
+            DbValue::SortedSet(
+                [("a".into(), 1.5), ("b".into(), f64::NEG_INFINITY)]
+                    .into_iter()
+                    .collect(),
+            ),
+        );
+        let expires_at = Instant::now() + Duration::from_secs(100);
+        databases[1].set_expiry(b"hash", Some(expires_at));
+
+        let payload = snapshot(&databases.iter().collect::<Vec<_>>());
+        let mut loaded = [Keyspace::default(), Keyspace::default()];
+        assert!(load(&payload, &mut loaded.iter_mut().collect::<Vec<_>>()));
+
+        assert_eq!(loaded[0].len(), 2);
+        assert_eq!(loaded[0].peek(b"list"), databases[0].peek(b"list"));
+        assert_eq!(loaded[1].peek(b"hash"), databases[1].peek(b"hash"));
+        assert_eq!(loaded[1].peek(b"zset"), databases[1].peek(b"zset"));
+        let loaded_at = loaded[1].expiry(b"hash").unwrap().unwrap();
+        let drift = loaded_at.max(expires_at) - loaded_at.min(expires_at);
+        assert!(drift < Duration::from_millis(10), "{drift:?}");
+        assert_eq!(loaded[0].expiry(b"string"), Some(None));
+    }
+
+    #[test]
+    fn test_load_rejects_bad_snapshots() {
+        let mut databases = [Keyspace::default(), Keyspace::default()];
+        databases[1].insert("key".into(), DbValue::String("value".into()));
+        let payload = snapshot(&databases.iter().collect::<Vec<_>>());
+
+        let mut corrupt = payload.to_vec();
+        corrupt[12] ^= 1;
+        let mut target = [Keyspace::default()];
+        target[0].insert("kept".into(), DbValue::String("value".into()));
+        let mut target = target.iter_mut().collect::<Vec<_>>();
+
+        assert!(!load(&corrupt, &mut target));
+        // Database 1 doesn't exist in the target
+        assert!(!load(&payload, &mut target));
+        assert_eq!(target[0].len(), 1);
+
+        assert!(load(&empty_snapshot(), &mut target));
+        assert_eq!(target[0].len(), 0);
+    }
+}
@@ -0,0 +1,181 @@
+use crate::{
+    client::{Client, ClientList},
+    cmd::{self, Command, command_frame},
+    connection::Connection,
+    db::Db,
+    frame::{FrameError, FrameValue},
+    log, rdb,
+    replication::{LinkState, MasterLink},
+};
+use std::{io, time::Duration};
+use tokio::{net::TcpStream, time};
+
+/// How long to wait before reconnecting after the link to the master drops
+const RECONNECT_DELAY: Duration = Duration::from_secs(1);
+
+/// How often the replica tells its master how much of the feed it has applied
+const ACK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Keeps `db` in sync with the master at `host:port`, reconnecting whenever the
+/// link drops
+///
+/// `listening_port` is the port this server accepts clients on, reported to the
+/// master during the handshake.
+pub async fn follow(master: (String, u16), listening_port: u16, db: Db, clients: ClientList) {
+    db.replication().set_master(Some(MasterLink {
+        host: master.0.clone(),
+        port: master.1,
+        state: LinkState::Connect,
+        offset: 0,
+    }));
+
+    loop {
+        set_state(&db, LinkState::Connecting);
+        match sync_with(&master, listening_port, &db, &clients).await {
+            Ok(()) => log!(Notice, "Master closed the replication link"),
+            Err(e) => log!(Warning, "Replication error: {e:?}"),
+        }
+        set_state(&db, LinkState::Connect);
+        time::sleep(RECONNECT_DELAY).await;
+    }
+}
+
+fn set_state(db: &Db, state: LinkState) {
+    db.replication()
+        .update_master(|master| master.state = state);
+}
+
+async fn sync_with(
+    (host, port): &(String, u16),
+    listening_port: u16,
+    db: &Db,
+    clients: &ClientList,
+) -> Result<(), FrameError> {
+    let stream = TcpStream::connect((host.as_str(), *port)).await?;
+    let addr = stream.peer_addr()?.to_string();
+    let mut connection = Connection::new(stream);
+
+    let offset = handshake(&mut connection, listening_port).await?;
+    db.replication().update_master(|master| {
+        master.state = LinkState::Sync;
+        master.offset = offset;
+    });
+    let snapshot = connection.read_rdb().await?;
+    if !rdb::load(&snapshot, &mut db.lock_all()) {
+        return Err(unexpected("snapshot"));
+    }
+    // What the file held before was replaced along with the databases
+    db.replication()
+        .rewrite_aof(|aof| aof.rewrite(&db.read_all()))?;
+    set_state(db, LinkState::Connected);
+    log!(Notice, "Synchronised with master {addr}");
+
+    let client = clients.register(addr, connection.stats().clone());
+    let result = apply_stream(&mut connection, db, &client).await;
+    clients.remove(client.id());
+    result
+}
+
+/// Introduces this server as a replica and asks for a full resync, returning the
+/// offset the master's stream starts at
+async fn handshake(
+    connection: &mut Connection<TcpStream>,
+    listening_port: u16,
+) -> Result<u64, FrameError> {
+    let port = listening_port.to_string();
+    let steps: [(&[&str], &str); 4] = [
+        (&["PING"], "PONG"),
+        (&["REPLCONF", "listening-port", &port], "OK"),
+        (&["REPLCONF", "capa", "psync2"], "OK"),
+        (&["PSYNC", "?", "-1"], "FULLRESYNC"),
+    ];
+
+    let mut reply = Default::default();
+    for (args, expected) in steps {
+        connection.write_frame(command_frame(args)).await?;
+        reply = match connection.read_frame().await? {
+            Some(FrameValue::SimpleString(reply)) if reply.starts_with(expected.as_bytes()) => {
+                reply
+            }
+            _ => return Err(unexpected(&format!("reply to {}", args[0]))),
+        };
+    }
+
+    // The last reply is `FULLRESYNC <replid> <offset>`
+    let offset = std::str::from_utf8(&reply)
+        .ok()
+        .and_then(|reply| reply.rsplit(' ').next()?.parse().ok())
+        .ok_or_else(|| unexpected("offset"))?;
+    Ok(offset)
+}
+
+/// Applies the write commands streamed by the master until it closes the link,
+/// following its SELECTs between databases
+///
+/// The offset applied so far is acknowledged every [`ACK_INTERVAL`] and whenever
+/// the master asks with `REPLCONF GETACK`. Anything else the master sends, such
+/// as its periodic PINGs, is ignored.
+async fn apply_stream(
+    connection: &mut Connection<TcpStream>,
+    db: &Db,
+    client: &Client,
+) -> Result<(), FrameError> {
+    let mut db = db.clone();
+    let mut ack = time::interval(ACK_INTERVAL);
+    loop {
+        let frame = tokio::select! {
+            frame = connection.read_frame() => match frame? {
+                Some(frame) => frame,
+                None => break,
+            },
+            _ = ack.tick() => {
+                send_ack(connection, &db).await?;
+                continue;
+            }
+        };
+        let len = frame.len() as u64;
+        db.replication()
+            .update_master(|master| master.offset += len);
+
+        let propagated = cmd::is_write(&frame).then(|| frame.clone());
+        match (Command::from_frame(frame), propagated) {
+            (Ok(Command::Select(cmd)), _) => {
+                cmd.apply(&mut db);
+            }
+            (Ok(Command::ReplConf(cmd)), _) if cmd.is_getack() => {
+                send_ack(connection, &db).await?;
+            }
+            (Ok(cmd), Some(propagated)) => {
+                // Chained replicas get the same stream
+                db.replication()
+                    .execute(db.index(), || cmd.apply_write(&db, client, propagated));
+                // The master isn't told, but the failure refuses clients' writes
+                let _ = db.replication().fsync_aof_always().await;
+                connection.record_command();
+            }
+            (Ok(_), None) => {}
+            (Err(e), _) => log!(
+                Warning,
+                "Ignoring command from master: {:?}",
+                e.into_frame()
+            ),
+        }
+    }
+    Ok(())
+}
+
+/// Tells the master the offset of its feed applied so far
+async fn send_ack(connection: &mut Connection<TcpStream>, db: &Db) -> Result<(), FrameError> {
+    let offset = db.replication().master().map_or(0, |master| master.offset);
+    connection
+        .write_frame(command_frame(&["REPLCONF", "ACK", &offset.to_string()]))
+        .await
+}
+
+fn unexpected(what: &str) -> FrameError {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("unexpected {what} from master"),
+    )
+    .into()
+}
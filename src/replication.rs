@@ -0,0 +1,424 @@
+use crate::{aof::Aof, cmd, config::AppendFsync, frame::FrameValue, log};
+use std::{
+    collections::BTreeMap,
+    hash::{BuildHasher, Hasher, RandomState},
+    io,
+    sync::{Arc, Mutex, MutexGuard, PoisonError},
+};
+use tokio::sync::{Notify, broadcast};
+
+/// Write commands a replica may fall behind by before it is disconnected
+const FEED_CAPACITY: usize = 1024;
+
+/// Replication state of this server
+#[derive(Clone)]
+pub struct Replication {
+    replid: Arc<str>,
+    /// Write commands in the order they were executed, forwarded to every replica
+    feed: broadcast::Sender<FrameValue>,
+    /// Also held while executing a write command so the feed matches execution order
+    state: Arc<Mutex<State>>,
+    /// Woken whenever a replica acknowledges an offset
+    acks: Arc<Notify>,
+    /// Woken whenever more of the append only file is fsynced
+    fsyncs: Arc<Notify>,
+}
+
+#[derive(Default)]
+struct State {
+    /// Bytes of write commands fed to replicas so far
+    offset: u64,
+    /// Database the feed last selected, `None` until the next write has to
+    /// select one
+    selected: Option<usize>,
+    /// Replicas attached to this server, keyed by client id
+    replicas: BTreeMap<u64, ReplicaInfo>,
+    /// Set while this server is itself a replica
+    master: Option<MasterLink>,
+    /// File every write command is appended to, if `appendonly` is on
+    aof: Option<Aof>,
+    /// Offset of the feed up to which the append only file has been fsynced
+    aof_fsynced: u64,
+    /// Set while an fsync of the append only file is underway
+    aof_fsyncing: bool,
+    /// Why the append only file last failed to be written or fsynced, cleared
+    /// once it is fsynced again
+    aof_error: Option<String>,
+}
+
+/// A replica as reported by ROLE
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ReplicaInfo {
+    pub ip: String,
+    /// Port the replica accepts clients on, as announced with REPLCONF
+    pub listening_port: u16,
+    /// Whether the replica got past PSYNC and is receiving the feed
+    pub attached: bool,
+    /// Offset of the feed the replica last said it has applied
+    pub ack_offset: u64,
+}
+
+/// Link of a replica to its master as reported by ROLE
+#[derive(Clone, Debug, PartialEq)]
+pub struct MasterLink {
+    pub host: String,
+    pub port: u16,
+    pub state: LinkState,
+    /// Bytes received from the master since the last full resync
+    pub offset: u64,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LinkState {
+    /// Waiting to reconnect
+    Connect,
+    Connecting,
+    Sync,
+    Connected,
+}
+
+impl LinkState {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Connect => "connect",
+            Self::Connecting => "connecting",
+            Self::Sync => "sync",
+            Self::Connected => "connected",
+        }
+    }
+}
+
+impl Default for Replication {
+    fn default() -> Self {
+        Self {
+            replid: random_replid().into(),
+            feed: broadcast::channel(FEED_CAPACITY).0,
+            state: Arc::default(),
+            acks: Arc::default(),
+            fsyncs: Arc::default(),
+        }
+    }
+}
+
+impl Replication {
+    /// Identifier of this server's replication history
+    pub fn replid(&self) -> &str {
+        &self.replid
+    }
+
+    /// Runs a write command against database `db`, forwarding to replicas the
+    /// frame it returns along with its reply, if any
+    ///
+    /// The frame is preceded by a SELECT whenever `db` differs from the database
+    /// the feed last selected. Both are appended to the append only file too, if
+    /// there is one, before the reply is returned. The reply is a MISCONF error
+    /// if that fails.
+    pub fn execute(
+        &self,
+        db: usize,
+        execute: impl FnOnce() -> (FrameValue, Option<FrameValue>),
+    ) -> FrameValue {
+        let mut guard = self.state();
+        let state = &mut *guard;
+        let (reply, propagated) = execute();
+        let Some(frame) = propagated else {
+            return reply;
+        };
+
+        let mut frames = Vec::with_capacity(2);
+        if state.selected != Some(db) {
+            frames.push(cmd::command_frame(&["SELECT", &db.to_string()]));
+            state.selected = Some(db);
+        }
+        frames.push(frame);
+
+        let appended = state
+            .aof
+            .as_mut()
+            .map(|aof| (aof.append(&frames), aof.fsync_policy()));
+        for frame in frames {
+            state.offset += frame.len() as u64;
+            // Nobody listening just means there are no replicas
+            let _ = self.feed.send(frame);
+        }
+        match appended {
+            // Written is as good as fsynced when the system decides when to fsync
+            Some((Ok(()), AppendFsync::No)) => {
+                state.aof_fsynced = state.offset;
+                self.fsyncs.notify_waiters();
+            }
+            Some((Err(e), _)) => {
+                log!(Warning, "Error writing to the append only file: {e}");
+                state.aof_error = Some(e.to_string());
+                return misconf(&e.to_string());
+            }
+            Some((Ok(()), _)) | None => {}
+        }
+        reply
+    }
+
+    /// Locks the state, even if a command panicked while it was held
+    ///
+    /// Commands run before the state is touched, so one panicking leaves the
+    /// state as it was. Carrying on keeps replication working for every other
+    /// connection, and keeps the cleanup of the panicking one from panicking
+    /// again during the unwind.
+    fn state(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Bytes of write commands fed to replicas so far
+    pub fn offset(&self) -> u64 {
+        self.state().offset
+    }
+
+    /// Records the port a connecting replica says it accepts clients on
+    pub fn announce_port(&self, id: u64, listening_port: u16) {
+        let mut state = self.state();
+        state.replicas.entry(id).or_default().listening_port = listening_port;
+    }
+
+    /// Records that the replica on client `id`, connected from `ip`, is being fed
+    /// and starts receiving the write commands executed from now on
+    ///
+    /// Also returns the offset the replica starts from along with what `start`
+    /// returns, typically a snapshot of the databases. Writes are propagated
+    /// with the state locked, so none can slip in between `start` and the offset.
+    pub fn attach<T>(
+        &self,
+        id: u64,
+        ip: String,
+        start: impl FnOnce() -> T,
+    ) -> (broadcast::Receiver<FrameValue>, u64, T) {
+        let mut state = self.state();
+        let started = start();
+        let offset = state.offset;
+        let replica = state.replicas.entry(id).or_default();
+        replica.ip = ip;
+        replica.attached = true;
+        replica.ack_offset = offset;
+        // The new replica starts out on database 0, so make the next write select
+        // its database explicitly
+        state.selected = None;
+        (self.feed.subscribe(), offset, started)
+    }
+
+    /// Records that the replica on client `id` has applied the feed up to `offset`
+    pub fn acknowledge(&self, id: u64, offset: u64) {
+        if let Some(replica) = self.state().replicas.get_mut(&id) {
+            replica.ack_offset = offset;
+        }
+        self.acks.notify_waiters();
+    }
+
+    /// Number of replicas that have acknowledged the feed up to `offset`
+    pub fn acknowledged(&self, offset: u64) -> usize {
+        let state = self.state();
+        state
+            .replicas
+            .values()
+            .filter(|replica| replica.attached && replica.ack_offset >= offset)
+            .count()
+    }
+
+    /// Asks every replica to acknowledge its offset right away rather than at its
+    /// next periodic acknowledgement
+    ///
+    /// The request travels down the feed, so it counts towards the offset.
+    pub fn request_acks(&self) {
+        let mut state = self.state();
+        if self.feed.receiver_count() > 0 {
+            let getack = cmd::command_frame(&["REPLCONF", "GETACK", "*"]);
+            state.offset += getack.len() as u64;
+            let _ = self.feed.send(getack);
+        }
+    }
+
+    /// Waits until at least `replicas` replicas have acknowledged the feed up to
+    /// `offset`, returning how many have
+    pub async fn wait_for_acks(&self, offset: u64, replicas: usize) -> usize {
+        loop {
+            // Registered before checking so no acknowledgement in between is missed
+            let acked = self.acks.notified();
+            let count = self.acknowledged(offset);
+            if count >= replicas {
+                return count;
+            }
+            acked.await;
+        }
+    }
+
+    /// Appends every write command executed from now on to `aof`
+    pub fn enable_aof(&self, aof: Aof) {
+        let mut state = self.state();
+        state.aof_fsynced = state.offset;
+        // The file may end on another database than the feed selected last
+        state.selected = None;
+        state.aof = Some(aof);
+    }
+
+    /// Fsync policy of the append only file, `None` if there is none
+    pub fn aof_fsync_policy(&self) -> Option<AppendFsync> {
+        self.state().aof.as_ref().map(Aof::fsync_policy)
+    }
+
+    /// Error write commands are refused with while the append only file can't
+    /// be written or fsynced, `None` if they can go ahead
+    pub fn aof_error(&self) -> Option<FrameValue> {
+        self.state().aof_error.as_deref().map(misconf)
+    }
+
+    /// Fsyncs the append only file, if there is one, up to the write commands
+    /// executed so far, without holding up write commands in the meantime
+    ///
+    /// Commands a failed write left behind are written first. Callers arriving
+    /// while an fsync is underway wait for it rather than fsyncing in parallel,
+    /// so that they share the next one. Once this succeeds, write commands are
+    /// no longer refused.
+    pub async fn fsync_aof(&self) -> io::Result<()> {
+        let target = self.offset();
+        let (file, offset) = loop {
+            // Registered before checking so no fsync in between is missed
+            let fsynced = self.fsyncs.notified();
+            {
+                let mut guard = self.state();
+                let state = &mut *guard;
+                let Some(aof) = &mut state.aof else {
+                    return Ok(());
+                };
+                if state.aof_fsynced >= target && state.aof_error.is_none() {
+                    return Ok(());
+                }
+                if !state.aof_fsyncing {
+                    if let Err(e) = aof.write_pending() {
+                        state.aof_error = Some(e.to_string());
+                        return Err(e);
+                    }
+                    state.aof_fsyncing = true;
+                    break (aof.file(), state.offset);
+                }
+            }
+            fsynced.await;
+        };
+        let result = tokio::task::spawn_blocking(move || file.sync_data())
+            .await
+            .unwrap_or_else(|e| Err(io::Error::other(e)));
+
+        let mut state = self.state();
+        state.aof_fsyncing = false;
+        match &result {
+            Ok(()) => {
+                state.aof_fsynced = state.aof_fsynced.max(offset);
+                state.aof_error = None;
+            }
+            Err(e) => state.aof_error = Some(e.to_string()),
+        }
+        self.fsyncs.notify_waiters();
+        result
+    }
+
+    /// Under `appendfsync always`, fsyncs the append only file up to the write
+    /// commands executed so far, for a write command to wait on before it
+    /// replies
+    ///
+    /// On failure returns the error the write command replies with instead.
+    pub async fn fsync_aof_always(&self) -> Result<(), FrameValue> {
+        if self.aof_fsync_policy() != Some(AppendFsync::Always) {
+            return Ok(());
+        }
+        self.fsync_aof().await.map_err(|e| {
+            log!(Warning, "Error fsyncing the append only file: {e}");
+            misconf(&e.to_string())
+        })
+    }
+
+    /// Whether the append only file has been fsynced up to `offset`, `None` if
+    /// there is no such file
+    pub fn aof_fsynced(&self, offset: u64) -> Option<bool> {
+        let state = self.state();
+        state.aof.as_ref().map(|_| state.aof_fsynced >= offset)
+    }
+
+    /// Waits until the append only file has been fsynced up to `offset`, which
+    /// never happens if there is no such file
+    pub async fn wait_for_aof_fsync(&self, offset: u64) {
+        loop {
+            // Registered before checking so no fsync in between is missed
+            let fsynced = self.fsyncs.notified();
+            if self.aof_fsynced(offset) == Some(true) {
+                return;
+            }
+            fsynced.await;
+        }
+    }
+
+    /// Has `rewrite` replace the contents of the append only file, if there is
+    /// one, with no write command executing in the meantime
+    ///
+    /// The file counts as fsynced up to the current offset afterwards.
+    pub fn rewrite_aof(&self, rewrite: impl FnOnce(&mut Aof) -> io::Result<()>) -> io::Result<()> {
+        let mut guard = self.state();
+        let state = &mut *guard;
+        let Some(aof) = &mut state.aof else {
+            return Ok(());
+        };
+        rewrite(aof)?;
+        state.selected = None;
+        state.aof_fsynced = state.offset;
+        state.aof_error = None;
+        self.fsyncs.notify_waiters();
+        Ok(())
+    }
+
+    /// Forgets the replica on client `id`, if it was one
+    pub fn detach(&self, id: u64) {
+        self.state().replicas.remove(&id);
+    }
+
+    /// Replicas currently being fed
+    pub fn replicas(&self) -> Vec<ReplicaInfo> {
+        let state = self.state();
+        state
+            .replicas
+            .values()
+            .filter(|replica| replica.attached)
+            .cloned()
+            .collect()
+    }
+
+    /// Sets or clears the link to this server's master
+    pub fn set_master(&self, master: Option<MasterLink>) {
+        self.state().master = master;
+    }
+
+    /// Updates the link to this server's master, if there is one
+    pub fn update_master(&self, update: impl FnOnce(&mut MasterLink)) {
+        if let Some(master) = &mut self.state().master {
+            update(master);
+        }
+    }
+
+    pub fn master(&self) -> Option<MasterLink> {
+        self.state().master.clone()
+    }
+}
+
+/// Error refusing a write command because the append only file can't be
+/// written, as Redis words it
+fn misconf(error: &str) -> FrameValue {
+    FrameValue::Error(format!("MISCONF Errors writing to the AOF file: {error}").into())
+}
+
+/// 40 random hex characters, like the ids Redis hands out
+fn random_replid() -> String {
+    let state = RandomState::new();
+    let mut replid: String = (0..3)
+        .map(|i| {
+            let mut hasher = state.build_hasher();
+            hasher.write_usize(i);
+            format!("{:016x}", hasher.finish())
+        })
+        .collect();
+    replid.truncate(40);
+    replid
+}
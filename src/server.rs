@@ -1,40 +1,1543 @@
+use crate::{
+    acl::{Acl, DEFAULT_USER},
+    aof::Aof,
+    client::ClientList,
+    cmd::{self, Command, CommandEffect},
+    config::{AppendFsync, Config},
+    connection::{Connection, ProtocolLog},
+    db::{Db, EncodingLimits},
+    frame::{FrameError, FrameValue},
+    log,
+    pubsub::{Message, OutputLimits},
+    rdb, replica,
+};
+use socket2::{Domain, Protocol, SockRef, Socket, TcpKeepalive, Type};
+use std::{
+    fs,
+    future::Future,
+    io,
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::Duration,
+};
 use tokio::{
-    io::{AsyncReadExt, AsyncWriteExt},
+    io::{AsyncRead, AsyncWrite},
     net::{TcpListener, TcpStream},
+    sync::broadcast::{self, error::RecvError},
 };
 
-pub async fn run(listener: TcpListener) {
+/// How often expired keys are swept from the keyspace
+const ACTIVE_EXPIRE_INTERVAL: Duration = Duration::from_millis(100);
+/// How often the append only file is fsynced under `appendfsync everysec`
+const AOF_FSYNC_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Handles shared by every connection
+#[derive(Clone)]
+struct Shared {
+    db: Db,
+    clients: ClientList,
+    config: Config,
+    /// Set once the server is shutting down, from when writes are refused
+    draining: Arc<AtomicBool>,
+    /// Tells connections to stop once the server is shutting down
+    ///
+    /// Every connection holds a receiver, so it also tells when they have all
+    /// closed. SHUTDOWN sends on it too, which [`run`] listens for.
+    notify_shutdown: broadcast::Sender<()>,
+}
+
+impl Shared {
+    fn new(config: Config) -> Self {
+        let (notify_shutdown, _) = broadcast::channel(1);
+        Self {
+            db: Db::with_databases(config.databases)
+                .with_acl(Acl::new(config.requirepass.clone()))
+                .with_max_value_len(config.proto_max_bulk_len)
+                .with_encoding_limits(EncodingLimits {
+                    list_max_listpack_size: config.list_max_listpack_size,
+                    set_max_intset_entries: config.set_max_intset_entries,
+                }),
+            clients: ClientList::default(),
+            config,
+            draining: Arc::default(),
+            notify_shutdown,
+        }
+    }
+}
+
+/// Binds a listener to `addr` with the backlog from `config`
+pub fn bind(addr: SocketAddr, config: &Config) -> io::Result<TcpListener> {
+    let socket = Socket::new(Domain::for_address(addr), Type::STREAM, Some(Protocol::TCP))?;
+    socket.set_reuse_address(true)?;
+    socket.set_nonblocking(true)?;
+    socket.bind(&addr.into())?;
+    socket.listen(config.tcp_backlog.try_into().unwrap_or(i32::MAX))?;
+    TcpListener::from_std(socket.into())
+}
+
+/// Accepts connections until `shutdown` completes or a client sends SHUTDOWN,
+/// then tells every connection to close and returns
+///
+/// Connections get until [`Config::shutdown_timeout`] to close by themselves
+/// first. In the meantime they can still read, but writes are refused.
+///
+/// Fails without serving anyone if [`Config::pidfile`] can't be written, or
+/// if [`Config::appendonly`] is on and the append only file can't be replayed.
+pub async fn run(listener: TcpListener, config: Config, shutdown: impl Future) -> io::Result<()> {
+    let _pidfile = config.pidfile.as_deref().map(PidFile::create).transpose()?;
+    let shared = Shared::new(config);
+    if shared.config.appendonly {
+        let aof = Aof::open(
+            &shared.config.appendfilename,
+            shared.config.appendfsync,
+            &shared.db,
+        )?;
+        shared.db.replication().enable_aof(aof);
+    }
+
+    let replica = shared.config.replicaof.clone().map(|master| {
+        let port = listener.local_addr().map_or(0, |addr| addr.port());
+        let follow = replica::follow(master, port, shared.db.clone(), shared.clients.clone());
+        tokio::spawn(follow)
+    });
+
+    let mut requested = shared.notify_shutdown.subscribe();
+    tokio::select! {
+        _ = accept_loop(&listener, &shared) => {}
+        _ = expire_keys(&shared.db) => {}
+        _ = fsync_aof(&shared.db) => {}
+        _ = shutdown => {
+            log!(Warning, "Shutting down!");
+        }
+        _ = requested.recv() => {
+            log!(Warning, "Shutting down on request!");
+        }
+    }
+
+    drop(requested);
+    drop(listener);
+    if let Some(replica) = replica {
+        replica.abort();
+    }
+
+    shared.draining.store(true, Ordering::Relaxed);
+    let drained = shared.notify_shutdown.closed();
+    if tokio::time::timeout(shared.config.shutdown_timeout, drained)
+        .await
+        .is_err()
+    {
+        log!(Warning, "Closing connections that are still open");
+    }
+    let _ = shared.notify_shutdown.send(());
+    if let Err(e) = shared.db.replication().fsync_aof().await {
+        log!(Warning, "Error fsyncing the append only file: {e}");
+    }
+    Ok(())
+}
+
+/// Holds this process's id in a file, removing the file once dropped
+struct PidFile(PathBuf);
+
+impl PidFile {
+    /// Writes the process id to `path`, replacing whatever the file held
+    fn create(path: &Path) -> io::Result<Self> {
+        fs::write(path, format!("{}\n", std::process::id())).map_err(|e| {
+            io::Error::new(
+                e.kind(),
+                format!("can't write pidfile {}: {e}", path.display()),
+            )
+        })?;
+        Ok(Self(path.to_path_buf()))
+    }
+}
+
+impl Drop for PidFile {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.0);
+    }
+}
+
+async fn accept_loop(listener: &TcpListener, shared: &Shared) {
     loop {
-        match listener.accept().await {
-            Ok((socket, _)) => {
-                println!("Accepted a connection!");
-                tokio::spawn(process(socket));
+        match accept(listener, &shared.config).await {
+            Ok((socket, addr)) => {
+                log!(Verbose, "Accepted a connection!");
+                tokio::spawn(process(socket, addr.to_string(), shared.clone()));
             }
             Err(e) => {
-                println!("Error: {}", e);
+                log!(Warning, "Error: {}", e);
                 continue;
             }
         }
     }
 }
 
-async fn process(mut socket: TcpStream) {
-    let mut buf = vec![0; 512];
-    let response = "+PONG\r\n";
+/// Periodically removes expired keys that nobody accesses, for as long as the
+/// server runs
+async fn expire_keys(db: &Db) {
+    let mut interval = tokio::time::interval(ACTIVE_EXPIRE_INTERVAL);
+    loop {
+        interval.tick().await;
+        db.active_expire_cycle();
+    }
+}
 
+/// Fsyncs the append only file every second under `appendfsync everysec`, for
+/// as long as the server runs
+///
+/// Under any policy, a file that failed to be written or fsynced is retried
+/// every second too, until write commands can go ahead again.
+async fn fsync_aof(db: &Db) {
+    let Some(policy) = db.replication().aof_fsync_policy() else {
+        return std::future::pending().await;
+    };
+    let mut interval = tokio::time::interval(AOF_FSYNC_INTERVAL);
     loop {
-        match socket.read(&mut buf).await {
-            Ok(_size @ 0) => {
-                println!("Connection closed!");
+        interval.tick().await;
+        let replication = db.replication();
+        if policy != AppendFsync::EverySec && replication.aof_error().is_none() {
+            continue;
+        }
+        if let Err(e) = replication.fsync_aof().await {
+            log!(Warning, "Error fsyncing the append only file: {e}");
+        }
+    }
+}
+
+/// Accepts a connection and applies the socket options from `config` to it
+async fn accept(listener: &TcpListener, config: &Config) -> io::Result<(TcpStream, SocketAddr)> {
+    let (socket, addr) = listener.accept().await?;
+    socket.set_nodelay(true)?;
+    if let Some(time) = config.tcp_keepalive {
+        SockRef::from(&socket).set_tcp_keepalive(&TcpKeepalive::new().with_time(time))?;
+    }
+    Ok((socket, addr))
+}
+
+async fn process<S>(stream: S, addr: String, shared: Shared)
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let clients = &shared.clients;
+    // Handle on the database this connection has selected
+    let mut db = shared.db.clone();
+    let mut shutdown = shared.notify_shutdown.subscribe();
+    let mut connection = Connection::with_capacity(
+        stream,
+        shared.config.read_buffer_size,
+        shared.config.write_buffer_size,
+    );
+    connection.limit_array_len(shared.config.max_array_len);
+    connection.limit_bulk_len(shared.config.proto_max_bulk_len);
+    if let Some(limit) = shared.config.protocol_log {
+        connection.log_protocol(ProtocolLog::new(addr.clone(), limit));
+    }
+    let client = clients.register(addr.clone(), connection.stats().clone());
+    let _guard = ConnectionGuard {
+        db: db.clone(),
+        clients: clients.clone(),
+        id: client.id(),
+    };
+    if !db.acl().requires_auth() {
+        client.set_user(DEFAULT_USER);
+    }
+    let limits = OutputLimits {
+        soft: shared.config.pubsub_output_soft_limit,
+        hard: shared.config.pubsub_output_hard_limit,
+        soft_duration: shared.config.pubsub_output_soft_duration,
+    };
+    let (mut subscriber, mut messages) = db.pubsub().subscriber(client.id(), limits);
+    // Set once the connection has become a replica
+    let mut feed = None;
+    let read_only = shared.config.replicaof.is_some() && shared.config.replica_read_only;
+    let idle_timeout = shared
+        .config
+        .tcp_keepalive
+        .zip(shared.config.idle_timeout_factor)
+        .map(|(keepalive, factor)| keepalive * factor);
+
+    'connection: loop {
+        let frame = tokio::select! {
+            frame = connection.read_frame() => match frame {
+                Ok(Some(frame)) => frame,
+                Ok(None) => {
+                    log!(Verbose, "Connection closed!");
+                    break;
+                }
+                Err(e) => {
+                    log!(Verbose, "Error: {e:?}");
+                    // Where the next frame starts can only be guessed after a
+                    // malformed one, so unless asked to guess let the client know
+                    // why before hanging up
+                    if e.is_protocol_error() {
+                        let reply = FrameValue::Error("ERR Protocol error".into());
+                        let _ = connection.write_frame(reply).await;
+                        if shared.config.protocol_resync && connection.resync() {
+                            continue;
+                        }
+                    }
+                    break;
+                }
+            },
+            _ = shutdown.recv() => break,
+            _ = idle(idle_timeout, subscriber.is_subscribed() || feed.is_some()) => {
+                log!(Verbose, "Closing idle connection");
                 break;
             }
-            Ok(_size) => {
-                socket.write_all(response.as_bytes()).await.unwrap();
+            Some(message) = messages.recv() => {
+                let (frame, size) = match message {
+                    Message::Published { frame, size } => (frame, size),
+                    Message::Invalidated { frame, size } if client.protocol() >= 3 => {
+                        (frame, size)
+                    }
+                    // Switched back to RESP2 since turning tracking on, where
+                    // the push would be taken for a reply
+                    Message::Invalidated { size, .. } => {
+                        subscriber.delivered(size);
+                        continue;
+                    }
+                    Message::Overflowed => {
+                        let reply = FrameValue::Error("ERR output buffer limit exceeded".into());
+                        let _ = connection.write_frame(reply).await;
+                        break;
+                    }
+                };
+                if let Err(e) = connection.write_frame(frame.for_protocol(client.protocol())).await {
+                    log!(Verbose, "Error: {e:?}");
+                    break;
+                }
+                subscriber.delivered(size);
+                continue;
+            }
+            frame = next_propagated(&mut feed) => {
+                let frame = match frame {
+                    Ok(frame) => frame,
+                    Err(e) => {
+                        log!(Warning, "Replica fell behind: {e:?}");
+                        break;
+                    }
+                };
+                if let Err(e) = connection.write_frame(frame).await {
+                    log!(Verbose, "Error: {e:?}");
+                    break;
+                }
+                continue;
+            }
+        };
+
+        log!(Debug, "[{addr}] {}", cmd::describe(&frame));
+        let propagated = cmd::is_write(&frame).then(|| frame.clone());
+        let blocking = cmd::is_blocking(&frame);
+        let needs_auth = client.user().is_none() && !cmd::allows_unauthenticated(&frame);
+        // Keys written invalidate what tracking connections read of them
+        let tracked_read = subscriber.is_tracking() && cmd::is_readonly(&frame);
+        let keys = if propagated.is_some() || tracked_read {
+            cmd::keys(&frame)
+        } else {
+            Vec::new()
+        };
+
+        // Set by commands that end the connection once they have replied
+        let mut close = false;
+        // Set by SHUTDOWN, which ends the connection without replying
+        let mut shutdown_requested = false;
+        // Set by commands listing a collection, whose reply is streamed
+        let mut array = None;
+        let responses = match Command::from_frame(frame) {
+            Ok(_) if needs_auth => {
+                vec![FrameValue::Error("NOAUTH Authentication required.".into())]
+            }
+            Ok(Command::PSync(cmd)) => {
+                connection.record_command();
+                let ip = addr.rsplit_once(':').map_or(&*addr, |(ip, _)| ip);
+                let (receiver, offset, snapshot) =
+                    db.replication().attach(client.id(), ip.to_string(), || {
+                        rdb::snapshot(&db.read_all())
+                    });
+                feed = Some(receiver);
+                let reply = cmd.apply(&db, offset);
+                if let Err(e) = full_resync(&mut connection, reply, &snapshot).await {
+                    log!(Verbose, "Error: {e:?}");
+                    break;
+                }
+                continue;
+            }
+            // Replicas acknowledge their offset without expecting a reply
+            Ok(Command::ReplConf(cmd)) if cmd.is_ack() => {
+                cmd.apply(&db, &client);
+                continue;
+            }
+            Ok(_) if read_only && propagated.is_some() => vec![FrameValue::Error(
+                "READONLY You can't write against a read only replica.".into(),
+            )],
+            Ok(_) if propagated.is_some() && shared.draining.load(Ordering::Relaxed) => {
+                vec![FrameValue::Error("ERR server is shutting down".into())]
+            }
+            Ok(cmd) => {
+                let write = propagated.is_some();
+                // CLIENT is let through so that the pause can still be lifted
+                if !matches!(cmd, Command::Client(_)) {
+                    shared.clients.wait_unpaused(write).await;
+                }
+                let dispatch = async {
+                    match cmd {
+                        Command::Subscribe(cmd) => cmd.apply(&mut subscriber),
+                        Command::Unsubscribe(cmd) => cmd.apply(&mut subscriber),
+                        Command::Client(cmd) if cmd.is_tracking() => {
+                            vec![cmd.apply_tracking(&mut subscriber, &client)]
+                        }
+                        Command::Select(cmd) => vec![cmd.apply(&mut db)],
+                        cmd => match propagated {
+                            Some(frame) => {
+                                let replication = db.replication();
+                                if let Some(error) = replication.aof_error() {
+                                    return vec![error];
+                                }
+                                let reply = replication
+                                    .execute(db.index(), || cmd.apply_write(&db, &client, frame));
+                                match replication.fsync_aof_always().await {
+                                    Ok(()) => vec![reply],
+                                    Err(error) => vec![error],
+                                }
+                            }
+                            None => match cmd.apply_async(&db, &client).await {
+                                CommandEffect::Reply(reply) => vec![reply],
+                                CommandEffect::CloseAfterReply(reply) => {
+                                    close = true;
+                                    vec![reply]
+                                }
+                                CommandEffect::ReplyArray(reply) => {
+                                    array = Some(reply);
+                                    vec![]
+                                }
+                                CommandEffect::Shutdown => {
+                                    shutdown_requested = true;
+                                    vec![]
+                                }
+                            },
+                        },
+                    }
+                };
+                let responses = match shared.config.command_timeout {
+                    Some(limit) if !blocking => tokio::time::timeout(limit, dispatch)
+                        .await
+                        .unwrap_or_else(|_| {
+                            vec![FrameValue::Error("ERR command timed out".into())]
+                        }),
+                    _ => dispatch.await,
+                };
+                connection.record_command();
+                if !matches!(responses.as_slice(), [FrameValue::Error(_)]) {
+                    if write {
+                        db.pubsub().invalidate(&keys);
+                    } else if tracked_read {
+                        subscriber.track(keys);
+                    }
+                }
+                responses
             }
             Err(e) => {
-                println!("Error: {e}");
-                break;
+                close = e.is_protocol_error();
+                vec![e.into_frame()]
+            }
+        };
+
+        if shutdown_requested {
+            let _ = shared.notify_shutdown.send(());
+            break;
+        }
+
+        let protocol = client.protocol();
+        let responses = responses
+            .into_iter()
+            .map(|response| response.for_protocol(protocol));
+        if let Err(e) = connection.buffer_frames(responses).await {
+            log!(Verbose, "Error: {e:?}");
+            break 'connection;
+        }
+        if let Some(array) = array {
+            let len = array.len();
+            let elements = array
+                .into_frames()
+                .map(|element| element.for_protocol(protocol));
+            if let Err(e) = connection.write_array_streaming(len, elements).await {
+                log!(Verbose, "Error: {e:?}");
+                break 'connection;
             }
         }
+        if close {
+            break;
+        }
+        // Replies to pipelined commands already read go out in one write, unless
+        // the next command may keep them waiting
+        let pipelined = connection
+            .peek_frame()
+            .is_some_and(|next| !cmd::is_blocking(next));
+        if !pipelined && let Err(e) = connection.flush().await {
+            log!(Verbose, "Error: {e:?}");
+            break;
+        }
+    }
+
+    // Whatever ended the connection, replies already buffered still go out
+    let _ = connection.flush().await;
+}
+
+/// Releases what a connection registered in shared state once `process` ends,
+/// whether on EOF, an error or a panic
+///
+/// Its subscriptions are released by dropping its [`Subscriber`](crate::pubsub::Subscriber).
+struct ConnectionGuard {
+    db: Db,
+    clients: ClientList,
+    id: u64,
+}
+
+impl Drop for ConnectionGuard {
+    fn drop(&mut self) {
+        self.db.replication().detach(self.id);
+        self.clients.remove(self.id);
+    }
+}
+
+/// Waits out `timeout`, forever if there is none or the connection is
+/// `exempt` from it
+async fn idle(timeout: Option<Duration>, exempt: bool) {
+    match timeout {
+        Some(timeout) if !exempt => tokio::time::sleep(timeout).await,
+        _ => std::future::pending().await,
+    }
+}
+
+/// Waits for the next write command to forward, forever if the connection isn't
+/// a replica
+async fn next_propagated(
+    feed: &mut Option<broadcast::Receiver<FrameValue>>,
+) -> Result<FrameValue, RecvError> {
+    match feed {
+        Some(feed) => feed.recv().await,
+        None => std::future::pending().await,
+    }
+}
+
+/// Replies to PSYNC and sends the replica the `snapshot` to start from
+async fn full_resync<S>(
+    connection: &mut Connection<S>,
+    reply: FrameValue,
+    snapshot: &[u8],
+) -> Result<(), FrameError>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    connection.write_frame(reply).await?;
+    connection.write_rdb(snapshot).await
+}
+
+#[cfg(test)]
+mod server_tests {
+    use super::*;
+    use crate::{cmd::command_frame, frame::Frame};
+    use bytes::{Bytes, BytesMut};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt, DuplexStream, duplex};
+    use tokio_util::codec::{Decoder, Encoder};
+
+    /// Runs `process` over an in-memory pipe, returning the client end
+    fn connect(db: Db) -> Connection<DuplexStream> {
+        Connection::new(connect_raw(db))
+    }
+
+    fn connect_raw(db: Db) -> DuplexStream {
+        connect_with(db, Config::default())
+    }
+
+    fn connect_with(db: Db, config: Config) -> DuplexStream {
+        connect_to(Shared {
+            db,
+            ..Shared::new(config)
+        })
+    }
+
+    /// Like [`connect_raw`], but to a server other connections can share, so
+    /// they get distinct client ids
+    fn connect_to(shared: Shared) -> DuplexStream {
+        let (client, server) = duplex(4 * 1024);
+        tokio::spawn(process(server, "memory".into(), shared));
+        client
+    }
+
+    async fn send(connection: &mut Connection<DuplexStream>, args: &[&str]) -> FrameValue {
+        connection.write_frame(command_frame(args)).await.unwrap();
+        connection.read_frame().await.unwrap().unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_set_get_without_tcp() {
+        let db = Db::new();
+        let mut connection = connect(db.clone());
+
+        assert_eq!(
+            send(&mut connection, &["SET", "key", "value"]).await,
+            FrameValue::SimpleString("OK".into())
+        );
+        assert_eq!(
+            send(&mut connection, &["GET", "key"]).await,
+            FrameValue::BulkString("value".into())
+        );
+
+        let mut other = connect(db);
+        assert_eq!(
+            send(&mut other, &["GET", "key"]).await,
+            FrameValue::BulkString("value".into())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_quit_closes_after_reply() {
+        let mut connection = connect(Db::new());
+
+        assert_eq!(
+            send(&mut connection, &["QUIT"]).await,
+            FrameValue::SimpleString("OK".into())
+        );
+        assert_eq!(connection.read_frame().await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_silent_connection_is_dropped() {
+        let config = Config {
+            tcp_keepalive: Some(Duration::from_millis(20)),
+            idle_timeout_factor: Some(2),
+            ..Config::default()
+        };
+        let mut silent = Connection::new(connect_with(Db::new(), config.clone()));
+        let mut subscriber = Connection::new(connect_with(Db::new(), config));
+        send(&mut subscriber, &["SUBSCRIBE", "news"]).await;
+
+        let closed = tokio::time::timeout(Duration::from_secs(5), silent.read_frame()).await;
+        assert_eq!(closed.unwrap().unwrap(), None);
+
+        // Subscribers wait for messages, so silence is expected of them
+        assert_eq!(
+            send(&mut subscriber, &["PING"]).await,
+            FrameValue::SimpleString("PONG".into())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_repl_offset_counts_writes_only() {
+        async fn offset(connection: &mut Connection<DuplexStream>) -> u64 {
+            let FrameValue::BulkString(info) = send(connection, &["INFO", "replication"]).await
+            else {
+                panic!("expected a bulk string");
+            };
+            let info = String::from_utf8(info.to_vec()).unwrap();
+            let line = info
+                .lines()
+                .find_map(|line| line.strip_prefix("master_repl_offset:"))
+                .unwrap();
+            line.parse().unwrap()
+        }
+
+        let mut connection = connect(Db::new());
+        assert_eq!(offset(&mut connection).await, 0);
+
+        send(&mut connection, &["SET", "key", "value"]).await;
+        let after_set = offset(&mut connection).await;
+        assert!(after_set > 0);
+
+        send(&mut connection, &["GET", "key"]).await;
+        send(&mut connection, &["DBSIZE"]).await;
+        assert_eq!(offset(&mut connection).await, after_set);
+
+        send(&mut connection, &["DEL", "key"]).await;
+        let after_del = offset(&mut connection).await;
+        assert!(after_del > after_set);
+        assert_eq!(
+            send(&mut connection, &["ROLE"]).await,
+            FrameValue::Array(vec![
+                FrameValue::BulkString("master".into()),
+                FrameValue::Integer(after_del as i64),
+                FrameValue::Array(vec![]),
+            ])
+        );
+    }
+
+    #[tokio::test]
+    async fn test_client_info_counts_commands() {
+        let mut connection = connect(Db::new());
+        send(&mut connection, &["PING"]).await;
+        send(&mut connection, &["SET", "key", "value"]).await;
+
+        let FrameValue::BulkString(info) = send(&mut connection, &["CLIENT", "INFO"]).await else {
+            panic!("expected a bulk string");
+        };
+        let info = String::from_utf8(info.to_vec()).unwrap();
+        assert!(info.contains(" tot-cmds=2"), "{info}");
+        assert!(!info.contains(" tot-net-in=0 "), "{info}");
+        assert!(!info.contains(" tot-net-out=0 "), "{info}");
+
+        let FrameValue::BulkString(info) = send(&mut connection, &["CLIENT", "INFO"]).await else {
+            panic!("expected a bulk string");
+        };
+        assert!(String::from_utf8_lossy(&info).contains(" tot-cmds=3"));
+    }
+
+    #[tokio::test]
+    async fn test_waiting_command_does_not_stall_others() {
+        let db = Db::new();
+        let mut sleeper = connect(db.clone());
+        let mut other = connect(db);
+
+        // The test runtime has a single thread, a blocked worker would stall both
+        sleeper
+            .write_frame(command_frame(&["DEBUG", "SLEEP", "5"]))
+            .await
+            .unwrap();
+        let reply = tokio::time::timeout(Duration::from_secs(1), async {
+            send(&mut other, &["SET", "key", "value"]).await;
+            send(&mut other, &["GET", "key"]).await
+        })
+        .await
+        .expect("unrelated connection stalled behind DEBUG SLEEP");
+        assert_eq!(reply, FrameValue::BulkString("value".into()));
+    }
+
+    #[tokio::test]
+    async fn test_subscribe_confirms_each_channel() {
+        let mut connection = connect(Db::new());
+        connection
+            .write_frame(command_frame(&["SUBSCRIBE", "a", "b", "c"]))
+            .await
+            .unwrap();
+
+        for (count, channel) in ["a", "b", "c"].into_iter().enumerate() {
+            assert_eq!(
+                connection.read_frame().await.unwrap().unwrap(),
+                FrameValue::Array(vec![
+                    FrameValue::BulkString("subscribe".into()),
+                    FrameValue::BulkString(channel.into()),
+                    FrameValue::Integer(count as i64 + 1),
+                ])
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_subscriptions_are_pushed_to_resp3_clients() {
+        let shared = Shared::new(Config::default());
+        let mut resp2 = Connection::new(connect_to(shared.clone()));
+        let mut resp3 = Connection::new(connect_to(shared.clone()));
+        let mut publisher = Connection::new(connect_to(shared));
+        send(&mut resp3, &["HELLO", "3"]).await;
+
+        let confirmation = vec![
+            FrameValue::BulkString("subscribe".into()),
+            FrameValue::BulkString("news".into()),
+            FrameValue::Integer(1),
+        ];
+        assert_eq!(
+            send(&mut resp3, &["SUBSCRIBE", "news"]).await,
+            FrameValue::Push(confirmation.clone())
+        );
+        assert_eq!(
+            send(&mut resp2, &["SUBSCRIBE", "news"]).await,
+            FrameValue::Array(confirmation)
+        );
+
+        send(&mut publisher, &["PUBLISH", "news", "hello"]).await;
+        let message = vec![
+            FrameValue::BulkString("message".into()),
+            FrameValue::BulkString("news".into()),
+            FrameValue::BulkString("hello".into()),
+        ];
+        assert_eq!(
+            resp3.read_frame().await.unwrap().unwrap(),
+            FrameValue::Push(message.clone())
+        );
+        assert_eq!(
+            resp2.read_frame().await.unwrap().unwrap(),
+            FrameValue::Array(message)
+        );
+
+        // Replies to ordinary commands stay distinguishable from pushes
+        assert_eq!(
+            send(&mut resp3, &["PING"]).await,
+            FrameValue::SimpleString("PONG".into())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_info_counts_commands_and_bytes() {
+        let mut connection = connect(Db::new());
+        let stat = |report: &FrameValue, field: &str| {
+            let FrameValue::BulkString(report) = report else {
+                panic!("expected a bulk string, got {report:?}");
+            };
+            let prefix = format!("{field}:");
+            String::from_utf8_lossy(report)
+                .lines()
+                .find_map(|line| line.strip_prefix(&prefix)?.parse::<u64>().ok())
+                .unwrap_or_else(|| panic!("{field} missing"))
+        };
+
+        let commands: [&[&str]; 4] = [
+            &["SET", "key", "value"],
+            &["GET", "key"],
+            &["PING"],
+            &["NOSUCHCOMMAND"],
+        ];
+        let mut input = 0;
+        let mut output = 0;
+        for args in commands {
+            input += command_frame(args).len();
+            output += send(&mut connection, args).await.len();
+        }
+        input += command_frame(&["INFO", "stats"]).len();
+
+        // INFO counts itself once it has run, and its reply once written
+        let report = send(&mut connection, &["INFO", "stats"]).await;
+        assert_eq!(stat(&report, "total_commands_processed"), 3);
+        assert_eq!(stat(&report, "total_net_input_bytes"), input as u64);
+        assert_eq!(stat(&report, "total_net_output_bytes"), output as u64);
+
+        let report = send(&mut connection, &["INFO", "stats"]).await;
+        assert_eq!(stat(&report, "total_commands_processed"), 4);
+    }
+
+    #[tokio::test]
+    async fn test_dropped_subscriber_is_released() {
+        let db = Db::new();
+        let mut subscriber = connect(db.clone());
+        send(&mut subscriber, &["SUBSCRIBE", "news"]).await;
+        assert_eq!(db.pubsub().numsub(b"news"), 1);
+
+        drop(subscriber);
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while db.pubsub().numsub(b"news") > 0 {
+                tokio::task::yield_now().await;
+            }
+        })
+        .await
+        .expect("subscription outlived its connection");
+    }
+
+    #[tokio::test]
+    async fn test_publish_reaches_subscriber() {
+        let db = Db::new();
+        let mut subscriber = connect(db.clone());
+        let mut publisher = connect(db);
+
+        send(&mut subscriber, &["SUBSCRIBE", "news"]).await;
+        assert_eq!(
+            send(&mut publisher, &["PUBLISH", "news", "hello"]).await,
+            FrameValue::Integer(1)
+        );
+        assert_eq!(
+            subscriber.read_frame().await.unwrap().unwrap(),
+            FrameValue::Array(vec![
+                FrameValue::BulkString("message".into()),
+                FrameValue::BulkString("news".into()),
+                FrameValue::BulkString("hello".into()),
+            ])
+        );
+
+        assert_eq!(
+            send(&mut subscriber, &["UNSUBSCRIBE"]).await,
+            FrameValue::Array(vec![
+                FrameValue::BulkString("unsubscribe".into()),
+                FrameValue::BulkString("news".into()),
+                FrameValue::Integer(0),
+            ])
+        );
+        assert_eq!(
+            send(&mut publisher, &["PUBLISH", "news", "hello"]).await,
+            FrameValue::Integer(0)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_tracked_key_is_invalidated() {
+        let db = Db::new();
+        let mut tracker = connect(db.clone());
+        let mut writer = connect(db);
+
+        send(&mut tracker, &["HELLO", "3"]).await;
+        assert_eq!(
+            send(&mut tracker, &["CLIENT", "TRACKING", "on"]).await,
+            FrameValue::SimpleString("OK".into())
+        );
+        send(&mut writer, &["SET", "key", "1"]).await;
+        send(&mut tracker, &["GET", "key"]).await;
+
+        send(&mut writer, &["SET", "key", "2"]).await;
+        assert_eq!(
+            tracker.read_frame().await.unwrap().unwrap(),
+            FrameValue::Push(vec![
+                FrameValue::BulkString("invalidate".into()),
+                FrameValue::Array(vec![FrameValue::BulkString("key".into())]),
+            ])
+        );
+
+        // The key has to be read again before it is reported again
+        send(&mut writer, &["SET", "key", "3"]).await;
+        send(&mut writer, &["SET", "other", "1"]).await;
+        assert_eq!(
+            send(&mut tracker, &["PING"]).await,
+            FrameValue::SimpleString("PONG".into())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_resp2_tracking_needs_a_redirect() {
+        let db = Db::new();
+        let mut tracker = connect(db.clone());
+        let mut listener = connect(db.clone());
+        let mut writer = connect(db);
+
+        assert_eq!(
+            send(&mut tracker, &["CLIENT", "TRACKING", "ON"]).await,
+            FrameValue::Error(
+                "ERR CLIENT TRACKING ON needs REDIRECT with RESP2, or HELLO 3 first".into()
+            )
+        );
+        assert_eq!(
+            send(
+                &mut tracker,
+                &["CLIENT", "TRACKING", "ON", "REDIRECT", "999"]
+            )
+            .await,
+            FrameValue::Error("ERR The client ID you want redirect to does not exist".into())
+        );
+
+        let FrameValue::BulkString(info) = send(&mut listener, &["CLIENT", "INFO"]).await else {
+            panic!("expected the client info");
+        };
+        let id = std::str::from_utf8(&info)
+            .unwrap()
+            .split(' ')
+            .find_map(|field| field.strip_prefix("id="))
+            .unwrap()
+            .to_string();
+        send(&mut listener, &["SUBSCRIBE", "__redis__:invalidate"]).await;
+        assert_eq!(
+            send(&mut tracker, &["CLIENT", "TRACKING", "ON", "REDIRECT", &id]).await,
+            FrameValue::SimpleString("OK".into())
+        );
+        send(&mut tracker, &["GET", "key"]).await;
+        send(&mut writer, &["SET", "key", "1"]).await;
+
+        // Published to the redirect client, nothing arriving inline
+        assert_eq!(
+            listener.read_frame().await.unwrap().unwrap(),
+            FrameValue::Array(vec![
+                FrameValue::BulkString("message".into()),
+                FrameValue::BulkString("__redis__:invalidate".into()),
+                FrameValue::Array(vec![FrameValue::BulkString("key".into())]),
+            ])
+        );
+        assert_eq!(
+            send(&mut tracker, &["PING"]).await,
+            FrameValue::SimpleString("PONG".into())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_collection_replies_are_streamed() {
+        let db = Db::new();
+        let members: Vec<String> = (0..10_000).map(|i| format!("member:{i}")).collect();
+        let mut args = vec!["SADD", "set"];
+        args.extend(members.iter().map(String::as_str));
+        cmd::run(&db, &args);
+        let mut connection = connect(db);
+
+        let reply = send(&mut connection, &["SMEMBERS", "set"]).await;
+        let mut expected: Vec<Bytes> = members.into_iter().map(Bytes::from).collect();
+        expected.sort();
+        assert_eq!(cmd::sorted_bulk_strings(reply), expected);
+        // The connection carries on past the streamed reply
+        assert_eq!(
+            send(&mut connection, &["SMEMBERS", "missing"]).await,
+            FrameValue::Array(vec![])
+        );
+    }
+
+    #[tokio::test]
+    async fn test_protocol_error_closes_connection() {
+        let mut client = connect_raw(Db::new());
+        client.write_all(b"?garbage\r\n").await.unwrap();
+        let mut connection = Connection::new(client);
+
+        assert_eq!(
+            connection.read_frame().await.unwrap(),
+            Some(FrameValue::Error("ERR Protocol error".into()))
+        );
+        assert_eq!(connection.read_frame().await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_protocol_resync_keeps_connection_open() {
+        let config = Config {
+            protocol_resync: true,
+            ..Config::default()
+        };
+        let mut client = connect_with(Db::new(), config);
+        client
+            .write_all(b"?garbage\r\n*1\r\n$4\r\nPING\r\n")
+            .await
+            .unwrap();
+        let mut connection = Connection::new(client);
+
+        assert_eq!(
+            connection.read_frame().await.unwrap(),
+            Some(FrameValue::Error("ERR Protocol error".into()))
+        );
+        assert_eq!(
+            connection.read_frame().await.unwrap(),
+            Some(FrameValue::SimpleString("PONG".into()))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_too_many_arguments_close_connection() {
+        let config = Config {
+            max_array_len: 3,
+            ..Config::default()
+        };
+        let mut connection = Connection::new(connect_with(Db::new(), config));
+
+        assert_eq!(
+            send(&mut connection, &["SET", "key", "value"]).await,
+            FrameValue::SimpleString("OK".into())
+        );
+        assert_eq!(
+            send(&mut connection, &["MSET", "key", "value", "other", "value"]).await,
+            FrameValue::Error("ERR Protocol error".into())
+        );
+        assert_eq!(connection.read_frame().await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_reply_sent_as_command_closes_connection() {
+        let mut client = connect_raw(Db::new());
+        client.write_all(b"+FOO\r\n").await.unwrap();
+        let mut connection = Connection::new(client);
+
+        assert_eq!(
+            connection.read_frame().await.unwrap(),
+            Some(FrameValue::Error(
+                "ERR Protocol error: expected '*', got '+'".into()
+            ))
+        );
+        assert_eq!(connection.read_frame().await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_null_replies_follow_protocol() {
+        /// Raw bytes of the replies to `commands`, sent as one pipeline
+        async fn replies(commands: &[&[&str]]) -> Vec<Bytes> {
+            let mut client = connect_raw(Db::new());
+            let mut request = BytesMut::new();
+            for args in commands {
+                Frame::new()
+                    .encode(command_frame(args), &mut request)
+                    .unwrap();
+            }
+            client.write_all(&request).await.unwrap();
+
+            let mut buf = BytesMut::new();
+            let mut replies = Vec::new();
+            while replies.len() < commands.len() {
+                let mut rest = buf.clone();
+                match Frame::new().decode(&mut rest).unwrap() {
+                    Some(_) => replies.push(buf.split_to(buf.len() - rest.len()).freeze()),
+                    None => assert!(client.read_buf(&mut buf).await.unwrap() > 0),
+                }
+            }
+            replies
+        }
+
+        let resp2 = replies(&[&["GET", "missing"], &["COMMAND", "INFO", "nosuch"]]).await;
+        assert_eq!(resp2, ["$-1\r\n", "*1\r\n*-1\r\n"]);
+
+        let resp3 = replies(&[
+            &["HELLO", "3"],
+            &["GET", "missing"],
+            &["COMMAND", "INFO", "nosuch"],
+        ])
+        .await;
+        assert_eq!(resp3[1..], ["_\r\n", "*1\r\n_\r\n"]);
+    }
+
+    #[tokio::test]
+    async fn test_command_error_keeps_connection_open() {
+        let mut connection = connect(Db::new());
+
+        assert!(matches!(
+            send(&mut connection, &["NOSUCHCOMMAND"]).await,
+            FrameValue::Error(_)
+        ));
+        assert_eq!(
+            send(&mut connection, &["PING"]).await,
+            FrameValue::SimpleString("PONG".into())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_accepted_sockets_are_tuned() {
+        let config = Config::default();
+        let listener = bind("127.0.0.1:0".parse().unwrap(), &config).unwrap();
+        let client = TcpStream::connect(listener.local_addr().unwrap());
+        let (accepted, client) = tokio::join!(accept(&listener, &config), client);
+        let (socket, addr) = accepted.unwrap();
+
+        assert_eq!(addr, client.unwrap().local_addr().unwrap());
+        assert!(socket.nodelay().unwrap());
+        assert!(SockRef::from(&socket).keepalive().unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_stalled_subscriber_is_dropped() {
+        let db = Db::new();
+        let config = Config {
+            pubsub_output_soft_limit: 1024,
+            pubsub_output_hard_limit: 4096,
+            ..Config::default()
+        };
+        let mut subscriber = Connection::new(connect_with(db.clone(), config));
+        let mut publisher = connect(db);
+        send(&mut subscriber, &["SUBSCRIBE", "news"]).await;
+
+        // Nothing reads the subscriber's end, so once the pipe fills up its
+        // messages start to pile up on the server
+        let message = "x".repeat(200);
+        let mut delivered = 0;
+        for _ in 0..100 {
+            match send(&mut publisher, &["PUBLISH", "news", &message]).await {
+                FrameValue::Integer(1) => delivered += 1,
+                FrameValue::Integer(0) => {}
+                frame => panic!("unexpected reply {frame:?}"),
+            }
+        }
+        assert!(delivered < 100);
+
+        for _ in 0..delivered {
+            assert!(matches!(
+                subscriber.read_frame().await.unwrap(),
+                Some(FrameValue::Array(_))
+            ));
+        }
+        assert_eq!(
+            subscriber.read_frame().await.unwrap(),
+            Some(FrameValue::Error("ERR output buffer limit exceeded".into()))
+        );
+        assert_eq!(subscriber.read_frame().await.unwrap(), None);
+    }
+
+    /// Reads messages published to `news`, checking they are numbered from 0 with
+    /// no gaps, up to the error a disconnected subscriber gets
+    async fn read_until_overflow(subscriber: &mut Connection<DuplexStream>) -> usize {
+        let mut received = 0;
+        loop {
+            match subscriber.read_frame().await.unwrap() {
+                Some(FrameValue::Array(frames)) => {
+                    let FrameValue::BulkString(message) = &frames[2] else {
+                        panic!("expected a bulk string");
+                    };
+                    assert!(message.starts_with(format!("{received}:").as_bytes()));
+                    received += 1;
+                }
+                Some(FrameValue::Error(e)) => {
+                    assert_eq!(e, "ERR output buffer limit exceeded");
+                    assert_eq!(subscriber.read_frame().await.unwrap(), None);
+                    return received;
+                }
+                frame => panic!("unexpected frame {frame:?}"),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_slow_subscriber_gets_every_message_until_disconnected() {
+        let db = Db::new();
+        let config = Config {
+            pubsub_output_hard_limit: 4096,
+            ..Config::default()
+        };
+        let mut subscriber = Connection::new(connect_with(db.clone(), config));
+        let mut publisher = connect(db);
+        send(&mut subscriber, &["SUBSCRIBE", "news"]).await;
+
+        let mut published = 0;
+        for i in 0..200 {
+            let message = format!("{i}:{}", "x".repeat(100));
+            match send(&mut publisher, &["PUBLISH", "news", &message]).await {
+                FrameValue::Integer(1) => published += 1,
+                FrameValue::Integer(0) => break,
+                frame => panic!("unexpected reply {frame:?}"),
+            }
+        }
+        assert!(published < 200);
+        assert_eq!(read_until_overflow(&mut subscriber).await, published);
+    }
+
+    #[tokio::test]
+    async fn test_subscriber_over_soft_limit_for_too_long_is_dropped() {
+        let db = Db::new();
+        let config = Config {
+            pubsub_output_soft_limit: 1024,
+            pubsub_output_soft_duration: Duration::from_millis(50),
+            ..Config::default()
+        };
+        let mut subscriber = Connection::new(connect_with(db.clone(), config));
+        let mut publisher = connect(db);
+        send(&mut subscriber, &["SUBSCRIBE", "news"]).await;
+
+        // Fill the pipe and then the queue past the soft limit, which is allowed
+        // for a while
+        let message = |i: usize| format!("{i}:{}", "x".repeat(500));
+        let mut published = 0;
+        while published < 30 {
+            let reply = send(&mut publisher, &["PUBLISH", "news", &message(published)]).await;
+            assert_eq!(reply, FrameValue::Integer(1));
+            published += 1;
+        }
+
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(
+            send(&mut publisher, &["PUBLISH", "news", &message(published)]).await,
+            FrameValue::Integer(0)
+        );
+        assert_eq!(read_until_overflow(&mut subscriber).await, published);
+    }
+
+    /// Sends PSYNC and reads back the reply and snapshot, returning the reply
+    async fn psync(client: &mut DuplexStream) -> String {
+        let mut request = BytesMut::new();
+        Frame::new()
+            .encode(command_frame(&["PSYNC", "?", "-1"]), &mut request)
+            .unwrap();
+        client.write_all(&request).await.unwrap();
+
+        // The snapshot isn't a frame, so read everything as raw bytes
+        let mut reply = vec![0; "+FULLRESYNC  0\r\n".len() + 40];
+        client.read_exact(&mut reply).await.unwrap();
+
+        let snapshot = rdb::empty_snapshot();
+        let expected = [format!("${}\r\n", snapshot.len()).as_bytes(), &snapshot].concat();
+        let mut payload = vec![0; expected.len()];
+        client.read_exact(&mut payload).await.unwrap();
+        assert_eq!(payload, expected);
+
+        String::from_utf8(reply).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_replica_handshake() {
+        let mut client = connect_raw(Db::new());
+        let mut handshake = BytesMut::new();
+        for args in [
+            &["PING"][..],
+            &["REPLCONF", "listening-port", "6380"],
+            &["REPLCONF", "capa", "psync2"],
+        ] {
+            Frame::new()
+                .encode(command_frame(args), &mut handshake)
+                .unwrap();
+        }
+        client.write_all(&handshake).await.unwrap();
+
+        let mut replies = vec![0; "+PONG\r\n+OK\r\n+OK\r\n".len()];
+        client.read_exact(&mut replies).await.unwrap();
+        assert_eq!(replies, b"+PONG\r\n+OK\r\n+OK\r\n");
+
+        let reply = psync(&mut client).await;
+        let fields: Vec<_> = reply.trim_end().split(' ').collect();
+        assert_eq!(fields[0], "+FULLRESYNC");
+        assert_eq!(fields[1].len(), 40);
+        assert!(fields[1].chars().all(|c| c.is_ascii_hexdigit()));
+        assert_eq!(fields[2], "0");
+    }
+
+    #[tokio::test]
+    async fn test_writes_reach_replica() {
+        let db = Db::new();
+        let mut replica = connect_raw(db.clone());
+        psync(&mut replica).await;
+        let mut replica = Connection::new(replica);
+        let mut master = connect(db);
+
+        send(&mut master, &["SET", "a", "1"]).await;
+        send(&mut master, &["GET", "a"]).await;
+        // Fails with WRONGTYPE, so it changes nothing
+        send(&mut master, &["SADD", "a", "x"]).await;
+        send(&mut master, &["SET", "b", "2"]).await;
+        send(&mut master, &["SELECT", "2"]).await;
+        send(&mut master, &["SET", "c", "3"]).await;
+
+        let expected = [
+            &["SELECT", "0"][..],
+            &["SET", "a", "1"],
+            &["SET", "b", "2"],
+            &["SELECT", "2"],
+            &["SET", "c", "3"],
+        ];
+        for args in expected {
+            assert_eq!(
+                replica.read_frame().await.unwrap(),
+                Some(command_frame(args))
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_spop_reaches_replica_as_srem() {
+        let db = Db::new();
+        let mut replica = connect_raw(db.clone());
+        psync(&mut replica).await;
+        let mut replica = Connection::new(replica);
+        let mut master = connect(db);
+
+        send(&mut master, &["SADD", "set", "a", "b", "c"]).await;
+        let FrameValue::Array(popped) = send(&mut master, &["SPOP", "set", "2"]).await else {
+            panic!("expected an array");
+        };
+        // Popping nothing changes nothing, so isn't forwarded
+        send(&mut master, &["SPOP", "missing"]).await;
+        let last = send(&mut master, &["SPOP", "set"]).await;
+
+        let srem = |popped: Vec<FrameValue>| {
+            let command = ["SREM", "set"].map(|arg| FrameValue::BulkString(arg.into()));
+            FrameValue::Array(command.into_iter().chain(popped).collect())
+        };
+        let expected = [
+            command_frame(&["SELECT", "0"]),
+            command_frame(&["SADD", "set", "a", "b", "c"]),
+            srem(popped),
+            srem(vec![last]),
+        ];
+        for frame in expected {
+            assert_eq!(replica.read_frame().await.unwrap(), Some(frame));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_guard_cleans_up_after_a_panic() {
+        let db = Db::new();
+        let clients = ClientList::default();
+        let client = clients.register("127.0.0.1:1".into(), Arc::default());
+        let (_feed, ..) = db
+            .replication()
+            .attach(client.id(), "127.0.0.1".into(), || ());
+
+        let task = tokio::spawn({
+            let (db, clients, id) = (db.clone(), clients.clone(), client.id());
+            async move {
+                let _guard = ConnectionGuard {
+                    db: db.clone(),
+                    clients,
+                    id,
+                };
+                // Poisons the replication state, which the guard then locks
+                db.replication().execute(0, || panic!("command panicked"));
+            }
+        });
+
+        assert!(task.await.unwrap_err().is_panic());
+        assert_eq!(client.info(), "");
+        assert!(db.replication().replicas().is_empty());
+        // Replication carries on for everyone else
+        let reply = db.replication().execute(0, || {
+            (
+                FrameValue::SimpleString("OK".into()),
+                Some(command_frame(&["SET", "key", "value"])),
+            )
+        });
+        assert_eq!(reply, FrameValue::SimpleString("OK".into()));
+    }
+
+    /// Stream that counts how often it is flushed
+    struct CountingFlushes {
+        inner: DuplexStream,
+        flushes: Arc<std::sync::atomic::AtomicUsize>,
+    }
+
+    impl AsyncRead for CountingFlushes {
+        fn poll_read(
+            mut self: std::pin::Pin<&mut Self>,
+            cx: &mut std::task::Context<'_>,
+            buf: &mut tokio::io::ReadBuf<'_>,
+        ) -> std::task::Poll<io::Result<()>> {
+            std::pin::Pin::new(&mut self.inner).poll_read(cx, buf)
+        }
+    }
+
+    impl AsyncWrite for CountingFlushes {
+        fn poll_write(
+            mut self: std::pin::Pin<&mut Self>,
+            cx: &mut std::task::Context<'_>,
+            buf: &[u8],
+        ) -> std::task::Poll<io::Result<usize>> {
+            std::pin::Pin::new(&mut self.inner).poll_write(cx, buf)
+        }
+
+        fn poll_flush(
+            mut self: std::pin::Pin<&mut Self>,
+            cx: &mut std::task::Context<'_>,
+        ) -> std::task::Poll<io::Result<()>> {
+            self.flushes.fetch_add(1, Ordering::Relaxed);
+            std::pin::Pin::new(&mut self.inner).poll_flush(cx)
+        }
+
+        fn poll_shutdown(
+            mut self: std::pin::Pin<&mut Self>,
+            cx: &mut std::task::Context<'_>,
+        ) -> std::task::Poll<io::Result<()>> {
+            std::pin::Pin::new(&mut self.inner).poll_shutdown(cx)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_pipelined_replies_share_a_flush() {
+        let (mut client, server) = duplex(4 * 1024);
+        let flushes = Arc::default();
+        let server = CountingFlushes {
+            inner: server,
+            flushes: Arc::clone(&flushes),
+        };
+        tokio::spawn(process(
+            server,
+            "memory".into(),
+            Shared::new(Config::default()),
+        ));
+
+        let mut pipeline = BytesMut::new();
+        for args in [&["SET", "key", "1"][..], &["INCR", "key"], &["GET", "key"]] {
+            Frame::new()
+                .encode(command_frame(args), &mut pipeline)
+                .unwrap();
+        }
+        client.write_all(&pipeline).await.unwrap();
+        let mut client = Connection::new(client);
+
+        for expected in [
+            FrameValue::SimpleString("OK".into()),
+            FrameValue::Integer(2),
+            FrameValue::BulkString("2".into()),
+        ] {
+            assert_eq!(client.read_frame().await.unwrap(), Some(expected));
+        }
+        assert_eq!(flushes.load(Ordering::Relaxed), 1);
+
+        // A command on its own is flushed by itself
+        client
+            .write_frame(command_frame(&["GET", "key"]))
+            .await
+            .unwrap();
+        client.read_frame().await.unwrap();
+        assert_eq!(flushes.load(Ordering::Relaxed), 2);
+    }
+
+    #[tokio::test]
+    async fn test_command_timeout_aborts_slow_command() {
+        let config = Config {
+            command_timeout: Some(Duration::from_millis(50)),
+            ..Config::default()
+        };
+        let mut connection = Connection::new(connect_with(Db::new(), config));
+
+        let started = tokio::time::Instant::now();
+        assert_eq!(
+            send(&mut connection, &["DEBUG", "SLEEP", "10"]).await,
+            FrameValue::Error("ERR command timed out".into())
+        );
+        assert!(started.elapsed() < Duration::from_secs(5));
+
+        assert_eq!(
+            send(&mut connection, &["DEBUG", "SLEEP", "0.001"]).await,
+            FrameValue::SimpleString("OK".into())
+        );
+        assert_eq!(
+            send(&mut connection, &["PING"]).await,
+            FrameValue::SimpleString("PONG".into())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_hello_with_inline_auth() {
+        let db = Db::new().with_acl(Acl::new(Some("secret".into())));
+        let mut connection = connect(db);
+        let noauth = FrameValue::Error("NOAUTH Authentication required.".into());
+        let wrongpass = FrameValue::Error(
+            "WRONGPASS invalid username-password pair or user is disabled.".into(),
+        );
+
+        assert_eq!(send(&mut connection, &["GET", "key"]).await, noauth);
+        assert_eq!(
+            send(&mut connection, &["HELLO", "3", "AUTH", "default", "wrong"]).await,
+            wrongpass
+        );
+        assert_eq!(send(&mut connection, &["GET", "key"]).await, noauth);
+
+        let FrameValue::Array(fields) = send(
+            &mut connection,
+            &["HELLO", "3", "AUTH", "default", "secret"],
+        )
+        .await
+        else {
+            panic!("expected an array");
+        };
+        let proto = fields
+            .chunks(2)
+            .find(|pair| pair[0] == FrameValue::BulkString("proto".into()));
+        assert_eq!(proto.unwrap()[1], FrameValue::Integer(3));
+        assert_eq!(
+            send(&mut connection, &["GET", "key"]).await,
+            FrameValue::Null
+        );
+
+        // Bad credentials leave the negotiated protocol alone
+        assert_eq!(
+            send(&mut connection, &["HELLO", "2", "AUTH", "default", "wrong"]).await,
+            wrongpass
+        );
+        let FrameValue::BulkString(info) = send(&mut connection, &["CLIENT", "INFO"]).await else {
+            panic!("expected a bulk string");
+        };
+        assert!(String::from_utf8_lossy(&info).contains(" user=default resp=3"));
+    }
+
+    #[tokio::test]
+    async fn test_read_only_replica_rejects_write_flagged_commands() {
+        // The replica link isn't started by `process`, so no master is needed
+        let replica = |replica_read_only| Config {
+            replicaof: Some(("127.0.0.1".into(), 0)),
+            replica_read_only,
+            ..Config::default()
+        };
+        let readonly =
+            FrameValue::Error("READONLY You can't write against a read only replica.".into());
+
+        let mut connection = Connection::new(connect_with(Db::new(), replica(true)));
+        for args in [
+            &["SET", "key", "value"][..],
+            &["DEL", "key"],
+            &["SADD", "set", "a"],
+        ] {
+            assert_eq!(send(&mut connection, args).await, readonly);
+        }
+        assert_eq!(
+            send(&mut connection, &["GET", "key"]).await,
+            FrameValue::NullBulkString
+        );
+
+        let mut connection = Connection::new(connect_with(Db::new(), replica(false)));
+        assert_eq!(
+            send(&mut connection, &["SET", "key", "value"]).await,
+            FrameValue::SimpleString("OK".into())
+        );
     }
 }
@@ -0,0 +1,240 @@
+//! Members ordered by a floating point score, the value behind Redis sorted sets
+
+use crate::dict::Dict;
+use bytes::Bytes;
+use std::{cmp::Ordering, collections::BTreeSet, ops::Bound};
+
+/// Members with their scores, kept ordered by score and then by member
+///
+/// Scores are looked up and scanned through a [`Dict`] and ranges walk an
+/// ordered set, so every member is stored in both. Scores are never NaN.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SortedSet {
+    scores: Dict<f64>,
+    ordered: BTreeSet<(Score, Bytes)>,
+}
+
+/// Score ordered totally, which is sound as NaN is never stored
+#[derive(Clone, Copy, Debug)]
+struct Score(f64);
+
+impl PartialEq for Score {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Score {}
+
+impl PartialOrd for Score {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Score {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // Unlike `total_cmp`, this has -0 and 0 equal as they are in Redis
+        self.0.partial_cmp(&other.0).expect("scores are never NaN")
+    }
+}
+
+impl SortedSet {
+    pub fn len(&self) -> usize {
+        self.scores.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.scores.is_empty()
+    }
+
+    pub fn score(&self, member: &[u8]) -> Option<f64> {
+        self.scores.get(member).copied()
+    }
+
+    /// Sets the score of `member`, returning the one it replaced if it was present
+    pub fn insert(&mut self, member: Bytes, score: f64) -> Option<f64> {
+        assert!(!score.is_nan(), "scores are never NaN");
+
+        let old = self.scores.insert(member.clone(), score);
+        if let Some(old) = old {
+            self.ordered.remove(&(Score(old), member.clone()));
+        }
+        self.ordered.insert((Score(score), member));
+        old
+    }
+
+    /// Removes `member`, returning its score if it was present
+    pub fn remove(&mut self, member: &[u8]) -> Option<f64> {
+        let (member, score) = self.scores.remove_entry(member)?;
+        self.ordered.remove(&(Score(score), member));
+        Some(score)
+    }
+
+    /// Members from scan position `cursor` on, in scan order, each with its
+    /// position and score
+    pub fn scan_from(&self, cursor: u64) -> impl Iterator<Item = (u64, &Bytes, f64)> {
+        self.scores
+            .scan_from(cursor)
+            .map(|(position, member, score)| (position, member, *score))
+    }
+
+    /// Number of members ordered before `member`, if it is present
+    ///
+    /// The ordered set keeps no counts, so this walks every member before it.
+    pub fn rank(&self, member: &Bytes) -> Option<usize> {
+        let score = self.score(member)?;
+        Some(self.ordered.range(..(Score(score), member.clone())).count())
+    }
+
+    /// Members and their scores from the lowest score up
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = (&Bytes, f64)> {
+        self.ordered.iter().map(|(score, member)| (member, score.0))
+    }
+
+    /// Members with scores between `min` and `max`, from the lowest score up
+    ///
+    /// The scan starts from the first member at or above `min`, so only members
+    /// with a score equal to an exclusive `min` are walked past.
+    pub fn range_by_score(
+        &self,
+        min: Bound<f64>,
+        max: Bound<f64>,
+    ) -> impl Iterator<Item = (&Bytes, f64)> {
+        // The empty member sorts before any other with the same score
+        let start = match min {
+            Bound::Included(min) | Bound::Excluded(min) => {
+                Bound::Included((Score(min), Bytes::new()))
+            }
+            Bound::Unbounded => Bound::Unbounded,
+        };
+
+        self.ordered
+            .range((start, Bound::Unbounded))
+            .map(|(score, member)| (member, score.0))
+            .skip_while(move |&(_, score)| min == Bound::Excluded(score))
+            .take_while(move |&(_, score)| match max {
+                Bound::Included(max) => score <= max,
+                Bound::Excluded(max) => score < max,
+                Bound::Unbounded => true,
+            })
+    }
+
+    /// Members between `min` and `max` in byte order, from the lowest up
+    ///
+    /// Like in Redis, this is only meaningful when every member has the same
+    /// score, the scan starting from `min` at the score of the first member.
+    pub fn range_by_lex(
+        &self,
+        min: Bound<Bytes>,
+        max: Bound<Bytes>,
+    ) -> impl Iterator<Item = (&Bytes, f64)> {
+        let score = self.ordered.first().map_or(Score(0.0), |(score, _)| *score);
+        let start = match min {
+            Bound::Included(min) => Bound::Included((score, min)),
+            Bound::Excluded(min) => Bound::Excluded((score, min)),
+            Bound::Unbounded => Bound::Unbounded,
+        };
+
+        self.ordered
+            .range((start, Bound::Unbounded))
+            .map(|(score, member)| (member, score.0))
+            .take_while(move |&(member, _)| match &max {
+                Bound::Included(max) => member <= max,
+                Bound::Excluded(max) => member < max,
+                Bound::Unbounded => true,
+            })
+    }
+}
+
+impl FromIterator<(Bytes, f64)> for SortedSet {
+    fn from_iter<I: IntoIterator<Item = (Bytes, f64)>>(iter: I) -> Self {
+        let mut set = Self::default();
+        for (member, score) in iter {
+            set.insert(member, score);
+        }
+        set
+    }
+}
+
+/// Formats a score the way Redis replies with one
+///
+/// That is the shortest decimal that reads back as the same number, with
+/// infinities spelled `inf` and `-inf`.
+pub fn format_score(score: f64) -> Bytes {
+    score.to_string().into()
+}
+
+#[cfg(test)]
+mod sorted_set_tests {
+    use super::*;
+
+    #[test]
+    fn test_ordered_by_score_then_member() {
+        let set: SortedSet = [
+            (Bytes::from("b"), 1.0),
+            (Bytes::from("c"), -2.5),
+            (Bytes::from("a"), 1.0),
+            (Bytes::from("d"), f64::INFINITY),
+        ]
+        .into_iter()
+        .collect();
+
+        let members: Vec<_> = set.iter().map(|(member, _)| member.clone()).collect();
+        assert_eq!(members, ["c", "a", "b", "d"]);
+    }
+
+    #[test]
+    fn test_insert_replaces_score() {
+        let mut set = SortedSet::default();
+
+        assert_eq!(set.insert("a".into(), 1.0), None);
+        assert_eq!(set.insert("b".into(), 2.0), None);
+        assert_eq!(set.insert("a".into(), 3.0), Some(1.0));
+
+        let order: Vec<_> = set
+            .iter()
+            .map(|(member, score)| (member.clone(), score))
+            .collect();
+        assert_eq!(order, [("b".into(), 2.0), ("a".into(), 3.0)]);
+        assert_eq!(set.len(), 2);
+        assert_eq!(set.score(b"a"), Some(3.0));
+        assert_eq!(set.rank(&"a".into()), Some(1));
+
+        assert_eq!(set.remove(b"b"), Some(2.0));
+        assert_eq!(set.remove(b"b"), None);
+        assert_eq!(set.rank(&"a".into()), Some(0));
+        assert_eq!(set.rank(&"b".into()), None);
+    }
+
+    #[test]
+    fn test_range_by_score() {
+        let set: SortedSet = [("a", 1.0), ("b", 2.0), ("c", 2.0), ("d", 3.0)]
+            .map(|(member, score)| (Bytes::from(member), score))
+            .into_iter()
+            .collect();
+        let members = |min, max| -> Vec<_> {
+            set.range_by_score(min, max)
+                .map(|(member, _)| member.clone())
+                .collect()
+        };
+
+        assert_eq!(
+            members(Bound::Included(2.0), Bound::Unbounded),
+            ["b", "c", "d"]
+        );
+        assert_eq!(
+            members(Bound::Excluded(1.0), Bound::Excluded(3.0)),
+            ["b", "c"]
+        );
+        assert_eq!(members(Bound::Excluded(2.0), Bound::Included(3.0)), ["d"]);
+        assert!(members(Bound::Included(3.5), Bound::Unbounded).is_empty());
+    }
+
+    #[test]
+    fn test_format_score() {
+        assert_eq!(format_score(1.5), "1.5");
+        assert_eq!(format_score(3.0), "3");
+        assert_eq!(format_score(f64::NEG_INFINITY), "-inf");
+    }
+}
@@ -0,0 +1,116 @@
+mod common;
+
+use common::{TestServer, request};
+use mini_redis::config::{AppendFsync, Config};
+use std::{fs, path::PathBuf, time::Duration};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpStream,
+    time::timeout,
+};
+
+/// Settings appending to a fresh file in the temporary directory, unique to
+/// this process and `name`
+fn appendonly(name: &str, appendfsync: AppendFsync) -> Config {
+    let appendfilename =
+        std::env::temp_dir().join(format!("mini-redis-{}-{name}.aof", std::process::id()));
+    let _ = fs::remove_file(&appendfilename);
+    Config {
+        appendonly: true,
+        appendfilename,
+        appendfsync,
+        ..Config::default()
+    }
+}
+
+/// Sends WAITAOF and returns its reply, which `request` can't read
+async fn waitaof(stream: &mut TcpStream, args: &[&str]) -> String {
+    let mut command = format!("*{}\r\n$7\r\nWAITAOF\r\n", args.len() + 1);
+    for arg in args {
+        command.push_str(&format!("${}\r\n{}\r\n", arg.len(), arg));
+    }
+    stream.write_all(command.as_bytes()).await.unwrap();
+
+    let mut reply = vec![0; "*2\r\n:1\r\n:0\r\n".len()];
+    stream.read_exact(&mut reply).await.unwrap();
+    String::from_utf8(reply).unwrap()
+}
+
+fn remove(path: PathBuf) {
+    fs::remove_file(path).unwrap();
+}
+
+#[tokio::test]
+async fn test_waitaof_counts_local_fsync_under_always() {
+    let config = appendonly("always", AppendFsync::Always);
+    let path = config.appendfilename.clone();
+    let server = TestServer::start_with(config).await;
+    let mut stream = TcpStream::connect(server.addr()).await.unwrap();
+
+    assert_eq!(
+        request(&mut stream, &["SET", "key", "value"]).await,
+        "+OK\r\n"
+    );
+    assert_eq!(
+        waitaof(&mut stream, &["1", "0", "0"]).await,
+        "*2\r\n:1\r\n:0\r\n"
+    );
+
+    drop(stream);
+    server.shutdown().await;
+    remove(path);
+}
+
+#[tokio::test]
+async fn test_waitaof_waits_for_everysec_fsync() {
+    let config = appendonly("everysec", AppendFsync::EverySec);
+    let path = config.appendfilename.clone();
+    let server = TestServer::start_with(config).await;
+    let mut stream = TcpStream::connect(server.addr()).await.unwrap();
+
+    request(&mut stream, &["SET", "key", "value"]).await;
+    let reply = timeout(
+        Duration::from_secs(3),
+        waitaof(&mut stream, &["1", "0", "0"]),
+    )
+    .await
+    .expect("the file was never fsynced");
+    assert_eq!(reply, "*2\r\n:1\r\n:0\r\n");
+
+    drop(stream);
+    server.shutdown().await;
+    remove(path);
+}
+
+#[tokio::test]
+async fn test_writes_are_replayed_on_restart() {
+    let config = appendonly("restart", AppendFsync::No);
+    let path = config.appendfilename.clone();
+    let server = TestServer::start_with(config.clone()).await;
+    let mut stream = TcpStream::connect(server.addr()).await.unwrap();
+    request(&mut stream, &["SET", "key", "value"]).await;
+    request(&mut stream, &["SET", "gone", "value"]).await;
+    request(&mut stream, &["DEL", "gone"]).await;
+    request(&mut stream, &["SELECT", "1"]).await;
+    request(&mut stream, &["SADD", "set", "a", "b"]).await;
+    drop(stream);
+    server.shutdown().await;
+
+    let server = TestServer::start_with(config).await;
+    let mut stream = TcpStream::connect(server.addr()).await.unwrap();
+    assert_eq!(
+        request(&mut stream, &["GET", "key"]).await,
+        "$5\r\nvalue\r\n"
+    );
+    assert_eq!(request(&mut stream, &["GET", "gone"]).await, "$-1\r\n");
+    request(&mut stream, &["SELECT", "1"]).await;
+    // Both members are there already
+    assert_eq!(
+        request(&mut stream, &["SADD", "set", "a", "b"]).await,
+        ":0\r\n"
+    );
+
+    drop(stream);
+    server.shutdown().await;
+    remove(path);
+}
@@ -0,0 +1,93 @@
+// Each test binary only uses part of the harness
+#![allow(dead_code)]
+
+use mini_redis::{config::Config, server};
+use std::{io, net::SocketAddr};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader},
+    net::{TcpListener, TcpStream},
+    sync::oneshot,
+    task::JoinHandle,
+};
+
+/// Server bound to an ephemeral port for the lifetime of a test
+///
+/// Dropping it signals the server to shut down.
+pub struct TestServer {
+    addr: SocketAddr,
+    shutdown: Option<oneshot::Sender<()>>,
+    handle: JoinHandle<io::Result<()>>,
+}
+
+impl TestServer {
+    pub async fn start() -> Self {
+        Self::start_with(Config::default()).await
+    }
+
+    pub async fn start_with(config: Config) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (shutdown, signal) = oneshot::channel();
+        let handle = tokio::spawn(server::run(listener, config, signal));
+
+        Self {
+            addr,
+            shutdown: Some(shutdown),
+            handle,
+        }
+    }
+
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// Signals the server to shut down without waiting for it
+    pub fn begin_shutdown(&mut self) {
+        if let Some(shutdown) = self.shutdown.take() {
+            let _ = shutdown.send(());
+        }
+    }
+
+    /// Waits for the server to stop by itself
+    pub async fn stopped(mut self) {
+        (&mut self.handle).await.unwrap().unwrap();
+    }
+
+    /// Shuts the server down and waits for it to stop
+    pub async fn shutdown(mut self) {
+        self.begin_shutdown();
+        (&mut self.handle).await.unwrap().unwrap();
+    }
+}
+
+impl Drop for TestServer {
+    fn drop(&mut self) {
+        if let Some(shutdown) = self.shutdown.take() {
+            let _ = shutdown.send(());
+        }
+    }
+}
+
+/// Sends a command and returns its reply as raw RESP
+///
+/// Only understands replies that are a single line or a bulk string.
+pub async fn request(stream: &mut TcpStream, args: &[&str]) -> String {
+    let mut command = format!("*{}\r\n", args.len());
+    for arg in args {
+        command.push_str(&format!("${}\r\n{}\r\n", arg.len(), arg));
+    }
+    stream.write_all(command.as_bytes()).await.unwrap();
+
+    let mut reader = BufReader::new(stream);
+    let mut reply = String::new();
+    reader.read_line(&mut reply).await.unwrap();
+
+    if let Some(len) = reply.strip_prefix('$')
+        && let Ok(len) = len.trim_end().parse::<usize>()
+    {
+        let mut body = vec![0; len + 2];
+        reader.read_exact(&mut body).await.unwrap();
+        reply.push_str(&String::from_utf8(body).unwrap());
+    }
+    reply
+}
@@ -0,0 +1,38 @@
+mod common;
+
+use common::{TestServer, request};
+use std::time::Duration;
+use tokio::{net::TcpStream, time::sleep};
+
+#[tokio::test]
+async fn test_expired_key_counts_until_accessed_without_active_expire() {
+    let server = TestServer::start().await;
+    let mut stream = TcpStream::connect(server.addr()).await.unwrap();
+
+    request(&mut stream, &["DEBUG", "SET-ACTIVE-EXPIRE", "0"]).await;
+    request(&mut stream, &["SET", "key", "value"]).await;
+    request(&mut stream, &["PEXPIRE", "key", "20"]).await;
+    // Several sweep intervals, none of which may remove the key
+    sleep(Duration::from_millis(300)).await;
+
+    assert_eq!(request(&mut stream, &["DBSIZE"]).await, ":1\r\n");
+    assert!(
+        request(&mut stream, &["DEBUG", "OBJECT", "key"])
+            .await
+            .ends_with("expired:1\r\n")
+    );
+    assert_eq!(request(&mut stream, &["GET", "key"]).await, "$-1\r\n");
+    assert_eq!(request(&mut stream, &["DBSIZE"]).await, ":0\r\n");
+}
+
+#[tokio::test]
+async fn test_active_expire_removes_untouched_keys() {
+    let server = TestServer::start().await;
+    let mut stream = TcpStream::connect(server.addr()).await.unwrap();
+
+    request(&mut stream, &["SET", "key", "value"]).await;
+    request(&mut stream, &["PEXPIRE", "key", "20"]).await;
+    sleep(Duration::from_millis(300)).await;
+
+    assert_eq!(request(&mut stream, &["DBSIZE"]).await, ":0\r\n");
+}
@@ -0,0 +1,111 @@
+mod common;
+
+use common::{TestServer, request};
+use mini_redis::config::Config;
+use std::time::Duration;
+use tokio::{net::TcpStream, time};
+
+async fn start_replica(master: &TestServer) -> TestServer {
+    let config = Config {
+        replicaof: Some((master.addr().ip().to_string(), master.addr().port())),
+        ..Config::default()
+    };
+    TestServer::start_with(config).await
+}
+
+#[tokio::test]
+async fn test_master_writes_reach_replica() {
+    let master = TestServer::start().await;
+    let replica = start_replica(&master).await;
+    let mut master_client = TcpStream::connect(master.addr()).await.unwrap();
+    let mut replica_client = TcpStream::connect(replica.addr()).await.unwrap();
+
+    // The replica may not have finished its handshake yet, in which case the
+    // write isn't forwarded, so keep writing until it shows up
+    let mut attempts = 0;
+    loop {
+        assert_eq!(
+            request(&mut master_client, &["SET", "key", "value"]).await,
+            "+OK\r\n"
+        );
+        if request(&mut replica_client, &["GET", "key"]).await == "$5\r\nvalue\r\n" {
+            break;
+        }
+
+        attempts += 1;
+        assert!(attempts < 100, "write never reached the replica");
+        time::sleep(Duration::from_millis(20)).await;
+    }
+}
+
+#[tokio::test]
+async fn test_replica_starts_from_master_snapshot() {
+    let master = TestServer::start().await;
+    let mut master_client = TcpStream::connect(master.addr()).await.unwrap();
+    request(&mut master_client, &["SET", "key", "value"]).await;
+    request(&mut master_client, &["SELECT", "1"]).await;
+    request(&mut master_client, &["RPUSH", "list", "a", "b"]).await;
+
+    let replica = start_replica(&master).await;
+    let mut replica_client = TcpStream::connect(replica.addr()).await.unwrap();
+
+    // Nothing is written after the replica starts, so the keys can only come
+    // from the snapshot it synchronised from
+    let mut attempts = 0;
+    while request(&mut replica_client, &["GET", "key"]).await != "$5\r\nvalue\r\n" {
+        attempts += 1;
+        assert!(attempts < 100, "snapshot never reached the replica");
+        time::sleep(Duration::from_millis(20)).await;
+    }
+    request(&mut replica_client, &["SELECT", "1"]).await;
+    assert_eq!(
+        request(&mut replica_client, &["LINDEX", "list", "1"]).await,
+        "$1\r\nb\r\n"
+    );
+}
+
+#[tokio::test]
+async fn test_replica_rejects_writes() {
+    let master = TestServer::start().await;
+    let replica = start_replica(&master).await;
+    let mut client = TcpStream::connect(replica.addr()).await.unwrap();
+
+    assert!(
+        request(&mut client, &["SET", "key", "value"])
+            .await
+            .starts_with("-READONLY ")
+    );
+    assert_eq!(request(&mut client, &["GET", "key"]).await, "$-1\r\n");
+}
+
+#[tokio::test]
+async fn test_wait_counts_replicas_that_caught_up() {
+    let master = TestServer::start().await;
+    let replica = start_replica(&master).await;
+    let mut master_client = TcpStream::connect(master.addr()).await.unwrap();
+    let mut replica_client = TcpStream::connect(replica.addr()).await.unwrap();
+
+    let mut attempts = 0;
+    loop {
+        request(&mut master_client, &["SET", "key", "value"]).await;
+        if request(&mut replica_client, &["GET", "key"]).await == "$5\r\nvalue\r\n" {
+            break;
+        }
+
+        attempts += 1;
+        assert!(attempts < 100, "write never reached the replica");
+        time::sleep(Duration::from_millis(20)).await;
+    }
+
+    request(&mut master_client, &["SET", "other", "value"]).await;
+    let waited = time::timeout(
+        Duration::from_secs(5),
+        request(&mut master_client, &["WAIT", "1", "0"]),
+    );
+    assert_eq!(waited.await.unwrap(), ":1\r\n");
+    // Only one replica exists, so asking for two waits out the timeout
+    assert_eq!(
+        request(&mut master_client, &["WAIT", "2", "50"]).await,
+        ":1\r\n"
+    );
+}
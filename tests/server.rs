@@ -0,0 +1,277 @@
+mod common;
+
+use common::{TestServer, request};
+use mini_redis::{FrameValue, config::Config, framed, server};
+use std::{
+    fs,
+    time::{Duration, Instant},
+};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+    time::sleep,
+};
+
+#[tokio::test]
+async fn test_ping() {
+    let server = TestServer::start().await;
+    let mut stream = TcpStream::connect(server.addr()).await.unwrap();
+
+    stream.write_all(b"*1\r\n$4\r\nPING\r\n").await.unwrap();
+
+    let mut response = [0; 7];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(&response, b"+PONG\r\n");
+}
+
+#[tokio::test]
+async fn test_framed_connection() {
+    use futures_core::Stream;
+    use futures_sink::Sink;
+    use std::{future::poll_fn, pin::Pin};
+
+    let server = TestServer::start().await;
+    let mut client = framed(TcpStream::connect(server.addr()).await.unwrap());
+
+    let ping = FrameValue::Array(vec![FrameValue::BulkString("PING".into())]);
+    poll_fn(|cx| Pin::new(&mut client).poll_ready(cx))
+        .await
+        .unwrap();
+    Pin::new(&mut client).start_send(ping).unwrap();
+    poll_fn(|cx| Pin::new(&mut client).poll_flush(cx))
+        .await
+        .unwrap();
+
+    let reply = poll_fn(|cx| Pin::new(&mut client).poll_next(cx)).await;
+    assert_eq!(
+        reply.unwrap().unwrap(),
+        FrameValue::SimpleString("PONG".into())
+    );
+}
+
+#[tokio::test]
+async fn test_shutdown_stops_accepting() {
+    let server = TestServer::start().await;
+    let addr = server.addr();
+    server.shutdown().await;
+
+    assert!(TcpStream::connect(addr).await.is_err());
+}
+
+#[tokio::test]
+async fn test_shutdown_drains_refusing_writes() {
+    let mut server = TestServer::start().await;
+    let mut stream = TcpStream::connect(server.addr()).await.unwrap();
+    request(&mut stream, &["SET", "key", "value"]).await;
+
+    server.begin_shutdown();
+    sleep(Duration::from_millis(100)).await;
+
+    assert_eq!(
+        request(&mut stream, &["SET", "key", "other"]).await,
+        "-ERR server is shutting down\r\n"
+    );
+    assert_eq!(
+        request(&mut stream, &["GET", "key"]).await,
+        "$5\r\nvalue\r\n"
+    );
+    assert!(TcpStream::connect(server.addr()).await.is_err());
+
+    // The drain ends as soon as the last connection closes
+    drop(stream);
+    tokio::time::timeout(Duration::from_secs(5), server.shutdown())
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn test_shutdown_command_stops_server() {
+    let server = TestServer::start().await;
+    let addr = server.addr();
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    let mut other = TcpStream::connect(addr).await.unwrap();
+
+    assert_eq!(
+        request(&mut stream, &["SHUTDOWN", "NOW"]).await,
+        "-ERR syntax error\r\n"
+    );
+    assert_eq!(
+        request(&mut stream, &["SHUTDOWN", "NOSAVE", "NOSAVE"]).await,
+        "-ERR wrong number of arguments for 'shutdown' command\r\n"
+    );
+    assert_eq!(
+        request(&mut stream, &["SHUTDOWN", "SAVE"]).await,
+        "-ERR SHUTDOWN SAVE is not supported, as there is no dump file to write\r\n"
+    );
+
+    // No reply, the connection just closes
+    stream
+        .write_all(b"*2\r\n$8\r\nSHUTDOWN\r\n$6\r\nNOSAVE\r\n")
+        .await
+        .unwrap();
+    let mut buf = [0; 16];
+    assert_eq!(stream.read(&mut buf).await.unwrap(), 0);
+    assert_eq!(other.read(&mut buf).await.unwrap(), 0);
+
+    tokio::time::timeout(Duration::from_secs(5), server.stopped())
+        .await
+        .unwrap();
+    assert!(TcpStream::connect(addr).await.is_err());
+}
+
+#[tokio::test]
+async fn test_pidfile_lives_as_long_as_the_server() {
+    let path = std::env::temp_dir().join(format!("mini-redis-{}.pid", std::process::id()));
+    fs::write(&path, "stale").unwrap();
+    let server = TestServer::start_with(Config {
+        pidfile: Some(path.clone()),
+        ..Config::default()
+    })
+    .await;
+
+    // The server may not have been polled yet, so it is asked for something first
+    let mut stream = TcpStream::connect(server.addr()).await.unwrap();
+    request(&mut stream, &["PING"]).await;
+    assert_eq!(
+        fs::read_to_string(&path).unwrap(),
+        format!("{}\n", std::process::id())
+    );
+
+    drop(stream);
+    server.shutdown().await;
+    assert!(!path.exists());
+}
+
+#[tokio::test]
+async fn test_unwritable_pidfile_fails_to_start() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let config = Config {
+        pidfile: Some("/nonexistent/mini-redis.pid".into()),
+        ..Config::default()
+    };
+
+    let error = server::run(listener, config, std::future::pending::<()>())
+        .await
+        .unwrap_err();
+    assert!(error.to_string().contains("/nonexistent/mini-redis.pid"));
+}
+
+#[tokio::test]
+async fn test_values_over_the_size_limit_are_rejected() {
+    let server = TestServer::start_with(Config {
+        proto_max_bulk_len: 8,
+        ..Config::default()
+    })
+    .await;
+    let mut stream = TcpStream::connect(server.addr()).await.unwrap();
+
+    assert_eq!(
+        request(&mut stream, &["SET", "key", "abcdefgh"]).await,
+        "+OK\r\n"
+    );
+    assert_eq!(
+        request(&mut stream, &["APPEND", "key", "i"]).await,
+        "-ERR string exceeds maximum allowed size\r\n"
+    );
+    assert_eq!(
+        request(&mut stream, &["GET", "key"]).await,
+        "$8\r\nabcdefgh\r\n"
+    );
+    // Too long to even be read as an argument
+    assert_eq!(
+        request(&mut stream, &["MSET", "a", "1", "b", "abcdefghi"]).await,
+        "-ERR Protocol error\r\n"
+    );
+}
+
+#[tokio::test]
+async fn test_values_larger_than_a_frame_round_trip() {
+    let server = TestServer::start().await;
+    let mut stream = TcpStream::connect(server.addr()).await.unwrap();
+    let value = "x".repeat(10 * 1024 * 1024);
+
+    assert_eq!(
+        request(&mut stream, &["SET", "key", &value]).await,
+        "+OK\r\n"
+    );
+    assert_eq!(
+        request(&mut stream, &["GET", "key"]).await,
+        format!("${}\r\n{value}\r\n", value.len())
+    );
+
+    drop(stream);
+    server.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_client_pause_delays_commands() {
+    let server = TestServer::start().await;
+    let mut admin = TcpStream::connect(server.addr()).await.unwrap();
+    let mut stream = TcpStream::connect(server.addr()).await.unwrap();
+
+    assert_eq!(
+        request(&mut admin, &["CLIENT", "PAUSE", "200", "WRITE"]).await,
+        "+OK\r\n"
+    );
+    let start = Instant::now();
+    // Reads go ahead during a write pause
+    assert_eq!(request(&mut stream, &["GET", "key"]).await, "$-1\r\n");
+    assert!(start.elapsed() < Duration::from_millis(150));
+    assert_eq!(
+        request(&mut stream, &["SET", "key", "value"]).await,
+        "+OK\r\n"
+    );
+    assert!(start.elapsed() >= Duration::from_millis(150));
+
+    request(&mut admin, &["CLIENT", "PAUSE", "100"]).await;
+    let start = Instant::now();
+    assert_eq!(
+        request(&mut stream, &["GET", "key"]).await,
+        "$5\r\nvalue\r\n"
+    );
+    assert!(start.elapsed() >= Duration::from_millis(50));
+}
+
+#[tokio::test]
+async fn test_client_unpause_releases_waiting_commands() {
+    let server = TestServer::start().await;
+    let mut admin = TcpStream::connect(server.addr()).await.unwrap();
+    let mut stream = TcpStream::connect(server.addr()).await.unwrap();
+
+    request(&mut admin, &["CLIENT", "PAUSE", "60000"]).await;
+    let start = Instant::now();
+    let waiting = tokio::spawn(async move { request(&mut stream, &["SET", "key", "value"]).await });
+    sleep(Duration::from_millis(50)).await;
+    assert!(!waiting.is_finished());
+
+    assert_eq!(request(&mut admin, &["CLIENT", "UNPAUSE"]).await, "+OK\r\n");
+    let reply = tokio::time::timeout(Duration::from_secs(5), waiting).await;
+    assert_eq!(reply.unwrap().unwrap(), "+OK\r\n");
+    assert!(start.elapsed() < Duration::from_secs(5));
+}
+
+#[tokio::test]
+async fn test_configured_databases_bound_select() {
+    let config = Config {
+        databases: 4,
+        ..Config::default()
+    };
+    let server = TestServer::start_with(config).await;
+    let mut stream = TcpStream::connect(server.addr()).await.unwrap();
+
+    assert_eq!(
+        request(&mut stream, &["SELECT", "4"]).await,
+        "-ERR DB index is out of range\r\n"
+    );
+    assert_eq!(request(&mut stream, &["SELECT", "3"]).await, "+OK\r\n");
+}
+
+#[test]
+fn test_zero_databases_is_invalid() {
+    let config = Config {
+        databases: 0,
+        ..Config::default()
+    };
+    assert!(config.validate().is_err());
+    assert!(Config::default().validate().is_ok());
+}